@@ -0,0 +1,238 @@
+//! Aligning and distributing elements by their bounds, like in diagram
+//! editors.
+
+use crate::editor::{parent_mut, ElementPath};
+use crate::elements::Element;
+use crate::geometry::BoundingBox;
+use crate::render;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::Document;
+
+/// Which edge or center of their bounds [`Document::align`] lines elements
+/// up on.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Alignment {
+    Left,
+    CenterX,
+    Right,
+    Top,
+    CenterY,
+    Bottom,
+}
+
+/// The direction [`Document::distribute`] spreads elements along.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    /// The start and end of `bounds` along the axis.
+    fn range(self, bounds: BoundingBox) -> (Scalar, Scalar) {
+        match self {
+            Axis::X => (bounds.min.0, bounds.max.0),
+            Axis::Y => (bounds.min.1, bounds.max.1),
+        }
+    }
+
+    /// `distance` along the axis.
+    fn offset(self, distance: Scalar) -> (Scalar, Scalar) {
+        match self {
+            Axis::X => (distance, 0.),
+            Axis::Y => (0., distance),
+        }
+    }
+}
+
+/// An element to move: its path, the transform of the layers it is in and
+/// its bounds in document coordinates.
+type Placement<'a> = (&'a [usize], Transform, BoundingBox);
+
+impl Document {
+    /// Moves the elements at `paths` so that their bounds line up on the
+    /// edge or center of the bounds of all of them.
+    ///
+    /// The elements are moved by a translation added to their transform.
+    /// Elements without bounds, e.g. empty layers, are not moved. Returns
+    /// `false` without changing anything if a path does not exist.
+    pub fn align(&mut self, paths: &[ElementPath], alignment: Alignment) -> bool {
+        let placements = match self.placements(paths) {
+            Some(placements) => placements,
+            None => return false,
+        };
+        let all = match placements.iter().map(|p| p.2).reduce(BoundingBox::union) {
+            Some(all) => all,
+            None => return true,
+        };
+        for (path, parents, bounds) in placements {
+            let offset = match alignment {
+                Alignment::Left => (all.min.0 - bounds.min.0, 0.),
+                Alignment::CenterX => (all.center().0 - bounds.center().0, 0.),
+                Alignment::Right => (all.max.0 - bounds.max.0, 0.),
+                Alignment::Top => (0., all.min.1 - bounds.min.1),
+                Alignment::CenterY => (0., all.center().1 - bounds.center().1),
+                Alignment::Bottom => (0., all.max.1 - bounds.max.1),
+            };
+            self.translate(path, parents, offset);
+        }
+        true
+    }
+
+    /// Moves the elements at `paths` along `axis` so that there is `spacing`
+    /// between their bounds, keeping their order and the first one in place.
+    ///
+    /// Without `spacing`, the last one stays in place as well and the gaps
+    /// are made equal. Like [`Document::align`] otherwise.
+    pub fn distribute(
+        &mut self,
+        paths: &[ElementPath],
+        axis: Axis,
+        spacing: impl Into<Option<Scalar>>,
+    ) -> bool {
+        let mut placements = match self.placements(paths) {
+            Some(placements) => placements,
+            None => return false,
+        };
+        if placements.len() < 2 {
+            return true;
+        }
+        placements.sort_by(|a, b| {
+            axis.range(a.2)
+                .0
+                .partial_cmp(&axis.range(b.2).0)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let size = |bounds: BoundingBox| {
+            let (start, end) = axis.range(bounds);
+            end - start
+        };
+        let mut next = axis.range(placements[0].2).0;
+        let spacing = spacing.into().unwrap_or_else(|| {
+            let end = placements
+                .iter()
+                .map(|p| axis.range(p.2).1)
+                .fold(next, Scalar::max);
+            let sizes: Scalar = placements.iter().map(|p| size(p.2)).sum();
+            (end - next - sizes) / (placements.len() - 1) as Scalar
+        });
+        for (path, parents, bounds) in placements {
+            self.translate(path, parents, axis.offset(next - axis.range(bounds).0));
+            next += size(bounds) + spacing;
+        }
+        true
+    }
+
+    /// The elements at `paths` that have bounds, `None` if a path does not
+    /// exist.
+    fn placements<'a>(&self, paths: &'a [ElementPath]) -> Option<Vec<Placement<'a>>> {
+        let mut placements = vec![];
+        for path in paths {
+            let (&last, layers) = path.split_last()?;
+            let mut parents = Transform::IDENTITY;
+            let mut elements = &self.elements;
+            for &i in layers {
+                match elements.get(i)? {
+                    Element::Layer(layer) => {
+                        parents = parents * layer.properties.transform;
+                        elements = &layer.elements;
+                    }
+                    _ => return None,
+                }
+            }
+            let element = elements.get(last)?;
+            let transform = parents * element.properties().transform;
+            if let Some(bounds) = render::element_bounds(element, transform) {
+                placements.push((&path[..], parents, bounds));
+            }
+        }
+        Some(placements)
+    }
+
+    /// Moves the element at `path` by `offset` in document coordinates.
+    fn translate(&mut self, path: &[usize], parents: Transform, offset: (Scalar, Scalar)) {
+        // Exact zeros leave the transform untouched
+        if offset == (0., 0.) {
+            return;
+        }
+        let inverse = match parents.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        if let Some((elements, i)) = parent_mut(&mut self.elements, path) {
+            if let Some(element) = elements.get_mut(i) {
+                (inverse * Transform::translate(offset.0, offset.1) * parents).apply_to(element);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::align::{Alignment, Axis};
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Properties};
+    use crate::geometry::BoundingBox;
+    use crate::render::RenderItem;
+    use crate::scalar::Scalar;
+    use crate::transform::Transform;
+    use crate::Document;
+
+    fn circle(x: Scalar, y: Scalar, radius: Scalar) -> Element {
+        Element::Ellipse(Ellipse {
+            position: (x, y),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 0.,
+            radius,
+            properties: Properties::default(),
+        })
+    }
+
+    fn bounds(doc: &Document) -> Vec<BoundingBox> {
+        doc.render_items()
+            .iter()
+            .filter_map(RenderItem::bounds)
+            .collect()
+    }
+
+    #[test]
+    fn align_and_distribute() {
+        let mut layer = Layer::new("scaled");
+        layer.properties.transform = Transform::scale(2., 2.);
+        layer.elements.push(circle(20., 0., 5.));
+        let mut doc = Document {
+            elements: vec![
+                circle(0., 0., 10.),
+                circle(50., 30., 5.),
+                Element::Layer(layer),
+            ],
+            ..Default::default()
+        };
+        let paths = vec![vec![0], vec![1], vec![2, 0]];
+        assert!(!doc.align(&[vec![5]], Alignment::Left));
+        assert!(!doc.align(&[vec![0, 0]], Alignment::Left));
+
+        assert!(doc.align(&paths, Alignment::Top));
+        assert!(bounds(&doc).iter().all(|b| b.min.1 == -10.));
+        // The first one is already at the top
+        assert_eq!(doc.elements[0].properties().transform, Transform::IDENTITY);
+
+        assert!(doc.distribute(&paths, Axis::X, 5.));
+        let xs: Vec<_> = bounds(&doc).iter().map(|b| (b.min.0, b.max.0)).collect();
+        assert_eq!(xs, vec![(-10., 10.), (40., 50.), (15., 35.)]);
+
+        // The outer ones stay, the gaps are made equal
+        doc.elements[1] = circle(100., 0., 5.);
+        assert!(doc.distribute(&paths, Axis::X, None));
+        let xs: Vec<_> = bounds(&doc).iter().map(|b| (b.min.0, b.max.0)).collect();
+        assert_eq!(xs, vec![(-10., 10.), (95., 105.), (42.5, 62.5)]);
+
+        // Layers are aligned by the bounds of their elements
+        let layer = doc.elements[2].clone();
+        assert!(doc.align(&[vec![2], vec![1]], Alignment::CenterY));
+        assert_eq!(doc.elements[2], layer);
+        assert_eq!(bounds(&doc)[1].center().1, 0.);
+    }
+}
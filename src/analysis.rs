@@ -0,0 +1,267 @@
+//! Structure recognition on handwriting.
+
+use crate::elements::Element;
+use crate::geometry::BoundingBox;
+use crate::render::RenderItem;
+use crate::scalar::Scalar;
+
+/// Strokes written close together.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Word {
+    /// The indices of the strokes in [`render_items`](crate::render::render_items),
+    /// from left to right.
+    pub items: Vec<usize>,
+    pub bounds: BoundingBox,
+}
+
+/// Words written next to each other, sharing a baseline.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextLine {
+    /// From left to right.
+    pub words: Vec<Word>,
+    pub bounds: BoundingBox,
+}
+
+/// Lines written below each other without a gap.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Paragraph {
+    /// From top to bottom.
+    pub lines: Vec<TextLine>,
+    pub bounds: BoundingBox,
+}
+
+impl Paragraph {
+    /// The indices of all strokes in the paragraph.
+    pub fn items(&self) -> Vec<usize> {
+        self.lines
+            .iter()
+            .flat_map(|l| &l.words)
+            .flat_map(|w| w.items.iter().copied())
+            .collect()
+    }
+}
+
+/// Clusters the strokes ([`Line`](crate::elements::Line)s and
+/// [`Polyline`](crate::elements::Polyline)s) of `items` into paragraphs,
+/// lines and words, from top to bottom.
+///
+/// Only spatial proximity is used, not the timestamps of lines, which only
+/// some strokes have, so corrected or later added words are placed where
+/// they were written. All distances are relative to the median stroke
+/// height, so the result does not depend on the scale of the handwriting.
+/// Shapes are ignored.
+pub fn cluster(items: &[RenderItem]) -> Vec<Paragraph> {
+    let strokes: Vec<(usize, BoundingBox)> = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| matches!(item.element, Element::Line(_) | Element::Polyline(_)))
+        .filter_map(|(i, item)| Some((i, item.bounds()?)))
+        .collect();
+    if strokes.is_empty() {
+        return vec![];
+    }
+    let mut heights: Vec<Scalar> = strokes.iter().map(|(_, b)| b.height()).collect();
+    heights.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let height = heights[heights.len() / 2].max(Scalar::EPSILON);
+
+    lines(strokes, height)
+        .into_iter()
+        .fold(vec![], |mut paragraphs: Vec<Paragraph>, line| {
+            match paragraphs.last_mut() {
+                Some(paragraph) if continues(paragraph, &line, height) => {
+                    paragraph.bounds = paragraph.bounds.union(line.bounds);
+                    paragraph.lines.push(line);
+                }
+                _ => paragraphs.push(Paragraph {
+                    bounds: line.bounds,
+                    lines: vec![line],
+                }),
+            }
+            paragraphs
+        })
+}
+
+/// The offsets (in document coordinates) to move the strokes of `paragraph`
+/// by, so its words wrap at `width`, keeping the spacing of the handwriting.
+///
+/// The paragraph keeps its top left corner, words wider than `width` get a
+/// line of their own.
+pub fn reflow(paragraph: &Paragraph, width: Scalar) -> Vec<(usize, (Scalar, Scalar))> {
+    let lines = &paragraph.lines;
+    let mut word_gaps: Vec<Scalar> = lines
+        .iter()
+        .flat_map(|l| l.words.windows(2))
+        .map(|w| w[1].bounds.min.0 - w[0].bounds.max.0)
+        .collect();
+    word_gaps.sort_by(|a, b| by_key(*a, *b));
+    let line_height = match lines.len() {
+        1 => lines[0].bounds.height() * 1.5,
+        n => (lines[n - 1].bounds.min.1 - lines[0].bounds.min.1) / (n - 1) as Scalar,
+    };
+    let word_gap = word_gaps
+        .get(word_gaps.len() / 2)
+        .copied()
+        .unwrap_or(line_height / 3.0);
+
+    let (left, top) = paragraph.bounds.min;
+    let mut offsets = vec![];
+    let (mut x, mut y) = (left, top);
+    for line in lines {
+        for word in &line.words {
+            if x > left && x + word.bounds.width() > left + width {
+                x = left;
+                y += line_height;
+            }
+            let offset = (x - word.bounds.min.0, y - line.bounds.min.1);
+            offsets.extend(word.items.iter().map(|&item| (item, offset)));
+            x += word.bounds.width() + word_gap;
+        }
+    }
+    offsets
+}
+
+/// Groups strokes into lines, sorted from top to bottom.
+fn lines(mut strokes: Vec<(usize, BoundingBox)>, height: Scalar) -> Vec<TextLine> {
+    // Small strokes like dots or accents come last, so they can join the
+    // line they are centered in or closest to
+    let small = |b: &BoundingBox| b.height() < height / 2.0;
+    strokes.sort_by(|a, b| {
+        small(&a.1)
+            .cmp(&small(&b.1))
+            .then(by_key(a.1.center().1, b.1.center().1))
+    });
+    // The strokes of each line and their combined bounds
+    let mut lines: Vec<(Vec<(usize, BoundingBox)>, BoundingBox)> = vec![];
+    for stroke in strokes {
+        let bounds = stroke.1;
+        let overlap = |line: &BoundingBox| {
+            let overlap = bounds.max.1.min(line.max.1) - bounds.min.1.max(line.min.1);
+            overlap / bounds.height().min(line.height()).max(Scalar::EPSILON)
+        };
+        let best = lines
+            .iter_mut()
+            .filter(|(_, line)| {
+                overlap(line) >= 0.5
+                    || small(&bounds)
+                        && bounds.center().1 > line.min.1 - height / 2.0
+                        && bounds.center().1 < line.max.1 + height / 2.0
+            })
+            .max_by(|a, b| by_key(overlap(&a.1), overlap(&b.1)));
+        match best {
+            Some((line, line_bounds)) => {
+                *line_bounds = line_bounds.union(bounds);
+                line.push(stroke);
+            }
+            None => lines.push((vec![stroke], bounds)),
+        }
+    }
+    lines.sort_by(|a, b| by_key(a.1.min.1, b.1.min.1));
+    lines
+        .into_iter()
+        .map(|(strokes, bounds)| TextLine {
+            words: words(strokes, height),
+            bounds,
+        })
+        .collect()
+}
+
+/// Groups the strokes of a line into words, sorted from left to right.
+fn words(mut strokes: Vec<(usize, BoundingBox)>, height: Scalar) -> Vec<Word> {
+    strokes.sort_by(|a, b| by_key(a.1.min.0, b.1.min.0));
+    let mut words: Vec<Word> = vec![];
+    for (item, bounds) in strokes {
+        match words.last_mut() {
+            Some(word) if bounds.min.0 - word.bounds.max.0 < height / 2.0 => {
+                word.bounds = word.bounds.union(bounds);
+                word.items.push(item);
+            }
+            _ => words.push(Word {
+                items: vec![item],
+                bounds,
+            }),
+        }
+    }
+    words
+}
+
+/// Whether `line` continues `paragraph`, i.e. starts right below it and
+/// overlaps it horizontally.
+fn continues(paragraph: &Paragraph, line: &TextLine, height: Scalar) -> bool {
+    let last = &paragraph.lines[paragraph.lines.len() - 1].bounds;
+    line.bounds.min.1 - last.max.1 < height
+        && line.bounds.min.0 < paragraph.bounds.max.0
+        && line.bounds.max.0 > paragraph.bounds.min.0
+}
+
+fn by_key(a: Scalar, b: Scalar) -> std::cmp::Ordering {
+    a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Line, LinePoint, Properties};
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    fn stroke(x: Scalar, y: Scalar, width: Scalar, height: Scalar) -> Element {
+        Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![LinePoint(x, y, 1.), LinePoint(x + width, y + height, 1.)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        })
+    }
+
+    #[test]
+    fn reflow() {
+        let mut doc = Document {
+            elements: (0..4)
+                .map(|i| stroke(i as Scalar * 20., 0., 10., 10.))
+                .collect(),
+            ..Default::default()
+        };
+        let paragraph = doc.cluster_ink().remove(0);
+        assert_eq!(paragraph.lines[0].words.len(), 4);
+        doc.reflow_paragraph(&paragraph, 40.);
+        let lines: Vec<_> = doc.cluster_ink()[0]
+            .lines
+            .iter()
+            .map(|l| (l.bounds.min, l.words.len()))
+            .collect();
+        assert_eq!(lines, vec![((-0.5, -0.5), 2), ((-0.5, 16.), 2)]);
+    }
+
+    #[test]
+    fn cluster() {
+        let doc = Document {
+            elements: vec![
+                // "ab cd" and "ef" below
+                stroke(0., 0., 8., 10.),
+                stroke(10., 1., 8., 9.),
+                stroke(30., 0., 8., 10.),
+                stroke(40., 0., 8., 10.),
+                stroke(0., 15., 8., 10.),
+                stroke(10., 15., 8., 10.),
+                // The dot of an "i"
+                stroke(22., -5., 0., 1.),
+                // A second paragraph far below
+                stroke(0., 100., 8., 10.),
+            ],
+            ..Default::default()
+        };
+        let paragraphs = doc.cluster_ink();
+        assert_eq!(paragraphs.len(), 2);
+        assert_eq!(paragraphs[0].lines.len(), 2);
+        let words: Vec<_> = paragraphs[0].lines[0]
+            .words
+            .iter()
+            .map(|w| w.items.clone())
+            .collect();
+        assert_eq!(words, vec![vec![0, 1, 6], vec![2, 3]]);
+        assert_eq!(paragraphs[0].lines[1].words.len(), 1);
+        assert_eq!(paragraphs[1].items(), vec![7]);
+        assert_eq!(paragraphs[1].bounds.min, (-0.5, 99.5));
+    }
+}
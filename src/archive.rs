@@ -0,0 +1,400 @@
+//! Exporting documents for long-term archiving, readable by any SVG
+//! renderer without knowing this format, see [`Document::export_archival`].
+
+use svg::node::element::Element as SvgElement;
+use svg::node::{self, Node};
+
+use crate::assets::Assets;
+use crate::colors::Color;
+use crate::elements::{Clip, Element, Text};
+use crate::escape::escape;
+use crate::fonts::FontLoader;
+use crate::geometry::BoundingBox;
+use crate::render::RenderItem;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::Document;
+
+/// The segments ellipses that are not circles after transforming, and arcs,
+/// are drawn with.
+const SEGMENTS: usize = 64;
+
+/// Sets the color `name` (`stroke` or `fill`) as SVG 1.1 has it, i.e. without
+/// alpha in the color.
+fn paint(element: &mut SvgElement, name: &str, color: Color) {
+    if color.a == 0 {
+        element.assign(name, "none");
+        return;
+    }
+    element.assign(name, color.to_string_na());
+    if color.a < 0xFF {
+        element.assign(format!("{}-opacity", name), color.opacity());
+    }
+}
+
+fn points(points: impl IntoIterator<Item = (Scalar, Scalar)>, transform: Transform) -> String {
+    points
+        .into_iter()
+        .map(|point| {
+            let (x, y) = transform.apply(point);
+            format!("{},{}", x, y)
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A `<polygon>` or `<polyline>` through `corners` in document coordinates.
+fn poly(
+    closed: bool,
+    corners: impl IntoIterator<Item = (Scalar, Scalar)>,
+    transform: Transform,
+    (stroke, fill, width): (Color, Color, Scalar),
+    opacity: f32,
+) -> SvgElement {
+    let mut element = SvgElement::new(if closed { "polygon" } else { "polyline" });
+    element.assign("points", points(corners, transform));
+    paint(&mut element, "stroke", stroke.faded(opacity));
+    paint(&mut element, "fill", fill.faded(opacity));
+    element.assign("stroke-width", width * transform.scale_factor());
+    element.assign("stroke-linecap", "round");
+    element.assign("stroke-linejoin", "round");
+    element
+}
+
+/// Whether `transform` keeps circles circles, i.e. only rotates, mirrors,
+/// moves and scales uniformly.
+fn is_similarity(t: Transform) -> bool {
+    let epsilon = 1e-6;
+    (t.a * t.a + t.b * t.b - t.c * t.c - t.d * t.d).abs() < epsilon
+        && (t.a * t.c + t.b * t.d).abs() < epsilon
+}
+
+/// The text as outlines from `fonts`, or as `<text>` if it does not have the
+/// font. Glyph outlines are curves, so the transform is kept as attribute.
+fn text(
+    text: &Text,
+    transform: Transform,
+    opacity: f32,
+    fonts: Option<&dyn FontLoader>,
+) -> SvgElement {
+    let (x, y) = text.position;
+    let lines = text
+        .content
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| (line, (x, y + i as Scalar * text.line_height())));
+    let outlines = fonts.and_then(|fonts| {
+        lines
+            .clone()
+            .map(|(line, position)| fonts.outline(line, &text.font, position))
+            .collect::<Option<Vec<_>>>()
+    });
+    let mut element = match outlines {
+        Some(outlines) => {
+            let mut path = SvgElement::new("path");
+            path.assign("d", outlines.join(" "));
+            path
+        }
+        None => {
+            let mut element = SvgElement::new("text");
+            element.assign("font-family", escape(&text.font.family));
+            element.assign("font-size", text.font.size);
+            for (line, (x, y)) in lines {
+                let mut tspan = SvgElement::new("tspan");
+                tspan.assign("x", x);
+                tspan.assign("y", y);
+                tspan.append(node::Text::new(escape(line)));
+                element.append(tspan);
+            }
+            element
+        }
+    };
+    paint(
+        &mut element,
+        "fill",
+        text.properties.fill(text.color).faded(opacity),
+    );
+    if !transform.is_identity() {
+        element.assign("transform", transform.to_string());
+    }
+    element
+}
+
+/// The element of `item` in document coordinates, `None` for elements
+/// without geometry.
+fn archival(
+    item: &RenderItem,
+    assets: &Assets,
+    fonts: Option<&dyn FontLoader>,
+) -> Option<SvgElement> {
+    let (t, opacity) = (item.transform, item.opacity);
+    Some(match item.element {
+        Element::Line(line) => {
+            let mut path = SvgElement::new("path");
+            path.assign("d", format!("M{}Z", points(line.outline(), t)));
+            paint(
+                &mut path,
+                "fill",
+                line.properties.stroke(line.color).faded(opacity),
+            );
+            path
+        }
+        Element::Polyline(polyline) => {
+            let corners = polyline.points.iter().map(|p| (p.0, p.1));
+            let style = (
+                polyline.properties.stroke(polyline.stroke),
+                polyline.properties.fill(polyline.fill),
+                polyline.width,
+            );
+            poly(polyline.closed, corners, t, style, opacity)
+        }
+        Element::Ngon(ngon) => poly(
+            true,
+            ngon.points(),
+            t,
+            (
+                ngon.properties.stroke(ngon.stroke),
+                ngon.properties.fill(ngon.fill),
+                ngon.width,
+            ),
+            opacity,
+        ),
+        Element::Shape(shape) => {
+            let style = (
+                shape.properties.stroke(shape.stroke),
+                shape.properties.fill(shape.fill),
+                shape.width,
+            );
+            poly(true, shape.points.iter().copied(), t, style, opacity)
+        }
+        Element::Ellipse(ellipse) if is_similarity(t) => {
+            let mut circle = SvgElement::new("circle");
+            let (cx, cy) = t.apply(ellipse.position);
+            circle.assign("cx", cx);
+            circle.assign("cy", cy);
+            circle.assign("r", ellipse.radius * t.scale_factor());
+            paint(
+                &mut circle,
+                "stroke",
+                ellipse.properties.stroke(ellipse.stroke).faded(opacity),
+            );
+            paint(
+                &mut circle,
+                "fill",
+                ellipse.properties.fill(ellipse.fill).faded(opacity),
+            );
+            circle.assign("stroke-width", ellipse.width * t.scale_factor());
+            circle
+        }
+        Element::Ellipse(ellipse) => {
+            let style = (
+                ellipse.properties.stroke(ellipse.stroke),
+                ellipse.properties.fill(ellipse.fill),
+                ellipse.width,
+            );
+            poly(true, ellipse.points(SEGMENTS), t, style, opacity)
+        }
+        Element::Arc(arc) => {
+            let style = (
+                arc.properties.stroke(arc.stroke),
+                arc.properties.fill(arc.fill),
+                arc.width,
+            );
+            poly(arc.pie, arc.points(SEGMENTS), t, style, opacity)
+        }
+        Element::Text(element) => text(element, t, opacity, fonts),
+        Element::FileRef(file) => {
+            let mut image = SvgElement::new("image");
+            image.assign("x", file.position.0);
+            image.assign("y", file.position.1);
+            image.assign("width", file.size.0);
+            image.assign("height", file.size.1);
+            image.assign("preserveAspectRatio", "none");
+            let href = match file.asset().and_then(|id| assets.get(id)) {
+                Some(asset) => asset.to_data_uri(),
+                None => file.path.clone(),
+            };
+            image.assign("xlink:href", href);
+            if opacity < 1. {
+                image.assign("opacity", opacity);
+            }
+            if !t.is_identity() {
+                image.assign("transform", t.to_string());
+            }
+            image
+        }
+        Element::Custom(custom) => {
+            let mut element = SvgElement::new(custom.element.tag());
+            for (name, value) in custom.element.attributes() {
+                if !name.starts_with("svgnote:") {
+                    element.assign(name, value);
+                }
+            }
+            if opacity < 1. {
+                element.assign("opacity", opacity);
+            }
+            if !t.is_identity() {
+                element.assign("transform", t.to_string());
+            }
+            element
+        }
+        // Transformed as a whole, like texts, as the label is rotated
+        Element::Dimension(dimension) => {
+            let mut path = SvgElement::new("path");
+            let segments = dimension.segments().into_iter();
+            let data =
+                segments.map(|[a, b]| format!("M{}", points(vec![a, b], Transform::IDENTITY)));
+            path.assign("d", data.collect::<Vec<_>>().join(" "));
+            paint(&mut path, "stroke", dimension.color);
+            path.assign("fill", "none");
+            path.assign("stroke-width", dimension.width);
+            let mut group = SvgElement::new("g");
+            group.append(path);
+            group.append(dimension.label_text());
+            if opacity < 1. {
+                group.assign("opacity", opacity);
+            }
+            if !t.is_identity() {
+                group.assign("transform", t.to_string());
+            }
+            group
+        }
+        Element::Layer(_) | Element::Instance(_) => return None,
+        // Never stored
+        Element::Ephemeral(_) => return None,
+        // Not printed
+        Element::Frame(_) => return None,
+    })
+}
+
+/// A `<clipPath>` of `clip` in document coordinates.
+fn clip_path(id: &str, clip: Clip, transform: Transform) -> SvgElement {
+    let corners: Vec<(Scalar, Scalar)> = match clip {
+        Clip::Rectangle {
+            position: (x, y),
+            size: (w, h),
+        } => vec![(x, y), (x + w, y), (x + w, y + h), (x, y + h)],
+        Clip::Ellipse {
+            position: (x, y),
+            radius: (rx, ry),
+        } => (0..SEGMENTS)
+            .map(|i| {
+                let angle = 2. * PI * i as Scalar / SEGMENTS as Scalar;
+                (x + rx * angle.cos(), y + ry * angle.sin())
+            })
+            .collect(),
+    };
+    let mut polygon = SvgElement::new("polygon");
+    polygon.assign("points", points(corners, transform));
+    let mut clip_path = SvgElement::new("clipPath");
+    clip_path.assign("id", id);
+    clip_path.append(polygon);
+    clip_path
+}
+
+impl Document {
+    /// The visible elements as plain SVG 1.1, for archiving notes so they do
+    /// not depend on software knowing this format.
+    ///
+    /// Layers and instances are flattened, transforms and layer opacities
+    /// are applied to the geometry and colors, pen strokes become filled
+    /// outlines and texts the glyph outlines from `fonts`, if it has their
+    /// font. No `svgnote:` attributes or metadata are written, so it cannot
+    /// be read back as the same document.
+    pub fn export_archival(&self, fonts: Option<&dyn FontLoader>) -> String {
+        let mut defs = SvgElement::new("defs");
+        let mut body = vec![];
+        let mut clips = 0;
+        for item in self.render_items() {
+            let mut element = match archival(&item, &self.assets, fonts) {
+                Some(element) => element,
+                None => continue,
+            };
+            for &(clip, transform) in &item.clips {
+                clips += 1;
+                let id = format!("clip{}", clips);
+                defs.append(clip_path(&id, clip, transform));
+                let mut group = SvgElement::new("g");
+                group.assign("clip-path", format!("url(#{})", id));
+                group.append(element);
+                element = group;
+            }
+            body.push(element);
+        }
+        let mut doc = svg::Document::new()
+            .set("xmlns:xlink", "http://www.w3.org/1999/xlink")
+            .set("version", "1.1")
+            .set("viewBox", (0, 0, 2000, 2000))
+            .set("width", "100mm")
+            .set("height", "100mm");
+        if clips > 0 {
+            doc = doc.add(defs);
+        }
+        if let Some(color) = self.metadata.background.filter(|color| color.a > 0) {
+            let mut rect = SvgElement::new("rect");
+            rect.assign("width", 2000);
+            rect.assign("height", 2000);
+            paint(&mut rect, "fill", color);
+            doc = doc.add(rect);
+        }
+        if let Some(ruling) = self.metadata.ruling {
+            let mut defs = SvgElement::new("defs");
+            defs.append(ruling.pattern());
+            doc = doc.add(defs).add(ruling.rect(BoundingBox {
+                min: (0., 0.),
+                max: (2000., 2000.),
+            }));
+        }
+        let doc = body.into_iter().fold(doc, |doc, element| doc.add(element));
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+             <!DOCTYPE svg PUBLIC \"-//W3C//DTD SVG 1.1//EN\" \"http://www.w3.org/Graphics/SVG/1.1/DTD/svg11.dtd\">\n{}",
+            doc
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, Text};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn export_archival() {
+        let line = Line::builder()
+            .color(Color::rgb(0xFF, 0, 0))
+            .point(0., 0., 1.)
+            .point(10., 0., 1.)
+            .transform(Transform::new(1., 0., 0., 1., 100., 0.))
+            .build()
+            .unwrap();
+        let mut layer = Layer::new("notes");
+        layer.opacity = 0.5;
+        layer.elements = vec![
+            Element::Line(line),
+            Element::Text(
+                Text::builder()
+                    .position(0., 50.)
+                    .content("a\nb")
+                    .build()
+                    .unwrap(),
+            ),
+        ];
+        let doc = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+
+        let archival = doc.export_archival(None);
+        assert!(!archival.contains("svgnote"));
+        assert!(!archival.contains("<g"));
+        assert!(archival.contains(r#"version="1.1""#));
+        assert!(archival.contains(r##"fill="#FF0000""##));
+        assert!(archival.contains(r#"fill-opacity="0.5""#));
+        assert!(archival.contains("M100,-0.5 110,-0.5"));
+        assert_eq!(archival.matches("<tspan").count(), 2);
+    }
+}
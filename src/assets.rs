@@ -0,0 +1,332 @@
+//! Binary data embedded in documents and notebooks, e.g. scanned images,
+//! stored once and referenced by id.
+//!
+//! [`FileRef`]s show an asset with a path of `asset:{id}`, see
+//! [`reference`]. In documents, assets are written once as data URIs, in a
+//! `<symbol>` the images `<use>`, in notebook containers as entries of their
+//! own, see [`Notebook::to_entries`](crate::notebook::Notebook::to_entries).
+
+use std::collections::btree_map::{self, BTreeMap};
+use std::hash::Hasher;
+
+use svg::node::element;
+use svg::node::Node;
+
+use crate::base64;
+use crate::elements::hash::Fnv1a;
+use crate::elements::{Attributes, Element, FileRef};
+use crate::DocumentError;
+
+/// The prefix of the paths of [`FileRef`]s showing an asset.
+pub const SCHEME: &str = "asset:";
+
+/// Used when the type of the data is not known.
+pub const UNKNOWN_MIME: &str = "application/octet-stream";
+
+/// The path of a [`FileRef`] showing the asset `id`.
+pub fn reference(id: &str) -> String {
+    format!("{}{}", SCHEME, id)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Asset {
+    pub data: Vec<u8>,
+    /// E.g. `image/png`.
+    pub mime: String,
+}
+
+impl Asset {
+    pub fn new(data: Vec<u8>, mime: impl Into<String>) -> Self {
+        Asset {
+            data,
+            mime: mime.into(),
+        }
+    }
+
+    /// The id [`Assets::add`] stores it under, derived from the type and
+    /// data so the same data is stored once.
+    pub fn content_id(&self) -> String {
+        let mut hasher = Fnv1a::default();
+        hasher.write(self.mime.as_bytes());
+        hasher.write_u8(0);
+        hasher.write(&self.data);
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// The asset as `data:{mime};base64,{data}`.
+    pub fn to_data_uri(&self) -> String {
+        format!("data:{};base64,{}", self.mime, base64::encode(&self.data))
+    }
+
+    /// Reads a base64 data URI, `None` for anything else.
+    pub fn from_data_uri(uri: &str) -> Option<Asset> {
+        let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+        let mime = header.strip_suffix(";base64")?;
+        Some(Asset::new(
+            base64::decode(data)?,
+            if mime.is_empty() { UNKNOWN_MIME } else { mime },
+        ))
+    }
+}
+
+/// The assets of a document or notebook by their id.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Assets {
+    assets: BTreeMap<String, Asset>,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Assets::default()
+    }
+
+    /// Stores `asset` by its [`Asset::content_id`], returning the id. Adding
+    /// the same data again does not store it twice.
+    pub fn add(&mut self, asset: Asset) -> String {
+        let id = asset.content_id();
+        self.assets.entry(id.clone()).or_insert(asset);
+        id
+    }
+
+    /// Stores `asset` under `id`, e.g. a file name, returning the one it
+    /// replaces.
+    pub fn insert(&mut self, id: impl Into<String>, asset: Asset) -> Option<Asset> {
+        self.assets.insert(id.into(), asset)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Asset> {
+        self.assets.get(id)
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Asset> {
+        self.assets.remove(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.assets.contains_key(id)
+    }
+
+    /// The assets ordered by id.
+    pub fn iter(&self) -> btree_map::Iter<String, Asset> {
+        self.assets.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+
+    /// Adds the assets of `other` that are not stored yet.
+    pub fn merge(&mut self, other: Assets) {
+        for (id, asset) in other.assets {
+            self.assets.entry(id).or_insert(asset);
+        }
+    }
+
+    /// Removes the assets that are not in `references`, see
+    /// [`Document::asset_references`](crate::Document::asset_references),
+    /// returning their ids.
+    pub fn retain_referenced(&mut self, references: &BTreeMap<String, usize>) -> Vec<String> {
+        let unused: Vec<String> = self
+            .assets
+            .keys()
+            .filter(|id| !references.contains_key(*id))
+            .cloned()
+            .collect();
+        for id in &unused {
+            self.assets.remove(id);
+        }
+        unused
+    }
+}
+
+impl<'a> IntoIterator for &'a Assets {
+    type Item = (&'a String, &'a Asset);
+    type IntoIter = btree_map::Iter<'a, String, Asset>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Counts the [`FileRef`]s showing each asset in `elements`, including
+/// those in layers.
+pub(crate) fn count_references(elements: &[Element], counts: &mut BTreeMap<String, usize>) {
+    for element in elements {
+        match element {
+            Element::FileRef(file) => {
+                if let Some(id) = file.asset() {
+                    *counts.entry(id.to_owned()).or_insert(0) += 1;
+                }
+            }
+            Element::Layer(layer) => count_references(&layer.elements, counts),
+            _ => {}
+        }
+    }
+}
+
+/// Reads the asset of an `<image>` with a data URI, or of a
+/// `<svgnote:asset>`, into `assets`, returning its id.
+///
+/// The id is taken from `svgnote:asset` or `id` if there is one, so assets
+/// stored under a name keep it.
+pub(crate) fn read(
+    assets: &mut Assets,
+    attributes: &Attributes<'_>,
+    id_attribute: &str,
+) -> Result<Option<String>, DocumentError> {
+    let href = match attributes
+        .get("href")
+        .or_else(|| attributes.get("xlink:href"))
+    {
+        Some(href) if href.starts_with("data:") => href,
+        _ => return Ok(None),
+    };
+    let asset = match Asset::from_data_uri(href) {
+        Some(asset) => asset,
+        // Percent-encoded data is kept as the path
+        None if !href.contains(";base64,") => return Ok(None),
+        None => {
+            return Err(DocumentError::InvalidAttribute(
+                "href".to_owned(),
+                href.chars().take(64).collect(),
+            ))
+        }
+    };
+    Ok(Some(match attributes.get(id_attribute) {
+        Some(id) => {
+            assets.insert(id.to_string(), asset);
+            id.to_string()
+        }
+        None => assets.add(asset),
+    }))
+}
+
+/// The id of the `<symbol>` holding the asset `id`.
+fn symbol_id(id: &str) -> String {
+    format!("svgnote-asset-{}", id)
+}
+
+/// A `<symbol>` for each asset in `references`, i.e. shown by a
+/// [`FileRef`], for the `<defs>`. The image fills the symbol, which is
+/// sized by each `<use>` of it.
+pub(crate) fn symbols(
+    assets: &Assets,
+    references: &BTreeMap<String, usize>,
+) -> Vec<element::Symbol> {
+    assets
+        .iter()
+        .filter(|(id, _)| references.contains_key(*id))
+        .map(|(id, asset)| {
+            let image = element::Image::new()
+                .set("width", "100%")
+                .set("height", "100%")
+                .set("href", asset.to_data_uri())
+                .set("svgnote:asset", id.clone());
+            element::Symbol::new()
+                .set("id", symbol_id(id))
+                .set("svgnote:asset", id.clone())
+                .add(image)
+        })
+        .collect()
+}
+
+/// A `<svgnote:asset>` for each asset not in `references`, i.e. not shown
+/// by a [`FileRef`], for the `<metadata>`.
+pub(crate) fn unreferenced(
+    assets: &Assets,
+    references: &BTreeMap<String, usize>,
+) -> Vec<element::Element> {
+    assets
+        .iter()
+        .filter(|(id, _)| !references.contains_key(*id))
+        .map(|(id, asset)| {
+            let mut node = element::Element::new("svgnote:asset");
+            node.assign("id", id.clone());
+            node.assign("href", asset.to_data_uri());
+            node
+        })
+        .collect()
+}
+
+impl FileRef {
+    /// The id of the asset it shows, if its path is an
+    /// [`asset:` reference](reference).
+    pub fn asset(&self) -> Option<&str> {
+        self.path.strip_prefix(SCHEME)
+    }
+
+    /// A `<use>` of the [symbol](symbols) of the asset it shows, if that is
+    /// in `assets`, instead of an `<image>`.
+    pub(crate) fn to_use(&self, assets: Option<&Assets>) -> Option<element::Use> {
+        let id = self
+            .asset()
+            .filter(|id| assets.map_or(false, |a| a.contains(id)))?;
+        let mut node = element::Use::new()
+            .set("href", format!("#{}", symbol_id(id)))
+            .set("x", self.position.0)
+            .set("y", self.position.1)
+            .set("width", self.size.0)
+            .set("height", self.size.1)
+            // Static
+            .set("svgnote:tool", "file")
+            .set("svgnote:asset", id);
+        self.properties.assign_to(&mut node);
+        Some(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::assets::{self, Asset};
+    use crate::elements::{Element, FileRef, Properties};
+    use crate::Document;
+
+    #[test]
+    fn assets() {
+        let png = Asset::new(vec![0x89, b'P', b'N', b'G', 0, 1, 2, 3], "image/png");
+        assert_eq!(Asset::from_data_uri(&png.to_data_uri()), Some(png.clone()));
+
+        let mut doc = Document::default();
+        let id = doc.assets.add(png.clone());
+        assert_eq!(doc.assets.add(png.clone()), id);
+        doc.assets
+            .insert("notes.ogg", Asset::new(vec![4, 5], "audio/ogg"));
+        let file = |x| {
+            Element::FileRef(FileRef {
+                path: assets::reference(&id),
+                position: (x, 0.),
+                size: (100., 100.),
+                properties: Properties::default(),
+            })
+        };
+        doc.elements = vec![file(0.), file(200.)];
+        assert_eq!(doc.asset_references()[&id], 2);
+
+        let string = doc.to_string();
+        assert_eq!(string.matches(&png.to_data_uri()).count(), 1);
+        assert_eq!(string.matches("<use").count(), 2);
+        let parsed = Document::from_str(&string).unwrap();
+        assert_eq!(parsed, doc);
+        assert_eq!(parsed.assets.len(), 2);
+
+        // Images of other editors are stored once as well
+        let svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg"><image href="{0}" width="1" height="1"/><image href="{0}" width="2" height="2"/></svg>"#,
+            png.to_data_uri()
+        );
+        let imported = Document::from_str(&svg).unwrap();
+        assert_eq!(imported.assets.len(), 1);
+        assert_eq!(imported.asset_references()[&id], 2);
+
+        assert_eq!(doc.remove_unused_assets(), vec!["notes.ogg".to_owned()]);
+        doc.elements.clear();
+        assert_eq!(doc.remove_unused_assets(), vec![id]);
+        assert!(doc.assets.is_empty());
+    }
+}
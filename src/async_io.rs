@@ -0,0 +1,77 @@
+//! Loading and saving documents without blocking, with the `AsyncRead` and
+//! `AsyncWrite` traits of `futures`, which adapters exist for in all
+//! runtimes.
+
+use std::convert::TryFrom;
+use std::io;
+use std::thread;
+
+use futures::channel::oneshot;
+use futures::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::{Document, DocumentError};
+
+impl Document {
+    /// Reads the whole of `reader` and parses it, in any of the encodings
+    /// supported by the `TryFrom<&[u8]>` implementation.
+    ///
+    /// The parsing runs on a thread of its own, so large documents do not
+    /// block the executor, whichever runtime it belongs to.
+    pub async fn from_async_reader<R: AsyncRead + Unpin>(
+        mut reader: R,
+    ) -> Result<Document, DocumentError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await?;
+        let (sender, receiver) = oneshot::channel();
+        thread::Builder::new()
+            .name("svg-notes-parse".to_owned())
+            .spawn(move || {
+                // The future may have been dropped in the meantime
+                let _ = sender.send(Document::try_from(&bytes[..]));
+            })?;
+        receiver.await.expect("parsing panicked")
+    }
+
+    /// Serializes the document into `writer` and flushes it.
+    pub async fn to_async_writer<W: AsyncWrite + Unpin>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.to_string().as_bytes()).await?;
+        writer.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Line, LinePoint, Properties};
+    use crate::{Document, DocumentError};
+
+    #[test]
+    fn async_io() {
+        let doc = Document {
+            elements: vec![Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        let mut file = Cursor::new(vec![]);
+        block_on(doc.to_async_writer(&mut file)).unwrap();
+        file.set_position(0);
+        assert_eq!(
+            block_on(Document::from_async_reader(&mut file)).unwrap(),
+            doc
+        );
+
+        let malformed = Cursor::new(vec![0xFF, 0xFE, 0x00]);
+        assert_matches!(
+            block_on(Document::from_async_reader(malformed)),
+            Err(DocumentError::Parse(_))
+        );
+    }
+}
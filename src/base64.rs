@@ -0,0 +1,66 @@
+//! The base64 of data URIs, e.g. of embedded fonts and assets.
+
+pub(crate) fn encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Standard or URL-safe base64, with or without padding. Whitespace is
+/// skipped, `None` for other characters.
+pub(crate) fn decode(s: &str) -> Option<Vec<u8>> {
+    let mut data = Vec::with_capacity(s.len() / 4 * 3);
+    let mut bits = 0u32;
+    let mut count = 0;
+    for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        bits = bits << 6 | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            data.push((bits >> count) as u8);
+        }
+    }
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::base64::{decode, encode};
+
+    #[test]
+    fn base64() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"font"), "Zm9udA==");
+        assert_eq!(encode(&[0xFF, 0xEE, 0xDD]), "/+7d");
+
+        let cases: [&[u8]; 5] = [b"", b"f", b"fo", b"font", &[0xFF, 0xEE, 0xDD]];
+        for &data in cases.iter() {
+            assert_eq!(decode(&encode(data)).as_deref(), Some(data));
+        }
+        assert_eq!(decode("_-7d\n"), Some(vec![0xFF, 0xEE, 0xDD]));
+        assert_eq!(decode("Zm9u!"), None);
+    }
+}
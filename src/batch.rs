@@ -0,0 +1,314 @@
+//! Applying an operation to many documents at once, e.g. recoloring,
+//! simplifying, migrating or exporting a whole directory of notes.
+
+use std::any::Any;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use thiserror::Error;
+
+use crate::save;
+use crate::{Document, DocumentError};
+
+/// Why a file could not be processed.
+#[derive(Error, Debug)]
+pub enum BatchError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("{0}")]
+    Document(#[from] DocumentError),
+    /// The operation panicked, with the message of the panic.
+    #[error("panicked: {0}")]
+    Panic(String),
+}
+
+/// Passed to the progress callback after each file.
+#[derive(Debug)]
+pub struct Progress<'a> {
+    /// The file that was just processed.
+    pub path: &'a Path,
+    pub result: &'a Result<(), BatchError>,
+    /// The number of files processed so far, including this one.
+    pub done: usize,
+    pub total: usize,
+}
+
+/// All `.svg` files in `dir` and its subdirectories, sorted.
+pub fn files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut dirs = vec![dir.to_owned()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                dirs.push(path);
+            } else if path.extension().map_or(false, |e| e == "svg") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Parses each of `paths`, applies `operation` and writes the document back,
+/// on `threads` threads.
+///
+/// `progress` is called on the calling thread after each file. Files that
+/// fail are left unchanged, and reported in the result, in the order of
+/// `paths`.
+pub fn map_files<F>(
+    paths: Vec<PathBuf>,
+    threads: usize,
+    operation: F,
+    progress: impl FnMut(Progress),
+) -> Vec<(PathBuf, Result<(), BatchError>)>
+where
+    F: Fn(&mut Document) + Send + Sync + 'static,
+{
+    run(paths, threads, progress, move |path| {
+        let mut doc = read(path)?;
+        operation(&mut doc);
+        write(path, doc.to_string().as_bytes())
+    })
+}
+
+/// Rewrites each of `paths` in the current format, repairing what
+/// [`Document::repair`] can, like [`map_files`].
+pub fn migrate_files(
+    paths: Vec<PathBuf>,
+    threads: usize,
+    progress: impl FnMut(Progress),
+) -> Vec<(PathBuf, Result<(), BatchError>)> {
+    run(paths, threads, progress, |path| {
+        let (doc, _) = Document::repair(&fs::read_to_string(path)?)?;
+        write(path, doc.to_string().as_bytes())
+    })
+}
+
+/// Writes what `export` makes of each of `paths` next to it, with the
+/// `extension`, e.g. `pdf` with a converter of the host, like
+/// [`map_files`]. The documents are left unchanged.
+pub fn export_files<F>(
+    paths: Vec<PathBuf>,
+    threads: usize,
+    extension: &str,
+    export: F,
+    progress: impl FnMut(Progress),
+) -> Vec<(PathBuf, Result<(), BatchError>)>
+where
+    F: Fn(&Document) -> Vec<u8> + Send + Sync + 'static,
+{
+    let extension = extension.to_owned();
+    run(paths, threads, progress, move |path| {
+        let data = export(&read(path)?);
+        write(&path.with_extension(&extension), &data)
+    })
+}
+
+fn run<F>(
+    paths: Vec<PathBuf>,
+    threads: usize,
+    mut progress: impl FnMut(Progress),
+    process: F,
+) -> Vec<(PathBuf, Result<(), BatchError>)>
+where
+    F: Fn(&Path) -> Result<(), BatchError> + Send + Sync + 'static,
+{
+    let total = paths.len();
+    let queue = Arc::new(Mutex::new(
+        paths.into_iter().enumerate().collect::<VecDeque<_>>(),
+    ));
+    let process = Arc::new(process);
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..threads.max(1).min(total) {
+        let queue = Arc::clone(&queue);
+        let process = Arc::clone(&process);
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            // The lock is only held while taking the next file
+            let next = queue.lock().unwrap().pop_front();
+            let (i, path) = match next {
+                Some(next) => next,
+                None => return,
+            };
+            // A panic only fails its file, instead of losing the others
+            // the thread would have processed
+            let result = panic::catch_unwind(AssertUnwindSafe(|| process(&path)))
+                .unwrap_or_else(|payload| Err(BatchError::Panic(message(payload))));
+            if sender.send((i, path, result)).is_err() {
+                return;
+            }
+        });
+    }
+    drop(sender);
+
+    let mut results = Vec::with_capacity(total);
+    for (i, path, result) in receiver {
+        progress(Progress {
+            path: &path,
+            result: &result,
+            done: results.len() + 1,
+            total,
+        });
+        results.push((i, path, result));
+    }
+    results.sort_by_key(|&(i, _, _)| i);
+    results
+        .into_iter()
+        .map(|(_, path, result)| (path, result))
+        .collect()
+}
+
+/// The message of a panic, empty if it is neither a `String` nor a `&str`.
+fn message(payload: Box<dyn Any + Send>) -> String {
+    match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => payload
+            .downcast_ref::<&str>()
+            .map_or_else(String::new, |&message| message.to_owned()),
+    }
+}
+
+/// [`map_files`] for all [`files`] in `dir`.
+pub fn map_dir<F>(
+    dir: &Path,
+    threads: usize,
+    operation: F,
+    progress: impl FnMut(Progress),
+) -> io::Result<Vec<(PathBuf, Result<(), BatchError>)>>
+where
+    F: Fn(&mut Document) + Send + Sync + 'static,
+{
+    Ok(map_files(files(dir)?, threads, operation, progress))
+}
+
+fn read(path: &Path) -> Result<Document, BatchError> {
+    Ok(Document::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<(), BatchError> {
+    Ok(save::write_atomic(path, data, 0)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::batch::{self, BatchError};
+    use crate::colors::{Color, ColorMap};
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::Document;
+
+    #[test]
+    fn map_dir() {
+        let dir = std::env::temp_dir().join(format!("svg_notes_batch_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        let doc = Document {
+            elements: vec![Element::Line(Line {
+                color: Color::rgb(255, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        fs::write(dir.join("a.svg"), doc.to_string()).unwrap();
+        fs::write(dir.join("nested/b.svg"), doc.to_string()).unwrap();
+        fs::write(
+            dir.join("broken.svg"),
+            r#"<svg><polyline points="0,0 1,1"/></svg>"#,
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let map = ColorMap {
+            replacements: vec![(Color::rgb(255, 0, 0), Color::rgb(0, 0, 255))],
+            fallback: None,
+        };
+        let mut progress = vec![];
+        let results = batch::map_dir(
+            &dir,
+            2,
+            move |doc| doc.remap_colors(&map),
+            |p| progress.push((p.done, p.total)),
+        )
+        .unwrap();
+        assert_eq!(progress, vec![(1, 3), (2, 3), (3, 3)]);
+        let failed: Vec<_> = results
+            .iter()
+            .filter(|(_, r)| r.is_err())
+            .map(|(path, _)| path.file_name().unwrap())
+            .collect();
+        assert_eq!(failed, vec!["broken.svg"]);
+
+        let recolored: Document = fs::read_to_string(dir.join("nested/b.svg"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        match &recolored.elements[..] {
+            [Element::Line(line)] => assert_eq!(line.color, Color::rgb(0, 0, 255)),
+            elements => panic!("{:?}", elements),
+        }
+
+        let paths = batch::files(&dir).unwrap();
+        let results = batch::export_files(
+            paths,
+            2,
+            "txt",
+            |doc| doc.elements.len().to_string().into_bytes(),
+            |_| {},
+        );
+        assert_eq!(results.iter().filter(|(_, r)| r.is_err()).count(), 1);
+        assert_eq!(fs::read(dir.join("nested/b.txt")).unwrap(), b"1");
+
+        let results = batch::migrate_files(vec![dir.join("broken.svg")], 1, |_| {});
+        assert!(results[0].1.is_ok());
+        let migrated = fs::read_to_string(dir.join("broken.svg")).unwrap();
+        assert!(migrated.parse::<Document>().is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn panic() {
+        let dir = std::env::temp_dir().join(format!("svg_notes_panic_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut paths = vec![];
+        for name in ["a.svg", "b.svg", "c.svg"] {
+            let mut doc = Document::default();
+            if name == "b.svg" {
+                doc.elements.push(Element::Layer(Layer::new("b")));
+            }
+            fs::write(dir.join(name), doc.to_string()).unwrap();
+            paths.push(dir.join(name));
+        }
+        let mut done = 0;
+        let results = batch::export_files(
+            paths,
+            1,
+            "txt",
+            |doc| {
+                if !doc.elements.is_empty() {
+                    panic!("a layer");
+                }
+                vec![]
+            },
+            |_| done += 1,
+        );
+        assert_eq!(done, 3);
+        assert_eq!(results.len(), 3);
+        assert!(results[0].1.is_ok() && results[2].1.is_ok());
+        assert_matches!(&results[1].1, Err(BatchError::Panic(message)) if message == "a layer");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
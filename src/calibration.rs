@@ -0,0 +1,115 @@
+//! Correcting the input of pens per device, see [`InputCalibration`].
+
+use crate::elements::LinePoint;
+use crate::geometry::distance;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+
+/// Corrects the raw input of a pen before it becomes a stroke, e.g. for
+/// cheap digitizers that are offset or report little pressure, see
+/// [`LineBuilder::calibration`](crate::elements::LineBuilder::calibration).
+///
+/// Stored in the [`Metadata`](crate::metadata::Metadata) of a document as
+/// `<svgnote:calibration>`, or kept by the app per device.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct InputCalibration {
+    /// From the coordinates reported by the device to the ones of the
+    /// stroke.
+    pub transform: Transform,
+    /// The raw pressures mapped to `0` and `1`, the ones outside are
+    /// clamped.
+    pub pressure: (Scalar, Scalar),
+    /// Points closer than this to the point kept before them are dropped,
+    /// e.g. the jitter while the pen rests.
+    pub deadband: Scalar,
+}
+
+impl Default for InputCalibration {
+    fn default() -> Self {
+        InputCalibration {
+            transform: Transform::IDENTITY,
+            pressure: (0., 1.),
+            deadband: 0.,
+        }
+    }
+}
+
+impl InputCalibration {
+    /// Whether it can be applied, i.e. the pressure range is not empty and
+    /// the deadband not negative.
+    pub fn is_valid(&self) -> bool {
+        let (min, max) = self.pressure;
+        min.is_finite() && max.is_finite() && min < max && self.deadband >= 0.
+    }
+
+    /// The raw point with the transform and pressure range applied.
+    pub fn correct(&self, x: Scalar, y: Scalar, pressure: Scalar) -> LinePoint {
+        let (x, y) = self.transform.apply((x, y));
+        let (min, max) = self.pressure;
+        LinePoint(x, y, ((pressure - min) / (max - min)).max(0.).min(1.))
+    }
+
+    /// Corrects the raw `points`, dropping those in the deadband with their
+    /// timestamps. The first point is always kept.
+    pub(crate) fn apply(&self, points: &mut Vec<LinePoint>, timestamps: &mut Vec<u64>) {
+        let mut kept: Vec<LinePoint> = vec![];
+        let mut kept_timestamps = vec![];
+        for (i, &LinePoint(x, y, pressure)) in points.iter().enumerate() {
+            let point = self.correct(x, y, pressure);
+            if let Some(last) = kept.last() {
+                if distance((last.0, last.1), (point.0, point.1)) < self.deadband {
+                    continue;
+                }
+            }
+            kept.push(point);
+            kept_timestamps.extend(timestamps.get(i));
+        }
+        *points = kept;
+        *timestamps = kept_timestamps;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::calibration::InputCalibration;
+    use crate::elements::{BuildError, LinePoint};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn calibration() {
+        let mut doc = Document::default();
+        doc.metadata.calibration = Some(InputCalibration {
+            transform: Transform::new(2., 0., 0., 2., 10., 0.),
+            pressure: (0.2, 0.6),
+            deadband: 1.,
+        });
+        let line = doc
+            .line_builder()
+            .timed_point(0., 0., 0.1, 0)
+            .timed_point(0.2, 0., 0.4, 1)
+            .timed_point(1., 0., 0.9, 2)
+            .build()
+            .unwrap();
+        assert_eq!(
+            line.points,
+            vec![LinePoint(10., 0., 0.), LinePoint(12., 0., 1.)]
+        );
+        assert_eq!(line.timestamps, vec![0, 2]);
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+
+        let inverted = InputCalibration {
+            pressure: (1., 0.),
+            ..Default::default()
+        };
+        assert_matches!(
+            doc.line_builder()
+                .calibration(inverted)
+                .point(0., 0., 1.)
+                .build(),
+            Err(BuildError::Invalid("calibration", _))
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Detecting corrupted files, e.g. truncated by a full disk.
+
+/// The CRC-32 (as used by zip and PNG) of `data`.
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::checksum::crc32;
+    use crate::colors::Color;
+    use crate::elements::{Element, Line, LinePoint, Properties};
+    use crate::options::{ParseOptions, SerializeOptions};
+    use crate::{Document, DocumentError};
+
+    #[test]
+    fn checksum() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+
+        let doc = Document {
+            elements: vec![Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        // Opt-in, as verifying serializes the document again
+        assert!(!doc.to_string().contains("checksum"));
+        let string = doc.to_string_with(&SerializeOptions {
+            checksum: true,
+            ..Default::default()
+        });
+        let attribute = format!(r#" svgnote:checksum="{:08x}""#, doc.checksum());
+        assert!(string.contains(&attribute));
+        let verify = ParseOptions {
+            verify_checksum: true,
+            ..Default::default()
+        };
+        let parse = |s: &str| Document::from_str_with(s, &verify).map(|(doc, _)| doc);
+        assert_eq!(parse(&string).unwrap(), doc);
+
+        let truncated = &string[..string.find("<path").unwrap()];
+        assert_matches!(parse(truncated), Err(DocumentError::Corrupted));
+        assert_matches!(
+            parse(&string.replace("1,1,1", "1,7,1")),
+            Err(DocumentError::Corrupted)
+        );
+        assert!(Document::from_str(&string.replace("1,1,1", "1,7,1")).is_ok());
+        // Files without a checksum are not verified
+        let unchecked = string.replace(&attribute, "");
+        assert!(!unchecked.contains("checksum"));
+        assert_eq!(parse(&unchecked).unwrap(), doc);
+    }
+}
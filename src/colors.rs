@@ -1,7 +1,9 @@
 use std::fmt::Display;
 use std::str::FromStr;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -44,6 +46,40 @@ impl Color {
         self
     }
 
+    /// Multiplies the alpha channel by `opacity`, e.g. the opacity of
+    /// the layer this color is used in.
+    pub fn faded(mut self, opacity: f32) -> Self {
+        self.a = f2u(self.opacity() * opacity);
+        self
+    }
+
+    /// Flips the lightness (in HSL) while keeping hue and saturation, e.g.
+    /// black becomes white and dark blue becomes light blue.
+    pub fn inverted_lightness(self) -> Self {
+        let max = self.r.max(self.g).max(self.b) as i16;
+        let min = self.r.min(self.g).min(self.b) as i16;
+        // Shifting all channels keeps the chroma, and with it hue and
+        // saturation, moving the lightness `(max + min) / 2` to its inverse
+        let shift = |c: u8| (c as i16 + 255 - max - min) as u8;
+        Color::rgba(shift(self.r), shift(self.g), shift(self.b), self.a)
+    }
+
+    /// The color `t` of the way from `self` to `other`, channel by channel
+    /// including alpha.
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        let lerp = |a: u8, b: u8| {
+            (a as f32 + (b as f32 - a as f32) * t)
+                .round()
+                .clamp(0., 255.) as u8
+        };
+        Color::rgba(
+            lerp(self.r, other.r),
+            lerp(self.g, other.g),
+            lerp(self.b, other.b),
+            lerp(self.a, other.a),
+        )
+    }
+
     pub fn opacity(&self) -> f32 {
         u2f(self.a)
     }
@@ -54,29 +90,81 @@ impl Color {
     }
 }
 
+/// Replaces colors, e.g. to show notes on a dark background.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ColorMap {
+    /// Colors replaced exactly, compared without the alpha channel, which is
+    /// kept.
+    pub replacements: Vec<(Color, Color)>,
+    /// Applied to all colors without a replacement.
+    pub fallback: Option<fn(Color) -> Color>,
+}
+
+impl ColorMap {
+    /// Inverts the lightness of all colors, for showing notes written on a
+    /// light background on a dark one.
+    pub fn dark_background() -> Self {
+        ColorMap {
+            replacements: vec![],
+            fallback: Some(Color::inverted_lightness),
+        }
+    }
+
+    pub fn map(&self, color: Color) -> Color {
+        let rgb = |c: Color| (c.r, c.g, c.b);
+        match self
+            .replacements
+            .iter()
+            .find(|(c, _)| rgb(*c) == rgb(color))
+        {
+            Some((_, replacement)) => Color {
+                a: color.a,
+                ..*replacement
+            },
+            None => self.fallback.map_or(color, |f| f(color)),
+        }
+    }
+}
+
+/// Why a string is not a [`Color`].
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum ColorParseError {
+    /// Only hex colors are supported, not e.g. `red` or `rgb(255, 0, 0)`.
+    #[error("Unsupported color `{0}`, expected a hex color like `#FF0000`")]
+    Notation(String),
+    #[error("Expected 3, 4, 6 or 8 hex digits, found {0}")]
+    Length(usize),
+    #[error("Invalid hex digit `{0}`")]
+    Digit(char),
+}
+
 impl FromStr for Color {
-    type Err = ();
+    type Err = ColorParseError;
 
+    /// Parses `#RGB`, `#RGBA`, `#RRGGBB` and `#RRGGBBAA`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.len() {
-            4 => Self::from_str(&(s.to_owned() + "F")),
-            5 => Self::from_str(&{
-                let mut r = "#".to_owned();
-                &s[1..5].chars().for_each(|c| {
-                    r.push(c);
-                    r.push(c);
-                });
-                r
-            }),
-            7 => Self::from_str(&(s.to_owned() + "FF")),
-            9 => Ok(Color {
-                r: (u8::from_str_radix(&s[1..3], 16).map_err(|_| ())?),
-                g: (u8::from_str_radix(&s[3..5], 16).map_err(|_| ())?),
-                b: (u8::from_str_radix(&s[5..7], 16).map_err(|_| ())?),
-                a: (u8::from_str_radix(&s[7..9], 16).map_err(|_| ())?),
-            }),
-            _ => Err(()),
+        let digits = s
+            .strip_prefix('#')
+            .ok_or_else(|| ColorParseError::Notation(s.to_owned()))?;
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::Digit(c));
         }
+        let digits: Vec<u8> = digits
+            .chars()
+            .filter_map(|c| c.to_digit(16))
+            .map(|d| d as u8)
+            .collect();
+        let channels: Vec<u8> = match digits.len() {
+            3 | 4 => digits.iter().map(|d| d * 0x11).collect(),
+            6 | 8 => digits.chunks(2).map(|d| d[0] * 0x10 + d[1]).collect(),
+            n => return Err(ColorParseError::Length(n)),
+        };
+        Ok(Color {
+            r: channels[0],
+            g: channels[1],
+            b: channels[2],
+            a: channels.get(3).copied().unwrap_or(0xFF),
+        })
     }
 }
 
@@ -92,9 +180,50 @@ impl Display for Color {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
     use std::str::FromStr;
 
-    use crate::colors::Color;
+    use crate::colors::{Color, ColorMap, ColorParseError};
+
+    #[test]
+    fn color_map() {
+        let dark = ColorMap::dark_background();
+        assert_eq!(dark.map(Color::rgb(0, 0, 0)), Color::rgb(255, 255, 255));
+        assert_eq!(
+            dark.map(Color::rgba(0, 0, 0x80, 0x40)),
+            Color::rgba(0x7F, 0x7F, 0xFF, 0x40)
+        );
+        assert_eq!(
+            dark.map(Color::rgb(0x80, 0x80, 0x80)),
+            Color::rgb(0x7F, 0x7F, 0x7F)
+        );
+
+        let map = ColorMap {
+            replacements: vec![(Color::rgb(0, 0, 0), Color::rgb(0xFF, 0xFF, 0))],
+            ..ColorMap::dark_background()
+        };
+        assert_eq!(
+            map.map(Color::rgba(0, 0, 0, 0x10)),
+            Color::rgba(0xFF, 0xFF, 0, 0x10)
+        );
+        assert_eq!(map.map(Color::rgb(0xFF, 0xFF, 0xFF)), Color::rgb(0, 0, 0));
+        assert_eq!(
+            ColorMap::default().map(Color::rgb(1, 2, 3)),
+            Color::rgb(1, 2, 3)
+        );
+    }
+
+    #[test]
+    fn color_as_key() {
+        let colors: HashSet<Color> = vec![
+            Color::rgb(0, 0, 0),
+            Color::rgba(0, 0, 0, 0xFF),
+            Color::rgba(0, 0, 0, 0),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(colors.len(), 2);
+    }
 
     #[test]
     fn encode_color() {
@@ -194,5 +323,12 @@ mod tests {
                 a: 0x54
             })
         );
+
+        assert_eq!(
+            Color::from_str("red"),
+            Err(ColorParseError::Notation("red".to_owned()))
+        );
+        assert_eq!(Color::from_str("#12345"), Err(ColorParseError::Length(5)));
+        assert_eq!(Color::from_str("#00G"), Err(ColorParseError::Digit('G')));
     }
 }
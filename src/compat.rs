@@ -0,0 +1,244 @@
+//! Predicting where documents render differently outside of this library,
+//! see [`Document::check_compat`].
+
+use std::slice;
+
+use crate::elements::{BlendMode, Element, Symbols};
+use crate::options::SerializeOptions;
+use crate::render::render_items_with;
+use crate::Document;
+
+/// The renderers a document is checked for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CompatTarget {
+    /// Current Chrome, Firefox and Safari, showing the document on its own
+    /// or as `<img>`.
+    Browsers,
+    /// Renderers implementing only SVG 1.1, e.g. older Inkscape, librsvg and
+    /// print workflows.
+    Svg11,
+}
+
+/// A construct that renders differently, see [`Document::check_compat`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CompatIssue {
+    /// Pen strokes with varying pressure, which other renderers draw with
+    /// the full width.
+    Pressure,
+    /// Texts in the font family, which is neither embedded nor outlined, so
+    /// they are set in whatever font the viewer has.
+    SystemFont(String),
+    /// Embedded fonts, which are CSS `@font-face` rules SVG 1.1 does not
+    /// have.
+    EmbeddedFont(String),
+    /// Images and instances, which are written with `href` that SVG 1.1
+    /// renderers only read as `xlink:href`.
+    Href,
+    /// Images of files outside the document, which browsers do not load for
+    /// documents shown as `<img>`.
+    ExternalImage(String),
+    /// Non-scaling strokes, which SVG 1.1 does not have, so they scale with
+    /// the document.
+    VectorEffect,
+    /// Layers blended other than normally, which SVG 1.1 renderers draw
+    /// normally, e.g. highlighters covering the text below.
+    BlendMode(BlendMode),
+}
+
+/// An issue and where it occurs.
+#[derive(Debug, PartialEq, Clone)]
+pub struct CompatWarning {
+    pub issue: CompatIssue,
+    /// The indices of the affected elements in
+    /// [`Document::render_items`], empty for issues of the whole document.
+    pub items: Vec<usize>,
+}
+
+fn warn(warnings: &mut Vec<CompatWarning>, issue: CompatIssue, item: Option<usize>) {
+    let index = match warnings.iter().position(|w| w.issue == issue) {
+        Some(index) => index,
+        None => {
+            warnings.push(CompatWarning {
+                issue,
+                items: vec![],
+            });
+            warnings.len() - 1
+        }
+    };
+    warnings[index].items.extend(item);
+}
+
+/// Warns of the blend modes of the layers in `elements`, whose render items
+/// start at `start`.
+fn blend_modes(
+    elements: &[Element],
+    symbols: &Symbols,
+    mut start: usize,
+    warnings: &mut Vec<CompatWarning>,
+) {
+    for element in elements {
+        let count = render_items_with(slice::from_ref(element), symbols, None).len();
+        if let Element::Layer(layer) = element {
+            if layer.blend != BlendMode::Normal {
+                for i in start..start + count {
+                    warn(warnings, CompatIssue::BlendMode(layer.blend), Some(i));
+                }
+            }
+            // Hidden layers have no render items
+            if count > 0 {
+                blend_modes(&layer.elements, symbols, start, warnings);
+            }
+        }
+        start += count;
+    }
+}
+
+fn has_instances(elements: &[Element]) -> bool {
+    elements.iter().any(|element| match element {
+        Element::Instance(_) => true,
+        Element::Layer(layer) => has_instances(&layer.elements),
+        _ => false,
+    })
+}
+
+impl Document {
+    /// The constructs of the document that `target` renders differently
+    /// than this library, when written with the default
+    /// [`SerializeOptions`], e.g. to warn before exporting.
+    pub fn check_compat(&self, target: CompatTarget) -> Vec<CompatWarning> {
+        self.check_compat_with(target, &SerializeOptions::default())
+    }
+
+    /// Like [`Document::check_compat`], for the document written with
+    /// `options`, e.g. with embedded or outlined fonts.
+    pub fn check_compat_with(
+        &self,
+        target: CompatTarget,
+        options: &SerializeOptions,
+    ) -> Vec<CompatWarning> {
+        let svg11 = target == CompatTarget::Svg11;
+        let mut warnings = vec![];
+        for (i, item) in self.render_items().iter().enumerate() {
+            let properties = item.element.properties();
+            if svg11 && (properties.non_scaling_stroke || options.non_scaling_strokes) {
+                warn(&mut warnings, CompatIssue::VectorEffect, Some(i));
+            }
+            match item.element {
+                Element::Line(line) if line.points.iter().any(|p| p.2 != 1.) => {
+                    warn(&mut warnings, CompatIssue::Pressure, Some(i))
+                }
+                Element::Text(text) => {
+                    let family = &text.font.family;
+                    let line = text.content.split('\n').next().unwrap_or_default();
+                    let outlined = options
+                        .outline_text
+                        .as_ref()
+                        .and_then(|loader| loader.outline(line, &text.font, text.position))
+                        .is_some();
+                    if outlined {
+                        continue;
+                    }
+                    let issue = if !options.fonts.contains_key(family) {
+                        CompatIssue::SystemFont(family.clone())
+                    } else if svg11 {
+                        CompatIssue::EmbeddedFont(family.clone())
+                    } else {
+                        continue;
+                    };
+                    warn(&mut warnings, issue, Some(i))
+                }
+                Element::FileRef(file) => {
+                    if svg11 {
+                        warn(&mut warnings, CompatIssue::Href, Some(i));
+                    }
+                    if !svg11 && file.asset().is_none() && !file.path.starts_with("data:") {
+                        let issue = CompatIssue::ExternalImage(file.path.clone());
+                        warn(&mut warnings, issue, Some(i));
+                    }
+                }
+                _ => {}
+            }
+        }
+        if svg11 {
+            blend_modes(&self.elements, &self.symbols, 0, &mut warnings);
+        }
+        if svg11 && has_instances(&self.elements) {
+            warn(&mut warnings, CompatIssue::Href, None);
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compat::{CompatIssue, CompatTarget, CompatWarning};
+    use crate::elements::{BlendMode, Element, FileRef, Layer, Line, Text};
+    use crate::Document;
+
+    #[test]
+    fn check_compat() {
+        let doc = Document {
+            elements: vec![
+                Element::Line(
+                    Line::builder()
+                        .point(0., 0., 1.)
+                        .point(10., 0., 0.5)
+                        .build()
+                        .unwrap(),
+                ),
+                Element::Text(
+                    Text::builder()
+                        .position(0., 50.)
+                        .content("Notes")
+                        .build()
+                        .unwrap(),
+                ),
+                Element::FileRef(
+                    FileRef::builder()
+                        .path("photo.png")
+                        .size(100., 100.)
+                        .build()
+                        .unwrap(),
+                ),
+                Element::Layer(
+                    Layer::builder("highlights")
+                        .blend(BlendMode::Multiply)
+                        .element(Element::Line(
+                            Line::builder().point(0., 0., 1.).build().unwrap(),
+                        ))
+                        .build()
+                        .unwrap(),
+                ),
+            ],
+            ..Default::default()
+        };
+        let issues = |target| -> Vec<CompatIssue> {
+            doc.check_compat(target)
+                .into_iter()
+                .map(|warning| warning.issue)
+                .collect()
+        };
+        assert_eq!(
+            issues(CompatTarget::Browsers),
+            vec![
+                CompatIssue::Pressure,
+                CompatIssue::SystemFont("sans-serif".to_owned()),
+                CompatIssue::ExternalImage("photo.png".to_owned()),
+            ]
+        );
+        assert_eq!(
+            doc.check_compat(CompatTarget::Svg11)[2],
+            CompatWarning {
+                issue: CompatIssue::Href,
+                items: vec![2],
+            }
+        );
+        assert_eq!(
+            doc.check_compat(CompatTarget::Svg11)[3],
+            CompatWarning {
+                issue: CompatIssue::BlendMode(BlendMode::Multiply),
+                items: vec![3],
+            }
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Exporting the points of pen strokes as CSV, e.g. for analyzing
+//! handwriting without parsing SVG.
+
+use std::fmt::Write;
+
+use crate::elements::{Element, Line};
+use crate::render::RenderItem;
+use crate::transform::Transform;
+
+/// The columns written by [`Line::to_csv`] and
+/// [`Document::to_csv`](crate::Document::to_csv).
+pub const HEADER: &str = "stroke,x,y,pressure,timestamp";
+
+/// A row per point of `line`, mapped by `transform`, with `stroke` in the
+/// first column.
+///
+/// The timestamp is left empty if the line has none.
+pub(crate) fn rows(line: &Line, stroke: usize, transform: Transform, csv: &mut String) {
+    for (i, point) in line.points.iter().enumerate() {
+        let (x, y) = transform.apply((point.0, point.1));
+        let timestamp = line
+            .timestamps
+            .get(i)
+            .map(|t| t.to_string())
+            .unwrap_or_default();
+        writeln!(csv, "{},{},{},{},{}", stroke, x, y, point.2, timestamp)
+            .expect("writing to a String does not fail");
+    }
+}
+
+/// The points of all [`Line`]s in `items` in document coordinates, the
+/// stroke is the index of the item.
+pub(crate) fn document(items: &[RenderItem]) -> String {
+    let mut csv = format!("{}\n", HEADER);
+    for (i, item) in items.iter().enumerate() {
+        if let Element::Line(line) = item.element {
+            rows(line, i, item.transform, &mut csv);
+        }
+    }
+    csv
+}
+
+impl Line {
+    /// The points as CSV with the [`HEADER`], in the line's own coordinates,
+    /// with `stroke` in the first column.
+    pub fn to_csv(&self, stroke: usize) -> String {
+        let mut csv = format!("{}\n", HEADER);
+        rows(self, stroke, Transform::IDENTITY, &mut csv);
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn csv() {
+        let line = Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![LinePoint(0., 1., 0.5), LinePoint(2., 3., 1.)],
+            timestamps: vec![1000, 1016],
+            properties: Properties::default(),
+        };
+        assert_eq!(
+            line.to_csv(7),
+            "stroke,x,y,pressure,timestamp\n7,0,1,0.5,1000\n7,2,3,1,1016\n"
+        );
+
+        let mut layer = Element::Layer(Layer::new("moved"));
+        if let Element::Layer(layer) = &mut layer {
+            layer.elements.push(Element::Line(Line {
+                timestamps: vec![],
+                ..line
+            }));
+        }
+        Transform::translate(10., 0.).apply_to(&mut layer);
+        let doc = Document {
+            elements: vec![layer],
+            ..Default::default()
+        };
+        assert_eq!(
+            doc.to_csv(),
+            "stroke,x,y,pressure,timestamp\n0,10,1,0.5,\n0,12,3,1,\n"
+        );
+    }
+}
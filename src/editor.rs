@@ -0,0 +1,444 @@
+//! Editing a document while notifying subscribers of each change, e.g. to
+//! keep thumbnails, search indexes or sync queues up to date.
+
+use std::collections::HashSet;
+use std::slice;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+
+use crate::elements::{Element, StoredBounds};
+use crate::ids::{self, ElementId, IdGenerator};
+use crate::Document;
+
+/// Where an element is: its index in the document, followed by the indices
+/// in the nested layers.
+pub type ElementPath = Vec<usize>;
+
+/// A change made through an [`Editor`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum ChangeEvent {
+    ElementAdded(ElementPath),
+    /// The element that was at the path.
+    ElementRemoved(ElementPath, Element),
+    ElementModified(ElementPath),
+    /// The element was removed at `from`, then inserted at `to`, e.g. when
+    /// layers are reordered.
+    ElementMoved {
+        from: ElementPath,
+        to: ElementPath,
+    },
+    /// Anything may have changed, see [`Editor::edit`].
+    DocumentChanged,
+}
+
+/// Returned by [`Editor::subscribe`], to unsubscribe.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Subscription(usize);
+
+/// Owns a document and notifies the subscribers after each change. Inserted
+/// elements are attributed to the [author](Editor::set_author), if set.
+#[derive(Default)]
+pub struct Editor {
+    document: Document,
+    /// Each returning whether it stays subscribed.
+    subscribers: Vec<(Subscription, Box<dyn FnMut(&ChangeEvent) -> bool>)>,
+    next_subscription: usize,
+    ids: Option<Arc<dyn IdGenerator + Send + Sync>>,
+    /// The ids given so far while there is a generator, so the ids of
+    /// removed elements are not given again.
+    taken: HashSet<ElementId>,
+    author: Option<String>,
+}
+
+impl Editor {
+    pub fn new(document: Document) -> Self {
+        Editor {
+            document,
+            ..Default::default()
+        }
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn into_document(self) -> Document {
+        self.document
+    }
+
+    /// Calls `callback` after each change, until it is unsubscribed.
+    pub fn subscribe(&mut self, mut callback: impl FnMut(&ChangeEvent) + 'static) -> Subscription {
+        self.subscribe_while(move |event| {
+            callback(event);
+            true
+        })
+    }
+
+    /// A channel receiving the changes, e.g. on another thread. It is
+    /// unsubscribed at the first change after the receiver was dropped.
+    pub fn subscribe_channel(&mut self) -> (Subscription, Receiver<ChangeEvent>) {
+        let (sender, receiver) = mpsc::channel();
+        let subscription = self.subscribe_while(move |event| sender.send(event.clone()).is_ok());
+        (subscription, receiver)
+    }
+
+    fn subscribe_while(
+        &mut self,
+        callback: impl FnMut(&ChangeEvent) -> bool + 'static,
+    ) -> Subscription {
+        let subscription = Subscription(self.next_subscription);
+        self.next_subscription += 1;
+        self.subscribers.push((subscription, Box::new(callback)));
+        subscription
+    }
+
+    /// Returns whether it was subscribed.
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let len = self.subscribers.len();
+        self.subscribers.retain(|(s, _)| *s != subscription);
+        self.subscribers.len() != len
+    }
+
+    /// Gives the elements inserted without an id one from `generator`,
+    /// also those in inserted layers.
+    pub fn set_id_generator(&mut self, generator: Option<Arc<dyn IdGenerator + Send + Sync>>) {
+        self.taken = match generator {
+            Some(_) => ids::taken(&self.document),
+            None => HashSet::new(),
+        };
+        self.ids = generator;
+    }
+
+    /// Gives the elements inserted without an author `author`, also those
+    /// in inserted layers.
+    pub fn set_author(&mut self, author: Option<String>) {
+        self.author = author;
+    }
+
+    /// `element` with the author and ids from the generator, if there are.
+    fn inserted(&mut self, mut element: Element) -> Element {
+        if let Some(author) = &self.author {
+            attribute(&mut element, author);
+        }
+        if let Some(generator) = &self.ids {
+            ids::collect(slice::from_ref(&element), &mut self.taken);
+            ids::assign(&mut element, &**generator, &mut self.taken);
+        }
+        element
+    }
+
+    fn notify(&mut self, event: ChangeEvent) {
+        let mut i = 0;
+        while i < self.subscribers.len() {
+            if (self.subscribers[i].1)(&event) {
+                i += 1;
+            } else {
+                self.subscribers.remove(i);
+            }
+        }
+    }
+
+    /// The element at `path`, if there is one.
+    pub fn get(&self, path: &[usize]) -> Option<&Element> {
+        let (&last, layers) = path.split_last()?;
+        let mut elements = &self.document.elements;
+        for &i in layers {
+            match elements.get(i)? {
+                Element::Layer(layer) => elements = &layer.elements,
+                _ => return None,
+            }
+        }
+        elements.get(last)
+    }
+
+    /// The path of the element with the id, if there is one.
+    pub fn find(&self, id: &str) -> Option<ElementPath> {
+        fn find(elements: &[Element], id: &str) -> Option<ElementPath> {
+            elements.iter().enumerate().find_map(|(i, element)| {
+                if element.properties().id.as_ref().map(ElementId::as_str) == Some(id) {
+                    return Some(vec![i]);
+                }
+                match element {
+                    Element::Layer(layer) => {
+                        let mut path = find(&layer.elements, id)?;
+                        path.insert(0, i);
+                        Some(path)
+                    }
+                    _ => None,
+                }
+            })
+        }
+        find(&self.document.elements, id)
+    }
+
+    /// Inserts `element` at `path`, shifting the following elements.
+    ///
+    /// Returns `element` if the parent layer does not exist, with its id if
+    /// it was given one.
+    pub fn insert(&mut self, path: &[usize], element: Element) -> Result<(), Element> {
+        let element = self.inserted(element);
+        match parent_mut(&mut self.document.elements, path) {
+            Some((elements, i)) if i <= elements.len() => elements.insert(i, element),
+            _ => return Err(element),
+        }
+        clear_bounds(&mut self.document.elements, &path[..path.len() - 1]);
+        self.notify(ChangeEvent::ElementAdded(path.to_vec()));
+        Ok(())
+    }
+
+    /// Appends `element` to the document.
+    pub fn push(&mut self, element: Element) {
+        let element = self.inserted(element);
+        let path = vec![self.document.elements.len()];
+        self.document.elements.push(element);
+        self.notify(ChangeEvent::ElementAdded(path));
+    }
+
+    pub fn remove(&mut self, path: &[usize]) -> Option<Element> {
+        let element = match parent_mut(&mut self.document.elements, path) {
+            Some((elements, i)) if i < elements.len() => elements.remove(i),
+            _ => return None,
+        };
+        clear_bounds(&mut self.document.elements, &path[..path.len() - 1]);
+        self.notify(ChangeEvent::ElementRemoved(path.to_vec(), element.clone()));
+        Some(element)
+    }
+
+    /// Changes the element at `path` with `change`, returning whether there
+    /// is one.
+    pub fn modify(&mut self, path: &[usize], change: impl FnOnce(&mut Element)) -> bool {
+        match parent_mut(&mut self.document.elements, path) {
+            Some((elements, i)) if i < elements.len() => {
+                change(&mut elements[i]);
+                elements[i].clear_stored_bounds();
+                if self.ids.is_some() {
+                    ids::collect(&elements[i..=i], &mut self.taken);
+                }
+            }
+            _ => return false,
+        }
+        clear_bounds(&mut self.document.elements, path);
+        self.notify(ChangeEvent::ElementModified(path.to_vec()));
+        true
+    }
+
+    /// Moves the element at `from` to `to`, which is its path after it was
+    /// removed from `from`.
+    ///
+    /// Nothing is changed if either path does not exist.
+    pub fn move_element(&mut self, from: &[usize], to: &[usize]) -> bool {
+        let element = match parent_mut(&mut self.document.elements, from) {
+            Some((elements, i)) if i < elements.len() => elements.remove(i),
+            _ => return false,
+        };
+        let element = match parent_mut(&mut self.document.elements, to) {
+            Some((elements, i)) if i <= elements.len() => {
+                elements.insert(i, element);
+                None
+            }
+            _ => Some(element),
+        };
+        if let Some(element) = element {
+            // Put it back
+            if let Some((elements, i)) = parent_mut(&mut self.document.elements, from) {
+                elements.insert(i, element);
+            }
+            return false;
+        }
+        clear_bounds(&mut self.document.elements, &from[..from.len() - 1]);
+        clear_bounds(&mut self.document.elements, &to[..to.len() - 1]);
+        self.notify(ChangeEvent::ElementMoved {
+            from: from.to_vec(),
+            to: to.to_vec(),
+        });
+        true
+    }
+
+    /// Changes the document with `change`, e.g. with
+    /// [`Document::remap_colors`], notifying a
+    /// [`DocumentChanged`](ChangeEvent::DocumentChanged).
+    pub fn edit<T>(&mut self, change: impl FnOnce(&mut Document) -> T) -> T {
+        let result = change(&mut self.document);
+        self.document.clear_stored_bounds();
+        if self.ids.is_some() {
+            self.taken.extend(ids::taken(&self.document));
+        }
+        self.notify(ChangeEvent::DocumentChanged);
+        result
+    }
+}
+
+/// Gives `element` and the elements in it without an author `author`.
+fn attribute(element: &mut Element, author: &str) {
+    let properties = element.properties_mut();
+    if properties.author.is_none() {
+        properties.author = Some(author.to_owned());
+    }
+    if let Element::Layer(layer) = element {
+        for element in &mut layer.elements {
+            attribute(element, author);
+        }
+    }
+}
+
+/// Forgets the [`StoredBounds`] of the element at `path` and of the layers
+/// containing it, whose bounds include it.
+pub(crate) fn clear_bounds(elements: &mut [Element], path: &[usize]) {
+    let mut elements = elements;
+    for &i in path {
+        let element = match elements.get_mut(i) {
+            Some(element) => element,
+            None => return,
+        };
+        element.properties_mut().bounds = StoredBounds(None);
+        match element {
+            Element::Layer(layer) => elements = &mut layer.elements,
+            _ => return,
+        }
+    }
+}
+
+/// The elements of the document or layer containing `path`, and the index in
+/// them.
+pub(crate) fn parent_mut<'a>(
+    elements: &'a mut Vec<Element>,
+    path: &[usize],
+) -> Option<(&'a mut Vec<Element>, usize)> {
+    let (&last, layers) = path.split_last()?;
+    let mut elements = elements;
+    for &i in layers {
+        match elements.get_mut(i)? {
+            Element::Layer(layer) => elements = &mut layer.elements,
+            _ => return None,
+        }
+    }
+    Some((elements, last))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    use crate::colors::Color;
+    use crate::editor::{ChangeEvent, Editor};
+    use crate::elements::{Element, Ellipse, Layer, Properties};
+    use crate::ids::{ContentHash, ElementId, Sequential};
+    use crate::options::ParseOptions;
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    fn ellipse(radius: Scalar) -> Element {
+        Element::Ellipse(Ellipse {
+            position: (0., 0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 1.,
+            radius,
+            properties: Properties::default(),
+        })
+    }
+
+    #[test]
+    fn editor() {
+        let mut editor = Editor::new(Document::default());
+        let events = Rc::new(RefCell::new(vec![]));
+        let subscription = editor.subscribe({
+            let events = Rc::clone(&events);
+            move |event| events.borrow_mut().push(event.clone())
+        });
+        let (_, receiver) = editor.subscribe_channel();
+
+        editor.push(Element::Layer(Layer::new("background")));
+        editor.push(ellipse(1.));
+        assert!(editor.insert(&[0, 0], ellipse(2.)).is_ok());
+        assert!(editor.insert(&[1, 0], ellipse(3.)).is_err());
+        assert!(editor.modify(&[0, 0], |e| e.set_hidden(true)));
+        assert!(editor.move_element(&[1], &[0]));
+        assert_eq!(editor.get(&[1, 0]).map(|e| e.is_hidden()), Some(true));
+        assert_eq!(editor.remove(&[0]), Some(ellipse(1.)));
+        editor.edit(|doc| doc.elements.clear());
+
+        let expected = vec![
+            ChangeEvent::ElementAdded(vec![0]),
+            ChangeEvent::ElementAdded(vec![1]),
+            ChangeEvent::ElementAdded(vec![0, 0]),
+            ChangeEvent::ElementModified(vec![0, 0]),
+            ChangeEvent::ElementMoved {
+                from: vec![1],
+                to: vec![0],
+            },
+            ChangeEvent::ElementRemoved(vec![0], ellipse(1.)),
+            ChangeEvent::DocumentChanged,
+        ];
+        assert_eq!(*events.borrow(), expected);
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), expected);
+
+        assert!(editor.unsubscribe(subscription));
+        editor.push(ellipse(4.));
+        assert_eq!(events.borrow().len(), expected.len());
+
+        // Dropped channels are unsubscribed
+        let (subscription, receiver) = editor.subscribe_channel();
+        drop(receiver);
+        editor.push(ellipse(5.));
+        assert!(!editor.unsubscribe(subscription));
+    }
+
+    #[test]
+    fn author() {
+        let mut editor = Editor::new(Document::default());
+        editor.push(ellipse(1.));
+        editor.set_author(Some("ada".to_owned()));
+        let mut layer = Layer::new("notes");
+        layer.elements.push(ellipse(2.));
+        let mut drawn = ellipse(3.);
+        drawn.properties_mut().author = Some("grace".to_owned());
+        layer.elements.push(drawn);
+        editor.push(Element::Layer(layer));
+
+        let author = |path: &[usize]| editor.get(path).unwrap().properties().author.clone();
+        assert_eq!(author(&[0]), None);
+        assert_eq!(author(&[1]).as_deref(), Some("ada"));
+        assert_eq!(author(&[1, 0]).as_deref(), Some("ada"));
+        assert_eq!(author(&[1, 1]).as_deref(), Some("grace"));
+    }
+
+    #[test]
+    fn ids() {
+        let mut editor = Editor::new(Document::default());
+        editor.set_id_generator(Some(Arc::new(ContentHash)));
+        editor.push(ellipse(1.));
+        editor.push(ellipse(1.));
+        let mut layer = Layer::new("notes");
+        layer.elements.push(ellipse(2.));
+        editor.push(Element::Layer(layer));
+
+        let id = editor.get(&[0]).and_then(|e| e.properties().id.clone());
+        let id = id.unwrap();
+        assert_eq!(id.as_str().len(), 16);
+        let duplicate = format!("{}-2", id);
+        assert_eq!(editor.find(&duplicate), Some(vec![1]));
+        let nested = editor.get(&[2, 0]).and_then(|e| e.properties().id.clone());
+        assert_eq!(editor.find(nested.unwrap().as_str()), Some(vec![2, 0]));
+        // The ids of removed elements are not given again
+        editor.remove(&[1]);
+        editor.push(ellipse(1.));
+        assert_eq!(editor.find(&format!("{}-3", id)), Some(vec![2]));
+
+        // Existing ids are kept
+        let options = ParseOptions {
+            ids: Some(Arc::new(Sequential::new("e"))),
+            ..Default::default()
+        };
+        let (doc, _) = Document::from_str_with(&editor.document().to_string(), &options).unwrap();
+        assert_eq!(doc, *editor.document());
+        let unnamed = Document {
+            elements: vec![ellipse(3.)],
+            ..Default::default()
+        };
+        let (doc, _) = Document::from_str_with(&unnamed.to_string(), &options).unwrap();
+        assert_eq!(doc.elements[0].properties().id, Some(ElementId::new("e1")));
+    }
+}
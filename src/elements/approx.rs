@@ -0,0 +1,330 @@
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+
+use super::{
+    Arc, Clip, Dimension, Element, Ellipse, FileRef, Frame, Instance, Layer, Line, Ngon, Polyline,
+    Properties, Shape, Text,
+};
+
+fn near(a: Scalar, b: Scalar, epsilon: Scalar) -> bool {
+    (a - b).abs() <= epsilon
+}
+
+fn all_near(a: &[Scalar], b: &[Scalar], epsilon: Scalar) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| near(a, b, epsilon))
+}
+
+fn points_near(a: (Scalar, Scalar), b: (Scalar, Scalar), epsilon: Scalar) -> bool {
+    near(a.0, b.0, epsilon) && near(a.1, b.1, epsilon)
+}
+
+impl Element {
+    /// Whether the elements are equal, with coordinates, sizes and other
+    /// numbers differing by at most `epsilon`, e.g. after a round trip
+    /// through text.
+    ///
+    /// Everything else, e.g. colors or the number of points, has to be
+    /// equal.
+    pub fn approx_eq(&self, other: &Element, epsilon: Scalar) -> bool {
+        match (self, other) {
+            (Element::Line(a), Element::Line(b)) => a.approx_eq(b, epsilon),
+            (Element::Ngon(a), Element::Ngon(b)) => a.approx_eq(b, epsilon),
+            (Element::Ellipse(a), Element::Ellipse(b)) => a.approx_eq(b, epsilon),
+            (Element::Polyline(a), Element::Polyline(b)) => a.approx_eq(b, epsilon),
+            (Element::Text(a), Element::Text(b)) => a.approx_eq(b, epsilon),
+            (Element::FileRef(a), Element::FileRef(b)) => a.approx_eq(b, epsilon),
+            (Element::Layer(a), Element::Layer(b)) => a.approx_eq(b, epsilon),
+            (Element::Shape(a), Element::Shape(b)) => a.approx_eq(b, epsilon),
+            (Element::Arc(a), Element::Arc(b)) => a.approx_eq(b, epsilon),
+            (Element::Instance(a), Element::Instance(b)) => a.approx_eq(b, epsilon),
+            (Element::Custom(a), Element::Custom(b)) => a == b,
+            (Element::Dimension(a), Element::Dimension(b)) => a.approx_eq(b, epsilon),
+            (Element::Frame(a), Element::Frame(b)) => a.approx_eq(b, epsilon),
+            (Element::Ephemeral(a), Element::Ephemeral(b)) => {
+                (a.ttl, a.created) == (b.ttl, b.created) && a.line.approx_eq(&b.line, epsilon)
+            }
+            // Listed, so new kinds of elements are not forgotten
+            (Element::Line(_), _)
+            | (Element::Ngon(_), _)
+            | (Element::Ellipse(_), _)
+            | (Element::Polyline(_), _)
+            | (Element::Text(_), _)
+            | (Element::FileRef(_), _)
+            | (Element::Layer(_), _)
+            | (Element::Shape(_), _)
+            | (Element::Arc(_), _)
+            | (Element::Instance(_), _)
+            | (Element::Custom(_), _)
+            | (Element::Dimension(_), _)
+            | (Element::Ephemeral(_), _)
+            | (Element::Frame(_), _) => false,
+        }
+    }
+}
+
+impl Line {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Line, epsilon: Scalar) -> bool {
+        self.color == other.color
+            && near(self.width, other.width, epsilon)
+            && self.points.len() == other.points.len()
+            && self
+                .points
+                .iter()
+                .zip(&other.points)
+                .all(|(a, b)| all_near(&[a.0, a.1, a.2], &[b.0, b.1, b.2], epsilon))
+            && self.timestamps == other.timestamps
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Ngon {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Ngon, epsilon: Scalar) -> bool {
+        (self.stroke, self.fill, self.n) == (other.stroke, other.fill, other.n)
+            && points_near(self.position, other.position, epsilon)
+            && all_near(
+                &[self.width, self.angle, self.radius],
+                &[other.width, other.angle, other.radius],
+                epsilon,
+            )
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Shape {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Shape, epsilon: Scalar) -> bool {
+        (&self.tool, self.stroke, self.fill) == (&other.tool, other.stroke, other.fill)
+            && points_near(self.position, other.position, epsilon)
+            && all_near(
+                &[self.width, self.angle, self.radius],
+                &[other.width, other.angle, other.radius],
+                epsilon,
+            )
+            && self.parameters.len() == other.parameters.len()
+            && self
+                .parameters
+                .iter()
+                .zip(&other.parameters)
+                .all(|((a, x), (b, y))| a == b && near(*x, *y, epsilon))
+            && self.points.len() == other.points.len()
+            && self
+                .points
+                .iter()
+                .zip(&other.points)
+                .all(|(&a, &b)| points_near(a, b, epsilon))
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Arc {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Arc, epsilon: Scalar) -> bool {
+        (self.stroke, self.fill, self.pie) == (other.stroke, other.fill, other.pie)
+            && points_near(self.position, other.position, epsilon)
+            && all_near(
+                &[self.width, self.radius, self.start, self.end],
+                &[other.width, other.radius, other.start, other.end],
+                epsilon,
+            )
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Dimension {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Dimension, epsilon: Scalar) -> bool {
+        (self.color, self.unit, &self.attachments) == (other.color, other.unit, &other.attachments)
+            && points_near(self.start, other.start, epsilon)
+            && points_near(self.end, other.end, epsilon)
+            && near(self.offset, other.offset, epsilon)
+            && near(self.width, other.width, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Ellipse {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Ellipse, epsilon: Scalar) -> bool {
+        (self.stroke, self.fill) == (other.stroke, other.fill)
+            && points_near(self.position, other.position, epsilon)
+            && near(self.width, other.width, epsilon)
+            && near(self.radius, other.radius, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Polyline {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Polyline, epsilon: Scalar) -> bool {
+        (self.stroke, self.fill, self.closed) == (other.stroke, other.fill, other.closed)
+            && near(self.width, other.width, epsilon)
+            && self.points.len() == other.points.len()
+            && self
+                .points
+                .iter()
+                .zip(&other.points)
+                .all(|(a, b)| points_near((a.0, a.1), (b.0, b.1), epsilon))
+            && all_near(&self.widths, &other.widths, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Text {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Text, epsilon: Scalar) -> bool {
+        (&self.content, self.color, &self.font.family)
+            == (&other.content, other.color, &other.font.family)
+            && points_near(self.position, other.position, epsilon)
+            && near(self.font.size, other.font.size, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl FileRef {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &FileRef, epsilon: Scalar) -> bool {
+        self.path == other.path
+            && points_near(self.position, other.position, epsilon)
+            && points_near(self.size, other.size, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Frame {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Frame, epsilon: Scalar) -> bool {
+        (&self.name, self.index) == (&other.name, other.index)
+            && points_near(self.position, other.position, epsilon)
+            && points_near(self.size, other.size, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Instance {
+    /// See [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Instance, epsilon: Scalar) -> bool {
+        self.symbol == other.symbol
+            && points_near(self.position, other.position, epsilon)
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Layer {
+    /// See [`Element::approx_eq`], the opacity and blend mode are compared
+    /// exactly.
+    pub fn approx_eq(&self, other: &Layer, epsilon: Scalar) -> bool {
+        (&self.name, self.opacity, self.blend) == (&other.name, other.opacity, other.blend)
+            && self.elements.len() == other.elements.len()
+            && self
+                .elements
+                .iter()
+                .zip(&other.elements)
+                .all(|(a, b)| a.approx_eq(b, epsilon))
+            && self.properties.approx_eq(&other.properties, epsilon)
+    }
+}
+
+impl Properties {
+    /// The transform and clip are compared with `epsilon`, the rest exactly.
+    pub fn approx_eq(&self, other: &Properties, epsilon: Scalar) -> bool {
+        let clips_near = match (self.clip, other.clip) {
+            (None, None) => true,
+            (
+                Some(Clip::Rectangle { position, size }),
+                Some(Clip::Rectangle {
+                    position: other_position,
+                    size: other_size,
+                }),
+            ) => {
+                points_near(position, other_position, epsilon)
+                    && points_near(size, other_size, epsilon)
+            }
+            (
+                Some(Clip::Ellipse { position, radius }),
+                Some(Clip::Ellipse {
+                    position: other_position,
+                    radius: other_radius,
+                }),
+            ) => {
+                points_near(position, other_position, epsilon)
+                    && points_near(radius, other_radius, epsilon)
+            }
+            _ => false,
+        };
+        clips_near
+            && self.transform.approx_eq(&other.transform, epsilon)
+            && (
+                &self.id,
+                self.hidden,
+                self.non_scaling_stroke,
+                &self.title,
+                &self.description,
+            ) == (
+                &other.id,
+                other.hidden,
+                other.non_scaling_stroke,
+                &other.title,
+                &other.description,
+            )
+            && (&self.author, &self.link, &self.foreign)
+                == (&other.author, &other.link, &other.foreign)
+            && (self.stroke_opacity, self.fill_opacity)
+                == (other.stroke_opacity, other.fill_opacity)
+    }
+}
+
+impl Transform {
+    /// Whether all coefficients differ by at most `epsilon`.
+    pub fn approx_eq(&self, other: &Transform, epsilon: Scalar) -> bool {
+        all_near(
+            &[self.a, self.b, self.c, self.d, self.e, self.f],
+            &[other.a, other.b, other.c, other.d, other.e, other.f],
+            epsilon,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn approx_eq() {
+        let line = |x, transform| {
+            Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(x, 1., 1.)],
+                timestamps: vec![],
+                properties: Properties {
+                    transform,
+                    ..Default::default()
+                },
+            })
+        };
+        let mut layer = Layer::new("ink");
+        layer
+            .elements
+            .push(line(0.1, Transform::translate(0.2, 0.)));
+        let a = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+        let mut b = a.clone();
+        if let Element::Layer(layer) = &mut b.elements[0] {
+            layer.elements[0] = line(0.1 + 1e-4, Transform::translate(0.2 - 1e-4, 0.));
+        }
+        assert_ne!(a, b);
+        assert!(a.approx_eq(&b, 1e-3));
+        assert!(!a.approx_eq(&b, 1e-5));
+
+        let mut c = b.clone();
+        c.elements.push(line(0., Transform::IDENTITY));
+        assert!(!a.approx_eq(&c, 1.));
+    }
+}
@@ -0,0 +1,190 @@
+use svg::node::element;
+use svg::node::element::path::Data;
+
+use crate::colors::Color;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+
+use super::attributes::{AttributeReader, Attributes};
+use super::style::{self, StyleSheet};
+use super::FromAttributes;
+use super::Properties;
+
+/// A part of a circle, stored as `<path svgnote:tool="arc">` with the arc as
+/// path data.
+///
+/// Angles are in radians, from the positive x axis towards the positive y
+/// axis, i.e. clockwise on screen.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Arc {
+    /// The center.
+    pub position: (Scalar, Scalar),
+    pub radius: Scalar,
+    pub start: Scalar,
+    /// Before `start` for an arc drawn counterclockwise.
+    pub end: Scalar,
+    /// Closed through the center, i.e. a pie slice, instead of open.
+    pub pie: bool,
+    pub stroke: Color,
+    pub fill: Color,
+    pub width: Scalar,
+    pub properties: Properties,
+}
+
+impl Arc {
+    /// The angle from `start` to `end`, at most a full circle either way.
+    pub fn sweep(&self) -> Scalar {
+        (self.end - self.start).clamp(-2. * PI, 2. * PI)
+    }
+
+    /// The point on the circle at `angle`.
+    pub fn point_at(&self, angle: Scalar) -> (Scalar, Scalar) {
+        (
+            self.position.0 + self.radius * angle.cos(),
+            self.position.1 + self.radius * angle.sin(),
+        )
+    }
+
+    /// The arc approximated by `segments` lines, followed by the center for
+    /// a pie.
+    pub fn points(&self, segments: usize) -> Vec<(Scalar, Scalar)> {
+        let segments = segments.max(1);
+        let mut points: Vec<_> = (0..=segments)
+            .map(|i| self.point_at(self.start + self.sweep() * i as Scalar / segments as Scalar))
+            .collect();
+        if self.pie {
+            points.push(self.position);
+        }
+        points
+    }
+
+    /// The path data with an elliptical arc, split in half for a full circle,
+    /// which a single one cannot draw.
+    fn data(&self) -> Data {
+        let sweep = self.sweep();
+        let arc_to = |data: Data, sweep: Scalar, (x, y): (Scalar, Scalar)| {
+            data.elliptical_arc_to((
+                self.radius as f32,
+                self.radius as f32,
+                0.,
+                (sweep.abs() > PI) as u8,
+                (sweep > 0.) as u8,
+                x as f32,
+                y as f32,
+            ))
+        };
+        let start = self.point_at(self.start);
+        let mut data = Data::new().move_to((start.0 as f32, start.1 as f32));
+        data = if sweep.abs() >= 2. * PI {
+            let data = arc_to(data, sweep / 2., self.point_at(self.start + sweep / 2.));
+            // Exactly back to the start
+            arc_to(data, sweep / 2., start)
+        } else {
+            arc_to(data, sweep, self.point_at(self.start + sweep))
+        };
+        if self.pie {
+            data = data
+                .line_to((self.position.0 as f32, self.position.1 as f32))
+                .close();
+        }
+        data
+    }
+}
+
+impl From<&Arc> for element::Path {
+    fn from(arc: &Arc) -> Self {
+        arc.to_path(None)
+    }
+}
+
+impl Arc {
+    /// Like `element::Path::from`, but with the style as a class if `styles`
+    /// has one for it.
+    pub(crate) fn to_path(&self, styles: Option<&StyleSheet>) -> element::Path {
+        let mut path = element::Path::new()
+            .set(
+                "svgnote:position",
+                format!("{},{}", self.position.0, self.position.1),
+            )
+            .set("svgnote:radius", self.radius)
+            .set("svgnote:start", self.start)
+            .set("svgnote:end", self.end)
+            .set("svgnote:pie", self.pie.to_string())
+            // Static
+            .set("svgnote:tool", "arc")
+            // Generated
+            .set("d", self.data());
+        style::assign(&mut path, self.style(), styles);
+        self.properties.assign_to(&mut path);
+        path
+    }
+}
+
+impl FromAttributes for Arc {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let reader = AttributeReader::new(&attributes);
+        Ok(Arc {
+            position: reader.point("svgnote:position")?,
+            radius: reader.scalar("svgnote:radius")?,
+            start: reader.scalar("svgnote:start")?,
+            end: reader.scalar("svgnote:end")?,
+            pie: attributes.get("svgnote:pie").map(|v| &**v) == Some("true"),
+            stroke: reader.color("stroke")?,
+            fill: reader.fill("fill")?,
+            width: reader.scalar("stroke-width")?,
+            properties: Properties::from_attributes(&attributes)?
+                .with_stroke_opacity(&attributes)
+                .with_fill_opacity(&attributes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use svg::node::Value;
+
+    use crate::colors::Color;
+    use crate::elements::{Arc, Element, Properties};
+    use crate::scalar::consts::PI;
+    use crate::Document;
+
+    #[test]
+    fn arc() {
+        let arc = |start, end, pie| Arc {
+            position: (10., 10.),
+            radius: 5.,
+            start,
+            end,
+            pie,
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 1.,
+            properties: Properties::default(),
+        };
+        let quarter = arc(0., PI / 2., false);
+        assert_eq!(data(&quarter), "M15,10 A5,5,0,0,1,10,15");
+        let pie = arc(0., -PI / 2., true);
+        assert_eq!(data(&pie), "M15,10 A5,5,0,0,0,10,5 L10,10 z");
+        // A single arc from a point to itself draws nothing
+        let circle = arc(0., 2. * PI, false);
+        assert_eq!(data(&circle), "M15,10 A5,5,0,0,1,5,10 A5,5,0,0,1,15,10");
+        assert_eq!(circle.points(4).len(), 5);
+
+        let document = Document {
+            elements: vec![
+                Element::Arc(quarter),
+                Element::Arc(pie),
+                Element::Arc(circle),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(Document::from_str(&document.to_string()).unwrap(), document);
+    }
+
+    fn data(arc: &Arc) -> String {
+        Value::from(arc.data()).to_string()
+    }
+}
@@ -0,0 +1,156 @@
+//! Reading the attributes of a tag, with the errors for missing and invalid
+//! ones.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+use svg::node::Value;
+
+use crate::colors::Color;
+use crate::scalar::{self, Scalar};
+use crate::DocumentError;
+
+use super::style;
+
+/// The attributes of a tag as the elements are read from, with the values
+/// still escaped.
+///
+/// With the `fast-parse` feature the names and values borrow from the
+/// document, so reading a tag does not allocate for them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Attributes<'a>(Vec<(Cow<'a, str>, Cow<'a, str>)>);
+
+impl<'a> Attributes<'a> {
+    pub fn new() -> Self {
+        Attributes::default()
+    }
+
+    /// The value of `name`, the last one for duplicates.
+    pub fn get(&self, name: &str) -> Option<&Cow<'a, str>> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Sets `name` to `value`, returning the previous value.
+    pub fn insert(
+        &mut self,
+        name: impl Into<Cow<'a, str>>,
+        value: impl Into<Cow<'a, str>>,
+    ) -> Option<Cow<'a, str>> {
+        let name = name.into();
+        let previous = self.remove(&name);
+        self.0.push((name, value.into()));
+        previous
+    }
+
+    /// Removes `name`, returning its value.
+    pub fn remove(&mut self, name: &str) -> Option<Cow<'a, str>> {
+        let mut removed = None;
+        self.0.retain(|(n, value)| {
+            if n != name {
+                return true;
+            }
+            removed = Some(value.clone());
+            false
+        });
+        removed
+    }
+
+    /// The names and values, in the order they were read.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(name, value)| (&**name, &**value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<'a> FromIterator<(Cow<'a, str>, Cow<'a, str>)> for Attributes<'a> {
+    fn from_iter<I: IntoIterator<Item = (Cow<'a, str>, Cow<'a, str>)>>(iter: I) -> Self {
+        Attributes(iter.into_iter().collect())
+    }
+}
+
+/// The attributes as read by `svg`.
+impl From<HashMap<String, Value>> for Attributes<'_> {
+    fn from(attributes: HashMap<String, Value>) -> Self {
+        attributes
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.to_string().into()))
+            .collect()
+    }
+}
+
+/// `x,y`, e.g. of `svgnote:position`.
+pub(crate) fn point(s: &str) -> Option<(Scalar, Scalar)> {
+    let (x, y) = s.split_once(',')?;
+    Some((scalar::parse(x)?, scalar::parse(y)?))
+}
+
+pub(crate) struct AttributeReader<'a> {
+    attributes: &'a Attributes<'a>,
+}
+
+impl<'a> AttributeReader<'a> {
+    pub(crate) fn new(attributes: &'a Attributes<'a>) -> Self {
+        AttributeReader { attributes }
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<&'a str, DocumentError> {
+        self.attributes
+            .get(name)
+            .map(|value| &**value)
+            .ok_or_else(|| DocumentError::MissingAttribute(name.to_owned()))
+    }
+
+    /// The error for the value of `name`.
+    pub(crate) fn invalid(&self, name: &str) -> DocumentError {
+        let value = self.attributes.get(name).map(|value| value.to_string());
+        DocumentError::InvalidAttribute(name.to_owned(), value.unwrap_or_default())
+    }
+
+    pub(crate) fn parse<T: FromStr>(&self, name: &str) -> Result<T, DocumentError> {
+        T::from_str(self.get(name)?).map_err(|_| self.invalid(name))
+    }
+
+    pub(crate) fn scalar(&self, name: &str) -> Result<Scalar, DocumentError> {
+        scalar::parse(self.get(name)?).ok_or_else(|| self.invalid(name))
+    }
+
+    /// Only values above `0` are valid, e.g. for sizes.
+    pub(crate) fn positive(&self, name: &str) -> Result<Scalar, DocumentError> {
+        match self.scalar(name)? {
+            value if value > 0. => Ok(value),
+            _ => Err(self.invalid(name)),
+        }
+    }
+
+    pub(crate) fn point(&self, name: &str) -> Result<(Scalar, Scalar), DocumentError> {
+        point(self.get(name)?).ok_or_else(|| self.invalid(name))
+    }
+
+    pub(crate) fn color(&self, name: &str) -> Result<Color, DocumentError> {
+        Color::from_str(self.get(name)?)
+            .map_err(|e| DocumentError::InvalidColor(name.to_owned(), e))
+    }
+
+    /// A color, or `none` for no fill.
+    pub(crate) fn fill(&self, name: &str) -> Result<Color, DocumentError> {
+        style::parse_fill(self.get(name)?)
+            .map_err(|e| DocumentError::InvalidColor(name.to_owned(), e))
+    }
+}
@@ -0,0 +1,674 @@
+//! Builders for the elements, checking the values at `build()`.
+
+use thiserror::Error;
+
+use crate::calibration::InputCalibration;
+use crate::colors::Color;
+use crate::scalar::Scalar;
+use crate::snap::Grid;
+use crate::transform::Transform;
+
+use super::{
+    BlendMode, Element, Ellipse, FileRef, Font, Layer, Line, LinePoint, Ngon, Polyline,
+    PolylinePoint, Properties, Text,
+};
+
+/// Why an element could not be built.
+#[derive(Error, Debug, PartialEq, Eq, Clone)]
+pub enum BuildError {
+    #[error("Missing {0}")]
+    Missing(&'static str),
+    #[error("Invalid {0}: {1}")]
+    Invalid(&'static str, String),
+}
+
+fn required<T>(value: Option<T>, name: &'static str) -> Result<T, BuildError> {
+    value.ok_or(BuildError::Missing(name))
+}
+
+fn finite(value: Scalar, name: &'static str) -> Result<Scalar, BuildError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(BuildError::Invalid(name, value.to_string()))
+    }
+}
+
+fn non_negative(value: Scalar, name: &'static str) -> Result<Scalar, BuildError> {
+    if finite(value, name)? >= 0. {
+        Ok(value)
+    } else {
+        Err(BuildError::Invalid(name, value.to_string()))
+    }
+}
+
+fn position(value: Option<(Scalar, Scalar)>) -> Result<(Scalar, Scalar), BuildError> {
+    let (x, y) = required(value, "position")?;
+    Ok((finite(x, "position")?, finite(y, "position")?))
+}
+
+/// The position and radius snapped to `grid`, radii to at least one grid
+/// step.
+fn on_grid(
+    grid: Option<Grid>,
+    position: (Scalar, Scalar),
+    radius: Scalar,
+) -> Result<((Scalar, Scalar), Scalar), BuildError> {
+    let grid = match grid {
+        Some(grid) => grid,
+        None => return Ok((position, radius)),
+    };
+    if !(grid.spacing.is_finite() && grid.spacing > 0.) {
+        return Err(BuildError::Invalid("grid", grid.spacing.to_string()));
+    }
+    let radius = if radius > 0. {
+        grid.snap_length(radius).max(grid.spacing)
+    } else {
+        radius
+    };
+    Ok((grid.snap(position), radius))
+}
+
+/// See [`Line::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct LineBuilder {
+    color: Option<Color>,
+    width: Option<Scalar>,
+    points: Vec<LinePoint>,
+    timestamps: Vec<u64>,
+    properties: Properties,
+    calibration: Option<InputCalibration>,
+}
+
+impl Line {
+    /// Black and `1` wide unless set, with at least one point.
+    pub fn builder() -> LineBuilder {
+        LineBuilder::default()
+    }
+}
+
+impl LineBuilder {
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn width(mut self, width: Scalar) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn point(mut self, x: Scalar, y: Scalar, pressure: Scalar) -> Self {
+        self.points.push(LinePoint(x, y, pressure));
+        self
+    }
+
+    /// A point drawn at `timestamp`, either all points or none have one.
+    pub fn timed_point(mut self, x: Scalar, y: Scalar, pressure: Scalar, timestamp: u64) -> Self {
+        self.timestamps.push(timestamp);
+        self.point(x, y, pressure)
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    /// Corrects the points at `build()`, which are then raw input of the
+    /// device.
+    pub fn calibration(mut self, calibration: InputCalibration) -> Self {
+        self.calibration = Some(calibration);
+        self
+    }
+
+    pub fn build(mut self) -> Result<Line, BuildError> {
+        if let Some(calibration) = self.calibration {
+            if !calibration.is_valid() {
+                return Err(BuildError::Invalid(
+                    "calibration",
+                    format!("{:?}", calibration),
+                ));
+            }
+            if self.timestamps.is_empty() || self.timestamps.len() == self.points.len() {
+                calibration.apply(&mut self.points, &mut self.timestamps);
+            }
+        }
+        if self.points.is_empty() {
+            return Err(BuildError::Missing("points"));
+        }
+        for &LinePoint(x, y, pressure) in &self.points {
+            finite(x, "point")?;
+            finite(y, "point")?;
+            if !(0. ..=1.).contains(&pressure) {
+                return Err(BuildError::Invalid("pressure", pressure.to_string()));
+            }
+        }
+        if !self.timestamps.is_empty() && self.timestamps.len() != self.points.len() {
+            return Err(BuildError::Invalid(
+                "timestamps",
+                format!("{} for {} points", self.timestamps.len(), self.points.len()),
+            ));
+        }
+        Ok(Line {
+            color: self.color.unwrap_or_else(|| Color::rgb(0, 0, 0)),
+            width: non_negative(self.width.unwrap_or(1.), "width")?,
+            points: self.points,
+            timestamps: self.timestamps,
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`Ngon::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct NgonBuilder {
+    position: Option<(Scalar, Scalar)>,
+    radius: Option<Scalar>,
+    n: Option<u16>,
+    angle: Scalar,
+    stroke: Option<Color>,
+    fill: Option<Color>,
+    width: Option<Scalar>,
+    properties: Properties,
+    grid: Option<Grid>,
+}
+
+impl Ngon {
+    /// Needs a position, radius and `n`. Black, `1` wide and not filled
+    /// unless set.
+    pub fn builder() -> NgonBuilder {
+        NgonBuilder::default()
+    }
+}
+
+impl NgonBuilder {
+    /// The center.
+    pub fn position(mut self, x: Scalar, y: Scalar) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn radius(mut self, radius: Scalar) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    /// The number of corners, at least `3`.
+    pub fn n(mut self, n: u16) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    pub fn angle(mut self, angle: Scalar) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Color) -> Self {
+        self.stroke = Some(stroke);
+        self
+    }
+
+    pub fn fill(mut self, fill: Color) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    pub fn width(mut self, width: Scalar) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    /// Snaps the position and radius to `grid`.
+    pub fn grid(mut self, grid: Grid) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
+    pub fn build(self) -> Result<Ngon, BuildError> {
+        let n = required(self.n, "n")?;
+        if n < 3 {
+            return Err(BuildError::Invalid("n", n.to_string()));
+        }
+        let radius = non_negative(required(self.radius, "radius")?, "radius")?;
+        let (position, radius) = on_grid(self.grid, position(self.position)?, radius)?;
+        Ok(Ngon {
+            position,
+            stroke: self.stroke.unwrap_or_else(|| Color::rgb(0, 0, 0)),
+            fill: self.fill.unwrap_or_else(|| Color::rgba(0, 0, 0, 0)),
+            width: non_negative(self.width.unwrap_or(1.), "width")?,
+            angle: finite(self.angle, "angle")?,
+            n,
+            radius,
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`Ellipse::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct EllipseBuilder {
+    position: Option<(Scalar, Scalar)>,
+    radius: Option<Scalar>,
+    stroke: Option<Color>,
+    fill: Option<Color>,
+    width: Option<Scalar>,
+    properties: Properties,
+    grid: Option<Grid>,
+}
+
+impl Ellipse {
+    /// Needs a position and radius. Black, `1` wide and not filled unless
+    /// set.
+    pub fn builder() -> EllipseBuilder {
+        EllipseBuilder::default()
+    }
+}
+
+impl EllipseBuilder {
+    /// The center.
+    pub fn position(mut self, x: Scalar, y: Scalar) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn radius(mut self, radius: Scalar) -> Self {
+        self.radius = Some(radius);
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Color) -> Self {
+        self.stroke = Some(stroke);
+        self
+    }
+
+    pub fn fill(mut self, fill: Color) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    pub fn width(mut self, width: Scalar) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    /// Snaps the position and radius to `grid`.
+    pub fn grid(mut self, grid: Grid) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+
+    pub fn build(self) -> Result<Ellipse, BuildError> {
+        let radius = non_negative(required(self.radius, "radius")?, "radius")?;
+        let (position, radius) = on_grid(self.grid, position(self.position)?, radius)?;
+        Ok(Ellipse {
+            position,
+            stroke: self.stroke.unwrap_or_else(|| Color::rgb(0, 0, 0)),
+            fill: self.fill.unwrap_or_else(|| Color::rgba(0, 0, 0, 0)),
+            width: non_negative(self.width.unwrap_or(1.), "width")?,
+            radius,
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`Polyline::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct PolylineBuilder {
+    points: Vec<PolylinePoint>,
+    widths: Vec<Scalar>,
+    closed: bool,
+    stroke: Option<Color>,
+    fill: Option<Color>,
+    width: Option<Scalar>,
+    properties: Properties,
+}
+
+impl Polyline {
+    /// Needs at least two points. Black, `1` wide and not filled unless set.
+    pub fn builder() -> PolylineBuilder {
+        PolylineBuilder::default()
+    }
+}
+
+impl PolylineBuilder {
+    pub fn point(mut self, x: Scalar, y: Scalar) -> Self {
+        self.points.push(PolylinePoint(x, y));
+        self
+    }
+
+    /// A point with its width relative to the [`width`](Self::width). Either
+    /// all points or none need one.
+    pub fn point_with_width(mut self, x: Scalar, y: Scalar, width: Scalar) -> Self {
+        self.points.push(PolylinePoint(x, y));
+        self.widths.push(width);
+        self
+    }
+
+    pub fn closed(mut self, closed: bool) -> Self {
+        self.closed = closed;
+        self
+    }
+
+    pub fn stroke(mut self, stroke: Color) -> Self {
+        self.stroke = Some(stroke);
+        self
+    }
+
+    pub fn fill(mut self, fill: Color) -> Self {
+        self.fill = Some(fill);
+        self
+    }
+
+    pub fn width(mut self, width: Scalar) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    pub fn build(self) -> Result<Polyline, BuildError> {
+        if self.points.len() < 2 {
+            return Err(BuildError::Invalid(
+                "points",
+                format!("{}, at least 2 are needed", self.points.len()),
+            ));
+        }
+        for &PolylinePoint(x, y) in &self.points {
+            finite(x, "point")?;
+            finite(y, "point")?;
+        }
+        if !self.widths.is_empty() && self.widths.len() != self.points.len() {
+            return Err(BuildError::Invalid(
+                "widths",
+                format!("{} for {} points", self.widths.len(), self.points.len()),
+            ));
+        }
+        for &width in &self.widths {
+            non_negative(width, "widths")?;
+        }
+        Ok(Polyline {
+            stroke: self.stroke.unwrap_or_else(|| Color::rgb(0, 0, 0)),
+            fill: self.fill.unwrap_or_else(|| Color::rgba(0, 0, 0, 0)),
+            width: non_negative(self.width.unwrap_or(1.), "width")?,
+            points: self.points,
+            widths: self.widths,
+            closed: self.closed,
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`Text::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct TextBuilder {
+    position: Option<(Scalar, Scalar)>,
+    content: Option<String>,
+    color: Option<Color>,
+    font: Font,
+    properties: Properties,
+}
+
+impl Text {
+    /// Needs a position and content. Black in the default [`Font`] unless
+    /// set.
+    pub fn builder() -> TextBuilder {
+        TextBuilder::default()
+    }
+}
+
+impl TextBuilder {
+    /// The start of the baseline of the first line.
+    pub fn position(mut self, x: Scalar, y: Scalar) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn font(mut self, family: impl Into<String>, size: Scalar) -> Self {
+        self.font = Font {
+            family: family.into(),
+            size,
+        };
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    pub fn build(self) -> Result<Text, BuildError> {
+        if self.font.family.is_empty() {
+            return Err(BuildError::Missing("font family"));
+        }
+        non_negative(self.font.size, "font size")?;
+        Ok(Text {
+            position: position(self.position)?,
+            content: required(self.content, "content")?,
+            color: self.color.unwrap_or_else(|| Color::rgb(0, 0, 0)),
+            font: self.font,
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`FileRef::builder`].
+#[derive(Debug, Clone, Default)]
+pub struct FileRefBuilder {
+    path: Option<String>,
+    position: Option<(Scalar, Scalar)>,
+    size: Option<(Scalar, Scalar)>,
+    properties: Properties,
+}
+
+impl FileRef {
+    /// Needs a path and size, at the origin unless set.
+    pub fn builder() -> FileRefBuilder {
+        FileRefBuilder::default()
+    }
+}
+
+impl FileRefBuilder {
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// The top left corner.
+    pub fn position(mut self, x: Scalar, y: Scalar) -> Self {
+        self.position = Some((x, y));
+        self
+    }
+
+    pub fn size(mut self, width: Scalar, height: Scalar) -> Self {
+        self.size = Some((width, height));
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.properties.transform = transform;
+        self
+    }
+
+    pub fn build(self) -> Result<FileRef, BuildError> {
+        let path = required(self.path, "path")?;
+        if path.is_empty() {
+            return Err(BuildError::Missing("path"));
+        }
+        let (width, height) = required(self.size, "size")?;
+        Ok(FileRef {
+            path,
+            position: position(self.position.or(Some((0., 0.))))?,
+            size: (non_negative(width, "size")?, non_negative(height, "size")?),
+            properties: self.properties,
+        })
+    }
+}
+
+/// See [`Layer::builder`].
+#[derive(Debug, Clone)]
+pub struct LayerBuilder {
+    layer: Layer,
+}
+
+impl Layer {
+    /// Opaque and empty unless set.
+    pub fn builder(name: impl Into<String>) -> LayerBuilder {
+        LayerBuilder {
+            layer: Layer::new(name),
+        }
+    }
+}
+
+impl LayerBuilder {
+    pub fn element(mut self, element: Element) -> Self {
+        self.layer.elements.push(element);
+        self
+    }
+
+    /// Between `0` and `1`.
+    pub fn opacity(mut self, opacity: f32) -> Self {
+        self.layer.opacity = opacity;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.layer.blend = blend;
+        self
+    }
+
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.layer.properties = properties;
+        self
+    }
+
+    pub fn transform(mut self, transform: Transform) -> Self {
+        self.layer.properties.transform = transform;
+        self
+    }
+
+    pub fn build(self) -> Result<Layer, BuildError> {
+        if !(0. ..=1.).contains(&self.layer.opacity) {
+            return Err(BuildError::Invalid(
+                "opacity",
+                self.layer.opacity.to_string(),
+            ));
+        }
+        Ok(self.layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{BuildError, Element, Layer, Line, LinePoint, Ngon};
+
+    #[test]
+    fn builders() {
+        let line = Line::builder()
+            .color(Color::rgb(0xFF, 0, 0))
+            .width(2.)
+            .timed_point(0., 0., 0.5, 1000)
+            .timed_point(1., 1., 1., 1016)
+            .build()
+            .unwrap();
+        assert_eq!(
+            line.points,
+            vec![LinePoint(0., 0., 0.5), LinePoint(1., 1., 1.)]
+        );
+        assert_eq!(line.timestamps, vec![1000, 1016]);
+        assert_eq!(
+            Line::builder().build().unwrap_err(),
+            BuildError::Missing("points")
+        );
+        assert_eq!(
+            Line::builder().point(0., 0., 2.).build().unwrap_err(),
+            BuildError::Invalid("pressure", "2".to_owned())
+        );
+
+        let ngon = Ngon::builder()
+            .position(1., 2.)
+            .radius(3.)
+            .n(5)
+            .build()
+            .unwrap();
+        assert_eq!(
+            (ngon.position, ngon.fill),
+            ((1., 2.), Color::rgba(0, 0, 0, 0))
+        );
+        assert_eq!(
+            Ngon::builder().position(1., 2.).n(5).build().unwrap_err(),
+            BuildError::Missing("radius")
+        );
+        assert_eq!(
+            Ngon::builder()
+                .position(1., 2.)
+                .radius(3.)
+                .n(2)
+                .build()
+                .unwrap_err(),
+            BuildError::Invalid("n", "2".to_owned())
+        );
+
+        let layer = Layer::builder("shapes")
+            .element(Element::Ngon(ngon))
+            .element(Element::Line(line))
+            .build()
+            .unwrap();
+        assert_eq!(layer.elements.len(), 2);
+        assert!(Layer::builder("faded").opacity(1.5).build().is_err());
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use svg::node::element::{self, tag};
+
+use crate::scalar::Scalar;
+use crate::xml::Event;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+
+use super::Attributes;
+use super::Element;
+use DocumentError::MissingAttribute;
+
+/// The area an element or layer is clipped to, in document coordinates.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Clip {
+    Rectangle {
+        position: (Scalar, Scalar),
+        size: (Scalar, Scalar),
+    },
+    Ellipse {
+        position: (Scalar, Scalar),
+        radius: (Scalar, Scalar),
+    },
+}
+
+impl Clip {
+    pub fn rectangle(position: (Scalar, Scalar), size: (Scalar, Scalar)) -> Self {
+        Clip::Rectangle { position, size }
+    }
+
+    pub fn ellipse(position: (Scalar, Scalar), radius: (Scalar, Scalar)) -> Self {
+        Clip::Ellipse { position, radius }
+    }
+
+    /// The id of the `<clipPath>` this clip is stored in.
+    ///
+    /// Derived from the geometry, so identical clips share a definition.
+    pub fn id(&self) -> String {
+        let (kind, (x, y), (w, h)) = match *self {
+            Clip::Rectangle { position, size } => ("rect", position, size),
+            Clip::Ellipse { position, radius } => ("ellipse", position, radius),
+        };
+        format!("svgnote-clip-{}-{}_{}_{}_{}", kind, x, y, w, h)
+    }
+
+    fn from_shape(name: &str, attributes: &Attributes<'_>) -> Result<Self, DocumentError> {
+        let get = |name: &str| -> Result<Scalar, DocumentError> {
+            let value = attributes
+                .get(name)
+                .ok_or_else(|| MissingAttribute(name.to_owned()))?;
+            Scalar::from_str(value)
+                .map_err(|_| InvalidAttribute(name.to_owned(), value.to_string()))
+        };
+        // The position of rectangles defaults to the origin
+        let offset = |name: &str| match attributes.get(name) {
+            Some(_) => get(name),
+            None => Ok(0.),
+        };
+        Ok(match name {
+            tag::Rectangle => Clip::rectangle(
+                (offset("x")?, offset("y")?),
+                (get("width")?, get("height")?),
+            ),
+            tag::Circle => {
+                let r = get("r")?;
+                Clip::ellipse((get("cx")?, get("cy")?), (r, r))
+            }
+            _ => Clip::ellipse((get("cx")?, get("cy")?), (get("rx")?, get("ry")?)),
+        })
+    }
+}
+
+impl From<&Clip> for element::ClipPath {
+    fn from(clip: &Clip) -> Self {
+        let clip_path = element::ClipPath::new().set("id", clip.id());
+        match *clip {
+            Clip::Rectangle { position, size } => clip_path.add(
+                element::Rectangle::new()
+                    .set("x", position.0)
+                    .set("y", position.1)
+                    .set("width", size.0)
+                    .set("height", size.1),
+            ),
+            Clip::Ellipse { position, radius } => clip_path.add(
+                element::Ellipse::new()
+                    .set("cx", position.0)
+                    .set("cy", position.1)
+                    .set("rx", radius.0)
+                    .set("ry", radius.1),
+            ),
+        }
+    }
+}
+
+/// Collects all `<clipPath>`s consisting of a single rectangle, circle or
+/// ellipse by their id.
+///
+/// Other clip paths are not supported and ignored.
+pub(crate) fn clip_paths<'a>(
+    events: impl Iterator<Item = Event<'a>>,
+) -> Result<HashMap<String, Clip>, DocumentError> {
+    let mut clips = HashMap::new();
+    // The id and the shapes of the `<clipPath>` currently parsed, `None` for
+    // shapes not supported
+    let mut current: Option<(String, Vec<Option<Clip>>)> = None;
+    for event in events {
+        match (event, &mut current) {
+            (Event::Tag(tag::ClipPath, tag::Type::Start, attributes), _) => {
+                current = attributes.get("id").map(|id| (id.to_string(), vec![]));
+            }
+            (Event::Tag(tag::ClipPath, tag::Type::End, _), _) => {
+                if let Some((id, shapes)) = current.take() {
+                    if let [Some(clip)] = shapes[..] {
+                        clips.insert(id, clip);
+                    }
+                }
+            }
+            (Event::Tag(name, tag::Type::Start, attributes), Some((_, shapes)))
+            | (Event::Tag(name, tag::Type::Empty, attributes), Some((_, shapes))) => {
+                shapes.push(match name {
+                    tag::Rectangle | tag::Circle | tag::Ellipse => {
+                        Some(Clip::from_shape(name, &attributes)?)
+                    }
+                    _ => None,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(clips)
+}
+
+/// All distinct clips used by `elements`, descending into layers.
+pub(crate) fn clips(elements: &[Element]) -> Vec<Clip> {
+    let mut clips: Vec<Clip> = vec![];
+    for element in elements {
+        let nested = match element {
+            Element::Layer(layer) => self::clips(&layer.elements),
+            _ => vec![],
+        };
+        for clip in element.clip().into_iter().chain(nested) {
+            if !clips.contains(&clip) {
+                clips.push(clip);
+            }
+        }
+    }
+    clips
+}
+
+/// Parses the id out of a `url(#id)` reference.
+pub(crate) fn url_id(value: &str) -> Option<&str> {
+    value
+        .trim()
+        .strip_prefix("url(#")
+        .and_then(|v| v.strip_suffix(')'))
+}
@@ -0,0 +1,360 @@
+use std::any::Any;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+use svg::node::element::Element as SvgElement;
+use svg::node::Node;
+
+use crate::geometry::BoundingBox;
+use crate::DocumentError;
+
+use super::Attributes;
+use super::Element;
+use super::Properties;
+
+/// An element kind of an app, stored as the SVG element [`tag`] with
+/// `svgnote:tool` set to [`tool`], see [`ElementRegistry`].
+///
+/// The library writes, hashes, compares and hit tests them by their
+/// attributes and bounds, but does not draw them, e.g. in the vertex
+/// buffers. They are leaf elements, not groups, and shared between
+/// clones.
+///
+/// [`tag`]: NoteElement::tag
+/// [`tool`]: NoteElement::tool
+pub trait NoteElement: fmt::Debug + Send + Sync {
+    /// The `svgnote:tool` it is stored with.
+    fn tool(&self) -> &str;
+
+    /// The name of the SVG element, e.g. `path`.
+    fn tag(&self) -> &str;
+
+    /// The attributes of the SVG element, without `svgnote:tool` and the
+    /// [`Properties`], which are written by the library.
+    fn attributes(&self) -> BTreeMap<String, String>;
+
+    /// The bounds before the transform of the properties, `None` if it
+    /// draws nothing.
+    fn bounds(&self) -> Option<BoundingBox>;
+
+    /// For [`Custom::downcast_ref`].
+    fn as_any(&self) -> &dyn Any;
+}
+
+/// A custom element as stored, for documents read without its parser, see
+/// [`ElementRegistry::resolve`]. Unknown tools are read as these from SVG,
+/// JSON and protobuf alike, so saving keeps them.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stored {
+    pub tool: String,
+    pub tag: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl NoteElement for Stored {
+    fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    fn attributes(&self) -> BTreeMap<String, String> {
+        self.attributes.clone()
+    }
+
+    fn bounds(&self) -> Option<BoundingBox> {
+        None
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// The attributes the [`Properties`] are read from, not kept in [`Stored`]
+/// ones, as they are written from the properties.
+const PROPERTIES: &[&str] = &[
+    "id",
+    "display",
+    "svgnote:hidden",
+    "clip-path",
+    "transform",
+    "vector-effect",
+    "svgnote:author",
+    "svgnote:link",
+    "svgnote:bbox",
+];
+
+/// An element of a kind in an [`ElementRegistry`].
+#[derive(Debug, Clone)]
+pub struct Custom {
+    pub element: Arc<dyn NoteElement>,
+    pub properties: Properties,
+}
+
+impl Custom {
+    pub fn new(element: impl NoteElement + 'static) -> Self {
+        Custom {
+            element: Arc::new(element),
+            properties: Properties::default(),
+        }
+    }
+
+    /// The element as the type of the app, if it is one.
+    pub fn downcast_ref<T: NoteElement + 'static>(&self) -> Option<&T> {
+        self.element.as_any().downcast_ref()
+    }
+
+    /// The element of an app stored as the SVG element `tag`, read without
+    /// its parser.
+    pub(crate) fn stored(tag: &str, attributes: &Attributes<'_>) -> Result<Self, DocumentError> {
+        let tool = attributes.get("svgnote:tool").map_or("", |tool| &**tool);
+        Ok(Custom {
+            element: Arc::new(Stored {
+                tool: tool.to_owned(),
+                tag: tag.to_owned(),
+                attributes: attributes
+                    .iter()
+                    .filter(|(name, _)| *name != "svgnote:tool" && !PROPERTIES.contains(name))
+                    .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                    .collect(),
+            }),
+            properties: Properties::from_attributes(attributes)?,
+        })
+    }
+
+    pub(crate) fn to_svg(&self) -> SvgElement {
+        let mut node = SvgElement::new(self.element.tag());
+        for (name, value) in self.element.attributes() {
+            node.assign(name, value);
+        }
+        node.assign("svgnote:tool", self.element.tool());
+        self.properties.assign_to(&mut node);
+        node
+    }
+}
+
+impl PartialEq for Custom {
+    fn eq(&self, other: &Custom) -> bool {
+        let (a, b) = (&self.element, &other.element);
+        self.properties == other.properties
+            && (a.tool(), a.tag(), a.attributes()) == (b.tool(), b.tag(), b.attributes())
+    }
+}
+
+type Parser =
+    Box<dyn Fn(&Attributes<'_>) -> Result<Arc<dyn NoteElement>, DocumentError> + Send + Sync>;
+
+/// Parsers for the [`Custom`] elements of an app by `svgnote:tool`, so it
+/// can add element kinds, see
+/// [`ParseOptions::elements`](crate::options::ParseOptions::elements).
+///
+/// Registered tools are parsed before the ones of the library, so they can
+/// be replaced as well.
+#[derive(Default)]
+pub struct ElementRegistry {
+    parsers: Vec<(String, Parser)>,
+}
+
+impl ElementRegistry {
+    /// A registry without any parsers.
+    pub fn new() -> Self {
+        ElementRegistry::default()
+    }
+
+    /// Adds `parse` for the elements with the `svgnote:tool`, replacing the
+    /// one for the same tool.
+    pub fn register<E, F>(&mut self, tool: impl Into<String>, parse: F)
+    where
+        E: NoteElement + 'static,
+        F: Fn(&Attributes<'_>) -> Result<E, DocumentError> + Send + Sync + 'static,
+    {
+        let tool = tool.into();
+        self.parsers.retain(|(t, _)| *t != tool);
+        let parse: Parser =
+            Box::new(move |attributes| Ok(Arc::new(parse(attributes)?) as Arc<dyn NoteElement>));
+        self.parsers.push((tool, parse));
+    }
+
+    /// The registered tools, in the order they were registered.
+    pub fn tools(&self) -> impl Iterator<Item = &str> {
+        self.parsers.iter().map(|(tool, _)| &**tool)
+    }
+
+    /// The element with the attributes, `None` if its tool is not
+    /// registered.
+    pub fn parse(&self, attributes: &Attributes<'_>) -> Option<Result<Custom, DocumentError>> {
+        let tool: &str = attributes.get("svgnote:tool")?;
+        let (_, parse) = self.parsers.iter().find(|(t, _)| t == tool)?;
+        Some(parse(attributes).and_then(|element| {
+            Ok(Custom {
+                element,
+                properties: Properties::from_attributes(attributes)?,
+            })
+        }))
+    }
+
+    /// Parses the [`Stored`] elements of registered tools, also in layers,
+    /// e.g. after reading JSON.
+    pub fn resolve(&self, elements: &mut [Element]) -> Result<(), DocumentError> {
+        for element in elements {
+            match element {
+                Element::Layer(layer) => self.resolve(&mut layer.elements)?,
+                Element::Custom(custom) => {
+                    let stored = match custom.downcast_ref::<Stored>() {
+                        Some(stored) => stored,
+                        None => continue,
+                    };
+                    let parsed = {
+                        let mut attributes: Attributes = stored
+                            .attributes
+                            .iter()
+                            .map(|(name, value)| (Cow::Borrowed(&**name), Cow::Borrowed(&**value)))
+                            .collect();
+                        attributes.insert("svgnote:tool", &*stored.tool);
+                        self.parse(&attributes)
+                    };
+                    if let Some(parsed) = parsed {
+                        custom.element = parsed?.element;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ElementRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.tools()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::any::Any;
+    use std::collections::BTreeMap;
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use crate::elements::{Custom, Element, ElementRegistry, NoteElement, Stored};
+    use crate::geometry::BoundingBox;
+    use crate::ids::ElementId;
+    use crate::options::ParseOptions;
+    use crate::scalar::Scalar;
+    use crate::{Document, DocumentError};
+
+    /// A marker of the app, stored as `<circle>`.
+    #[derive(Debug, PartialEq)]
+    struct Stamp {
+        position: (Scalar, Scalar),
+    }
+
+    impl NoteElement for Stamp {
+        fn tool(&self) -> &str {
+            "stamp"
+        }
+
+        fn tag(&self) -> &str {
+            "circle"
+        }
+
+        fn attributes(&self) -> BTreeMap<String, String> {
+            vec![
+                ("cx".to_owned(), self.position.0.to_string()),
+                ("cy".to_owned(), self.position.1.to_string()),
+                ("r".to_owned(), "5".to_owned()),
+            ]
+            .into_iter()
+            .collect()
+        }
+
+        fn bounds(&self) -> Option<BoundingBox> {
+            let (x, y) = self.position;
+            Some(BoundingBox {
+                min: (x - 5., y - 5.),
+                max: (x + 5., y + 5.),
+            })
+        }
+
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = ElementRegistry::new();
+        registry.register("stamp", |attributes| {
+            let scalar = |name: &str| {
+                attributes
+                    .get(name)
+                    .and_then(|v| Scalar::from_str(v).ok())
+                    .ok_or_else(|| DocumentError::MissingAttribute(name.to_owned()))
+            };
+            Ok(Stamp {
+                position: (scalar("cx")?, scalar("cy")?),
+            })
+        });
+        let mut stamp = Custom::new(Stamp {
+            position: (10., 20.),
+        });
+        stamp.properties.id = Some(ElementId::new("stamp"));
+        let doc = Document {
+            elements: vec![Element::Custom(stamp)],
+            ..Default::default()
+        };
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"svgnote:tool="stamp""#));
+        assert!(svg.contains(r#"cx="10""#));
+
+        // Kept as stored without the parser
+        let stored = Document::from_str(&svg).unwrap();
+        assert_eq!(stored, doc);
+        assert_eq!(stored.to_string(), svg);
+        let lasso = r#"<svg><polygon svgnote:tool="lasso" points="0,0 1,1 1,0"/></svg>"#;
+        match &Document::from_str(lasso).unwrap().elements[..] {
+            [Element::Custom(custom)] => {
+                assert_eq!(custom.element.tag(), "polygon");
+                assert_eq!(custom.element.attributes()["points"], "0,0 1,1 1,0");
+            }
+            elements => panic!("not custom: {:?}", elements),
+        }
+        let options = ParseOptions {
+            elements: Some(Arc::new(registry)),
+            ..Default::default()
+        };
+        let (parsed, _) = Document::from_str_with(&svg, &options).unwrap();
+        assert_eq!(parsed, doc);
+        match &parsed.elements[0] {
+            Element::Custom(custom) => {
+                assert_eq!(
+                    custom.downcast_ref::<Stamp>(),
+                    Some(&Stamp {
+                        position: (10., 20.)
+                    })
+                )
+            }
+            element => panic!("not custom: {:?}", element),
+        }
+
+        // Read without the parser
+        let stored = Stored {
+            tool: "stamp".to_owned(),
+            tag: "circle".to_owned(),
+            attributes: Stamp {
+                position: (10., 20.),
+            }
+            .attributes(),
+        };
+        let mut elements = vec![Element::Custom(Custom::new(stored))];
+        assert_ne!(elements[0].distance_to((10., 20.)), 0.);
+        options.elements.unwrap().resolve(&mut elements).unwrap();
+        assert_eq!(elements[0].distance_to((10., 20.)), 0.);
+    }
+}
@@ -0,0 +1,350 @@
+use std::fmt;
+use std::str::FromStr;
+
+use svg::node::element::Element as SvgElement;
+use svg::node::{self, Node};
+
+use crate::colors::Color;
+use crate::geometry::distance;
+use crate::ids::ElementId;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+use crate::UNITS_PER_MM;
+
+use super::attributes::{AttributeReader, Attributes};
+use super::FromAttributes;
+use super::Properties;
+
+/// The font size of the labels, `2mm`. Ticks and extension lines are sized
+/// after it.
+pub(crate) const LABEL_SIZE: Scalar = 2. * UNITS_PER_MM;
+
+/// A unit lengths are shown in, e.g. by [`Dimension`]s.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LengthUnit {
+    Millimeters,
+    Centimeters,
+    Inches,
+}
+
+impl Default for LengthUnit {
+    fn default() -> Self {
+        LengthUnit::Millimeters
+    }
+}
+
+impl LengthUnit {
+    /// The user units in one of the unit.
+    pub fn units(self) -> Scalar {
+        match self {
+            LengthUnit::Millimeters => UNITS_PER_MM,
+            LengthUnit::Centimeters => 10. * UNITS_PER_MM,
+            LengthUnit::Inches => 25.4 * UNITS_PER_MM,
+        }
+    }
+
+    /// `length` in user units written in the unit, e.g. `12.5 mm`, with up
+    /// to one decimal for millimeters and two otherwise.
+    pub fn format(self, length: Scalar) -> String {
+        let decimals = match self {
+            LengthUnit::Millimeters => 1,
+            LengthUnit::Centimeters | LengthUnit::Inches => 2,
+        };
+        let value = format!("{:.*}", decimals, length / self.units());
+        let value = value.trim_end_matches('0').trim_end_matches('.');
+        format!("{} {}", value, self)
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LengthUnit::Millimeters => "mm",
+            LengthUnit::Centimeters => "cm",
+            LengthUnit::Inches => "in",
+        })
+    }
+}
+
+impl FromStr for LengthUnit {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mm" => Ok(LengthUnit::Millimeters),
+            "cm" => Ok(LengthUnit::Centimeters),
+            "in" => Ok(LengthUnit::Inches),
+            _ => Err(()),
+        }
+    }
+}
+
+/// An anchor point of another element an end of a [`Dimension`] is
+/// attached to, see
+/// [`Document::update_dimensions`](crate::Document::update_dimensions).
+#[derive(Debug, PartialEq, Clone)]
+pub struct Attachment {
+    /// The [id](Properties::id) of the element.
+    pub id: ElementId,
+    /// The index in the [anchors](crate::snap::anchors) of the element.
+    pub anchor: usize,
+}
+
+/// A measurement of the distance from `start` to `end`, stored as
+/// `<g svgnote:tool="dimension">` with the dimension line and its label,
+/// which are generated.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Dimension {
+    pub start: (Scalar, Scalar),
+    pub end: (Scalar, Scalar),
+    /// The elements `start` and `end` are taken from, if any.
+    pub attachments: [Option<Attachment>; 2],
+    /// The distance of the dimension line from the measured points, to the
+    /// left on screen looking from `start` to `end`, negative for the right.
+    pub offset: Scalar,
+    /// The unit of the label.
+    pub unit: LengthUnit,
+    pub color: Color,
+    pub width: Scalar,
+    pub properties: Properties,
+}
+
+impl Dimension {
+    /// Black, `1` wide and `5mm` off the points, without attachments.
+    pub fn new(start: (Scalar, Scalar), end: (Scalar, Scalar), unit: LengthUnit) -> Self {
+        Dimension {
+            start,
+            end,
+            attachments: [None, None],
+            offset: 5. * UNITS_PER_MM,
+            unit,
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            properties: Properties::default(),
+        }
+    }
+
+    /// The distance measured, in user units.
+    pub fn length(&self) -> Scalar {
+        distance(self.start, self.end)
+    }
+
+    /// The label, e.g. `12.5 mm`.
+    pub fn label(&self) -> String {
+        self.unit.format(self.length())
+    }
+
+    /// The direction from `start` to `end` and the one to the left of it,
+    /// both of length `1`.
+    fn axes(&self) -> ((Scalar, Scalar), (Scalar, Scalar)) {
+        let length = self.length();
+        let along = if length > 0. {
+            (
+                (self.end.0 - self.start.0) / length,
+                (self.end.1 - self.start.1) / length,
+            )
+        } else {
+            (1., 0.)
+        };
+        (along, (along.1, -along.0))
+    }
+
+    /// `point` moved `along` and `across`, to the left.
+    fn moved(&self, (x, y): (Scalar, Scalar), along: Scalar, across: Scalar) -> (Scalar, Scalar) {
+        let (d, n) = self.axes();
+        (
+            x + d.0 * along + n.0 * across,
+            y + d.1 * along + n.1 * across,
+        )
+    }
+
+    /// The lines drawn: the extension lines from the points, the dimension
+    /// line and the ticks at its ends.
+    pub fn segments(&self) -> Vec<[(Scalar, Scalar); 2]> {
+        // The extension lines reach a bit past the dimension line
+        let past = self.offset + self.offset.signum() * LABEL_SIZE / 4.;
+        let tick = LABEL_SIZE / 4.;
+        let a = self.moved(self.start, 0., self.offset);
+        let b = self.moved(self.end, 0., self.offset);
+        vec![
+            [self.start, self.moved(self.start, 0., past)],
+            [self.end, self.moved(self.end, 0., past)],
+            [a, b],
+            [self.moved(a, -tick, -tick), self.moved(a, tick, tick)],
+            [self.moved(b, -tick, -tick), self.moved(b, tick, tick)],
+        ]
+    }
+
+    /// The start of the baseline of the centered label and its angle in
+    /// radians, turned so it is never upside down.
+    pub(crate) fn label_position(&self) -> ((Scalar, Scalar), Scalar) {
+        let (d, _) = self.axes();
+        let mut angle = d.1.atan2(d.0);
+        // The text grows to the left of the direction it is read in, its
+        // baseline is kept off the line on that side
+        let mut up = 1.;
+        if angle.abs() > PI / 2. {
+            angle -= PI.copysign(angle);
+            up = -1.;
+        }
+        let middle = (
+            (self.start.0 + self.end.0) / 2.,
+            (self.start.1 + self.end.1) / 2.,
+        );
+        let position = self.moved(middle, 0., self.offset + up * LABEL_SIZE / 4.);
+        (position, angle)
+    }
+
+    pub(crate) fn to_group(&self) -> SvgElement {
+        let mut group = SvgElement::new("g");
+        let point = |(x, y): (Scalar, Scalar)| format!("{},{}", x, y);
+        group.assign("svgnote:start", point(self.start));
+        group.assign("svgnote:end", point(self.end));
+        for (name, attachment) in ["start", "end"].iter().zip(&self.attachments) {
+            if let Some(attachment) = attachment {
+                group.assign(format!("svgnote:{}-id", name), attachment.id.to_string());
+                group.assign(
+                    format!("svgnote:{}-anchor", name),
+                    attachment.anchor.to_string(),
+                );
+            }
+        }
+        group.assign("svgnote:offset", self.offset);
+        group.assign("svgnote:unit", self.unit.to_string());
+        // Static
+        group.assign("svgnote:tool", "dimension");
+        group.assign("stroke", self.color.to_string_na());
+        if self.color.a < 0xFF {
+            group.assign("stroke-opacity", self.color.opacity());
+        }
+        group.assign("stroke-width", self.width);
+        // Generated
+        let mut path = SvgElement::new("path");
+        path.assign(
+            "d",
+            self.segments()
+                .iter()
+                .map(|[a, b]| format!("M{},{} L{},{}", a.0, a.1, b.0, b.1))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        path.assign("fill", "none");
+        group.append(path);
+        group.append(self.label_text());
+        self.properties.assign_to(&mut group);
+        group
+    }
+
+    /// The `<text>` of the label, without any of our attributes.
+    pub(crate) fn label_text(&self) -> SvgElement {
+        let ((x, y), angle) = self.label_position();
+        let mut text = SvgElement::new("text");
+        text.assign("x", x);
+        text.assign("y", y);
+        text.assign("font-family", "sans-serif");
+        text.assign("font-size", LABEL_SIZE);
+        text.assign("text-anchor", "middle");
+        text.assign("stroke", "none");
+        text.assign("fill", self.color.to_string_na());
+        if self.color.a < 0xFF {
+            text.assign("fill-opacity", self.color.opacity());
+        }
+        if angle != 0. {
+            text.assign(
+                "transform",
+                format!("rotate({} {} {})", angle.to_degrees(), x, y),
+            );
+        }
+        text.append(node::Text::new(self.label()));
+        text
+    }
+}
+
+impl FromAttributes for Dimension {
+    /// The generated children are skipped by the parser.
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let reader = AttributeReader::new(&attributes);
+        let attachment = |name: &str| -> Result<Option<Attachment>, DocumentError> {
+            let id = match attributes.get(&format!("svgnote:{}-id", name)) {
+                Some(id) => ElementId::new(id.to_string()),
+                None => return Ok(None),
+            };
+            let anchor = format!("svgnote:{}-anchor", name);
+            Ok(Some(Attachment {
+                id,
+                anchor: reader.parse(&anchor)?,
+            }))
+        };
+        let color = reader.color("stroke")?;
+        Ok(Dimension {
+            start: reader.point("svgnote:start")?,
+            end: reader.point("svgnote:end")?,
+            attachments: [attachment("start")?, attachment("end")?],
+            offset: reader.scalar("svgnote:offset")?,
+            unit: reader.parse("svgnote:unit")?,
+            color: match attributes.get("stroke-opacity") {
+                Some(_) => color.faded(reader.scalar("stroke-opacity")? as f32),
+                None => color,
+            },
+            width: reader.scalar("stroke-width")?,
+            properties: Properties::from_attributes(&attributes)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Attachment, Dimension, Element, Ellipse, LengthUnit};
+    use crate::ids::ElementId;
+    use crate::Document;
+
+    #[test]
+    fn dimension() {
+        let mut dimension = Dimension::new((0., 0.), (250., 0.), LengthUnit::Millimeters);
+        assert_eq!(dimension.label(), "12.5 mm");
+        assert_eq!(LengthUnit::Centimeters.format(200.), "1 cm");
+        assert_eq!(LengthUnit::Inches.format(508.), "1 in");
+
+        // From the center of a circle to the center of another
+        let circle = |id: &str, x| {
+            let mut ellipse = Ellipse::builder()
+                .position(x, 100.)
+                .radius(10.)
+                .build()
+                .unwrap();
+            ellipse.properties.id = Some(ElementId::new(id));
+            Element::Ellipse(ellipse)
+        };
+        dimension.attachments = [
+            Some(Attachment {
+                id: ElementId::new("a"),
+                anchor: 0,
+            }),
+            Some(Attachment {
+                id: ElementId::new("b"),
+                anchor: 0,
+            }),
+        ];
+        dimension.color = Color::rgba(0xFF, 0, 0, 0x80);
+        let mut doc = Document {
+            elements: vec![
+                circle("a", 0.),
+                circle("b", 400.),
+                Element::Dimension(dimension),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(doc.update_dimensions(), 1);
+        doc.metadata.unit = Some(LengthUnit::Inches);
+        assert_eq!(doc.dimension((0., 0.), (1., 1.)).unit, LengthUnit::Inches);
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"svgnote:tool="dimension""#));
+        assert!(svg.contains("20 mm"));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+        assert_eq!(doc.update_dimensions(), 0);
+    }
+}
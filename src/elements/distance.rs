@@ -0,0 +1,228 @@
+use crate::geometry::{
+    self, contains, distance, distance_to_segment, project_to_segment, BoundingBox,
+};
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+
+use super::ApproximateShaper;
+use super::Element;
+use super::Ephemeral;
+
+impl Element {
+    /// The distance of `point` to the painted area of the element, `0` if
+    /// `point` is on the stroke or in a visible fill.
+    ///
+    /// `point` is in the coordinates the element is placed in, i.e. the
+    /// element's own transform is applied. Hidden elements in layers are
+    /// ignored, empty layers and instances are infinitely far away.
+    pub fn distance_to(&self, point: (Scalar, Scalar)) -> Scalar {
+        self.distance_with(self.properties().transform, point)
+    }
+
+    /// The distance of `point` to the element after applying `transform`
+    /// instead of its own transform.
+    pub(crate) fn distance_with(&self, transform: Transform, point: (Scalar, Scalar)) -> Scalar {
+        let local = match transform.invert() {
+            Some(inverse) => inverse.apply(point),
+            None => return Scalar::INFINITY,
+        };
+        let scale = transform.scale_factor();
+        let distance = match self {
+            Element::Line(line) | Element::Ephemeral(Ephemeral { line, .. }) => {
+                let points = &line.points;
+                if points.len() == 1 {
+                    distance(local, points[0].into()) - line.width * points[0].2 / 2.0
+                } else {
+                    points
+                        .windows(2)
+                        .map(|w| {
+                            let (a, b) = (w[0].into(), w[1].into());
+                            let t = project_to_segment(local, a, b);
+                            let pressure = w[0].2 + t * (w[1].2 - w[0].2);
+                            distance_to_segment(local, a, b) - line.width * pressure / 2.0
+                        })
+                        .fold(Scalar::INFINITY, Scalar::min)
+                }
+            }
+            Element::Polyline(polyline) => {
+                let mut points: Vec<(Scalar, Scalar)> =
+                    polyline.points.iter().map(|&p| p.into()).collect();
+                if polyline.closed
+                    && polyline.properties.fill(polyline.fill).a > 0
+                    && contains(&points, local)
+                {
+                    0.0
+                } else {
+                    if polyline.closed {
+                        let first = points.first().copied();
+                        points.extend(first);
+                    }
+                    path_distance(&points, local) - polyline.max_width() / 2.0
+                }
+            }
+            Element::Ngon(ngon) => {
+                let points = ngon.points();
+                if ngon.properties.fill(ngon.fill).a > 0 && contains(&points, local) {
+                    0.0
+                } else {
+                    let mut closed = points.clone();
+                    closed.extend(points.first());
+                    path_distance(&closed, local) - ngon.width / 2.0
+                }
+            }
+            Element::Shape(shape) => {
+                if shape.properties.fill(shape.fill).a > 0 && contains(&shape.points, local) {
+                    0.0
+                } else {
+                    let mut closed = shape.points.clone();
+                    closed.extend(shape.points.first());
+                    path_distance(&closed, local) - shape.width / 2.0
+                }
+            }
+            Element::Arc(arc) => {
+                // Close enough for hit testing. Without the pie, the fill is
+                // closed by the chord, like in SVG
+                let points = arc.points(64);
+                if arc.properties.fill(arc.fill).a > 0 && contains(&points, local) {
+                    0.0
+                } else {
+                    let mut path = points.clone();
+                    if arc.pie {
+                        path.extend(points.first());
+                    }
+                    path_distance(&path, local) - arc.width / 2.0
+                }
+            }
+            Element::Ellipse(ellipse) => {
+                let center = distance(local, ellipse.position);
+                if ellipse.properties.fill(ellipse.fill).a > 0 && center < ellipse.radius {
+                    0.0
+                } else {
+                    (center - ellipse.radius).abs() - ellipse.width / 2.0
+                }
+            }
+            Element::Text(text) => match text.bounds(&ApproximateShaper) {
+                Some(BoundingBox { min, max }) => {
+                    let corners = [min, (max.0, min.1), max, (min.0, max.1), min];
+                    if contains(&corners, local) {
+                        0.0
+                    } else {
+                        path_distance(&corners, local)
+                    }
+                }
+                None => distance(local, text.position),
+            },
+            Element::FileRef(file) => {
+                let BoundingBox { min, max } = file.bounds();
+                let corners = [min, (max.0, min.1), max, (min.0, max.1), min];
+                if contains(&corners, local) {
+                    0.0
+                } else {
+                    path_distance(&corners, local)
+                }
+            }
+            Element::Layer(layer) => {
+                return layer
+                    .elements
+                    .iter()
+                    .filter(|e| !e.is_hidden())
+                    .map(|e| e.distance_with(transform * e.properties().transform, point))
+                    .fold(Scalar::INFINITY, Scalar::min)
+            }
+            // The symbol is not known here
+            Element::Instance(_) => return Scalar::INFINITY,
+            Element::Custom(custom) => match custom.element.bounds() {
+                Some(BoundingBox { min, max }) => {
+                    let corners = [min, (max.0, min.1), max, (min.0, max.1), min];
+                    if contains(&corners, local) {
+                        0.0
+                    } else {
+                        path_distance(&corners, local)
+                    }
+                }
+                None => return Scalar::INFINITY,
+            },
+            Element::Dimension(dimension) => {
+                // Close enough for hit testing, without the label
+                dimension
+                    .segments()
+                    .iter()
+                    .map(|[a, b]| distance_to_segment(local, *a, *b))
+                    .fold(Scalar::INFINITY, Scalar::min)
+                    - dimension.width / 2.0
+            }
+            // Only the border, so the elements in it can still be picked
+            Element::Frame(frame) => {
+                let corners = frame.corners();
+                path_distance(&[&corners[..], &corners[..1]].concat(), local)
+            }
+        };
+        distance.max(0.0) * scale
+    }
+}
+
+/// The distance of `point` to the path along `points`.
+fn path_distance(points: &[(Scalar, Scalar)], point: (Scalar, Scalar)) -> Scalar {
+    match points {
+        [] => Scalar::INFINITY,
+        [p] => geometry::distance(*p, point),
+        _ => points
+            .windows(2)
+            .map(|w| distance_to_segment(point, w[0], w[1]))
+            .fold(Scalar::INFINITY, Scalar::min),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Line, LinePoint, Properties};
+    use crate::scalar::Scalar;
+    use crate::transform::Transform;
+    use crate::Document;
+
+    fn assert_close(a: Scalar, b: Scalar) {
+        assert!((a - b).abs() < 1e-5, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn distance_to() {
+        let line = Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 2.,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(10., 0., 0.)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        });
+        assert_close(line.distance_to((0., 5.)), 4.);
+        assert_close(line.distance_to((5., 3.)), 2.5);
+        assert_close(line.distance_to((5., 0.2)), 0.);
+
+        let mut circle = Element::Ellipse(Ellipse {
+            position: (0., 0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 2.,
+            radius: 10.,
+            properties: Properties::default(),
+        });
+        assert_close(circle.distance_to((0., 0.)), 9.);
+        assert_close(circle.distance_to((0., 20.)), 9.);
+        Transform::scale(2., 2.).apply_to(&mut circle);
+        assert_close(circle.distance_to((0., 0.)), 18.);
+
+        let mut layer = Layer::new("layer");
+        layer.elements = vec![line, circle];
+        layer.elements[0].set_hidden(true);
+        let doc = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+        assert_close(doc.elements[0].distance_to((0., 5.)), 13.);
+        assert_matches!(
+            doc.nearest_element((0., 5.)),
+            Some((Element::Ellipse(_), d)) if (d - 13.).abs() < 1e-5
+        );
+        assert_eq!(Document::default().nearest_element((0., 0.)), None);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::Document;
+
+use super::Element;
+use super::Line;
+
+/// A stroke shown only for a while, e.g. the trail of a laser pointer while
+/// presenting, see [`Document::expire`].
+///
+/// It is drawn and hit tested like a [`Line`], but never stored: it is left
+/// out of SVG, JSON, protobuf and archives.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Ephemeral {
+    pub line: Line,
+    /// How long each point is shown, in milliseconds.
+    pub ttl: u64,
+    /// When the stroke was drawn, in milliseconds since the Unix epoch, for
+    /// lines without [timestamps](Line::timestamps).
+    pub created: u64,
+}
+
+impl Ephemeral {
+    pub fn new(line: Line, ttl: u64, created: u64) -> Self {
+        Ephemeral { line, ttl, created }
+    }
+
+    /// Removes the points shown for longer than the ttl at `now`, so the
+    /// trail shrinks from its start, returning whether any are left.
+    pub fn expire(&mut self, now: u64) -> bool {
+        let (ttl, created) = (self.ttl, self.created);
+        let shown = |t: u64| t.saturating_add(ttl) > now;
+        let line = &mut self.line;
+        let expired = if line.timestamps.is_empty() {
+            if shown(created) {
+                0
+            } else {
+                line.points.len()
+            }
+        } else {
+            line.timestamps
+                .iter()
+                .position(|&t| shown(t))
+                .unwrap_or(line.timestamps.len())
+        };
+        line.points.drain(..expired);
+        line.timestamps.drain(..expired.min(line.timestamps.len()));
+        !line.points.is_empty()
+    }
+}
+
+/// Expires the ephemeral elements in `elements` and in layers, returning the
+/// number of removed ones.
+fn expire(elements: &mut Vec<Element>, now: u64) -> usize {
+    let mut removed = 0;
+    for element in elements.iter_mut() {
+        match element {
+            Element::Ephemeral(e) => {
+                e.expire(now);
+            }
+            Element::Layer(layer) => removed += expire(&mut layer.elements, now),
+            _ => {}
+        }
+    }
+    let before = elements.len();
+    elements.retain(|e| !matches!(e, Element::Ephemeral(e) if e.line.points.is_empty()));
+    removed + before - elements.len()
+}
+
+impl Document {
+    /// Removes the [`Ephemeral`] elements shown for longer than their ttl at
+    /// `now`, in milliseconds since the Unix epoch, and the expired points of
+    /// the others, returning the number of removed elements.
+    ///
+    /// Call it before rendering, e.g. on every frame while there are any.
+    pub fn expire(&mut self, now: u64) -> usize {
+        expire(&mut self.elements, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ephemeral, Line};
+    use crate::Document;
+
+    #[test]
+    fn ephemeral() {
+        let line = Line::builder()
+            .color(Color::rgb(0xFF, 0, 0))
+            .width(4.)
+            .timed_point(0., 0., 1., 1000)
+            .timed_point(100., 0., 1., 1100)
+            .timed_point(200., 0., 1., 1200)
+            .build()
+            .unwrap();
+        let mut doc = Document {
+            elements: vec![Element::Ephemeral(Ephemeral::new(line, 500, 1000))],
+            ..Default::default()
+        };
+        assert_eq!(doc.render_items().len(), 1);
+        let (nearest, distance) = doc.nearest_element((150., 1.)).unwrap();
+        assert!(matches!(nearest, Element::Ephemeral(_)));
+        assert_eq!(distance, 0.);
+
+        // Never stored
+        let svg = doc.to_string();
+        assert!(!svg.contains("<path"));
+        assert!(Document::from_str(&svg).unwrap().elements.is_empty());
+
+        // The trail shrinks from its start
+        assert_eq!(doc.expire(1550), 0);
+        match &doc.elements[0] {
+            Element::Ephemeral(e) => assert_eq!(e.line.points.len(), 2),
+            element => panic!("not ephemeral: {:?}", element),
+        }
+        assert_eq!(doc.expire(1700), 1);
+        assert!(doc.elements.is_empty());
+    }
+}
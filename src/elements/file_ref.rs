@@ -0,0 +1,148 @@
+use std::str::FromStr;
+
+use svg::node::element;
+
+use crate::escape::{escape, unescape};
+use crate::geometry::BoundingBox;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+use DocumentError::MissingAttribute;
+
+use super::Attributes;
+use super::FromAttributes;
+use super::Properties;
+
+/// A file shown in the document but not embedded in it, e.g. a large image,
+/// or an embedded [asset](crate::assets), stored as
+/// `<image svgnote:tool="file">`, or `<use svgnote:tool="file">` of the
+/// symbol of the asset.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileRef {
+    /// Relative to the document, or an absolute URL.
+    pub path: String,
+    /// The top left corner.
+    pub position: (Scalar, Scalar),
+    pub size: (Scalar, Scalar),
+    pub properties: Properties,
+}
+
+impl FileRef {
+    /// Whether the path is relative, i.e. has to be changed when the document
+    /// is moved without the file.
+    pub fn is_relative(&self) -> bool {
+        !(self.path.starts_with('/') || self.path.contains(':'))
+    }
+
+    /// Prefixes a relative path with `base_dir`, e.g. `..` if the document
+    /// was moved into a subdirectory without the file.
+    ///
+    /// Paths are separated by `/`, as in URLs.
+    pub fn relink(&mut self, base_dir: &str) {
+        if !self.is_relative() {
+            return;
+        }
+        let mut components: Vec<&str> = vec![];
+        for component in base_dir.split('/').chain(self.path.split('/')) {
+            match component {
+                "" | "." => {}
+                ".." if components.last().map_or(false, |&c| c != "..") => {
+                    components.pop();
+                }
+                component => components.push(component),
+            }
+        }
+        let path = components.join("/");
+        self.path = if base_dir.starts_with('/') {
+            format!("/{}", path)
+        } else {
+            path
+        };
+    }
+
+    /// The rectangle the file is shown in.
+    pub fn bounds(&self) -> BoundingBox {
+        let (x, y) = self.position;
+        let (width, height) = self.size;
+        BoundingBox {
+            min: (x, y),
+            max: (x + width, y + height),
+        }
+    }
+}
+
+impl From<&FileRef> for element::Image {
+    fn from(file: &FileRef) -> Self {
+        let mut element = element::Image::new()
+            .set("x", file.position.0)
+            .set("y", file.position.1)
+            .set("width", file.size.0)
+            .set("height", file.size.1)
+            .set("href", escape(&file.path))
+            // Static
+            .set("svgnote:tool", "file");
+        file.properties.assign_to(&mut element);
+        element
+    }
+}
+
+impl FromAttributes for FileRef {
+    /// Images of other editors may only have the older `xlink:href`.
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let scalar = |name: &str| -> Result<Scalar, DocumentError> {
+            match attributes.get(name) {
+                Some(value) => Scalar::from_str(value)
+                    .map_err(|_| InvalidAttribute(name.to_owned(), value.to_string())),
+                None => Ok(0.0),
+            }
+        };
+        Ok(FileRef {
+            path: attributes
+                .get("href")
+                .or_else(|| attributes.get("xlink:href"))
+                .map(|path| unescape(path))
+                .ok_or_else(|| MissingAttribute("href".to_owned()))?,
+            position: (scalar("x")?, scalar("y")?),
+            size: (scalar("width")?, scalar("height")?),
+            properties: Properties::from_attributes(&attributes)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Element, FileRef, Properties};
+    use crate::Document;
+
+    #[test]
+    fn relink() {
+        let file = |path: &str| FileRef {
+            path: path.to_owned(),
+            position: (10., 20.),
+            size: (300., 200.),
+            properties: Properties::default(),
+        };
+        let mut doc = Document {
+            elements: vec![
+                Element::FileRef(file("images/scan.png")),
+                Element::FileRef(file("https://example.com/scan.png")),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+
+        doc.relink("..");
+        assert_eq!(
+            doc.elements[0],
+            Element::FileRef(file("../images/scan.png"))
+        );
+        assert_eq!(
+            doc.elements[1],
+            Element::FileRef(file("https://example.com/scan.png"))
+        );
+        doc.relink("notes");
+        assert_eq!(doc.elements[0], Element::FileRef(file("images/scan.png")));
+    }
+}
@@ -0,0 +1,183 @@
+use svg::node::element::Element as SvgElement;
+use svg::node::Node;
+
+use crate::escape::{escape, unescape};
+use crate::geometry::BoundingBox;
+use crate::options::SerializeOptions;
+use crate::scalar::Scalar;
+use crate::Document;
+use crate::DocumentError;
+
+use super::attributes::{AttributeReader, Attributes};
+use super::Element;
+use super::FromAttributes;
+use super::Properties;
+
+/// A named section of the canvas, exported as a page or slide of its own,
+/// see [`Document::frames`].
+///
+/// Stored as `<rect svgnote:tool="frame">` without fill and stroke, so only
+/// apps drawing frames show it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub name: String,
+    /// The position among the frames, lowest first. Frames with the same
+    /// index are in document order.
+    pub index: u32,
+    /// The top left corner.
+    pub position: (Scalar, Scalar),
+    pub size: (Scalar, Scalar),
+    pub properties: Properties,
+}
+
+impl Frame {
+    pub fn new(name: impl Into<String>, index: u32, bounds: BoundingBox) -> Self {
+        Frame {
+            name: name.into(),
+            index,
+            position: bounds.min,
+            size: (bounds.width(), bounds.height()),
+            properties: Properties::default(),
+        }
+    }
+
+    /// The rectangle of the frame, in its own coordinates.
+    pub fn bounds(&self) -> BoundingBox {
+        let (x, y) = self.position;
+        let (width, height) = self.size;
+        BoundingBox {
+            min: (x, y),
+            max: (x + width, y + height),
+        }
+    }
+
+    /// The corners, clockwise from the top left one.
+    pub(crate) fn corners(&self) -> [(Scalar, Scalar); 4] {
+        let BoundingBox { min, max } = self.bounds();
+        [min, (max.0, min.1), max, (min.0, max.1)]
+    }
+
+    pub(crate) fn to_rect(&self) -> SvgElement {
+        let mut rect = SvgElement::new("rect");
+        rect.assign("x", self.position.0);
+        rect.assign("y", self.position.1);
+        rect.assign("width", self.size.0);
+        rect.assign("height", self.size.1);
+        rect.assign("svgnote:name", escape(&self.name));
+        rect.assign("svgnote:index", self.index);
+        // Static
+        rect.assign("svgnote:tool", "frame");
+        rect.assign("fill", "none");
+        rect.assign("stroke", "none");
+        self.properties.assign_to(&mut rect);
+        rect
+    }
+}
+
+impl FromAttributes for Frame {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let reader = AttributeReader::new(&attributes);
+        Ok(Frame {
+            name: unescape(reader.get("svgnote:name")?),
+            index: reader.parse("svgnote:index")?,
+            position: (reader.scalar("x")?, reader.scalar("y")?),
+            // Empty frames would be pages without area
+            size: (reader.positive("width")?, reader.positive("height")?),
+            properties: Properties::from_attributes(&attributes)?,
+        })
+    }
+}
+
+impl Document {
+    /// The visible frames, also in layers, by their index, each with its
+    /// bounds in document coordinates.
+    pub fn frames(&self) -> Vec<(&Frame, BoundingBox)> {
+        let mut frames: Vec<_> = self
+            .render_items()
+            .into_iter()
+            .filter_map(|item| match item.element {
+                Element::Frame(frame) => Some((frame, item.bounds()?)),
+                _ => None,
+            })
+            .collect();
+        // Stable, so equal indices keep document order
+        frames.sort_by_key(|(frame, _)| frame.index);
+        frames
+    }
+
+    /// A document per [frame](Document::frames) with its name, showing only
+    /// the area of the frame, e.g. as the pages of a PDF or the slides of a
+    /// talk.
+    pub fn export_frames(&self) -> Vec<(String, String)> {
+        self.frames()
+            .into_iter()
+            .map(|(frame, bounds)| {
+                let options = SerializeOptions {
+                    view_box: Some(bounds),
+                    ..Default::default()
+                };
+                (frame.name.clone(), self.to_string_with(&options))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Element, Frame, Layer};
+    use crate::geometry::BoundingBox;
+    use crate::transform::Transform;
+    use crate::{Document, DocumentError};
+
+    #[test]
+    fn frames() {
+        let frame = |name: &str, index, x| {
+            Element::Frame(Frame::new(
+                name,
+                index,
+                BoundingBox {
+                    min: (x, 0.),
+                    max: (x + 1600., 900.),
+                },
+            ))
+        };
+        let mut layer = Layer::new("later");
+        layer.elements.push(frame("intro", 0, 0.));
+        layer.properties.transform = Transform::translate(0., 1000.);
+        let doc = Document {
+            elements: vec![
+                frame("end", 2, 2000.),
+                frame("<middle> & \"more\"", 1, 4000.),
+                Element::Layer(layer),
+            ],
+            ..Default::default()
+        };
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"svgnote:tool="frame""#));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+
+        let frames = doc.frames();
+        let names: Vec<_> = frames.iter().map(|(frame, _)| &*frame.name).collect();
+        assert_eq!(names, ["intro", "<middle> & \"more\"", "end"]);
+        assert_eq!(
+            frames[0].1,
+            BoundingBox {
+                min: (0., 1000.),
+                max: (1600., 1900.)
+            }
+        );
+
+        let pages = doc.export_frames();
+        assert_eq!(pages.len(), 3);
+        assert_eq!(pages[0].0, "intro");
+        assert!(pages[0].1.contains(r#"viewBox="0 1000 1600 900""#));
+
+        let empty = r#"<svg><rect svgnote:tool="frame" svgnote:name="a" svgnote:index="0" x="0" y="0" width="0" height="9"/></svg>"#;
+        assert_matches!(
+            Document::from_str(empty).map_err(DocumentError::without_location),
+            Err(DocumentError::InvalidAttribute(name, _)) if name == "width"
+        );
+    }
+}
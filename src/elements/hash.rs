@@ -0,0 +1,435 @@
+use std::collections::hash_map::DefaultHasher;
+use std::convert::TryFrom;
+use std::hash::Hasher;
+
+use crate::colors::Color;
+use crate::ids::ElementId;
+use crate::metadata::Target;
+use crate::scalar::{Scalar, FIXED_POINT_SCALE};
+
+use super::Clip;
+use super::Element;
+use super::Ephemeral;
+use super::Layer;
+use super::Line;
+use super::Properties;
+
+impl Element {
+    /// A hash over all data of the element, including nested elements.
+    ///
+    /// It changes whenever the element is changed, so it can be used as key
+    /// when caching e.g. tessellations or textures of the element. Computing
+    /// it is linear in the number of points.
+    ///
+    /// The key is not meant to be persisted, it can differ between builds.
+    pub fn cache_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_content(false, &mut hasher);
+        hasher.finish()
+    }
+
+    /// A hash over the geometry and style of the element, including nested
+    /// elements, that is the same on all platforms and builds, e.g. for
+    /// finding duplicates or detecting changes when syncing.
+    ///
+    /// Coordinates and sizes are rounded to fixed-point (see
+    /// [`to_fixed_point`](crate::scalar::to_fixed_point)) first, so tiny
+    /// differences from rounding do not change it. Timestamps and links are
+    /// not included.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        self.hash_content(true, &mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_content(&self, q: bool, h: &mut impl Hasher) {
+        match self {
+            Element::Line(e) => {
+                h.write_u8(0);
+                hash_line(e, q, h);
+            }
+            // Not equal to the line, as it is not stored
+            Element::Ephemeral(Ephemeral { line, ttl, created }) => {
+                h.write_u8(13);
+                hash_line(line, q, h);
+                h.write_u64(*ttl);
+                h.write_u64(*created);
+            }
+            Element::Ngon(e) => {
+                h.write_u8(1);
+                hash_scalars(
+                    &[e.position.0, e.position.1, e.width, e.angle, e.radius],
+                    q,
+                    h,
+                );
+                // Like before `n` was a `u16`, if it fits
+                match u8::try_from(e.n) {
+                    Ok(n) => h.write_u8(n),
+                    Err(_) => {
+                        h.write_u8(0);
+                        h.write(&e.n.to_le_bytes());
+                    }
+                }
+                hash_color(e.stroke, h);
+                hash_color(e.fill, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Ellipse(e) => {
+                h.write_u8(2);
+                hash_scalars(&[e.position.0, e.position.1, e.width, e.radius], q, h);
+                hash_color(e.stroke, h);
+                hash_color(e.fill, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Polyline(e) => {
+                h.write_u8(3);
+                hash_color(e.stroke, h);
+                hash_color(e.fill, h);
+                hash_scalar(e.width, q, h);
+                for p in &e.points {
+                    hash_scalars(&[p.0, p.1], q, h);
+                }
+                hash_scalars(&e.widths, q, h);
+                h.write_u8(e.closed as u8);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Text(e) => {
+                h.write_u8(5);
+                hash_scalars(&[e.position.0, e.position.1, e.font.size], q, h);
+                h.write_usize(e.content.len());
+                h.write(e.content.as_bytes());
+                h.write_usize(e.font.family.len());
+                h.write(e.font.family.as_bytes());
+                hash_color(e.color, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::FileRef(e) => {
+                h.write_u8(6);
+                h.write_usize(e.path.len());
+                h.write(e.path.as_bytes());
+                hash_scalars(&[e.position.0, e.position.1, e.size.0, e.size.1], q, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Shape(e) => {
+                h.write_u8(7);
+                h.write_usize(e.tool.len());
+                h.write(e.tool.as_bytes());
+                hash_scalars(
+                    &[e.position.0, e.position.1, e.width, e.angle, e.radius],
+                    q,
+                    h,
+                );
+                for (name, &value) in &e.parameters {
+                    h.write_usize(name.len());
+                    h.write(name.as_bytes());
+                    hash_scalar(value, q, h);
+                }
+                for p in &e.points {
+                    hash_scalars(&[p.0, p.1], q, h);
+                }
+                hash_color(e.stroke, h);
+                hash_color(e.fill, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Arc(e) => {
+                h.write_u8(8);
+                hash_scalars(
+                    &[
+                        e.position.0,
+                        e.position.1,
+                        e.width,
+                        e.radius,
+                        e.start,
+                        e.end,
+                    ],
+                    q,
+                    h,
+                );
+                h.write_u8(e.pie as u8);
+                hash_color(e.stroke, h);
+                hash_color(e.fill, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Instance(e) => {
+                h.write_u8(9);
+                h.write_usize(e.symbol.len());
+                h.write(e.symbol.as_bytes());
+                hash_scalars(&[e.position.0, e.position.1], q, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Custom(e) => {
+                h.write_u8(10);
+                let element = &e.element;
+                for s in [element.tool(), element.tag()].iter() {
+                    h.write_usize(s.len());
+                    h.write(s.as_bytes());
+                }
+                for (name, value) in element.attributes() {
+                    h.write_usize(name.len());
+                    h.write(name.as_bytes());
+                    h.write_usize(value.len());
+                    h.write(value.as_bytes());
+                }
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Dimension(e) => {
+                h.write_u8(11);
+                hash_scalars(
+                    &[e.start.0, e.start.1, e.end.0, e.end.1, e.offset, e.width],
+                    q,
+                    h,
+                );
+                for attachment in &e.attachments {
+                    match attachment {
+                        Some(attachment) => {
+                            h.write_u8(1);
+                            h.write_usize(attachment.id.as_str().len());
+                            h.write(attachment.id.as_str().as_bytes());
+                            h.write_usize(attachment.anchor);
+                        }
+                        None => h.write_u8(0),
+                    }
+                }
+                h.write_u8(e.unit as u8);
+                hash_color(e.color, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Frame(e) => {
+                h.write_u8(12);
+                h.write_usize(e.name.len());
+                h.write(e.name.as_bytes());
+                h.write_u32(e.index);
+                hash_scalars(&[e.position.0, e.position.1, e.size.0, e.size.1], q, h);
+                hash_properties(&e.properties, q, h);
+            }
+            Element::Layer(e) => {
+                hash_layer(e, q, h);
+                for element in &e.elements {
+                    element.hash_content(q, h);
+                }
+                hash_properties(&e.properties, q, h);
+            }
+        }
+    }
+}
+
+/// Hashes everything stored of `elements`, exactly, i.e. also what
+/// [`Element::fingerprint`] leaves out, but not [`Ephemeral`] ones, see
+/// [`Document::content_hash`](crate::Document::content_hash).
+pub(crate) fn hash_stored(elements: &[Element], h: &mut impl Hasher) {
+    let stored: Vec<_> = elements.iter().filter(|e| !e.is_ephemeral()).collect();
+    h.write_usize(stored.len());
+    for element in stored {
+        match element {
+            Element::Layer(e) => {
+                hash_layer(e, false, h);
+                hash_stored(&e.elements, h);
+                hash_properties(&e.properties, false, h);
+            }
+            element => element.hash_content(false, h),
+        }
+        let properties = element.properties();
+        hash_str(properties.id.as_ref().map(ElementId::as_str), h);
+        hash_str(
+            properties.link.as_ref().map(Target::to_string).as_deref(),
+            h,
+        );
+        h.write_u8(properties.non_scaling_stroke as u8);
+        if let Element::Line(line) = element {
+            h.write_usize(line.timestamps.len());
+            for &t in &line.timestamps {
+                h.write_u64(t);
+            }
+        }
+    }
+}
+
+fn hash_layer(layer: &Layer, quantized: bool, h: &mut impl Hasher) {
+    h.write_u8(4);
+    h.write_usize(layer.name.len());
+    h.write(layer.name.as_bytes());
+    hash_f32(layer.opacity, quantized, h);
+    h.write_u8(layer.blend as u8);
+}
+
+/// The [fingerprint](Element::fingerprint) of `elements` in their order, e.g.
+/// the ones of a document.
+pub(crate) fn fingerprint(elements: &[Element]) -> u64 {
+    let mut hasher = Fnv1a::default();
+    hasher.write_usize(elements.len());
+    for element in elements {
+        element.hash_content(true, &mut hasher);
+    }
+    hasher.finish()
+}
+
+fn hash_line(line: &Line, quantized: bool, h: &mut impl Hasher) {
+    hash_color(line.color, h);
+    hash_scalar(line.width, quantized, h);
+    for p in &line.points {
+        hash_scalars(&[p.0, p.1, p.2], quantized, h);
+    }
+    hash_properties(&line.properties, quantized, h);
+}
+
+fn hash_scalar(f: Scalar, quantized: bool, h: &mut impl Hasher) {
+    if quantized {
+        h.write_i64((f * FIXED_POINT_SCALE).round() as i64);
+    } else {
+        // `0.0 == -0.0`, so they need the same hash
+        h.write_u64(if f == 0.0 { 0 } else { f.to_bits() }.into());
+    }
+}
+
+fn hash_f32(f: f32, quantized: bool, h: &mut impl Hasher) {
+    hash_scalar(f as Scalar, quantized, h);
+}
+
+fn hash_scalars(fs: &[Scalar], quantized: bool, h: &mut impl Hasher) {
+    for &f in fs {
+        hash_scalar(f, quantized, h);
+    }
+}
+
+fn hash_str(s: Option<&str>, h: &mut impl Hasher) {
+    match s {
+        Some(s) => {
+            h.write_usize(s.len() + 1);
+            h.write(s.as_bytes());
+        }
+        None => h.write_usize(0),
+    }
+}
+
+fn hash_color(c: Color, h: &mut impl Hasher) {
+    h.write(&[c.r, c.g, c.b, c.a]);
+}
+
+fn hash_properties(p: &Properties, q: bool, h: &mut impl Hasher) {
+    h.write_u8(p.hidden as u8);
+    match p.clip {
+        Some(Clip::Rectangle { position, size }) => {
+            h.write_u8(1);
+            hash_scalars(&[position.0, position.1, size.0, size.1], q, h);
+        }
+        Some(Clip::Ellipse { position, radius }) => {
+            h.write_u8(2);
+            hash_scalars(&[position.0, position.1, radius.0, radius.1], q, h);
+        }
+        None => h.write_u8(0),
+    }
+    let t = p.transform;
+    hash_scalars(&[t.a, t.b, t.c, t.d, t.e, t.f], q, h);
+    // Only if set, so the hashes of opaque elements stay the same
+    if (p.stroke_opacity, p.fill_opacity) != (1., 1.) {
+        hash_f32(p.stroke_opacity, q, h);
+        hash_f32(p.fill_opacity, q, h);
+    }
+    hash_str(p.title.as_deref(), h);
+    hash_str(p.description.as_deref(), h);
+    hash_str(p.author.as_deref(), h);
+}
+
+/// The 64 bit FNV-1a hash, with integers written as little endian `u64`s,
+/// so it is the same everywhere.
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Ephemeral, Line, Properties};
+    use crate::Document;
+
+    #[test]
+    fn cache_key() {
+        let mut ellipse = Element::Ellipse(Ellipse {
+            position: (0., -0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0, 0, 0),
+            width: 1.,
+            radius: 2.,
+            properties: Properties::default(),
+        });
+        let key = ellipse.cache_key();
+        let clone = ellipse.clone();
+        clone.lod(1);
+        assert_eq!(clone.cache_key(), key);
+
+        if let Element::Ellipse(e) = &mut ellipse {
+            e.position.1 = 0.;
+        }
+        assert_eq!(ellipse.cache_key(), key);
+        ellipse.set_hidden(true);
+        assert_ne!(ellipse.cache_key(), key);
+
+        let line = Line::builder().point(0., 0., 1.).build().unwrap();
+        let ephemeral = Element::Ephemeral(Ephemeral::new(line.clone(), 500, 0));
+        assert_ne!(ephemeral.cache_key(), Element::Line(line).cache_key());
+    }
+
+    #[test]
+    fn fingerprint() {
+        let ellipse = |x| {
+            Element::Ellipse(Ellipse {
+                position: (x, 20.),
+                stroke: Color::rgb(0, 0, 0),
+                fill: Color::rgb(0xFF, 0, 0),
+                width: 1.,
+                radius: 2.,
+                properties: Properties::default(),
+            })
+        };
+        let fingerprint = ellipse(10.).fingerprint();
+        assert_eq!(fingerprint, ellipse(10.).fingerprint());
+        assert_eq!(ellipse(10.0001).fingerprint(), fingerprint);
+        assert_ne!(ellipse(10.1).fingerprint(), fingerprint);
+
+        let doc = Document {
+            elements: vec![ellipse(10.), ellipse(30.)],
+            ..Default::default()
+        };
+        let mut reordered = doc.clone();
+        reordered.elements.reverse();
+        assert_ne!(doc.fingerprint(), reordered.fingerprint());
+        assert_eq!(doc.fingerprint(), doc.clone().fingerprint());
+    }
+}
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::str::FromStr;
+
+use svg::node::element;
+use svg::node::Node;
+
+use crate::elems_eq;
+use crate::escape::{escape, unescape};
+use crate::render::render_items;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+
+use super::Attributes;
+use super::Element;
+use super::ForeignAttributes;
+use super::FromAttributes;
+use super::Output;
+use super::Properties;
+use super::StoredOpacity;
+
+/// How a layer is composited with what is below it, stored as
+/// `style="mix-blend-mode: …"`. Not applied by the library.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BlendMode {
+    Normal,
+    /// Darkens, e.g. for highlighters or marking over a scanned worksheet.
+    Multiply,
+    /// Lightens, the inverse of [`BlendMode::Multiply`].
+    Screen,
+    /// Multiplies the dark and screens the light parts of what is below.
+    Overlay,
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Normal
+    }
+}
+
+impl fmt::Display for BlendMode {
+    /// The CSS name, e.g. `multiply`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+        })
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "normal" => Ok(BlendMode::Normal),
+            "multiply" => Ok(BlendMode::Multiply),
+            "screen" => Ok(BlendMode::Screen),
+            "overlay" => Ok(BlendMode::Overlay),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A named group of elements, stored as `<g svgnote:tool="layer">`.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub name: String,
+    pub elements: Vec<Element>,
+    /// Applied on top of the opacity of the contained elements.
+    pub opacity: f32,
+    pub blend: BlendMode,
+    pub properties: Properties,
+}
+
+impl Default for Layer {
+    fn default() -> Self {
+        Layer {
+            name: String::new(),
+            elements: vec![],
+            opacity: 1.0,
+            blend: BlendMode::Normal,
+            properties: Properties::default(),
+        }
+    }
+}
+
+impl PartialEq for Layer {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.opacity == other.opacity
+            && self.blend == other.blend
+            && self.properties == other.properties
+            && elems_eq(&self.elements, &other.elements)
+    }
+}
+
+impl Layer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Layer {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// All elements that would be rendered, with layers flattened.
+    ///
+    /// Returns nothing if the layer itself is hidden.
+    pub fn visible_elements(&self) -> Vec<&Element> {
+        if self.properties.hidden {
+            vec![]
+        } else {
+            render_items(&self.elements)
+                .into_iter()
+                .map(|item| item.element)
+                .collect()
+        }
+    }
+
+    /// Like `element::Group::from`, but written as configured in `output`, see [`Element::add_to_with`].
+    pub(crate) fn to_group(&self, output: &Output) -> element::Group {
+        let mut group = element::Group::new()
+            .set("svgnote:name", escape(&self.name))
+            .set(
+                "opacity",
+                StoredOpacity::value(&self.properties.stored_opacity.layer, self.opacity),
+            )
+            // Static
+            .set("svgnote:tool", "layer");
+        self.properties.foreign.assign_to(&mut group);
+        if self.blend != BlendMode::Normal {
+            group.assign("style", format!("mix-blend-mode: {}", self.blend));
+        }
+        // Before the elements, so the title is the first child
+        self.properties.assign_to(&mut group);
+        self.elements
+            .iter()
+            .fold(group, |group, element| element.add_to_with(group, output))
+    }
+}
+
+/// Removes all hidden elements, descending into layers.
+pub(crate) fn retain_visible(elements: &mut Vec<Element>) {
+    elements.retain(|e| !e.properties().hidden);
+    for element in elements {
+        if let Element::Layer(l) = element {
+            retain_visible(&mut l.elements);
+        }
+    }
+}
+
+impl From<&Layer> for element::Group {
+    fn from(layer: &Layer) -> Self {
+        layer.to_group(&Output::default())
+    }
+}
+
+impl FromAttributes for Layer {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let mut foreign = ForeignAttributes::default();
+        let mut blend = BlendMode::Normal;
+        if let Some(style) = attributes.get("style") {
+            let mode = style
+                .split(';')
+                .filter_map(|declaration| declaration.split_once(':'))
+                .find(|(name, _)| name.trim() == "mix-blend-mode")
+                .map(|(_, mode)| mode.trim());
+            match mode.map(BlendMode::from_str) {
+                Some(Ok(mode)) => blend = mode,
+                // Blend modes of newer CSS are kept as written and shown as
+                // normal
+                Some(Err(())) => {
+                    foreign.0.insert("style".to_owned(), style.to_string());
+                }
+                None => {}
+            }
+        }
+        Ok(Layer {
+            name: attributes
+                .get("svgnote:name")
+                .map(|v| unescape(v))
+                .unwrap_or_default(),
+            elements: vec![],
+            opacity: match attributes.get("opacity") {
+                Some(value) => f32::from_str(value)
+                    .map_err(|_| InvalidAttribute("opacity".to_owned(), value.to_string()))?,
+                None => 1.0,
+            },
+            blend,
+            properties: Properties {
+                stored_opacity: StoredOpacity {
+                    layer: StoredOpacity::read(&attributes, "opacity").and_then(|(_, s)| s),
+                    ..Default::default()
+                },
+                foreign,
+                ..Properties::from_attributes(&attributes)?
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{BlendMode, Element, Layer};
+    use crate::Document;
+
+    #[test]
+    fn blend_mode() {
+        let layer = Layer::builder("highlights")
+            .blend(BlendMode::Multiply)
+            .build()
+            .unwrap();
+        let doc = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"style="mix-blend-mode: multiply""#));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+
+        let layer = |style: &str| {
+            let svg = format!(
+                r#"<svg><g svgnote:tool="layer" svgnote:name="a" style="{}"></g></svg>"#,
+                style
+            );
+            match Document::from_str(&svg).map(|doc| doc.elements.into_iter().next()) {
+                Ok(Some(Element::Layer(layer))) => Ok(layer.blend),
+                result => Err(result),
+            }
+        };
+        assert_eq!(
+            layer("fill:none; mix-blend-mode:screen").unwrap(),
+            BlendMode::Screen
+        );
+        assert_eq!(layer("fill:none").unwrap(), BlendMode::Normal);
+        assert_eq!(layer("mix-blend-mode: hue").unwrap(), BlendMode::Normal);
+
+        let svg = r#"<svg><g svgnote:tool="layer" style="mix-blend-mode: hue"></g></svg>"#;
+        let doc = Document::from_str(svg).unwrap();
+        assert!(doc.to_string().contains(r#"style="mix-blend-mode: hue""#));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::colors::Color;
+use crate::scalar::Scalar;
+
+use super::{
+    Arc, Dimension, Element, Ellipse, Ephemeral, FileRef, Font, Frame, Instance, Layer, Line,
+    LinePoint, Ngon, Polyline, PolylinePoint, Properties, Shape, Text,
+};
+
+fn lerp(a: Scalar, b: Scalar, t: Scalar) -> Scalar {
+    a + (b - a) * t
+}
+
+fn lerp_point(a: (Scalar, Scalar), b: (Scalar, Scalar), t: Scalar) -> (Scalar, Scalar) {
+    (lerp(a.0, b.0, t), lerp(a.1, b.1, t))
+}
+
+fn lerp_color(a: Color, b: Color, t: Scalar) -> Color {
+    a.lerp(b, t as f32)
+}
+
+/// `a` for `t` before the middle, `b` after, for what cannot be
+/// interpolated.
+fn nearest<T: Clone>(a: &T, b: &T, t: Scalar) -> T {
+    if t < 0.5 { a } else { b }.clone()
+}
+
+/// `points` with `n` points, spread evenly by index and interpolated
+/// between the ones next to them. Keeps the first and last point.
+fn resample<P: Copy>(points: &[P], n: usize, lerp: impl Fn(P, P, Scalar) -> P) -> Vec<P> {
+    if points.len() == n || points.len() < 2 || n < 2 {
+        return points.iter().copied().cycle().take(n).collect();
+    }
+    (0..n)
+        .map(|i| {
+            let position = i as Scalar * (points.len() - 1) as Scalar / (n - 1) as Scalar;
+            let index = (position as usize).min(points.len() - 2);
+            lerp(points[index], points[index + 1], position - index as Scalar)
+        })
+        .collect()
+}
+
+/// Both lists resampled to the longer one and interpolated, `None` if only
+/// one is empty.
+fn lerp_points<P: Copy>(
+    a: &[P],
+    b: &[P],
+    t: Scalar,
+    lerp: impl Fn(P, P, Scalar) -> P,
+) -> Option<Vec<P>> {
+    if a.is_empty() != b.is_empty() {
+        return None;
+    }
+    let n = a.len().max(b.len());
+    Some(
+        resample(a, n, &lerp)
+            .into_iter()
+            .zip(resample(b, n, &lerp))
+            .map(|(a, b)| lerp(a, b, t))
+            .collect(),
+    )
+}
+
+impl Element {
+    /// The element `t` of the way from `a` to `b`, e.g. for tweening
+    /// between two states in an animation. `t` is not clamped, so easing
+    /// can overshoot.
+    ///
+    /// Positions, sizes, widths, colors and transforms are interpolated.
+    /// Points are matched by index, the stroke with fewer is resampled to
+    /// the same number first. Anything else, e.g. the text of a [`Text`],
+    /// is taken from `a` up to the middle and from `b` after it.
+    ///
+    /// `None` if the elements are not compatible, i.e. of different kinds,
+    /// [`Ngon`]s with different numbers of sides, [`Shape`]s of different
+    /// tools, images of different files, layers with different numbers
+    /// of elements or instances of different symbols. Custom elements are
+    /// never compatible, their fields are not known.
+    pub fn lerp(a: &Element, b: &Element, t: Scalar) -> Option<Element> {
+        Some(match (a, b) {
+            (Element::Line(a), Element::Line(b)) => Element::Line(Line::lerp(a, b, t)?),
+            (Element::Ngon(a), Element::Ngon(b)) => Element::Ngon(Ngon::lerp(a, b, t)?),
+            (Element::Ellipse(a), Element::Ellipse(b)) => Element::Ellipse(Ellipse::lerp(a, b, t)),
+            (Element::Shape(a), Element::Shape(b)) => Element::Shape(Shape::lerp(a, b, t)?),
+            (Element::Arc(a), Element::Arc(b)) => Element::Arc(Arc::lerp(a, b, t)),
+            (Element::Polyline(a), Element::Polyline(b)) => {
+                Element::Polyline(Polyline::lerp(a, b, t)?)
+            }
+            (Element::Text(a), Element::Text(b)) => Element::Text(Text::lerp(a, b, t)),
+            (Element::FileRef(a), Element::FileRef(b)) => Element::FileRef(FileRef::lerp(a, b, t)?),
+            (Element::Layer(a), Element::Layer(b)) => Element::Layer(Layer::lerp(a, b, t)?),
+            (Element::Instance(a), Element::Instance(b)) => {
+                Element::Instance(Instance::lerp(a, b, t)?)
+            }
+            (Element::Dimension(a), Element::Dimension(b)) => {
+                Element::Dimension(Dimension::lerp(a, b, t))
+            }
+            (Element::Frame(a), Element::Frame(b)) => Element::Frame(Frame::lerp(a, b, t)),
+            (Element::Ephemeral(a), Element::Ephemeral(b)) => Element::Ephemeral(Ephemeral {
+                line: Line::lerp(&a.line, &b.line, t)?,
+                ..nearest(a, b, t)
+            }),
+            _ => return None,
+        })
+    }
+}
+
+impl Line {
+    /// See [`Element::lerp`]. The timestamps are kept only if both have the
+    /// same points.
+    pub fn lerp(a: &Line, b: &Line, t: Scalar) -> Option<Line> {
+        let point = |a: LinePoint, b: LinePoint, t| {
+            LinePoint(lerp(a.0, b.0, t), lerp(a.1, b.1, t), lerp(a.2, b.2, t))
+        };
+        let timestamps =
+            if a.points.len() == b.points.len() && a.timestamps.len() == b.timestamps.len() {
+                a.timestamps
+                    .iter()
+                    .zip(&b.timestamps)
+                    // In `f64`, `f32` is not precise enough for milliseconds since 1970
+                    .map(|(&a, &b)| (a as f64 + (b as f64 - a as f64) * t as f64).round() as u64)
+                    .collect()
+            } else {
+                vec![]
+            };
+        Some(Line {
+            color: lerp_color(a.color, b.color, t),
+            width: lerp(a.width, b.width, t),
+            points: lerp_points(&a.points, &b.points, t, point)?,
+            timestamps,
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Ngon {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Ngon, b: &Ngon, t: Scalar) -> Option<Ngon> {
+        if a.n != b.n {
+            return None;
+        }
+        Some(Ngon {
+            position: lerp_point(a.position, b.position, t),
+            stroke: lerp_color(a.stroke, b.stroke, t),
+            fill: lerp_color(a.fill, b.fill, t),
+            width: lerp(a.width, b.width, t),
+            angle: lerp(a.angle, b.angle, t),
+            n: a.n,
+            radius: lerp(a.radius, b.radius, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Ellipse {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Ellipse, b: &Ellipse, t: Scalar) -> Ellipse {
+        Ellipse {
+            position: lerp_point(a.position, b.position, t),
+            stroke: lerp_color(a.stroke, b.stroke, t),
+            fill: lerp_color(a.fill, b.fill, t),
+            width: lerp(a.width, b.width, t),
+            radius: lerp(a.radius, b.radius, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        }
+    }
+}
+
+impl Shape {
+    /// See [`Element::lerp`]. Parameters only one of them has are taken
+    /// from it.
+    pub fn lerp(a: &Shape, b: &Shape, t: Scalar) -> Option<Shape> {
+        if a.tool != b.tool {
+            return None;
+        }
+        let mut parameters = b.parameters.clone();
+        for (name, &value) in &a.parameters {
+            let value = match b.parameters.get(name) {
+                Some(&other) => lerp(value, other, t),
+                None => value,
+            };
+            parameters.insert(name.clone(), value);
+        }
+        Some(Shape {
+            tool: a.tool.clone(),
+            position: lerp_point(a.position, b.position, t),
+            radius: lerp(a.radius, b.radius, t),
+            angle: lerp(a.angle, b.angle, t),
+            parameters,
+            points: lerp_points(&a.points, &b.points, t, lerp_point)?,
+            stroke: lerp_color(a.stroke, b.stroke, t),
+            fill: lerp_color(a.fill, b.fill, t),
+            width: lerp(a.width, b.width, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Arc {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Arc, b: &Arc, t: Scalar) -> Arc {
+        Arc {
+            position: lerp_point(a.position, b.position, t),
+            radius: lerp(a.radius, b.radius, t),
+            start: lerp(a.start, b.start, t),
+            end: lerp(a.end, b.end, t),
+            pie: nearest(&a.pie, &b.pie, t),
+            stroke: lerp_color(a.stroke, b.stroke, t),
+            fill: lerp_color(a.fill, b.fill, t),
+            width: lerp(a.width, b.width, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        }
+    }
+}
+
+impl Polyline {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Polyline, b: &Polyline, t: Scalar) -> Option<Polyline> {
+        if a.closed != b.closed {
+            return None;
+        }
+        let point = |a: PolylinePoint, b: PolylinePoint, t| {
+            PolylinePoint(lerp(a.0, b.0, t), lerp(a.1, b.1, t))
+        };
+        let widths = if a.widths.is_empty() && b.widths.is_empty() {
+            vec![]
+        } else {
+            lerp_points(&a.point_widths(), &b.point_widths(), t, lerp)?
+        };
+        Some(Polyline {
+            stroke: lerp_color(a.stroke, b.stroke, t),
+            fill: lerp_color(a.fill, b.fill, t),
+            width: lerp(a.width, b.width, t),
+            points: lerp_points(&a.points, &b.points, t, point)?,
+            widths,
+            closed: a.closed,
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Text {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Text, b: &Text, t: Scalar) -> Text {
+        Text {
+            position: lerp_point(a.position, b.position, t),
+            content: nearest(&a.content, &b.content, t),
+            color: lerp_color(a.color, b.color, t),
+            font: Font {
+                family: nearest(&a.font.family, &b.font.family, t),
+                size: lerp(a.font.size, b.font.size, t),
+            },
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        }
+    }
+}
+
+impl FileRef {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &FileRef, b: &FileRef, t: Scalar) -> Option<FileRef> {
+        if a.path != b.path {
+            return None;
+        }
+        Some(FileRef {
+            path: a.path.clone(),
+            position: lerp_point(a.position, b.position, t),
+            size: lerp_point(a.size, b.size, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Instance {
+    /// See [`Element::lerp`].
+    pub fn lerp(a: &Instance, b: &Instance, t: Scalar) -> Option<Instance> {
+        if a.symbol != b.symbol {
+            return None;
+        }
+        Some(Instance {
+            symbol: a.symbol.clone(),
+            position: lerp_point(a.position, b.position, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Dimension {
+    /// See [`Element::lerp`]. The attachments are taken from the nearer
+    /// one, like the unit.
+    pub fn lerp(a: &Dimension, b: &Dimension, t: Scalar) -> Dimension {
+        Dimension {
+            start: lerp_point(a.start, b.start, t),
+            end: lerp_point(a.end, b.end, t),
+            attachments: nearest(&a.attachments, &b.attachments, t),
+            offset: lerp(a.offset, b.offset, t),
+            unit: nearest(&a.unit, &b.unit, t),
+            color: lerp_color(a.color, b.color, t),
+            width: lerp(a.width, b.width, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        }
+    }
+}
+
+impl Frame {
+    /// See [`Element::lerp`], e.g. for a camera moving between slides.
+    pub fn lerp(a: &Frame, b: &Frame, t: Scalar) -> Frame {
+        Frame {
+            name: nearest(&a.name, &b.name, t),
+            index: nearest(&a.index, &b.index, t),
+            position: lerp_point(a.position, b.position, t),
+            size: lerp_point(a.size, b.size, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        }
+    }
+}
+
+impl Layer {
+    /// See [`Element::lerp`]. The elements are matched by index.
+    pub fn lerp(a: &Layer, b: &Layer, t: Scalar) -> Option<Layer> {
+        if a.elements.len() != b.elements.len() {
+            return None;
+        }
+        Some(Layer {
+            name: nearest(&a.name, &b.name, t),
+            elements: a
+                .elements
+                .iter()
+                .zip(&b.elements)
+                .map(|(a, b)| Element::lerp(a, b, t))
+                .collect::<Option<_>>()?,
+            opacity: lerp(a.opacity as Scalar, b.opacity as Scalar, t) as f32,
+            blend: nearest(&a.blend, &b.blend, t),
+            properties: Properties::lerp(&a.properties, &b.properties, t),
+        })
+    }
+}
+
+impl Properties {
+    /// The transform and opacities are interpolated, the rest is taken from
+    /// the nearer one.
+    pub fn lerp(a: &Properties, b: &Properties, t: Scalar) -> Properties {
+        let opacity = |a: f32, b: f32| lerp(a as Scalar, b as Scalar, t) as f32;
+        Properties {
+            transform: a.transform.lerp(b.transform, t),
+            stroke_opacity: opacity(a.stroke_opacity, b.stroke_opacity),
+            fill_opacity: opacity(a.fill_opacity, b.fill_opacity),
+            ..nearest(a, b, t)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Line, LinePoint, Ngon, Properties};
+    use crate::transform::Transform;
+
+    #[test]
+    fn lerp() {
+        let line = |points: Vec<LinePoint>, color, width| Line {
+            color,
+            width,
+            points,
+            timestamps: vec![],
+            properties: Properties::default(),
+        };
+        let a = line(
+            vec![LinePoint(0., 0., 1.), LinePoint(10., 0., 1.)],
+            Color::rgb(0, 0, 0),
+            1.,
+        );
+        let b = line(
+            vec![
+                LinePoint(0., 10., 0.),
+                LinePoint(10., 10., 0.),
+                LinePoint(20., 10., 0.),
+            ],
+            Color::rgb(200, 100, 0),
+            3.,
+        );
+        let half = Line::lerp(&a, &b, 0.5).unwrap();
+        assert_eq!(
+            half.points,
+            vec![
+                LinePoint(0., 5., 0.5),
+                LinePoint(7.5, 5., 0.5),
+                LinePoint(15., 5., 0.5),
+            ]
+        );
+        assert_eq!(half.color, Color::rgb(100, 50, 0));
+        assert_eq!(half.width, 2.);
+        assert_eq!(
+            Line::lerp(&a, &b, 0.).unwrap().points[1],
+            LinePoint(5., 0., 1.)
+        );
+        assert!(Line::lerp(&a, &line(vec![], Color::rgb(0, 0, 0), 1.), 0.5).is_none());
+
+        let mut ellipse = Ellipse::builder().radius(10.).build().unwrap();
+        ellipse.properties.transform = Transform::translate(10., 0.);
+        let mut layer = Layer::new("a");
+        layer.elements.push(Element::Ellipse(ellipse.clone()));
+        let mut other = layer.clone();
+        other.name = "b".to_owned();
+        if let Element::Ellipse(ellipse) = &mut other.elements[0] {
+            ellipse.radius = 20.;
+            ellipse.properties.transform = Transform::translate(20., 0.);
+        }
+        let (a, b) = (Element::Layer(layer), Element::Layer(other));
+        match Element::lerp(&a, &b, 0.75) {
+            Some(Element::Layer(layer)) => {
+                assert_eq!(layer.name, "b");
+                match &layer.elements[0] {
+                    Element::Ellipse(e) => {
+                        assert_eq!(e.radius, 17.5);
+                        assert_eq!(e.properties.transform, Transform::translate(17.5, 0.));
+                    }
+                    e => panic!("{:?}", e),
+                }
+            }
+            e => panic!("{:?}", e),
+        }
+        assert_eq!(Element::lerp(&a, &a, 0.3), Some(a.clone()));
+
+        let ngon = |n| Element::Ngon(Ngon::builder().n(n).build().unwrap());
+        assert!(Element::lerp(&ngon(3), &ngon(4), 0.5).is_none());
+        assert!(Element::lerp(&ngon(3), &Element::Ellipse(ellipse), 0.5).is_none());
+    }
+}
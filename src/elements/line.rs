@@ -2,18 +2,26 @@ use std::fmt;
 use std::str::FromStr;
 
 use svg::node::element;
+use svg::node::Node;
 use DocumentError::InvalidAttribute;
+use DocumentError::InvalidColor;
 use DocumentError::InvalidPoint;
 use DocumentError::MissingAttribute;
 
 use crate::colors::Color;
 use crate::elems_eq;
+use crate::geometry;
+use crate::scalar;
+use crate::scalar::Scalar;
 use crate::DocumentError;
 
+use super::path_data;
+use super::style::{self, StyleSheet};
 use super::FromAttributes;
+use super::{Attributes, ForeignAttributes, Properties};
 
 #[derive(PartialEq, Clone, Copy)]
-pub struct LinePoint(pub f32, pub f32, pub f32);
+pub struct LinePoint(pub Scalar, pub Scalar, pub Scalar);
 
 impl fmt::Debug for LinePoint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -22,7 +30,7 @@ impl fmt::Debug for LinePoint {
 }
 
 impl LinePoint {
-    pub fn distance_to(&self, other: Self) -> f32 {
+    pub fn distance_to(&self, other: Self) -> Scalar {
         ((self.0 - other.0).powi(2) + (self.1 - other.1).powi(2)).sqrt()
     }
 }
@@ -33,7 +41,7 @@ impl fmt::Display for LinePoint {
     }
 }
 
-impl From<LinePoint> for (f32, f32) {
+impl From<LinePoint> for (Scalar, Scalar) {
     fn from(val: LinePoint) -> Self {
         (val.0, val.1)
     }
@@ -42,8 +50,126 @@ impl From<LinePoint> for (f32, f32) {
 #[derive(Debug, Clone)]
 pub struct Line {
     pub color: Color,
-    pub width: f32,
+    pub width: Scalar,
     pub points: Vec<LinePoint>,
+    /// When each point was drawn, in milliseconds since the Unix epoch.
+    ///
+    /// Stored as `svgnote:start` and the offsets from it in `svgnote:times`.
+    /// Empty if unknown, e.g. for strokes drawn by other editors.
+    pub timestamps: Vec<u64>,
+    pub properties: Properties,
+}
+
+impl Line {
+    /// The outline of the stroke as a closed polygon, with the width at each
+    /// point scaled by its pressure.
+    pub fn outline(&self) -> Vec<(Scalar, Scalar)> {
+        let points: Vec<_> = self.points.iter().map(|&p| p.into()).collect();
+        let widths: Vec<_> = self.points.iter().map(|p| self.width * p.2).collect();
+        geometry::stroke_outline(&points, &widths)
+    }
+
+    /// The indices of the points where the stroke turns by at least `angle`
+    /// radians, e.g. the corners of a zigzag.
+    ///
+    /// The turn is measured to the points `reach` before and after along the
+    /// stroke, so jitter shorter than `reach` is not a corner. Of the corners
+    /// closer than `reach` to each other only the sharpest is kept.
+    pub fn corners(&self, angle: Scalar, reach: Scalar) -> Vec<usize> {
+        let points = &self.points;
+        let mut lengths = Vec::with_capacity(points.len());
+        let mut length = 0.;
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                length += points[i - 1].distance_to(*point);
+            }
+            lengths.push(length);
+        }
+        let turn_at = |i: usize| {
+            let before = (0..i).rev().find(|&j| lengths[i] - lengths[j] >= reach)?;
+            let after = (i + 1..points.len()).find(|&k| lengths[k] - lengths[i] >= reach)?;
+            let (a, b, c) = (points[before], points[i], points[after]);
+            let (u, v) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+            let turn = (u.0 * v.1 - u.1 * v.0).atan2(u.0 * v.0 + u.1 * v.1).abs();
+            Some(turn).filter(|&turn| turn >= angle)
+        };
+        let mut corners: Vec<(usize, Scalar)> = vec![];
+        for i in 1..points.len().saturating_sub(1) {
+            let turn = match turn_at(i) {
+                Some(turn) => turn,
+                None => continue,
+            };
+            match corners.last_mut() {
+                Some(last) if lengths[i] - lengths[last.0] < reach => {
+                    if turn > last.1 {
+                        *last = (i, turn);
+                    }
+                }
+                _ => corners.push((i, turn)),
+            }
+        }
+        corners.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// The stroke split at its [corners](Line::corners), each part sharing
+    /// the corner point with the one before. A stroke without points is
+    /// returned as is.
+    pub fn split_at_corners(&self, angle: Scalar, reach: Scalar) -> Vec<Line> {
+        if self.points.is_empty() {
+            return vec![self.clone()];
+        }
+        let mut ends = self.corners(angle, reach);
+        ends.push(self.points.len().saturating_sub(1));
+        let mut start = 0;
+        ends.into_iter()
+            .map(|end| {
+                let part = Line {
+                    points: self.points[start..=end].to_vec(),
+                    timestamps: self
+                        .timestamps
+                        .get(start..=end)
+                        .map_or_else(Vec::new, <[u64]>::to_vec),
+                    ..self.clone()
+                };
+                start = end;
+                part
+            })
+            .collect()
+    }
+
+    /// Replaces the stroke by a straight one between its ends if no point is
+    /// further than `tolerance` from that, with the average pressure.
+    /// Strokes whose ends are within `tolerance` of being horizontal or
+    /// vertical are made exactly so, by moving the end. Returns whether it
+    /// was straightened.
+    pub fn straighten(&mut self, tolerance: Scalar) -> bool {
+        let (first, last) = match (self.points.first(), self.points.last()) {
+            (Some(&first), Some(&last)) if self.points.len() > 1 => (first, last),
+            _ => return false,
+        };
+        let straight = self.points.iter().all(|&p| {
+            geometry::distance_to_segment(p.into(), first.into(), last.into()) <= tolerance
+        });
+        if !straight {
+            return false;
+        }
+        let pressure =
+            self.points.iter().map(|p| p.2).sum::<Scalar>() / self.points.len() as Scalar;
+        let mut end = (last.0, last.1);
+        if (end.1 - first.1).abs() <= tolerance {
+            end.1 = first.1;
+        } else if (end.0 - first.0).abs() <= tolerance {
+            end.0 = first.0;
+        }
+        self.points = vec![
+            LinePoint(first.0, first.1, pressure),
+            LinePoint(end.0, end.1, pressure),
+        ];
+        if let (Some(&start), Some(&stop)) = (self.timestamps.first(), self.timestamps.last()) {
+            self.timestamps = vec![start, stop];
+        }
+        true
+    }
 }
 
 impl PartialEq for Line {
@@ -51,91 +177,214 @@ impl PartialEq for Line {
         self.color == other.color
             && self.width == other.width
             && elems_eq(&self.points, &other.points)
+            && self.timestamps == other.timestamps
+            && self.properties == other.properties
     }
 }
 
 impl From<&Line> for element::Path {
     fn from(line: &Line) -> Self {
-        let d = line.points.iter().skip(1).fold(
+        line.to_path(None, false)
+    }
+}
+
+impl Line {
+    /// Like `element::Path::from`, but with the style as a class if `styles`
+    /// has one for it, and without `svgnote:points` if `omit_points` and they
+    /// can be read from `d`.
+    pub(crate) fn to_path(&self, styles: Option<&StyleSheet>, omit_points: bool) -> element::Path {
+        // Path data is `f32` in `svg`, `svgnote:points` keeps the precision
+        let d = self.points.iter().skip(1).fold(
             element::path::Data::new().move_to(
-                line.points
+                self.points
                     .first()
-                    .map(|p| -> (f32, f32) { (*p).into() })
+                    .map(|p| (p.0 as f32, p.1 as f32))
                     .unwrap_or((0.0, 0.0)),
             ),
-            |d, &p| d.line_to::<(f32, f32)>(p.into()),
+            |d, &p| d.line_to((p.0 as f32, p.1 as f32)),
         );
-        element::Path::new()
-            .set("stroke", line.color.to_string_na())
-            .set("stroke-opacity", line.color.opacity())
-            .set("stroke-width", line.width)
-            .set("svgnote:width", line.width)
-            .set(
+        let mut path = element::Path::new().set("svgnote:width", self.width);
+        self.properties.foreign.assign_to(&mut path);
+        if !(omit_points && self.points_in_data()) {
+            path.assign(
                 "svgnote:points",
-                line.points
+                self.points
                     .iter()
                     .map(LinePoint::to_string)
                     .collect::<Vec<String>>(),
-            )
+            );
+        }
+        path = path
             // Static
             .set("svgnote:tool", "pen")
-            .set("fill-opacity", "0")
-            .set("stroke-linecap", "round")
-            .set("stroke-linejoin", "round")
             // Generated
-            .set("d", d)
+            .set("d", d);
+        style::assign(&mut path, self.style(), styles);
+        // The offsets are positive, as points may have been reordered
+        if let Some(&start) = self.timestamps.iter().min() {
+            path.assign("svgnote:start", start.to_string());
+            path.assign(
+                "svgnote:times",
+                self.timestamps
+                    .iter()
+                    .map(|t| (t - start).to_string())
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            );
+        }
+        self.properties.assign_to(&mut path);
+        path
+    }
+
+    /// Whether the points are the same when read from the path data, i.e.
+    /// they have no pressure and survive the conversion to `f32`.
+    fn points_in_data(&self) -> bool {
+        let same = |value: Scalar| value.to_string() == (value as f32).to_string();
+        !self.points.is_empty()
+            && self
+                .points
+                .iter()
+                .all(|p| p.2 == 1.0 && same(p.0) && same(p.1))
     }
 }
 
 impl FromAttributes for Line {
-    fn from_attributes(
-        attributes: std::collections::HashMap<String, svg::node::Value>,
-    ) -> Result<Self, crate::DocumentError> {
-        Ok(Line {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, crate::DocumentError> {
+        let mut line = Line {
+            properties: Properties {
+                foreign: ForeignAttributes::from_attributes(&attributes),
+                ..Properties::from_attributes(&attributes)?.with_stroke_opacity(&attributes)
+            },
             color: {
                 let color: &str = attributes
                     .get("stroke")
                     .ok_or(MissingAttribute("stroke".to_owned()))?;
-                Color::from_str(color)
-                    .map_err(|_| InvalidAttribute("stroke".to_owned(), color.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("stroke-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                Color::from_str(color).map_err(|e| InvalidColor("stroke".to_owned(), e))?
             },
-            points: {
-                let points: &str = attributes
-                    .get("svgnote:points")
-                    .ok_or(MissingAttribute("svgnote:points".to_owned()))?;
-                points
+            points: match attributes.get("svgnote:points") {
+                // Paths not written by us, e.g. edited by hand, have only the
+                // path data
+                None => {
+                    let d: &str = attributes
+                        .get("d")
+                        .ok_or(MissingAttribute("svgnote:points".to_owned()))?;
+                    path_data::points(d)
+                        .ok_or_else(|| InvalidAttribute("d".to_owned(), d.to_owned()))?
+                        .into_iter()
+                        .map(|(x, y)| LinePoint(x, y, 1.0))
+                        .collect()
+                }
+                Some(points) => points
                     .split_ascii_whitespace()
                     .map(|s| {
                         let a: Vec<&str> = s.split(',').collect();
                         if a.len() == 3 {
                             Ok(LinePoint(
-                                f32::from_str(a[0]).map_err(|_| InvalidPoint(s.to_owned()))?,
-                                f32::from_str(a[1]).map_err(|_| InvalidPoint(s.to_owned()))?,
-                                f32::from_str(a[2]).map_err(|_| InvalidPoint(s.to_owned()))?,
+                                scalar::parse(a[0]).ok_or_else(|| InvalidPoint(s.to_owned()))?,
+                                scalar::parse(a[1]).ok_or_else(|| InvalidPoint(s.to_owned()))?,
+                                scalar::parse(a[2]).ok_or_else(|| InvalidPoint(s.to_owned()))?,
                             ))
                         } else {
                             Err(InvalidPoint(s.to_owned()))
                         }
                     })
-                    .collect::<Result<_, _>>()?
+                    .collect::<Result<_, _>>()?,
             },
             width: {
                 let width: &str = attributes
                     .get("svgnote:width")
                     .ok_or(MissingAttribute("svgnote:width".to_owned()))?;
-                f32::from_str(width)
+                Scalar::from_str(width)
                     .map_err(|_| InvalidAttribute("svgnote:width".to_owned(), width.to_owned()))?
             },
+            timestamps: vec![],
+        };
+        line.timestamps = timestamps(&attributes, line.points.len())?;
+        Ok(line)
+    }
+}
+
+/// Parses `svgnote:start` and `svgnote:times`, which have to have one offset
+/// for each of the `points`.
+fn timestamps(attributes: &Attributes<'_>, points: usize) -> Result<Vec<u64>, DocumentError> {
+    let (start, times) = match (
+        attributes.get("svgnote:start"),
+        attributes.get("svgnote:times"),
+    ) {
+        (Some(start), Some(times)) => (start, times),
+        _ => return Ok(vec![]),
+    };
+    let invalid = || InvalidAttribute("svgnote:times".to_owned(), times.to_string());
+    let start = u64::from_str(start)
+        .map_err(|_| InvalidAttribute("svgnote:start".to_owned(), start.to_string()))?;
+    let timestamps: Vec<u64> = times
+        .split_ascii_whitespace()
+        .map(|offset| {
+            u64::from_str(offset)
+                .ok()
+                .and_then(|offset| start.checked_add(offset))
         })
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    if timestamps.len() != points {
+        return Err(invalid());
+    }
+    Ok(timestamps)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elements::{Line, LinePoint};
+
+    #[test]
+    fn corners() {
+        // A zigzag with jitter on its first stretch
+        let mut builder = Line::builder();
+        for &(x, y) in &[
+            (0., 0.),
+            (1., 0.2),
+            (2., -0.2),
+            (3., 0.),
+            (10., 0.),
+            (10., 10.),
+            (20., 10.),
+        ] {
+            builder = builder.timed_point(x, y, 1., x as u64);
+        }
+        let line = builder.build().unwrap();
+        assert_eq!(line.corners(1., 2.), vec![4, 5]);
+        let parts = line.split_at_corners(1., 2.);
+        let points: Vec<_> = parts.iter().map(|part| part.points.clone()).collect();
+        assert_eq!(
+            points[1..],
+            [
+                vec![LinePoint(10., 0., 1.), LinePoint(10., 10., 1.)],
+                vec![LinePoint(10., 10., 1.), LinePoint(20., 10., 1.)],
+            ]
+        );
+        assert_eq!(parts[0].timestamps, vec![0, 1, 2, 3, 10]);
+        let empty = Line {
+            points: vec![],
+            timestamps: vec![],
+            ..line.clone()
+        };
+        assert_eq!(empty.split_at_corners(1., 2.), vec![empty]);
+        assert_eq!(line.split_at_corners(2., 2.), vec![line]);
+    }
+
+    #[test]
+    fn straighten() {
+        let mut line = Line::builder()
+            .point(0., 0., 0.25)
+            .point(50., 3., 0.5)
+            .point(100., 2., 0.75)
+            .build()
+            .unwrap();
+        assert!(!line.clone().straighten(0.5));
+        assert!(line.straighten(2.));
+        assert_eq!(
+            line.points,
+            vec![LinePoint(0., 0., 0.5), LinePoint(100., 0., 0.5)]
+        );
     }
 }
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::geometry::simplify;
+use crate::scalar::Scalar;
+
+use super::Element;
+use super::Layer;
+use super::Line;
+
+/// Caches the simplified versions of an element, see [`Element::lod`].
+///
+/// Ignored when comparing elements, and empty in clones.
+#[derive(Default)]
+pub struct LodCache(Mutex<Vec<(u8, u64, Arc<Element>)>>);
+
+impl LodCache {
+    fn get(&self, level: u8, hash: u64) -> Option<Arc<Element>> {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(l, h, _)| *l == level && *h == hash)
+            .map(|(_, _, e)| e.clone())
+    }
+
+    fn insert(&self, level: u8, hash: u64, element: Arc<Element>) {
+        let mut entries = self.0.lock().unwrap();
+        entries.retain(|(l, h, _)| *l != level && *h == hash);
+        entries.push((level, hash, element));
+    }
+}
+
+impl Clone for LodCache {
+    fn clone(&self) -> Self {
+        LodCache::default()
+    }
+}
+
+impl PartialEq for LodCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for LodCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LodCache")
+    }
+}
+
+/// The maximum distance the geometry of a level of detail deviates from the
+/// original.
+pub fn tolerance(level: u8) -> Scalar {
+    match level {
+        0 => 0.0,
+        level => Scalar::powi(2., level as i32 - 1),
+    }
+}
+
+impl Element {
+    /// A simplified version of this element for rendering at a lower zoom
+    /// level.
+    ///
+    /// Level `0` is the original geometry, every level above doubles the
+    /// [`tolerance`] the points may deviate, starting at `1` unit. Only
+    /// [`Line`]s and [`Polyline`](super::Polyline)s (also in layers) are
+    /// simplified.
+    ///
+    /// The result is cached, until the element is changed.
+    pub fn lod(&self, level: u8) -> Arc<Element> {
+        if level == 0 {
+            return Arc::new(self.clone());
+        }
+        let hash = self.cache_key();
+        let cache = &self.properties().lod_cache;
+        if let Some(element) = cache.get(level, hash) {
+            return element;
+        }
+        let tolerance = tolerance(level);
+        let element = Arc::new(match self {
+            Element::Line(line) => Element::Line(Line {
+                color: line.color,
+                width: line.width,
+                points: simplify(&line.points, tolerance, |&p| p.into()),
+                // Only used for rendering, which does not need them
+                timestamps: vec![],
+                properties: line.properties.clone(),
+            }),
+            Element::Polyline(polyline) => {
+                let mut polyline = Element::Polyline(polyline.clone());
+                polyline.simplify(tolerance);
+                polyline
+            }
+            Element::Layer(layer) => Element::Layer(Layer {
+                name: layer.name.clone(),
+                elements: layer
+                    .elements
+                    .iter()
+                    .map(|e| Element::clone(&e.lod(level)))
+                    .collect(),
+                opacity: layer.opacity,
+                blend: layer.blend,
+                properties: layer.properties.clone(),
+            }),
+            element => element.clone(),
+        });
+        cache.insert(level, hash, element.clone());
+        element
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::scalar::Scalar;
+
+    #[test]
+    fn lod() {
+        let mut line = Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 2.,
+            points: (0..100)
+                .map(|i| LinePoint(i as Scalar, (i % 2) as Scalar * 0.1, 1.))
+                .collect(),
+            timestamps: vec![],
+            properties: Properties::default(),
+        });
+
+        let simplified = line.lod(1);
+        match &*simplified {
+            Element::Line(l) => assert_eq!(
+                l.points,
+                vec![LinePoint(0., 0., 1.), LinePoint(99., 0.1, 1.)]
+            ),
+            e => panic!("expected a line, got {:?}", e),
+        }
+        assert!(Arc::ptr_eq(&simplified, &line.lod(1)));
+        assert_eq!(*line.lod(0), line);
+
+        if let Element::Line(l) = &mut line {
+            l.color = Color::rgb(0xFF, 0, 0);
+        }
+        let simplified = line.lod(1);
+        assert!(matches!(&*simplified, Element::Line(l) if l.color == Color::rgb(0xFF, 0, 0)));
+
+        let mut layer = Layer::new("layer");
+        layer.elements.push(line);
+        match &*Element::Layer(layer).lod(1) {
+            Element::Layer(l) => assert_eq!(l.elements, vec![Element::clone(&simplified)]),
+            e => panic!("expected a layer, got {:?}", e),
+        }
+    }
+}
@@ -1,40 +1,138 @@
+mod approx;
+mod arc;
+pub(crate) mod attributes;
+mod builder;
+pub(crate) mod clip;
+mod custom;
+mod dimension;
+mod distance;
+mod ephemeral;
+mod file_ref;
+mod frame;
+pub(crate) mod hash;
+pub(crate) mod layer;
+mod lerp;
 mod line;
+mod lod;
+pub(crate) mod path_data;
 mod polygon;
+pub(crate) mod properties;
+mod shape;
+pub(crate) mod style;
+pub(crate) mod symbol;
+mod text;
 
-use std::collections::HashMap;
-use std::f32::consts::PI;
 use std::str::FromStr;
 
+use crate::assets::Assets;
 use crate::colors::Color;
+use crate::colors::ColorMap;
+use crate::fonts::FontLoader;
+use crate::geometry;
+use crate::geometry::BoundingBox;
+use crate::metadata::Target;
+use crate::render;
+use crate::scalar;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
 use crate::DocumentError;
 
 use derivative::Derivative;
 use svg::node::element::{self, tag};
-use svg::node::Value;
+use svg::node::Node;
 
 use svg::parser::Event;
 use DocumentError::InvalidAttribute;
+use DocumentError::InvalidColor;
 use DocumentError::MissingAttribute;
 
+use self::style::StyleSheet;
+
+pub use self::arc::Arc;
+pub use self::attributes::Attributes;
+pub use self::builder::BuildError;
+pub use self::builder::EllipseBuilder;
+pub use self::builder::FileRefBuilder;
+pub use self::builder::LayerBuilder;
+pub use self::builder::LineBuilder;
+pub use self::builder::NgonBuilder;
+pub use self::builder::PolylineBuilder;
+pub use self::builder::TextBuilder;
+pub use self::clip::Clip;
+pub use self::custom::Custom;
+pub use self::custom::ElementRegistry;
+pub use self::custom::NoteElement;
+pub use self::custom::Stored;
+pub use self::dimension::Attachment;
+pub use self::dimension::Dimension;
+pub use self::dimension::LengthUnit;
+pub use self::ephemeral::Ephemeral;
+pub use self::file_ref::FileRef;
+pub use self::frame::Frame;
+pub use self::layer::BlendMode;
+pub use self::layer::Layer;
 pub use self::line::Line;
 pub use self::line::LinePoint;
+pub use self::lod::tolerance;
+pub use self::lod::LodCache;
 pub use self::polygon::Polyline;
 pub use self::polygon::PolylinePoint;
+pub use self::properties::ForeignAttributes;
+pub use self::properties::Properties;
+pub use self::properties::StoredBounds;
+pub use self::properties::StoredOpacity;
+pub use self::shape::Cross;
+pub use self::shape::Parameters;
+pub use self::shape::Shape;
+pub use self::shape::ShapeKind;
+pub use self::shape::ShapeRegistry;
+pub use self::shape::Star;
+pub use self::symbol::Instance;
+pub use self::symbol::Symbols;
+pub use self::text::ApproximateShaper;
+pub use self::text::Font;
+pub use self::text::Text;
+pub use self::text::TextMetrics;
+pub use self::text::TextRun;
+pub use self::text::TextShaper;
+
+/// How [`Element::add_to_with`] writes elements.
+#[derive(Default)]
+pub(crate) struct Output<'a> {
+    /// Converts texts to paths of their outlines where it has the font.
+    pub loader: Option<&'a dyn FontLoader>,
+    /// Writes styles as classes where it has one.
+    pub styles: Option<StyleSheet>,
+    /// Leaves out `svgnote:points` of pen strokes where `d` has the same.
+    pub omit_points: bool,
+    /// Writes the bounds of elements as `svgnote:bbox`, see [`StoredBounds`].
+    pub bounding_boxes: bool,
+    /// Writes the assets shown by [`FileRef`]s as data URIs.
+    pub assets: Option<&'a Assets>,
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Ngon {
-    pub position: (f32, f32),
+    pub position: (Scalar, Scalar),
     pub stroke: Color,
     pub fill: Color,
-    pub width: f32,
-    pub angle: f32,
-    pub n: u8,
-    pub radius: f32,
+    pub width: Scalar,
+    pub angle: Scalar,
+    pub n: u16,
+    pub radius: Scalar,
+    pub properties: Properties,
 }
 
 impl FromAttributes for Ngon {
-    fn from_attributes(attributes: HashMap<String, Value>) -> Result<Self, DocumentError> {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
         Ok(Ngon {
+            properties: Properties {
+                foreign: ForeignAttributes::from_attributes(&attributes),
+                ..Properties::from_attributes(&attributes)?
+                    .with_stroke_opacity(&attributes)
+                    .with_fill_opacity(&attributes)
+            },
             position: {
                 let value: &str = attributes
                     .get("svgnote:position")
@@ -43,7 +141,7 @@ impl FromAttributes for Ngon {
                     .split_once(',')
                     .ok_or(())
                     .and_then(|v| {
-                        if let (Ok(x), Ok(y)) = (f32::from_str(v.0), f32::from_str(v.1)) {
+                        if let (Ok(x), Ok(y)) = (Scalar::from_str(v.0), Scalar::from_str(v.1)) {
                             Ok((x, y))
                         } else {
                             Err(())
@@ -57,63 +155,41 @@ impl FromAttributes for Ngon {
                 let value: &str = attributes
                     .get("stroke-width")
                     .ok_or(MissingAttribute("stroke-width".to_owned()))?;
-                f32::from_str(value)
+                Scalar::from_str(value)
                     .map_err(|_| InvalidAttribute("stroke-width".to_owned(), value.to_owned()))?
             },
             radius: {
                 let value: &str = attributes
                     .get("svgnote:radius")
                     .ok_or(MissingAttribute("svgnote:radius".to_owned()))?;
-                f32::from_str(value)
+                Scalar::from_str(value)
                     .map_err(|_| InvalidAttribute("svgnote:radius".to_owned(), value.to_owned()))?
             },
             n: {
                 let value: &str = attributes
                     .get("svgnote:n")
                     .ok_or(MissingAttribute("svgnote:n".to_owned()))?;
-                u8::from_str(value)
+                u16::from_str(value)
                     .map_err(|_| InvalidAttribute("svgnote:n".to_owned(), value.to_owned()))?
             },
             angle: {
                 let value: &str = attributes
                     .get("svgnote:angle")
                     .ok_or(MissingAttribute("svgnote:angle".to_owned()))?;
-                f32::from_str(value)
+                Scalar::from_str(value)
                     .map_err(|_| InvalidAttribute("svgnote:angle".to_owned(), value.to_owned()))?
             },
             fill: {
                 let value: &str = attributes
                     .get("fill")
                     .ok_or(MissingAttribute("fill".to_owned()))?;
-                Color::from_str(value)
-                    .map_err(|_| InvalidAttribute("fill".to_owned(), value.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("fill-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                style::parse_fill(value).map_err(|e| InvalidColor("fill".to_owned(), e))?
             },
             stroke: {
                 let value: &str = attributes
                     .get("stroke")
                     .ok_or(MissingAttribute("stroke".to_owned()))?;
-                Color::from_str(value)
-                    .map_err(|_| InvalidAttribute("stroke".to_owned(), value.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("stroke-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                Color::from_str(value).map_err(|e| InvalidColor("stroke".to_owned(), e))?
             },
         })
     }
@@ -121,45 +197,100 @@ impl FromAttributes for Ngon {
 
 impl From<&Ngon> for element::Polygon {
     fn from(n: &Ngon) -> Self {
-        Self::new()
+        n.to_polygon(None)
+    }
+}
+
+impl Ngon {
+    /// Like `element::Polygon::from`, but with the style as a class if
+    /// `styles` has one for it.
+    pub(crate) fn to_polygon(&self, styles: Option<&StyleSheet>) -> element::Polygon {
+        let mut polygon = element::Polygon::new()
             .set(
                 "svgnote:position",
-                format!("{},{}", n.position.0, n.position.1),
+                format!("{},{}", self.position.0, self.position.1),
             )
-            .set("stroke", n.stroke.to_string_na())
-            .set("fill", n.fill.to_string_na())
-            .set("stroke-opacity", n.stroke.opacity())
-            .set("fill-opacity", n.fill.opacity())
-            .set("stroke-width", n.width)
-            .set("svgnote:angle", n.angle)
-            .set("svgnote:n", n.n)
-            .set("svgnote:radius", n.radius)
+            .set("svgnote:angle", self.angle)
+            .set("svgnote:n", self.n)
+            .set("svgnote:radius", self.radius)
             // Static
             .set("svgnote:tool", "ngon")
-            .set("stroke-linecap", "round")
-            .set("stroke-linejoin", "round")
             // Generated
             .set(
                 "points",
-                n.points()
+                self.points()
                     .iter()
                     .map(|(x, y)| format!("{},{}", x, y))
                     .collect::<Vec<String>>(),
-            )
+            );
+        self.properties.foreign.assign_to(&mut polygon);
+        style::assign(&mut polygon, self.style(), styles);
+        self.properties.assign_to(&mut polygon);
+        polygon
     }
 }
 
 impl Ngon {
-    fn points(&self) -> Vec<(f32, f32)> {
+    /// The regular polygon with the corners `points` (in any order), if
+    /// there is one.
+    ///
+    /// Corners may deviate by 1% of the radius, e.g. due to rounding. The
+    /// colors are black and transparent, the width `0`.
+    pub fn fit(points: &[(Scalar, Scalar)]) -> Option<Ngon> {
+        let mut points = points.to_vec();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 3 || points.len() > u16::MAX as usize {
+            return None;
+        }
+        let n = points.len() as Scalar;
+        let position = points
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p.0 / n, y + p.1 / n));
+        let radius = points
+            .iter()
+            .map(|&p| geometry::distance(p, position))
+            .sum::<Scalar>()
+            / n;
+        // The angle of the first corner, relative to where it is without
+        // rotation, see `points`
+        let step = 2. * PI / n;
+        let first = (points[0].1 - position.1).atan2(points[0].0 - position.0);
+        let ngon = Ngon {
+            position,
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 0.,
+            angle: (first - PI / 2. - step / 2.).rem_euclid(step),
+            n: points.len() as u16,
+            radius,
+            properties: Properties::default(),
+        };
+        let tolerance = radius / 100.;
+        let fits = ngon.points().iter().all(|&corner| {
+            points
+                .iter()
+                .any(|&p| geometry::distance(p, corner) <= tolerance)
+        });
+        if radius > 0. && fits {
+            Some(ngon)
+        } else {
+            None
+        }
+    }
+
+    /// The corners of the polygon.
+    pub fn points(&self) -> Vec<(Scalar, Scalar)> {
         let mut points = vec![];
-        let angle = 2. * PI / self.n as f32;
+        let angle = 2. * PI / self.n as Scalar;
         let offset_angle = PI / 2. + angle / 2.;
         for i in 0..self.n {
             points.push((
                 self.position.0
-                    + self.radius * (i as f32 * angle + offset_angle + self.angle).cos(),
+                    + self.radius * (i as Scalar * angle + offset_angle + self.angle).cos(),
                 self.position.1
-                    + self.radius * (i as f32 * angle + offset_angle + self.angle).sin(),
+                    + self.radius * (i as Scalar * angle + offset_angle + self.angle).sin(),
             ))
         }
         points
@@ -168,44 +299,74 @@ impl Ngon {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Ellipse {
-    pub position: (f32, f32),
+    pub position: (Scalar, Scalar),
     pub stroke: Color,
     pub fill: Color,
-    pub width: f32,
-    pub radius: f32,
+    pub width: Scalar,
+    pub radius: Scalar,
+    pub properties: Properties,
 }
+
+impl Ellipse {
+    /// Approximates the outline with `segments` points.
+    pub fn points(&self, segments: usize) -> Vec<(Scalar, Scalar)> {
+        (0..segments)
+            .map(|i| {
+                let angle = 2. * PI * i as Scalar / segments as Scalar;
+                (
+                    self.position.0 + self.radius * angle.cos(),
+                    self.position.1 + self.radius * angle.sin(),
+                )
+            })
+            .collect()
+    }
+}
+
 impl From<&Ellipse> for element::Ellipse {
     fn from(n: &Ellipse) -> Self {
-        Self::new()
-            .set("stroke", n.stroke.to_string_na())
-            .set("stroke-opacity", n.stroke.opacity())
-            .set("fill", n.fill.to_string_na())
-            .set("fill-opacity", n.fill.opacity())
-            .set("stroke-width", n.width)
-            .set("cx", n.position.0)
-            .set("cy", n.position.1)
-            .set("rx", n.radius)
-            .set("ry", n.radius)
+        n.to_ellipse(None)
+    }
+}
+
+impl Ellipse {
+    /// Like `element::Ellipse::from`, but with the style as a class if
+    /// `styles` has one for it.
+    pub(crate) fn to_ellipse(&self, styles: Option<&StyleSheet>) -> element::Ellipse {
+        let mut ellipse = element::Ellipse::new()
+            .set("cx", self.position.0)
+            .set("cy", self.position.1)
+            .set("rx", self.radius)
+            .set("ry", self.radius);
+        self.properties.foreign.assign_to(&mut ellipse);
+        style::assign(&mut ellipse, self.style(), styles);
+        self.properties.assign_to(&mut ellipse);
+        ellipse
     }
 }
 
 impl FromAttributes for Ellipse {
-    fn from_attributes(attributes: HashMap<String, Value>) -> Result<Self, DocumentError> {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
         Ok(Ellipse {
+            properties: Properties {
+                foreign: ForeignAttributes::from_attributes(&attributes),
+                ..Properties::from_attributes(&attributes)?
+                    .with_stroke_opacity(&attributes)
+                    .with_fill_opacity(&attributes)
+            },
             position: {
                 (
                     {
                         let value = attributes
                             .get("cx")
                             .ok_or(MissingAttribute("cx".to_owned()))?;
-                        f32::from_str(value)
+                        Scalar::from_str(value)
                             .map_err(|_| InvalidAttribute("cx".to_owned(), value.to_string()))?
                     },
                     {
                         let value = attributes
                             .get("cy")
                             .ok_or(MissingAttribute("cy".to_owned()))?;
-                        f32::from_str(value)
+                        Scalar::from_str(value)
                             .map_err(|_| InvalidAttribute("cy".to_owned(), value.to_string()))?
                     },
                 )
@@ -214,49 +375,27 @@ impl FromAttributes for Ellipse {
                 let value: &str = attributes
                     .get("stroke-width")
                     .ok_or(MissingAttribute("stroke-width".to_owned()))?;
-                f32::from_str(value)
+                Scalar::from_str(value)
                     .map_err(|_| InvalidAttribute("stroke-width".to_owned(), value.to_owned()))?
             },
             radius: {
                 let value: &str = attributes
                     .get("rx")
                     .ok_or(MissingAttribute("rx".to_owned()))?;
-                f32::from_str(value)
+                Scalar::from_str(value)
                     .map_err(|_| InvalidAttribute("rx".to_owned(), value.to_owned()))?
             },
             fill: {
                 let value: &str = attributes
                     .get("fill")
                     .ok_or(MissingAttribute("fill".to_owned()))?;
-                Color::from_str(value)
-                    .map_err(|_| InvalidAttribute("fill".to_owned(), value.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("fill-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                style::parse_fill(value).map_err(|e| InvalidColor("fill".to_owned(), e))?
             },
             stroke: {
                 let value: &str = attributes
                     .get("stroke")
                     .ok_or(MissingAttribute("stroke".to_owned()))?;
-                Color::from_str(value)
-                    .map_err(|_| InvalidAttribute("stroke".to_owned(), value.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("stroke-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                Color::from_str(value).map_err(|e| InvalidColor("stroke".to_owned(), e))?
             },
         })
     }
@@ -269,38 +408,394 @@ pub enum Element {
     Ngon(Ngon),
     Ellipse(Ellipse),
     Polyline(Polyline),
+    Text(Text),
+    FileRef(FileRef),
+    Layer(Layer),
+    Shape(Shape),
+    Arc(Arc),
+    Instance(Instance),
+    Custom(Custom),
+    Dimension(Dimension),
+    Ephemeral(Ephemeral),
+    Frame(Frame),
 }
 
+/// The `svgnote:tool`s of the elements of the library, the others are of
+/// apps, see [`Custom`]. Shapes are told apart by their parameters.
+const TOOLS: &[&str] = &[
+    "pen",
+    "text",
+    "arc",
+    "ngon",
+    "polyline",
+    "file",
+    "frame",
+    "dimension",
+];
+
 pub trait FromAttributes: Sized {
-    fn from_attributes(attributes: HashMap<String, Value>) -> Result<Self, DocumentError>;
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError>;
 }
 
 impl Element {
+    pub fn properties(&self) -> &Properties {
+        match self {
+            Element::Line(e) => &e.properties,
+            Element::Ngon(e) => &e.properties,
+            Element::Shape(e) => &e.properties,
+            Element::Arc(e) => &e.properties,
+            Element::Ellipse(e) => &e.properties,
+            Element::Polyline(e) => &e.properties,
+            Element::Text(e) => &e.properties,
+            Element::FileRef(e) => &e.properties,
+            Element::Layer(e) => &e.properties,
+            Element::Instance(e) => &e.properties,
+            Element::Custom(e) => &e.properties,
+            Element::Dimension(e) => &e.properties,
+            Element::Ephemeral(e) => &e.line.properties,
+            Element::Frame(e) => &e.properties,
+        }
+    }
+
+    pub fn properties_mut(&mut self) -> &mut Properties {
+        match self {
+            Element::Line(e) => &mut e.properties,
+            Element::Ngon(e) => &mut e.properties,
+            Element::Shape(e) => &mut e.properties,
+            Element::Arc(e) => &mut e.properties,
+            Element::Ellipse(e) => &mut e.properties,
+            Element::Polyline(e) => &mut e.properties,
+            Element::Text(e) => &mut e.properties,
+            Element::FileRef(e) => &mut e.properties,
+            Element::Layer(e) => &mut e.properties,
+            Element::Instance(e) => &mut e.properties,
+            Element::Custom(e) => &mut e.properties,
+            Element::Dimension(e) => &mut e.properties,
+            Element::Ephemeral(e) => &mut e.line.properties,
+            Element::Frame(e) => &mut e.properties,
+        }
+    }
+
+    /// The links of the element, and of all elements in a layer.
+    pub fn links(&self) -> Vec<&Target> {
+        let mut links: Vec<&Target> = self.properties().link.iter().collect();
+        if let Element::Layer(layer) = self {
+            links.extend(layer.elements.iter().flat_map(Element::links));
+        }
+        links
+    }
+
+    /// Whether the element is an [`Ephemeral`] one, which is never stored.
+    pub fn is_ephemeral(&self) -> bool {
+        matches!(self, Element::Ephemeral(_))
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.properties().hidden
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.properties_mut().hidden = hidden;
+    }
+
+    pub fn clip(&self) -> Option<Clip> {
+        self.properties().clip
+    }
+
+    /// Clips the element (or all elements in a layer) to `clip`, or removes
+    /// the clip on `None`.
+    pub fn set_clip(&mut self, clip: impl Into<Option<Clip>>) {
+        self.properties_mut().clip = clip.into();
+    }
+
+    /// Replaces the colors of the element (or all elements in a layer)
+    /// according to `map`.
+    pub fn remap_colors(&mut self, map: &ColorMap) {
+        match self {
+            Element::Line(e) | Element::Ephemeral(Ephemeral { line: e, .. }) => {
+                e.color = map.map(e.color)
+            }
+            Element::Polyline(e) => {
+                e.stroke = map.map(e.stroke);
+                e.fill = map.map(e.fill);
+            }
+            Element::Ngon(e) => {
+                e.stroke = map.map(e.stroke);
+                e.fill = map.map(e.fill);
+            }
+            Element::Ellipse(e) => {
+                e.stroke = map.map(e.stroke);
+                e.fill = map.map(e.fill);
+            }
+            Element::Shape(e) => {
+                e.stroke = map.map(e.stroke);
+                e.fill = map.map(e.fill);
+            }
+            Element::Arc(e) => {
+                e.stroke = map.map(e.stroke);
+                e.fill = map.map(e.fill);
+            }
+            Element::Text(e) => e.color = map.map(e.color),
+            Element::Dimension(e) => e.color = map.map(e.color),
+            Element::FileRef(_) | Element::Instance(_) | Element::Custom(_) | Element::Frame(_) => {
+            }
+            Element::Layer(e) => e.elements.iter_mut().for_each(|e| e.remap_colors(map)),
+        }
+    }
+
+    /// Rounds the geometry of the element (or all elements in a layer) to
+    /// `decimals` decimal places.
+    pub fn round(&mut self, decimals: u8) {
+        let factor = Scalar::powi(10., decimals.into());
+        let round = |value: Scalar| (value * factor).round() / factor;
+        self.map_scalars(&round, &round);
+    }
+
+    /// Forgets the [`StoredBounds`] of the element and, for layers, of all
+    /// elements in it.
+    pub fn clear_stored_bounds(&mut self) {
+        self.properties_mut().bounds = StoredBounds(None);
+        if let Element::Layer(e) = self {
+            e.elements.iter_mut().for_each(Element::clear_stored_bounds);
+        }
+    }
+
+    /// Snaps the coordinates and sizes of the element (or all elements in a
+    /// layer) to fixed-point, see [`scalar::to_fixed_point`].
+    ///
+    /// Pressures, angles and the scale, rotation and skew of transforms are
+    /// kept, as fixed-point steps would be coarse for them.
+    pub fn snap_to_fixed_point(&mut self) {
+        self.map_scalars(&scalar::to_fixed_point, &|value| value);
+    }
+
+    /// Applies `f` to all coordinates, sizes, widths and translations and
+    /// clips, and `other` to pressures, angles and the rest of transforms.
+    fn map_scalars(&mut self, f: &dyn Fn(Scalar) -> Scalar, other: &dyn Fn(Scalar) -> Scalar) {
+        let map = |value: &mut Scalar| *value = f(*value);
+        let map_other = |value: &mut Scalar| *value = other(*value);
+        let map_point = |(x, y): &mut (Scalar, Scalar)| {
+            map(x);
+            map(y);
+        };
+        match self {
+            Element::Line(e) | Element::Ephemeral(Ephemeral { line: e, .. }) => {
+                map(&mut e.width);
+                for LinePoint(x, y, pressure) in &mut e.points {
+                    map(x);
+                    map(y);
+                    map_other(pressure);
+                }
+            }
+            Element::Polyline(e) => {
+                map(&mut e.width);
+                for PolylinePoint(x, y) in &mut e.points {
+                    map(x);
+                    map(y);
+                }
+                for width in &mut e.widths {
+                    map(width);
+                }
+            }
+            Element::Ngon(e) => {
+                map(&mut e.width);
+                map(&mut e.radius);
+                map_other(&mut e.angle);
+                map_point(&mut e.position);
+            }
+            Element::Ellipse(e) => {
+                map(&mut e.width);
+                map(&mut e.radius);
+                map_point(&mut e.position);
+            }
+            Element::Shape(e) => {
+                map(&mut e.width);
+                map(&mut e.radius);
+                map_other(&mut e.angle);
+                map_point(&mut e.position);
+                e.points.iter_mut().for_each(map_point);
+            }
+            Element::Arc(e) => {
+                map(&mut e.width);
+                map(&mut e.radius);
+                map_other(&mut e.start);
+                map_other(&mut e.end);
+                map_point(&mut e.position);
+            }
+            Element::Text(e) => {
+                map(&mut e.font.size);
+                map_point(&mut e.position);
+            }
+            Element::FileRef(e) => {
+                map_point(&mut e.position);
+                map_point(&mut e.size);
+            }
+            Element::Frame(e) => {
+                map_point(&mut e.position);
+                map_point(&mut e.size);
+            }
+            Element::Layer(e) => e.elements.iter_mut().for_each(|e| e.map_scalars(f, other)),
+            Element::Instance(e) => map_point(&mut e.position),
+            // Opaque to the library
+            Element::Custom(_) => {}
+            Element::Dimension(e) => {
+                map(&mut e.width);
+                map(&mut e.offset);
+                map_point(&mut e.start);
+                map_point(&mut e.end);
+            }
+        }
+        let properties = self.properties_mut();
+        properties.bounds = StoredBounds(None);
+        let Transform { a, b, c, d, e, f } = &mut properties.transform;
+        for value in vec![a, b, c, d] {
+            map_other(value);
+        }
+        map(e);
+        map(f);
+        match &mut properties.clip {
+            Some(Clip::Rectangle { position, size }) => {
+                map_point(position);
+                map_point(size);
+            }
+            Some(Clip::Ellipse { position, radius }) => {
+                map_point(position);
+                map_point(radius);
+            }
+            None => {}
+        }
+    }
+
+    /// Appends the SVG representation of this element to `node`.
+    pub fn add_to<T: Node>(&self, node: T) -> T {
+        self.add_to_with(node, &Output::default())
+    }
+
+    /// Like [`Element::add_to`], but written as configured in `output`.
+    pub(crate) fn add_to_with<T: Node>(&self, mut node: T, output: &Output) -> T {
+        fn bounded<T: Node>(mut node: T, bounds: Option<BoundingBox>) -> T {
+            if let Some(bounds) = bounds {
+                node.assign("svgnote:bbox", StoredBounds::to_attribute(bounds));
+            }
+            node
+        }
+        let styles = output.styles.as_ref();
+        // Always computed, stored ones may be stale
+        let bounds = if output.bounding_boxes {
+            render::element_bounds(self, Transform::IDENTITY)
+        } else {
+            None
+        };
+        match self {
+            Element::Line(e) => node.append(bounded(e.to_path(styles, output.omit_points), bounds)),
+            Element::Ngon(e) => node.append(bounded(e.to_polygon(styles), bounds)),
+            Element::Ellipse(e) => node.append(bounded(e.to_ellipse(styles), bounds)),
+            Element::Shape(e) => node.append(bounded(e.to_polygon(styles), bounds)),
+            Element::Arc(e) => node.append(bounded(e.to_path(styles), bounds)),
+            Element::Polyline(e) if e.closed => node.append(bounded(e.to_polygon(styles), bounds)),
+            Element::Polyline(e) => node.append(bounded(e.to_polyline(styles), bounds)),
+            Element::Text(e) => match output.loader.and_then(|loader| e.to_outline(loader)) {
+                Some(path) => node.append(bounded(path, bounds)),
+                None => node.append(bounded(e.to_text(styles), bounds)),
+            },
+            Element::FileRef(e) => match e.to_use(output.assets) {
+                Some(asset) => node.append(bounded(asset, bounds)),
+                None => node.append(bounded(element::Image::from(e), bounds)),
+            },
+            Element::Layer(e) => node.append(bounded(e.to_group(output), bounds)),
+            Element::Instance(e) => node.append(bounded(element::Use::from(e), bounds)),
+            Element::Custom(e) => node.append(bounded(e.to_svg(), bounds)),
+            Element::Dimension(e) => node.append(bounded(e.to_group(), bounds)),
+            // Never stored
+            Element::Ephemeral(_) => {}
+            Element::Frame(e) => node.append(bounded(e.to_rect(), bounds)),
+        }
+        node
+    }
+
+    /// A `<polygon>` without our attributes, as an [`Ngon`] if it is
+    /// regular, otherwise as a closed [`Polyline`].
+    fn from_polygon(mut attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let mut polyline = Polyline::from_attributes(attributes.clone())?;
+        let points: Vec<_> = polyline.points.iter().map(|&p| p.into()).collect();
+        match Ngon::fit(&points) {
+            Some(ngon) => {
+                let (x, y) = ngon.position;
+                let parameters = [
+                    ("svgnote:position", format!("{},{}", x, y)),
+                    ("svgnote:radius", ngon.radius.to_string()),
+                    ("svgnote:n", ngon.n.to_string()),
+                    ("svgnote:angle", ngon.angle.to_string()),
+                ];
+                for (name, value) in parameters.iter() {
+                    attributes.insert(*name, value.clone());
+                }
+                Ok(Element::Ngon(Ngon::from_attributes(attributes)?))
+            }
+            None => {
+                polyline.closed = true;
+                Ok(Element::Polyline(polyline))
+            }
+        }
+    }
+
     pub fn from_event(e: Event) -> Result<Self, DocumentError> {
         match e {
-            Event::Tag(tag::Path, _, attributes) => {
+            Event::Tag(name, _, attributes) => Element::from_tag(name, attributes.into()),
+            _ => Err(DocumentError::UnknownEvent),
+        }
+    }
+
+    /// The element of the tag `name`, see [`Element::from_event`].
+    pub(crate) fn from_tag(name: &str, attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let tool = attributes.get("svgnote:tool").map(|tool| &**tool);
+        let shape = name == tag::Polygon && attributes.contains_key("svgnote:parameters");
+        match tool {
+            // Read without the registry, see `Stored`
+            Some(tool) if !TOOLS.contains(&tool) && !shape => {
+                return Ok(Element::Custom(Custom::stored(name, &attributes)?));
+            }
+            _ => {}
+        }
+        match name {
+            tag::Path => {
                 let tool: &str = attributes
                     .get("svgnote:tool")
                     .ok_or(MissingAttribute("svgnote:tool".to_owned()))?;
                 match tool {
                     "pen" => Ok(Element::Line(Line::from_attributes(attributes)?)),
+                    "text" => Ok(Element::Text(Text::from_outline(attributes)?)),
+                    "arc" => Ok(Element::Arc(Arc::from_attributes(attributes)?)),
                     _ => Err(InvalidAttribute("svgnote:tool".to_owned(), tool.to_owned()))?,
                 }
             }
-            Event::Tag(tag::Polygon, _, attributes) => {
-                let tool: &str = attributes
-                    .get("svgnote:tool")
-                    .ok_or(MissingAttribute("svgnote:tool".to_owned()))?;
-                match tool {
-                    "ngon" => Ok(Element::Ngon(Ngon::from_attributes(attributes)?)),
-                    _ => Err(InvalidAttribute("svgnote:tool".to_owned(), tool.to_owned()))?,
+            tag::Polygon => match attributes.get("svgnote:tool") {
+                Some(tool) if &**tool == "ngon" => {
+                    Ok(Element::Ngon(Ngon::from_attributes(attributes)?))
                 }
+                Some(tool) if &**tool == "polyline" => Ok(Element::Polyline(Polyline {
+                    closed: true,
+                    ..Polyline::from_attributes(attributes)?
+                })),
+                // Read without the registry, see `Shape`
+                Some(_) => Ok(Element::Shape(Shape::from_attributes(attributes)?)),
+                // Drawn by other editors
+                None => Element::from_polygon(attributes),
+            },
+            tag::Polyline => Ok(Element::Polyline(Polyline::from_attributes(attributes)?)),
+            tag::Ellipse => Ok(Element::Ellipse(Ellipse::from_attributes(attributes)?)),
+            tag::Text => Ok(Element::Text(Text::from_attributes(attributes)?)),
+            tag::Image => Ok(Element::FileRef(FileRef::from_attributes(attributes)?)),
+            // Of an asset, see `FileRef::to_use`
+            tag::Use if tool == Some("file") => {
+                Ok(Element::FileRef(FileRef::from_attributes(attributes)?))
             }
-            Event::Tag(tag::Polyline, _, attributes) => {
-                Ok(Element::Polyline(Polyline::from_attributes(attributes)?))
+            tag::Use => Ok(Element::Instance(Instance::from_attributes(attributes)?)),
+            tag::Rectangle if tool == Some("frame") => {
+                Ok(Element::Frame(Frame::from_attributes(attributes)?))
             }
-            Event::Tag(tag::Ellipse, _, attributes) => {
-                Ok(Element::Ellipse(Ellipse::from_attributes(attributes)?))
+            tag::Group if tool == Some("dimension") => {
+                Ok(Element::Dimension(Dimension::from_attributes(attributes)?))
             }
             _ => Err(DocumentError::UnknownEvent),
         }
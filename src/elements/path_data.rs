@@ -0,0 +1,121 @@
+//! Parsing the `d` attribute of `<path>`s into points.
+
+use crate::scalar;
+use crate::scalar::Scalar;
+
+/// The end points of all segments in path data, curves are not sampled.
+///
+/// Supports all commands except arcs, `None` if `d` is malformed or empty.
+pub(crate) fn points(d: &str) -> Option<Vec<(Scalar, Scalar)>> {
+    let mut tokens = Tokens(d.trim_start());
+    let mut points = vec![];
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    let mut command = tokens.command()?;
+    loop {
+        let relative = command.is_ascii_lowercase();
+        let offset = if relative { current } else { (0.0, 0.0) };
+        let point = match command.to_ascii_uppercase() {
+            'Z' => start,
+            'H' => (tokens.number()? + offset.0, current.1),
+            'V' => (current.0, tokens.number()? + offset.1),
+            command => {
+                // Control points are skipped
+                let skip = match command {
+                    'M' | 'L' | 'T' => 0,
+                    'S' | 'Q' => 1,
+                    'C' => 2,
+                    _ => return None,
+                };
+                for _ in 0..skip * 2 {
+                    tokens.number()?;
+                }
+                (tokens.number()? + offset.0, tokens.number()? + offset.1)
+            }
+        };
+        if command.eq_ignore_ascii_case(&'M') {
+            start = point;
+            // Following coordinates are implicit line tos
+            command = if relative { 'l' } else { 'L' };
+        }
+        points.push(point);
+        current = point;
+        if tokens.is_empty() {
+            return Some(points);
+        }
+        if let Some(next) = tokens.command() {
+            command = next;
+        } else if command.eq_ignore_ascii_case(&'Z') {
+            return None;
+        }
+    }
+}
+
+struct Tokens<'a>(&'a str);
+
+impl Tokens<'_> {
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn skip_separators(&mut self) {
+        self.0 = self
+            .0
+            .trim_start_matches(|c: char| c.is_ascii_whitespace() || c == ',');
+    }
+
+    fn command(&mut self) -> Option<char> {
+        let c = self.0.chars().next().filter(|c| c.is_ascii_alphabetic())?;
+        self.0 = &self.0[1..];
+        self.skip_separators();
+        Some(c)
+    }
+
+    fn number(&mut self) -> Option<Scalar> {
+        let mut end = 0;
+        let (mut dot, mut exponent) = (false, false);
+        for (i, c) in self.0.char_indices() {
+            match c {
+                '0'..='9' => {}
+                // A second dot starts the next number, e.g. in `.5.5`
+                '.' if !dot && !exponent => dot = true,
+                'e' | 'E' if !exponent => exponent = true,
+                '-' | '+' if i == 0 || matches!(self.0.as_bytes()[i - 1], b'e' | b'E') => {}
+                _ => break,
+            }
+            end = i + 1;
+        }
+        let number = scalar::parse(&self.0[..end])?;
+        self.0 = &self.0[end..];
+        self.skip_separators();
+        Some(number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::points;
+
+    #[test]
+    fn path_data() {
+        assert_eq!(
+            points("M0,0 L1,1 2 2"),
+            Some(vec![(0., 0.), (1., 1.), (2., 2.)])
+        );
+        assert_eq!(
+            points("m1 1l1 1h2v-1e1 C 0 0 0 0 5,5z"),
+            Some(vec![
+                (1., 1.),
+                (2., 2.),
+                (4., 2.),
+                (4., -8.),
+                (5., 5.),
+                (1., 1.)
+            ])
+        );
+        assert_eq!(points("M1-2.5.5.5"), Some(vec![(1., -2.5), (0.5, 0.5)]));
+        assert_eq!(points(""), None);
+        assert_eq!(points("M0,0 L1"), None);
+        assert_eq!(points("M0,0 A1 1 0 0 0 1 1"), None);
+    }
+}
@@ -2,18 +2,24 @@ use std::fmt;
 use std::str::FromStr;
 
 use svg::node::element;
+use svg::node::Node;
 use DocumentError::InvalidAttribute;
+use DocumentError::InvalidColor;
 use DocumentError::InvalidPoint;
 use DocumentError::MissingAttribute;
 
 use crate::colors::Color;
 use crate::elems_eq;
+use crate::scalar;
+use crate::scalar::Scalar;
 use crate::DocumentError;
 
+use super::style::{self, StyleSheet};
 use super::FromAttributes;
+use super::{Attributes, ForeignAttributes, Properties};
 
 #[derive(PartialEq, Clone, Copy)]
-pub struct PolylinePoint(pub f32, pub f32);
+pub struct PolylinePoint(pub Scalar, pub Scalar);
 
 impl fmt::Debug for PolylinePoint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -22,7 +28,7 @@ impl fmt::Debug for PolylinePoint {
 }
 
 impl PolylinePoint {
-    pub fn distance_to(&self, other: Self) -> f32 {
+    pub fn distance_to(&self, other: Self) -> Scalar {
         ((self.0 - other.0).powi(2) + (self.1 - other.1).powi(2)).sqrt()
     }
 }
@@ -33,7 +39,7 @@ impl fmt::Display for PolylinePoint {
     }
 }
 
-impl From<PolylinePoint> for (f32, f32) {
+impl From<PolylinePoint> for (Scalar, Scalar) {
     fn from(val: PolylinePoint) -> Self {
         (val.0, val.1)
     }
@@ -43,77 +49,116 @@ impl From<PolylinePoint> for (f32, f32) {
 pub struct Polyline {
     pub stroke: Color,
     pub fill: Color,
-    pub width: f32,
+    /// The width other renderers draw the whole polyline with.
+    pub width: Scalar,
     pub points: Vec<PolylinePoint>,
+    /// The width at each point relative to `width`, e.g. the pressures of a
+    /// converted pen stroke. Empty if it is uniform.
+    pub widths: Vec<Scalar>,
+    /// Whether the last point is joined to the first, stored as `<polygon>`.
+    pub closed: bool,
+    pub properties: Properties,
 }
 
 impl PartialEq for Polyline {
     fn eq(&self, other: &Self) -> bool {
-        (self.stroke, self.fill, self.width) == (other.stroke, other.fill, other.width)
+        (self.stroke, self.fill, self.width, self.closed)
+            == (other.stroke, other.fill, other.width, other.closed)
             && elems_eq(&self.points, &other.points)
+            && elems_eq(&self.widths, &other.widths)
+            && self.properties == other.properties
     }
 }
 
 impl From<&Polyline> for element::Polyline {
+    /// Also for closed polylines, see [`Polyline::to_polygon`].
     fn from(polygon: &Polyline) -> Self {
-                element::Polyline::new()
-                    .set("stroke", polygon.stroke.to_string_na())
-                    .set("fill", polygon.fill.to_string_na())
-                    .set("stroke-opacity", polygon.stroke.opacity())
-                    .set("fill-opacity", polygon.fill.opacity())
-                    .set("stroke-width", polygon.width)
-                    .set(
-                        "points",
-                        polygon
-                            .points
-                            .iter()
-                            .map(PolylinePoint::to_string)
-                            .collect::<Vec<String>>(),
-                    )
-                    // Static
-                    .set("stroke-linecap", "round")
-                    .set("stroke-linejoin", "round")
+        polygon.to_polyline(None)
     }
 }
 
-impl FromAttributes for Polyline{
-    fn from_attributes(
-        attributes: std::collections::HashMap<String, svg::node::Value>,
-    ) -> Result<Self, crate::DocumentError> {
-        Ok(Polyline {
+impl Polyline {
+    /// The width at point `i`.
+    pub fn width_at(&self, i: usize) -> Scalar {
+        self.width * self.widths.get(i).copied().unwrap_or(1.)
+    }
+
+    /// The width at the widest point.
+    pub fn max_width(&self) -> Scalar {
+        self.widths
+            .iter()
+            .map(|w| self.width * w)
+            .fold(self.width, Scalar::max)
+    }
+
+    /// The relative width of each point, `1` for all if it is uniform.
+    pub fn point_widths(&self) -> Vec<Scalar> {
+        if self.widths.is_empty() {
+            vec![1.; self.points.len()]
+        } else {
+            self.widths.clone()
+        }
+    }
+
+    /// Like `element::Polyline::from`, but with the style as a class if
+    /// `styles` has one for it.
+    pub(crate) fn to_polyline(&self, styles: Option<&StyleSheet>) -> element::Polyline {
+        let mut polyline = element::Polyline::new();
+        self.assign_to(&mut polyline, styles);
+        polyline
+    }
+
+    /// Like [`Polyline::to_polyline`], as `<polygon>` for closed polylines.
+    pub(crate) fn to_polygon(&self, styles: Option<&StyleSheet>) -> element::Polygon {
+        let mut polygon = element::Polygon::new().set("svgnote:tool", "polyline");
+        self.assign_to(&mut polygon, styles);
+        polygon
+    }
+
+    fn assign_to<T: Node>(&self, node: &mut T, styles: Option<&StyleSheet>) {
+        node.assign(
+            "points",
+            self.points
+                .iter()
+                .map(PolylinePoint::to_string)
+                .collect::<Vec<String>>(),
+        );
+        if !self.widths.is_empty() {
+            node.assign(
+                "svgnote:widths",
+                self.widths
+                    .iter()
+                    .map(Scalar::to_string)
+                    .collect::<Vec<String>>()
+                    .join(" "),
+            );
+        }
+        self.properties.foreign.assign_to(node);
+        style::assign(node, self.style(), styles);
+        self.properties.assign_to(node);
+    }
+}
+
+impl FromAttributes for Polyline {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, crate::DocumentError> {
+        let polyline = Polyline {
+            properties: Properties {
+                foreign: ForeignAttributes::from_attributes(&attributes),
+                ..Properties::from_attributes(&attributes)?
+                    .with_stroke_opacity(&attributes)
+                    .with_fill_opacity(&attributes)
+            },
             stroke: {
                 let color: &str = attributes
                     .get("stroke")
                     .ok_or(MissingAttribute("stroke".to_owned()))?;
-                Color::from_str(color)
-                    .map_err(|_| InvalidAttribute("stroke".to_owned(), color.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("stroke-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                Color::from_str(color).map_err(|e| InvalidColor("stroke".to_owned(), e))?
             },
             fill: {
                 let color: &str = attributes
                     .get("fill")
                     .ok_or(MissingAttribute("fill".to_owned()))?;
-                Color::from_str(color)
-                    .map_err(|_| InvalidAttribute("fill".to_owned(), color.to_owned()))
-                    .map(|c| {
-                        // TODO Give an Error on a malformed opacity maybe
-                        if let Some(Ok(value)) =
-                            attributes.get("fill-opacity").map(|s| f32::from_str(s))
-                        {
-                            c.with_opacity(value)
-                        } else {
-                            c
-                        }
-                    })?
+                style::parse_fill(color).map_err(|e| InvalidColor("fill".to_owned(), e))?
             },
             points: {
                 let points: &str = attributes
@@ -125,8 +170,8 @@ impl FromAttributes for Polyline{
                         let a: Vec<&str> = s.split(',').collect();
                         if a.len() == 2 {
                             Ok(PolylinePoint(
-                                f32::from_str(a[0]).map_err(|_| InvalidPoint(s.to_owned()))?,
-                                f32::from_str(a[1]).map_err(|_| InvalidPoint(s.to_owned()))?,
+                                scalar::parse(a[0]).ok_or_else(|| InvalidPoint(s.to_owned()))?,
+                                scalar::parse(a[1]).ok_or_else(|| InvalidPoint(s.to_owned()))?,
                             ))
                         } else {
                             Err(InvalidPoint(s.to_owned()))
@@ -138,9 +183,71 @@ impl FromAttributes for Polyline{
                 let width: &str = attributes
                     .get("stroke-width")
                     .ok_or(MissingAttribute("stroke-width".to_owned()))?;
-                f32::from_str(width)
+                Scalar::from_str(width)
                     .map_err(|_| InvalidAttribute("stroke-width".to_owned(), width.to_owned()))?
             },
-        })
+            widths: match attributes.get("svgnote:widths") {
+                Some(value) => value
+                    .split_ascii_whitespace()
+                    .map(|w| scalar::parse(w).filter(|w| *w >= 0.))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| {
+                        InvalidAttribute("svgnote:widths".to_owned(), value.to_string())
+                    })?,
+                None => vec![],
+            },
+            // Set by `Element::from_event` for `<polygon>`s
+            closed: false,
+        };
+        if !polyline.widths.is_empty() && polyline.widths.len() != polyline.points.len() {
+            let widths = attributes.get("svgnote:widths").map(|w| w.to_string());
+            return Err(InvalidAttribute(
+                "svgnote:widths".to_owned(),
+                widths.unwrap_or_default(),
+            ));
+        }
+        Ok(polyline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Element, Polyline};
+    use crate::Document;
+
+    #[test]
+    fn closed_widths() {
+        let doc = Document {
+            elements: vec![Element::Polyline(
+                Polyline::builder()
+                    .point_with_width(0., 0., 1.)
+                    .point_with_width(10., 0., 0.5)
+                    .point_with_width(10., 10., 0.25)
+                    .width(4.)
+                    .closed(true)
+                    .build()
+                    .unwrap(),
+            )],
+            ..Default::default()
+        };
+        let string = doc.to_string();
+        assert!(string.contains("<polygon"));
+        assert!(string.contains(r#"svgnote:tool="polyline""#));
+        assert!(string.contains(r#"svgnote:widths="1 0.5 0.25""#));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+        #[cfg(feature = "json")]
+        assert_eq!(Document::from_json(&doc.to_json()).unwrap(), doc);
+        match &doc.elements[0] {
+            Element::Polyline(polyline) => assert_eq!(polyline.width_at(2), 1.),
+            element => panic!("{:?}", element),
+        }
+
+        assert!(Polyline::builder()
+            .point_with_width(0., 0., 1.)
+            .point(1., 1.)
+            .build()
+            .is_err());
     }
 }
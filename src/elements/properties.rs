@@ -0,0 +1,460 @@
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+use svg::node::element;
+use svg::node::{Node, Text};
+
+use crate::colors::Color;
+use crate::escape::{escape, unescape};
+use crate::geometry::BoundingBox;
+use crate::ids::ElementId;
+use crate::metadata::Target;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+
+use super::Attributes;
+use super::Clip;
+use super::LodCache;
+
+/// Properties shared by every element, independent of its geometry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Properties {
+    /// Identifies the element across edits and syncs, stored as `id`, see
+    /// [`IdGenerator`](crate::ids::IdGenerator).
+    pub id: Option<ElementId>,
+    /// Hidden elements are kept in the document, but not rendered.
+    pub hidden: bool,
+    pub clip: Option<Clip>,
+    pub transform: Transform,
+    /// Keep the stroke width when the document is scaled, e.g. by the
+    /// `viewBox` of a diagram in a web page, stored as
+    /// `vector-effect="non-scaling-stroke"`. Not applied by the library.
+    pub non_scaling_stroke: bool,
+    /// A short text alternative, e.g. for screen readers, stored as
+    /// `<title>`.
+    pub title: Option<String>,
+    /// A longer description, stored as `<desc>`.
+    pub description: Option<String>,
+    /// Who drew the element, in collaborative documents.
+    pub author: Option<String>,
+    /// Where to go when the element is clicked, stored as `svgnote:link`.
+    pub link: Option<Target>,
+    /// Read from `svgnote:bbox`, see [`StoredBounds`].
+    pub bounds: StoredBounds,
+    /// Applied on top of the alpha of the stroke color, stored as
+    /// `stroke-opacity`, see [`Properties::stroke`].
+    pub stroke_opacity: f32,
+    /// Applied on top of the alpha of the fill color, stored as
+    /// `fill-opacity`, see [`Properties::fill`].
+    pub fill_opacity: f32,
+    /// See [`StoredOpacity`].
+    pub stored_opacity: StoredOpacity,
+    /// See [`ForeignAttributes`].
+    pub foreign: ForeignAttributes,
+    pub lod_cache: LodCache,
+}
+
+/// The bounds of an element as stored in `svgnote:bbox`, in its own
+/// coordinates (i.e. without its transform), see
+/// [`SerializeOptions::bounding_boxes`].
+///
+/// Cleared when the library changes the geometry, e.g. through the
+/// [`Editor`] or [`Op`]s, and always written anew. Clear them with
+/// [`Document::clear_stored_bounds`] after changing elements directly.
+///
+/// [`SerializeOptions::bounding_boxes`]: crate::options::SerializeOptions::bounding_boxes
+/// [`Editor`]: crate::editor::Editor
+/// [`Op`]: crate::ops::Op
+/// [`Document::clear_stored_bounds`]: crate::Document::clear_stored_bounds
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct StoredBounds(pub Option<BoundingBox>);
+
+impl StoredBounds {
+    /// The value of `svgnote:bbox`.
+    pub(crate) fn to_attribute(bounds: BoundingBox) -> String {
+        format!(
+            "{} {} {} {}",
+            bounds.min.0, bounds.min.1, bounds.max.0, bounds.max.1
+        )
+    }
+
+    fn from_attribute(value: &str) -> Option<BoundingBox> {
+        let values = value
+            .split_ascii_whitespace()
+            .map(|v| Scalar::from_str(v).ok())
+            .collect::<Option<Vec<_>>>()?;
+        match values[..] {
+            [min_x, min_y, max_x, max_y] => Some(BoundingBox {
+                min: (min_x, min_y),
+                max: (max_x, max_y),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The `stroke-opacity` and `fill-opacity` of an element, and the
+/// `opacity` of a layer, as read, if that differs from how the value is
+/// written, e.g. `0.3333333333` for the `f32` written as `0.33333334`.
+///
+/// They are written as they were read as long as the opacity has not
+/// changed, so untouched elements are saved byte for byte.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct StoredOpacity {
+    pub stroke: Option<String>,
+    pub fill: Option<String>,
+    pub layer: Option<String>,
+}
+
+impl StoredOpacity {
+    /// The opacity in the attribute `name`, and how it was written if that
+    /// differs from how it is written. `None` if missing or invalid.
+    pub(crate) fn read(attributes: &Attributes<'_>, name: &str) -> Option<(f32, Option<String>)> {
+        let value = attributes.get(name)?;
+        let opacity = f32::from_str(value).ok()?;
+        Some((
+            opacity,
+            Some(value.to_string()).filter(|v| *v != opacity.to_string()),
+        ))
+    }
+
+    /// The value of an opacity attribute, `stored` if it is still `opacity`.
+    pub(crate) fn value(stored: &Option<String>, opacity: f32) -> String {
+        match stored {
+            Some(value) if f32::from_str(value) == Ok(opacity) => value.clone(),
+            _ => opacity.to_string(),
+        }
+    }
+}
+
+/// The attributes the library reads or writes itself, besides the `svgnote:`
+/// ones. `style` is dropped, as it would override the presentation
+/// attributes written.
+const KNOWN: &[&str] = &[
+    "d",
+    "points",
+    "cx",
+    "cy",
+    "r",
+    "rx",
+    "ry",
+    "x",
+    "y",
+    "width",
+    "height",
+    "href",
+    "xlink:href",
+    "stroke",
+    "fill",
+    "stroke-width",
+    "stroke-opacity",
+    "fill-opacity",
+    "opacity",
+    "stroke-linecap",
+    "stroke-linejoin",
+    "font-family",
+    "font-size",
+    "transform",
+    "display",
+    "clip-path",
+    "vector-effect",
+    "id",
+    "style",
+];
+
+/// The attributes of other editors on pen strokes, n-gons, ellipses and
+/// polylines, e.g. `inkscape:label` or `class`, written back as they
+/// were read, so saving a file edited in both does not strip them. On
+/// layers, the `style` with a blend mode [`BlendMode`] does not have.
+///
+/// [`BlendMode`]: super::BlendMode
+///
+/// The namespaces of prefixed attributes are declared on the element, as
+/// the `<svg>` tag is written anew. Only kept in SVG. A `class` is replaced
+/// by the one of the style with [`SerializeOptions::style_classes`].
+///
+/// [`SerializeOptions::style_classes`]: crate::options::SerializeOptions::style_classes
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ForeignAttributes(pub BTreeMap<String, String>);
+
+impl ForeignAttributes {
+    pub(crate) fn from_attributes(attributes: &Attributes<'_>) -> Self {
+        ForeignAttributes(
+            attributes
+                .iter()
+                .filter(|(name, _)| {
+                    !name.starts_with("svgnote:") && *name != "xmlns" && !KNOWN.contains(name)
+                })
+                .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+        )
+    }
+
+    /// Adds the declarations from `namespaces`, those of the `<svg>` tag,
+    /// for the prefixes the attributes use.
+    pub(crate) fn declare(&mut self, namespaces: &HashMap<String, String>) {
+        let prefixes: Vec<String> = self
+            .0
+            .keys()
+            .filter_map(|name| name.split_once(':'))
+            .map(|(prefix, _)| prefix.to_owned())
+            .filter(|prefix| prefix != "xmlns")
+            .collect();
+        for prefix in prefixes {
+            if let Some(namespace) = namespaces.get(&prefix) {
+                self.0
+                    .entry(format!("xmlns:{}", prefix))
+                    .or_insert_with(|| namespace.clone());
+            }
+        }
+    }
+
+    /// Assigns the attributes to `node`, before the ones of the element, so
+    /// those win.
+    pub(crate) fn assign_to<T: Node>(&self, node: &mut T) {
+        for (name, value) in &self.0 {
+            node.assign(name.clone(), value.clone());
+        }
+    }
+}
+
+impl Default for Properties {
+    fn default() -> Self {
+        Properties {
+            id: None,
+            hidden: false,
+            clip: None,
+            transform: Transform::default(),
+            non_scaling_stroke: false,
+            title: None,
+            description: None,
+            author: None,
+            link: None,
+            bounds: StoredBounds::default(),
+            stroke_opacity: 1.,
+            fill_opacity: 1.,
+            stored_opacity: StoredOpacity::default(),
+            foreign: ForeignAttributes::default(),
+            lod_cache: LodCache::default(),
+        }
+    }
+}
+
+impl Properties {
+    /// `color` with the [stroke opacity](Properties::stroke_opacity)
+    /// applied, as the stroke is shown.
+    pub fn stroke(&self, color: Color) -> Color {
+        color.faded(self.stroke_opacity)
+    }
+
+    /// `color` with the [fill opacity](Properties::fill_opacity) applied,
+    /// as the fill is shown.
+    pub fn fill(&self, color: Color) -> Color {
+        color.faded(self.fill_opacity)
+    }
+
+    /// Reads the `stroke-opacity`, for elements with a stroke. Invalid
+    /// values are ignored.
+    pub(crate) fn with_stroke_opacity(mut self, attributes: &Attributes<'_>) -> Self {
+        if let Some((opacity, stored)) = StoredOpacity::read(attributes, "stroke-opacity") {
+            self.stroke_opacity = opacity;
+            self.stored_opacity.stroke = stored;
+        }
+        self
+    }
+
+    /// Reads the `fill-opacity`, for elements with a fill. Invalid values
+    /// are ignored.
+    pub(crate) fn with_fill_opacity(mut self, attributes: &Attributes<'_>) -> Self {
+        if let Some((opacity, stored)) = StoredOpacity::read(attributes, "fill-opacity") {
+            self.fill_opacity = opacity;
+            self.stored_opacity.fill = stored;
+        }
+        self
+    }
+
+    /// The value of `stroke-opacity`, as read while unchanged.
+    pub(crate) fn stroke_opacity_value(&self) -> String {
+        StoredOpacity::value(&self.stored_opacity.stroke, self.stroke_opacity)
+    }
+
+    /// The value of `fill-opacity`, as read while unchanged.
+    pub(crate) fn fill_opacity_value(&self) -> String {
+        StoredOpacity::value(&self.stored_opacity.fill, self.fill_opacity)
+    }
+
+    pub fn from_attributes(attributes: &Attributes<'_>) -> Result<Self, DocumentError> {
+        Ok(Properties {
+            id: attributes.get("id").map(|v| ElementId::new(v.to_string())),
+            hidden: attributes.get("svgnote:hidden").map(|v| &**v) == Some("true")
+                || attributes.get("display").map(|v| &**v) == Some("none"),
+            // `clip-path` references a `<clipPath>`, so it is resolved by the
+            // `Document`
+            clip: None,
+            transform: match attributes.get("transform") {
+                Some(value) => Transform::from_str(value)
+                    .map_err(|_| InvalidAttribute("transform".to_owned(), value.to_string()))?,
+                None => Transform::IDENTITY,
+            },
+            non_scaling_stroke: attributes.get("vector-effect").map(|v| &**v)
+                == Some("non-scaling-stroke"),
+            // Stored as child elements, so they are set by the `Document`
+            title: None,
+            description: None,
+            author: attributes.get("svgnote:author").map(|v| unescape(v)),
+            link: attributes
+                .get("svgnote:link")
+                .map(|value| {
+                    Target::from_str(&unescape(value))
+                        .map_err(|_| InvalidAttribute("svgnote:link".to_owned(), value.to_string()))
+                })
+                .transpose()?,
+            bounds: StoredBounds(
+                attributes
+                    .get("svgnote:bbox")
+                    .map(|value| {
+                        StoredBounds::from_attribute(value).ok_or_else(|| {
+                            InvalidAttribute("svgnote:bbox".to_owned(), value.to_string())
+                        })
+                    })
+                    .transpose()?,
+            ),
+            // Read by the elements that have them
+            stroke_opacity: 1.,
+            fill_opacity: 1.,
+            stored_opacity: StoredOpacity::default(),
+            // Only kept for some elements, see `ForeignAttributes`
+            foreign: ForeignAttributes::default(),
+            lod_cache: LodCache::default(),
+        })
+    }
+
+    /// Assigns the properties to `node` as attributes, and appends the title
+    /// and description as children.
+    pub fn assign_to<T: Node>(&self, node: &mut T) {
+        if let Some(id) = &self.id {
+            node.assign("id", id.as_str());
+        }
+        if self.hidden {
+            node.assign("display", "none");
+            node.assign("svgnote:hidden", true);
+        }
+        if let Some(clip) = self.clip {
+            node.assign("clip-path", format!("url(#{})", clip.id()));
+        }
+        if !self.transform.is_identity() {
+            node.assign("transform", self.transform.to_string());
+        }
+        if self.non_scaling_stroke {
+            node.assign("vector-effect", "non-scaling-stroke");
+        }
+        if let Some(author) = &self.author {
+            node.assign("svgnote:author", escape(author));
+        }
+        if let Some(link) = &self.link {
+            node.assign("svgnote:link", escape(&link.to_string()));
+        }
+        if let Some(title) = &self.title {
+            node.append(element::Title::new().add(Text::new(escape(title))));
+        }
+        if let Some(description) = &self.description {
+            node.append(element::Description::new().add(Text::new(escape(description))));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ngon};
+    use crate::options::SerializeOptions;
+    use crate::Document;
+
+    #[test]
+    fn non_scaling_stroke() {
+        let doc = Document {
+            elements: vec![Element::Ngon(
+                Ngon::builder()
+                    .position(10., 10.)
+                    .radius(5.)
+                    .build()
+                    .unwrap(),
+            )],
+            ..Default::default()
+        };
+        let string = doc.to_string_with(&SerializeOptions {
+            non_scaling_strokes: true,
+            ..Default::default()
+        });
+        assert!(string.contains(r#"vector-effect="non-scaling-stroke""#));
+        let parsed = Document::from_str(&string).unwrap();
+        assert!(parsed.elements[0].properties().non_scaling_stroke);
+        assert!(!doc.elements[0].properties().non_scaling_stroke);
+    }
+
+    #[test]
+    fn opacity() {
+        let mut doc = Document::from_str(
+            r##"<svg>
+                <polyline points="0,0 1,0 1,1" stroke="#FF0000" stroke-opacity="0.3333333333" fill="#0000FF80" stroke-width="1"/>
+                <g svgnote:tool="layer" opacity="0.10"></g>
+            </svg>"##,
+        )
+        .unwrap();
+        let string = doc.to_string();
+        assert!(string.contains(r#"stroke-opacity="0.3333333333""#));
+        assert!(string.contains(r#"fill="#0000FF80""#));
+        assert!(string.contains(r#"opacity="0.10""#));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+        match &mut doc.elements[0] {
+            Element::Polyline(polyline) => {
+                assert_eq!(polyline.stroke, Color::rgb(0xFF, 0, 0));
+                assert_eq!(polyline.fill, Color::rgba(0, 0, 0xFF, 0x80));
+                let shown = polyline.properties.stroke(polyline.stroke);
+                assert_eq!(shown, Color::rgba(0xFF, 0, 0, 0x55));
+                polyline.properties.stroke_opacity = 0.5;
+            }
+            element => panic!("{:?}", element),
+        }
+        let string = doc.to_string();
+        assert!(string.contains(r#"stroke-opacity="0.5""#));
+        assert!(!string.contains(r#"stroke-opacity="0.3333333333""#));
+    }
+
+    #[test]
+    fn foreign_attributes() {
+        let doc = Document::from_str(
+            r##"<svg xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape">
+                <ellipse id="ellipse12" class="marked" inkscape:label="Sun" style="stroke:red" cx="5" cy="5" rx="2" ry="2" stroke="#000000" fill="#FFFFFF" stroke-width="1"/>
+                <polyline sodipodi:nodetypes="ccc" points="0,0 1,0 1,1" stroke="#FF0000" fill="none" stroke-width="1"/>
+            </svg>"##,
+        )
+        .unwrap();
+        match &doc.elements[0] {
+            Element::Ellipse(ellipse) => {
+                let foreign = &ellipse.properties.foreign.0;
+                assert_eq!(foreign.get("class").map(|c| &**c), Some("marked"));
+                assert!(!foreign.contains_key("style"));
+                assert!(!foreign.contains_key("cx"));
+            }
+            element => panic!("{:?}", element),
+        }
+        match &doc.elements[1] {
+            Element::Polyline(polyline) => assert_eq!(polyline.fill, Color::rgba(0, 0, 0, 0)),
+            element => panic!("{:?}", element),
+        }
+        let string = doc.to_string();
+        assert!(string.contains(r#"fill="none""#));
+        assert!(string.contains(r#"id="ellipse12""#));
+        assert!(string.contains(r#"class="marked""#));
+        assert!(string.contains(r#"inkscape:label="Sun""#));
+        assert!(string.contains(r#"xmlns:inkscape="http://www.inkscape.org/namespaces/inkscape""#));
+        assert!(string.contains(r#"sodipodi:nodetypes="ccc""#));
+        assert!(!string.contains("style="));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use svg::node::element;
+
+use crate::colors::Color;
+use crate::scalar;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+
+use super::attributes::{point, AttributeReader, Attributes};
+use super::style::{self, StyleSheet};
+use super::FromAttributes;
+use super::Properties;
+
+/// The parameters of a [`Shape`] by name, e.g. the `inner` radius of a star.
+pub type Parameters = BTreeMap<String, Scalar>;
+
+/// Generates the outline of a kind of [`Shape`], see [`ShapeRegistry`].
+pub trait ShapeKind {
+    /// The `svgnote:tool` the shapes are stored with.
+    fn tool(&self) -> &str;
+
+    /// The parameters new shapes start with, missing ones are taken from
+    /// here.
+    fn defaults(&self) -> Parameters;
+
+    /// The corners of the closed outline around `(0, 0)`, with a radius of
+    /// `1` and pointing up.
+    ///
+    /// The outline need not be convex, but all of it must be visible from
+    /// `(0, 0)`, as fills are fanned out from there when tessellating.
+    fn outline(&self, parameters: &Parameters) -> Vec<(Scalar, Scalar)>;
+}
+
+/// A plus sign, with arms `thickness` wide relative to the radius.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Cross;
+
+impl ShapeKind for Cross {
+    fn tool(&self) -> &str {
+        "cross"
+    }
+
+    fn defaults(&self) -> Parameters {
+        vec![("thickness".to_owned(), 0.5)].into_iter().collect()
+    }
+
+    fn outline(&self, parameters: &Parameters) -> Vec<(Scalar, Scalar)> {
+        let t = parameters.get("thickness").copied().unwrap_or(0.5) / 2.;
+        vec![
+            (-t, -1.),
+            (t, -1.),
+            (t, -t),
+            (1., -t),
+            (1., t),
+            (t, t),
+            (t, 1.),
+            (-t, 1.),
+            (-t, t),
+            (-1., t),
+            (-1., -t),
+            (-t, -t),
+        ]
+    }
+}
+
+/// A star with `points` points, from `2` to `1000`, the corners between
+/// them at the `inner` radius relative to the radius.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Star;
+
+impl ShapeKind for Star {
+    fn tool(&self) -> &str {
+        "star"
+    }
+
+    fn defaults(&self) -> Parameters {
+        vec![("points".to_owned(), 5.), ("inner".to_owned(), 0.4)]
+            .into_iter()
+            .collect()
+    }
+
+    fn outline(&self, parameters: &Parameters) -> Vec<(Scalar, Scalar)> {
+        let n = parameters
+            .get("points")
+            .copied()
+            .unwrap_or(5.)
+            .round()
+            .max(2.)
+            .min(1000.) as usize;
+        let inner = parameters.get("inner").copied().unwrap_or(0.4);
+        (0..2 * n)
+            .map(|i| {
+                let radius = if i % 2 == 0 { 1. } else { inner };
+                let angle = -PI / 2. + i as Scalar * PI / n as Scalar;
+                (radius * angle.cos(), radius * angle.sin())
+            })
+            .collect()
+    }
+}
+
+/// The kinds of [`Shape`]s by `svgnote:tool`, so an app can add its own
+/// quick-shapes.
+///
+/// The default has the [`Cross`] and the [`Star`].
+pub struct ShapeRegistry {
+    kinds: Vec<Box<dyn ShapeKind>>,
+}
+
+impl ShapeRegistry {
+    /// A registry without any kinds.
+    pub fn new() -> Self {
+        ShapeRegistry { kinds: vec![] }
+    }
+
+    /// Adds `kind`, replacing the one with the same tool.
+    pub fn register(&mut self, kind: impl ShapeKind + 'static) {
+        self.kinds.retain(|k| k.tool() != kind.tool());
+        self.kinds.push(Box::new(kind));
+    }
+
+    /// The kind for `tool`.
+    pub fn get(&self, tool: &str) -> Option<&dyn ShapeKind> {
+        self.kinds
+            .iter()
+            .find(|kind| kind.tool() == tool)
+            .map(|kind| &**kind)
+    }
+
+    /// The tools of all kinds, in the order they were registered.
+    pub fn tools(&self) -> impl Iterator<Item = &str> {
+        self.kinds.iter().map(|kind| kind.tool())
+    }
+
+    /// A new black shape of the kind `tool`, with its default parameters.
+    /// `None` if there is no such kind.
+    pub fn create(&self, tool: &str, position: (Scalar, Scalar), radius: Scalar) -> Option<Shape> {
+        let kind = self.get(tool)?;
+        let parameters = kind.defaults();
+        let mut shape = Shape {
+            tool: tool.to_owned(),
+            position,
+            radius,
+            angle: 0.,
+            points: vec![],
+            parameters,
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 1.,
+            properties: Properties::default(),
+        };
+        shape.points = shape.generate(kind);
+        Some(shape)
+    }
+
+    /// Generates the points of `shape` again, e.g. after its parameters were
+    /// changed. Returns `false` if there is no kind for its tool.
+    pub fn update(&self, shape: &mut Shape) -> bool {
+        match self.get(&shape.tool) {
+            Some(kind) => {
+                shape.points = shape.generate(kind);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for ShapeRegistry {
+    fn default() -> Self {
+        let mut registry = ShapeRegistry::new();
+        registry.register(Cross);
+        registry.register(Star);
+        registry
+    }
+}
+
+impl fmt::Debug for ShapeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.tools()).finish()
+    }
+}
+
+/// A parametric shape of a kind in a [`ShapeRegistry`], stored as
+/// `<polygon>` with its tool as `svgnote:tool`.
+///
+/// The points are stored as well, so documents can be read without the
+/// registry.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Shape {
+    pub tool: String,
+    pub position: (Scalar, Scalar),
+    pub radius: Scalar,
+    pub angle: Scalar,
+    pub parameters: Parameters,
+    /// The corners of the outline, generated by [`ShapeRegistry::update`].
+    pub points: Vec<(Scalar, Scalar)>,
+    pub stroke: Color,
+    pub fill: Color,
+    pub width: Scalar,
+    pub properties: Properties,
+}
+
+impl Shape {
+    /// The outline `kind` generates for this shape.
+    fn generate(&self, kind: &dyn ShapeKind) -> Vec<(Scalar, Scalar)> {
+        let mut parameters = kind.defaults();
+        parameters.extend(self.parameters.clone());
+        let (sin, cos) = self.angle.sin_cos();
+        kind.outline(&parameters)
+            .into_iter()
+            .map(|(x, y)| {
+                (
+                    self.position.0 + self.radius * (x * cos - y * sin),
+                    self.position.1 + self.radius * (x * sin + y * cos),
+                )
+            })
+            .collect()
+    }
+}
+
+impl From<&Shape> for element::Polygon {
+    fn from(shape: &Shape) -> Self {
+        shape.to_polygon(None)
+    }
+}
+
+impl Shape {
+    /// Like `element::Polygon::from`, but with the style as a class if
+    /// `styles` has one for it.
+    pub(crate) fn to_polygon(&self, styles: Option<&StyleSheet>) -> element::Polygon {
+        let mut polygon = element::Polygon::new()
+            .set("svgnote:tool", self.tool.clone())
+            .set(
+                "svgnote:position",
+                format!("{},{}", self.position.0, self.position.1),
+            )
+            .set("svgnote:radius", self.radius)
+            .set("svgnote:angle", self.angle)
+            .set(
+                "svgnote:parameters",
+                self.parameters
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<String>>(),
+            )
+            .set(
+                "points",
+                self.points
+                    .iter()
+                    .map(|(x, y)| format!("{},{}", x, y))
+                    .collect::<Vec<String>>(),
+            );
+        style::assign(&mut polygon, self.style(), styles);
+        self.properties.assign_to(&mut polygon);
+        polygon
+    }
+}
+
+impl FromAttributes for Shape {
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let reader = AttributeReader::new(&attributes);
+        Ok(Shape {
+            tool: reader.get("svgnote:tool")?.to_owned(),
+            position: reader.point("svgnote:position")?,
+            radius: reader.scalar("svgnote:radius")?,
+            angle: reader.scalar("svgnote:angle")?,
+            parameters: reader
+                .get("svgnote:parameters")?
+                .split_ascii_whitespace()
+                .map(|s| {
+                    let (name, value) = s.split_once('=')?;
+                    Some((name.to_owned(), scalar::parse(value)?))
+                })
+                .collect::<Option<_>>()
+                .ok_or_else(|| reader.invalid("svgnote:parameters"))?,
+            points: reader
+                .get("points")?
+                .split_ascii_whitespace()
+                .map(point)
+                .collect::<Option<_>>()
+                .ok_or_else(|| reader.invalid("points"))?,
+            stroke: reader.color("stroke")?,
+            fill: reader.fill("fill")?,
+            width: reader.scalar("stroke-width")?,
+            properties: Properties::from_attributes(&attributes)?
+                .with_stroke_opacity(&attributes)
+                .with_fill_opacity(&attributes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Cross, Element, Parameters, Shape, ShapeKind, ShapeRegistry};
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    struct Diamond;
+
+    impl ShapeKind for Diamond {
+        fn tool(&self) -> &str {
+            "diamond"
+        }
+
+        fn defaults(&self) -> Parameters {
+            Parameters::new()
+        }
+
+        fn outline(&self, _: &Parameters) -> Vec<(Scalar, Scalar)> {
+            vec![(0., -1.), (1., 0.), (0., 1.), (-1., 0.)]
+        }
+    }
+
+    #[test]
+    fn registry() {
+        let mut registry = ShapeRegistry::default();
+        registry.register(Diamond);
+        assert_eq!(
+            registry.tools().collect::<Vec<_>>(),
+            vec!["cross", "star", "diamond"]
+        );
+        assert!(registry.create("arrow", (0., 0.), 1.).is_none());
+
+        let diamond = registry.create("diamond", (10., 20.), 2.).unwrap();
+        assert_eq!(
+            diamond.points,
+            vec![(10., 18.), (12., 20.), (10., 22.), (8., 20.)]
+        );
+
+        let mut star = registry.create("star", (0., 0.), 10.).unwrap();
+        assert_eq!(star.points.len(), 10);
+        star.parameters.insert("points".to_owned(), 6.);
+        assert!(registry.update(&mut star));
+        assert_eq!(star.points.len(), 12);
+
+        let mut cross: Shape = registry.create("cross", (0., 0.), 1.).unwrap();
+        cross.parameters.clear();
+        registry.update(&mut cross);
+        // Missing parameters are the defaults
+        assert_eq!(cross.points, Cross.outline(&Cross.defaults()));
+
+        let document = Document {
+            elements: vec![Element::Shape(diamond), Element::Shape(star)],
+            ..Default::default()
+        };
+        let string = document.to_string();
+        assert!(string.contains(r#"svgnote:parameters="inner=0.4 points=6""#));
+        // Read without the registry
+        assert_eq!(Document::from_str(&string).unwrap(), document);
+    }
+}
@@ -0,0 +1,347 @@
+//! Presentation attributes shared through CSS classes, see
+//! [`SerializeOptions::style_classes`](crate::options::SerializeOptions::style_classes).
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use svg::node::element::{self, tag};
+use svg::node::Node;
+
+use crate::colors::{Color, ColorParseError};
+use crate::escape::unescape;
+use crate::xml::Event;
+
+use super::{Arc, Attributes, Element, Ellipse, Line, Ngon, Polyline, Shape, Text};
+
+/// The presentation attributes of an element, in the order they are written.
+pub(crate) type Style = Vec<(&'static str, String)>;
+
+/// The declarations of the class selectors in `<style>`s.
+pub(crate) type Classes = HashMap<String, Vec<(String, String)>>;
+
+impl Line {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.color)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+            ("fill-opacity", "0".to_owned()),
+            ("stroke-linecap", "round".to_owned()),
+            ("stroke-linejoin", "round".to_owned()),
+        ]
+    }
+}
+
+impl Ngon {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.stroke)),
+            ("fill", fill(self.fill)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+            ("stroke-linecap", "round".to_owned()),
+            ("stroke-linejoin", "round".to_owned()),
+        ]
+    }
+}
+
+impl Shape {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.stroke)),
+            ("fill", fill(self.fill)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+            ("stroke-linecap", "round".to_owned()),
+            ("stroke-linejoin", "round".to_owned()),
+        ]
+    }
+}
+
+impl Arc {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.stroke)),
+            ("fill", fill(self.fill)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+            ("stroke-linecap", "round".to_owned()),
+            ("stroke-linejoin", "round".to_owned()),
+        ]
+    }
+}
+
+impl Ellipse {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.stroke)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("fill", fill(self.fill)),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+        ]
+    }
+}
+
+impl Polyline {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("stroke", paint(self.stroke)),
+            ("fill", fill(self.fill)),
+            ("stroke-opacity", self.properties.stroke_opacity_value()),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            ("stroke-width", self.width.to_string()),
+            ("stroke-linecap", "round".to_owned()),
+            ("stroke-linejoin", "round".to_owned()),
+        ]
+    }
+}
+
+impl Text {
+    pub(crate) fn style(&self) -> Style {
+        vec![
+            ("fill", fill(self.color)),
+            ("fill-opacity", self.properties.fill_opacity_value()),
+            // Renderers without the font fall back to a similar one
+            ("font-family", format!("'{}', sans-serif", self.font.family)),
+            ("font-size", self.font.size.to_string()),
+        ]
+    }
+}
+
+/// `color` as `#RRGGBB`, or as `#RRGGBBAA` if it is not opaque, e.g. as read.
+pub(crate) fn paint(color: Color) -> String {
+    if color.a == 0xFF {
+        color.to_string_na()
+    } else {
+        color.to_string()
+    }
+}
+
+/// The fill read as `none`.
+const NONE: Color = Color::rgba(0, 0, 0, 0);
+
+/// Like [`paint`], but `none` for no fill, so a `fill="none"` stays one.
+pub(crate) fn fill(color: Color) -> String {
+    if color == NONE {
+        "none".to_owned()
+    } else {
+        paint(color)
+    }
+}
+
+/// A `fill`, either a color or `none` as fully transparent.
+pub(crate) fn parse_fill(value: &str) -> Result<Color, ColorParseError> {
+    match value {
+        "none" => Ok(NONE),
+        value => Color::from_str(value),
+    }
+}
+
+impl Element {
+    /// `None` for elements without presentation attributes, i.e. files,
+    /// layers and instances.
+    fn style(&self) -> Option<Style> {
+        match self {
+            Element::Line(e) => Some(e.style()),
+            Element::Ngon(e) => Some(e.style()),
+            Element::Ellipse(e) => Some(e.style()),
+            Element::Shape(e) => Some(e.style()),
+            Element::Arc(e) => Some(e.style()),
+            Element::Polyline(e) => Some(e.style()),
+            Element::Text(e) => Some(e.style()),
+            Element::FileRef(_)
+            | Element::Layer(_)
+            | Element::Instance(_)
+            | Element::Custom(_)
+            | Element::Dimension(_)
+            | Element::Ephemeral(_)
+            | Element::Frame(_) => None,
+        }
+    }
+}
+
+/// The classes for the styles used by more than one element.
+#[derive(Debug, Default)]
+pub(crate) struct StyleSheet {
+    /// In the order they are first used.
+    styles: Vec<Style>,
+    classes: HashMap<Style, String>,
+}
+
+impl StyleSheet {
+    pub(crate) fn new(elements: &[Element]) -> Self {
+        /// Counts the uses of each style, `order` gets them in the order
+        /// they are first used.
+        fn count(elements: &[Element], order: &mut Vec<Style>, counts: &mut HashMap<Style, usize>) {
+            for element in elements {
+                if let Element::Layer(layer) = element {
+                    count(&layer.elements, order, counts);
+                } else if let Some(style) = element.style() {
+                    match counts.get_mut(&style) {
+                        Some(n) => *n += 1,
+                        None => {
+                            counts.insert(style.clone(), 1);
+                            order.push(style);
+                        }
+                    }
+                }
+            }
+        }
+        let (mut order, mut counts) = (vec![], HashMap::new());
+        count(elements, &mut order, &mut counts);
+        let styles: Vec<Style> = order
+            .into_iter()
+            .filter(|style| counts[style] > 1)
+            .collect();
+        // Prefixed, so they do not clash with the classes of other editors
+        let classes = styles
+            .iter()
+            .enumerate()
+            .map(|(i, style)| (style.clone(), format!("svgnote-s{}", i)))
+            .collect();
+        StyleSheet { styles, classes }
+    }
+
+    /// A rule per class, `None` if there are none.
+    pub(crate) fn to_style(&self) -> Option<element::Style> {
+        if self.styles.is_empty() {
+            return None;
+        }
+        let rules: Vec<String> = self
+            .styles
+            .iter()
+            .map(|style| {
+                let declarations: Vec<String> = style
+                    .iter()
+                    .map(|(name, value)| format!("{}: {};", name, value))
+                    .collect();
+                format!(".{} {{ {} }}", self.classes[style], declarations.join(" "))
+            })
+            .collect();
+        Some(element::Style::new(rules.join("\n")))
+    }
+}
+
+/// Assigns `style` to `node`, as a class if `styles` has one for it.
+pub(crate) fn assign<T: Node>(node: &mut T, style: Style, styles: Option<&StyleSheet>) {
+    match styles.and_then(|styles| styles.classes.get(&style)) {
+        Some(class) => node.assign("class", class.clone()),
+        None => {
+            for (name, value) in style {
+                node.assign(name, value);
+            }
+        }
+    }
+}
+
+/// Reads the class rules of all `<style>`s, other rules, e.g.
+/// `@font-face`, are skipped.
+#[derive(Default)]
+pub(crate) struct ClassReader {
+    classes: Classes,
+    in_style: bool,
+}
+
+impl ClassReader {
+    pub(crate) fn read(&mut self, event: &Event) {
+        match event {
+            Event::Tag(tag::Style, tag::Type::Start, _) => self.in_style = true,
+            Event::Tag(tag::Style, tag::Type::End, _) => self.in_style = false,
+            Event::Text(css) if self.in_style => {
+                for rule in unescape(css.trim()).split('}') {
+                    let (selectors, declarations) = match rule.split_once('{') {
+                        Some(rule) => rule,
+                        None => continue,
+                    };
+                    let declarations: Vec<(String, String)> = declarations
+                        .split(';')
+                        .filter_map(|declaration| declaration.split_once(':'))
+                        .map(|(name, value)| (name.trim().to_owned(), value.trim().to_owned()))
+                        .collect();
+                    for selector in selectors.split(',') {
+                        if let Some(class) = selector.trim().strip_prefix('.') {
+                            self.classes
+                                .entry(class.to_owned())
+                                .or_default()
+                                .extend(declarations.iter().cloned());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn finish(self) -> Classes {
+        self.classes
+    }
+}
+
+/// Adds the declarations of the classes of an element to its `attributes`,
+/// replacing them like CSS does.
+pub(crate) fn apply(classes: &Classes, attributes: &mut Attributes<'_>) {
+    let class = match attributes.get("class") {
+        Some(class) => class.to_string(),
+        None => return,
+    };
+    for class in class.split_whitespace() {
+        for (name, value) in classes.get(class).into_iter().flatten() {
+            attributes.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Line, LinePoint, Properties};
+    use crate::options::SerializeOptions;
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    #[test]
+    fn style_classes() {
+        let line = |x| {
+            Element::Line(Line {
+                color: Color::rgb(0, 0, 0xFF),
+                width: 2.,
+                points: vec![LinePoint(x, 0., 1.), LinePoint(x, 10., 1.)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })
+        };
+        let mut layer = Layer::new("ink");
+        layer.elements = (0..10).map(|x| line(x as Scalar)).collect();
+        let document = Document {
+            elements: vec![
+                line(20.),
+                Element::Layer(layer),
+                Element::Ellipse(Ellipse {
+                    position: (0., 0.),
+                    stroke: Color::rgb(0, 0, 0),
+                    fill: Color::rgb(0xFF, 0, 0),
+                    width: 1.,
+                    radius: 5.,
+                    properties: Properties::default(),
+                }),
+            ],
+            ..Default::default()
+        };
+        let options = SerializeOptions {
+            style_classes: true,
+            ..Default::default()
+        };
+        let string = document.to_string_with(&options);
+        assert_eq!(string.matches("class=\"svgnote-s0\"").count(), 11);
+        // The ellipse's style is not shared
+        assert!(!string.contains("class=\"svgnote-s1\""));
+        assert!(string.len() < document.to_string().len());
+        assert_eq!(Document::from_str(&string).unwrap(), document);
+    }
+}
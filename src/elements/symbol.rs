@@ -0,0 +1,190 @@
+//! Elements stored once and placed many times, e.g. logos or stamps, as
+//! `<symbol>`s in `<defs>` and `<use>`s referencing them.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use svg::node::element::{self, tag};
+use DocumentError::InvalidAttribute;
+use DocumentError::MissingAttribute;
+
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::DocumentError;
+
+use super::{Attributes, Element, FromAttributes, Output, Properties};
+
+/// The symbols of a document by their id, see [`Instance`].
+pub type Symbols = BTreeMap<String, Vec<Element>>;
+
+/// A symbol of the document placed at `position`, stored as
+/// `<use href="#{symbol}">`.
+///
+/// Instances of symbols that do not exist are kept, but not rendered.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Instance {
+    /// The id of the symbol in [`Document::symbols`](crate::Document::symbols).
+    pub symbol: String,
+    /// Where the origin of the symbol is placed.
+    pub position: (Scalar, Scalar),
+    pub properties: Properties,
+}
+
+impl Instance {
+    pub fn new(symbol: impl Into<String>, position: (Scalar, Scalar)) -> Self {
+        Instance {
+            symbol: symbol.into(),
+            position,
+            properties: Properties::default(),
+        }
+    }
+
+    /// From the coordinates of the symbol to the ones the instance is in,
+    /// without its transform.
+    pub fn placement(&self) -> Transform {
+        Transform::translate(self.position.0, self.position.1)
+    }
+}
+
+impl From<&Instance> for element::Use {
+    fn from(instance: &Instance) -> Self {
+        let mut element = element::Use::new()
+            .set("href", format!("#{}", instance.symbol))
+            .set("x", instance.position.0)
+            .set("y", instance.position.1);
+        instance.properties.assign_to(&mut element);
+        element
+    }
+}
+
+impl FromAttributes for Instance {
+    /// Other editors may only write the older `xlink:href`. References to
+    /// other documents are not supported and skipped like unknown tags.
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let scalar = |name: &str| -> Result<Scalar, DocumentError> {
+            match attributes.get(name) {
+                Some(value) => Scalar::from_str(value)
+                    .map_err(|_| InvalidAttribute(name.to_owned(), value.to_string())),
+                None => Ok(0.0),
+            }
+        };
+        let href = attributes
+            .get("href")
+            .or_else(|| attributes.get("xlink:href"))
+            .ok_or_else(|| MissingAttribute("href".to_owned()))?;
+        Ok(Instance {
+            symbol: href
+                .strip_prefix('#')
+                .ok_or(DocumentError::UnknownEvent)?
+                .to_owned(),
+            position: (scalar("x")?, scalar("y")?),
+            properties: Properties::from_attributes(&attributes)?,
+        })
+    }
+}
+
+/// A `<symbol>` per symbol, for the `<defs>`.
+pub(crate) fn to_symbols(symbols: &Symbols, output: &Output) -> Vec<element::Symbol> {
+    symbols
+        .iter()
+        .map(|(id, elements)| {
+            elements.iter().fold(
+                element::Symbol::new().set("id", id.clone()),
+                |symbol, element| element.add_to_with(symbol, output),
+            )
+        })
+        .collect()
+}
+
+/// A `<symbol>`, or an element with an id directly in `<defs>`, whose
+/// elements are parsed with the document.
+pub(crate) struct Pending {
+    pub(crate) id: String,
+    pub(crate) elements: Vec<Element>,
+    /// Left out if it has no elements we support, e.g. a gradient.
+    pub(crate) definition: bool,
+    /// The depth of its tag, it ends when the document is back at it.
+    pub(crate) depth: usize,
+    /// The depth inside `<defs>`, `<clipPath>` and `<symbol>` of its
+    /// elements, deeper ones are not rendered.
+    pub(crate) definitions: usize,
+    /// The number of groups open around it.
+    pub(crate) groups: usize,
+}
+
+impl Pending {
+    pub(crate) fn finish(self, symbols: &mut Symbols) {
+        if !(self.definition && self.elements.is_empty()) {
+            symbols.insert(self.id, self.elements);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Instance, Properties};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn symbols() {
+        let dot = Element::Ellipse(Ellipse {
+            position: (0., 0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0xFF, 0, 0),
+            width: 1.,
+            radius: 5.,
+            properties: Properties::default(),
+        });
+        let mut moved = Instance::new("dot", (100., 0.));
+        moved.properties.transform = Transform::scale(2., 2.);
+        let mut doc = Document {
+            elements: vec![
+                Element::Instance(Instance::new("dot", (10., 20.))),
+                Element::Instance(moved),
+                Element::Instance(Instance::new("missing", (0., 0.))),
+            ],
+            ..Default::default()
+        };
+        doc.symbols.insert("dot".to_owned(), vec![dot.clone()]);
+
+        let items = doc.render_items();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].transform, Transform::translate(10., 20.));
+        assert_eq!(
+            items[1].transform,
+            Transform::scale(2., 2.) * Transform::translate(100., 0.)
+        );
+
+        let string = doc.to_string();
+        assert!(string.contains(r##"href="#dot""##));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+
+        // Imported, with a clip path
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink">
+            <defs>
+                <clipPath id="c"><rect x="0" y="0" width="1" height="1"/></clipPath>
+                <g id="stamp" transform="translate(5,0)">
+                    <ellipse cx="0" cy="0" rx="5" ry="5" clip-path="url(#c)" stroke="#000000" fill="#FF0000" stroke-width="1"/>
+                </g>
+            </defs>
+            <symbol id="logo"><polyline points="0,0 1,1" stroke="#000000" fill="#000000" stroke-width="1"/></symbol>
+            <use xlink:href="#stamp" x="1" y="2"/>
+            <use href="#logo"/>
+        </svg>"##;
+        let doc = Document::from_str(svg).unwrap();
+        assert_eq!(doc.symbols.len(), 2);
+        assert_eq!(doc.elements.len(), 2);
+        match &doc.symbols["stamp"][..] {
+            [Element::Ellipse(e)] => {
+                assert_eq!(e.properties.transform, Transform::translate(5., 0.));
+                assert!(e.properties.clip.is_some());
+            }
+            elements => panic!("{:?}", elements),
+        }
+        assert_eq!(doc.render_items().len(), 2);
+    }
+}
@@ -0,0 +1,333 @@
+use std::str::FromStr;
+
+use svg::node::element;
+use svg::node::{self, Node};
+
+use crate::colors::Color;
+use crate::escape::{escape, unescape};
+use crate::fonts::FontLoader;
+use crate::geometry::BoundingBox;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+use DocumentError::InvalidColor;
+use DocumentError::MissingAttribute;
+
+use super::style::{self, StyleSheet};
+use super::Attributes;
+use super::FromAttributes;
+use super::Properties;
+
+/// The distance between the baselines of two lines, relative to the font
+/// size.
+pub const LINE_HEIGHT: Scalar = 1.2;
+
+/// Typed text, stored as `<text svgnote:tool="text">` with a `<tspan>` per
+/// line.
+///
+/// The library has no font stack, so measuring and laying out the text needs
+/// a [`TextShaper`] provided by the host.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Text {
+    /// The start of the baseline of the first line.
+    pub position: (Scalar, Scalar),
+    /// The lines are separated by `\n`.
+    pub content: String,
+    pub color: Color,
+    pub font: Font,
+    pub properties: Properties,
+}
+
+/// The font chosen for a [`Text`], stored as `svgnote:font-family` and
+/// `svgnote:font-size`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Font {
+    pub family: String,
+    pub size: Scalar,
+}
+
+impl Default for Font {
+    /// The SVG default, i.e. `16` units of the renderer's sans-serif font.
+    fn default() -> Self {
+        Font {
+            family: "sans-serif".to_owned(),
+            size: 16.,
+        }
+    }
+}
+
+/// The extent of a string, relative to the start of its baseline.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TextMetrics {
+    pub width: Scalar,
+    /// The height above the baseline.
+    pub ascent: Scalar,
+    /// The depth below the baseline, positive.
+    pub descent: Scalar,
+}
+
+/// Measures text, implemented by the host with whatever font stack it uses.
+pub trait TextShaper {
+    /// The metrics of `text` on a single line, set in `font`.
+    fn measure(&self, text: &str, font: &Font) -> TextMetrics;
+}
+
+/// Estimates the metrics from the font size alone, for when no
+/// [`TextShaper`] is available, e.g. for [`RenderItem::bounds`].
+///
+/// [`RenderItem::bounds`]: crate::render::RenderItem::bounds
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApproximateShaper;
+
+impl TextShaper for ApproximateShaper {
+    fn measure(&self, text: &str, font: &Font) -> TextMetrics {
+        TextMetrics {
+            width: text.chars().count() as Scalar * font.size / 2.,
+            ascent: font.size * 0.8,
+            descent: font.size * 0.2,
+        }
+    }
+}
+
+/// A line of a [`Text`] placed by [`Text::layout`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct TextRun {
+    pub text: String,
+    /// The start of the baseline.
+    pub position: (Scalar, Scalar),
+    pub metrics: TextMetrics,
+}
+
+impl Text {
+    pub fn line_height(&self) -> Scalar {
+        self.font.size * LINE_HEIGHT
+    }
+
+    /// Places the lines of the text, wrapping them at spaces so they are at
+    /// most `max_width` wide where possible.
+    pub fn layout(&self, shaper: &dyn TextShaper, max_width: Option<Scalar>) -> Vec<TextRun> {
+        let mut lines = vec![];
+        for line in self.content.split('\n') {
+            let mut current = String::new();
+            for word in line.split(' ') {
+                let candidate = if current.is_empty() {
+                    word.to_owned()
+                } else {
+                    format!("{} {}", current, word)
+                };
+                match max_width {
+                    Some(max_width)
+                        if !current.is_empty()
+                            && shaper.measure(&candidate, &self.font).width > max_width =>
+                    {
+                        lines.push(current);
+                        current = word.to_owned();
+                    }
+                    _ => current = candidate,
+                }
+            }
+            lines.push(current);
+        }
+        let (x, y) = self.position;
+        lines
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| TextRun {
+                metrics: shaper.measure(&text, &self.font),
+                position: (x, y + i as Scalar * self.line_height()),
+                text,
+            })
+            .collect()
+    }
+
+    /// The area covered by the text in its own coordinates.
+    ///
+    /// `None` if the text is empty.
+    pub fn bounds(&self, shaper: &dyn TextShaper) -> Option<BoundingBox> {
+        BoundingBox::from_points(
+            self.layout(shaper, None)
+                .into_iter()
+                .filter(|run| !run.text.is_empty())
+                .flat_map(|run| {
+                    let (x, y) = run.position;
+                    vec![
+                        (x, y - run.metrics.ascent),
+                        (x + run.metrics.width, y + run.metrics.descent),
+                    ]
+                }),
+        )
+    }
+
+    /// The text as a `<path>` of the glyph outlines from `loader`, with the
+    /// content in `svgnote:content` so it stays editable.
+    ///
+    /// `None` if `loader` does not have the font.
+    pub(crate) fn to_outline(&self, loader: &dyn FontLoader) -> Option<element::Path> {
+        let (x, y) = self.position;
+        let mut outlines = vec![];
+        for (i, line) in self.content.split('\n').enumerate() {
+            let position = (x, y + i as Scalar * self.line_height());
+            outlines.push(loader.outline(line, &self.font, position)?);
+        }
+        let mut path = element::Path::new()
+            .set("d", outlines.join(" "))
+            .set("fill", style::fill(self.color))
+            .set("fill-opacity", self.properties.fill_opacity_value())
+            .set("svgnote:tool", "text")
+            .set("svgnote:position", format!("{},{}", x, y))
+            .set("svgnote:font-family", escape(&self.font.family))
+            .set("svgnote:font-size", self.font.size)
+            .set("svgnote:content", escape(&self.content));
+        self.properties.assign_to(&mut path);
+        Some(path)
+    }
+
+    /// A text converted to a path by [`Text::to_outline`].
+    pub(crate) fn from_outline(mut attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let position = attributes
+            .remove("svgnote:position")
+            .ok_or_else(|| MissingAttribute("svgnote:position".to_owned()))?;
+        let (x, y) = position
+            .split_once(',')
+            .ok_or_else(|| InvalidAttribute("svgnote:position".to_owned(), position.to_string()))?;
+        attributes.insert("x", x.to_owned());
+        attributes.insert("y", y.to_owned());
+        let content = attributes
+            .get("svgnote:content")
+            .map(|content| unescape(content))
+            .unwrap_or_default();
+        Ok(Text {
+            content,
+            ..Text::from_attributes(attributes)?
+        })
+    }
+
+    /// Appends a line from a `<tspan>`.
+    pub(crate) fn push_line(&mut self, line: String) {
+        if !self.content.is_empty() {
+            self.content.push('\n');
+        }
+        self.content.push_str(&line);
+    }
+}
+
+impl From<&Text> for element::Text {
+    fn from(text: &Text) -> Self {
+        text.to_text(None)
+    }
+}
+
+impl Text {
+    /// Like `element::Text::from`, but with the style as a class if `styles`
+    /// has one for it.
+    pub(crate) fn to_text(&self, styles: Option<&StyleSheet>) -> element::Text {
+        let (x, y) = self.position;
+        let mut element = element::Text::new()
+            .set("x", x)
+            .set("y", y)
+            .set("svgnote:tool", "text")
+            .set("svgnote:font-family", escape(&self.font.family))
+            .set("svgnote:font-size", self.font.size);
+        style::assign(&mut element, self.style(), styles);
+        for (i, line) in self.content.split('\n').enumerate() {
+            let mut tspan = element::Element::new("tspan");
+            tspan.assign("x", x);
+            tspan.assign("y", y + i as Scalar * self.line_height());
+            tspan.append(node::Text::new(escape(line)));
+            element.append(tspan);
+        }
+        self.properties.assign_to(&mut element);
+        element
+    }
+}
+
+impl FromAttributes for Text {
+    /// The content is added by the parser from the `<tspan>`s.
+    fn from_attributes(attributes: Attributes<'_>) -> Result<Self, DocumentError> {
+        let scalar = |name: &str| -> Result<Option<Scalar>, DocumentError> {
+            attributes
+                .get(name)
+                .map(|value| {
+                    Scalar::from_str(value.trim_end_matches("px"))
+                        .map_err(|_| InvalidAttribute(name.to_owned(), value.to_string()))
+                })
+                .transpose()
+        };
+        let position = match (scalar("x")?, scalar("y")?) {
+            (Some(x), Some(y)) => (x, y),
+            (None, _) => return Err(MissingAttribute("x".to_owned())),
+            (_, None) => return Err(MissingAttribute("y".to_owned())),
+        };
+        let family = match attributes.get("svgnote:font-family") {
+            Some(family) => Some(unescape(family)),
+            // The first font of the list, as written by other editors
+            None => attributes.get("font-family").and_then(|families| {
+                families
+                    .split(',')
+                    .next()
+                    .map(|family| family.trim().trim_matches(|c| c == '\'' || c == '"'))
+                    .map(str::to_owned)
+            }),
+        };
+        let color: &str = attributes
+            .get("fill")
+            .ok_or_else(|| MissingAttribute("fill".to_owned()))?;
+        let color = style::parse_fill(color).map_err(|e| InvalidColor("fill".to_owned(), e))?;
+        Ok(Text {
+            position,
+            content: String::new(),
+            color,
+            font: Font {
+                family: family.unwrap_or_else(|| Font::default().family),
+                size: match scalar("svgnote:font-size")? {
+                    Some(size) => size,
+                    None => scalar("font-size")?.unwrap_or_else(|| Font::default().size),
+                },
+            },
+            properties: Properties::from_attributes(&attributes)?.with_fill_opacity(&attributes),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::text::{Font, Text, TextMetrics, TextShaper};
+    use crate::elements::Properties;
+    use crate::scalar::Scalar;
+
+    /// Every character is as wide as the font size.
+    struct Monospace;
+
+    impl TextShaper for Monospace {
+        fn measure(&self, text: &str, font: &Font) -> TextMetrics {
+            TextMetrics {
+                width: text.len() as Scalar * font.size,
+                ascent: font.size,
+                descent: 0.,
+            }
+        }
+    }
+
+    #[test]
+    fn layout() {
+        let text = Text {
+            position: (10., 20.),
+            content: "lorem ipsum dolor\nsit".to_owned(),
+            color: Color::rgb(0, 0, 0),
+            font: Font {
+                family: "Mono".to_owned(),
+                size: 10.,
+            },
+            properties: Properties::default(),
+        };
+        let runs = text.layout(&Monospace, Some(120.));
+        let lines: Vec<_> = runs.iter().map(|run| run.text.as_str()).collect();
+        assert_eq!(lines, vec!["lorem ipsum", "dolor", "sit"]);
+        assert_eq!(runs[2].position, (10., 44.));
+
+        let bounds = text.bounds(&Monospace).unwrap();
+        assert_eq!(bounds.min, (10., 10.));
+        assert_eq!(bounds.max, (180., 32.));
+    }
+}
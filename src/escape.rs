@@ -0,0 +1,91 @@
+//! Escaping the strings of users, e.g. the names of layers or the content
+//! of texts. `svg` writes attribute values and texts as they are, and reads
+//! neither unescaped.
+
+/// `value` with the characters that would end or break an attribute value
+/// escaped, and line breaks, which XML reads as spaces in attributes.
+pub(crate) fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Reverses [`escape`], and replaces other character references, e.g.
+/// `&#x20AC;` of other editors. Unknown references are kept as they are.
+pub(crate) fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                reference => reference
+                    .strip_prefix('#')
+                    .and_then(|number| match number.strip_prefix('x') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => number.parse().ok(),
+                    })
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match reference {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Element, Layer};
+    use crate::escape::{escape, unescape};
+    use crate::metadata::Target;
+    use crate::Document;
+
+    #[test]
+    fn escape_unescape() {
+        let value = "<b>\"Tom & Jerry's\"</b>\n&amp;";
+        assert_eq!(
+            escape(value),
+            "&lt;b&gt;&quot;Tom &amp; Jerry&apos;s&quot;&lt;/b&gt;&#10;&amp;amp;"
+        );
+        assert_eq!(unescape(&escape(value)), value);
+        assert_eq!(unescape("&#x20AC;&#65; &nbsp; a&b;c"), "€A &nbsp; a&b;c");
+
+        let mut layer = Layer::new(value);
+        layer.properties.author = Some(value.to_owned());
+        layer.properties.link = Some(Target::bookmark(value));
+        let doc = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+    }
+}
@@ -0,0 +1,229 @@
+//! Exporting documents as figures for Typst and LaTeX, e.g. a sketch for a
+//! paper, see [`Document::export_figure`].
+
+use std::fmt::Write;
+
+use crate::colors::Color;
+use crate::elements::Element;
+use crate::render::RenderItem;
+use crate::scalar::Scalar;
+use crate::{Document, UNITS_PER_MM};
+
+/// The segments circles and arcs are drawn with in TikZ.
+const SEGMENTS: usize = 64;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FigureFormat {
+    /// An `#image()` of the SVG.
+    Typst,
+    /// A TikZ picture if the document only has simple shapes, otherwise an
+    /// `\includesvg` of the SVG, which needs the `svg` package.
+    Latex,
+}
+
+/// A figure exported by [`Document::export_figure`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Figure {
+    /// The name and content of the file to save next to the source, `None`
+    /// if the snippet draws the figure itself.
+    pub file: Option<(String, String)>,
+    /// What to put into the source to include the figure.
+    pub snippet: String,
+}
+
+/// A length in document units as `mm`, rounded to a hundredth.
+fn mm(value: Scalar) -> String {
+    let value = format!("{:.2}", value / UNITS_PER_MM);
+    value.trim_end_matches('0').trim_end_matches('.').to_owned()
+}
+
+fn xcolor(color: Color) -> String {
+    format!(
+        "{{rgb,255:red,{};green,{};blue,{}}}",
+        color.r, color.g, color.b
+    )
+}
+
+/// A TikZ path command for `item`, `None` if it is not a simple shape or
+/// is clipped.
+fn tikz_path(item: &RenderItem) -> Option<String> {
+    if !item.clips.is_empty() {
+        return None;
+    }
+    let (points, stroke, fill, width, closed) = match item.element {
+        Element::Line(line) => {
+            let points = line.points.iter().map(|p| (p.0, p.1)).collect();
+            (points, line.color, None, line.width, false)
+        }
+        Element::Polyline(polyline) => {
+            let points = polyline.points.iter().map(|p| (p.0, p.1)).collect();
+            let fill = Some(polyline.fill);
+            (
+                points,
+                polyline.stroke,
+                fill,
+                polyline.width,
+                polyline.closed,
+            )
+        }
+        Element::Ngon(ngon) => (
+            ngon.points(),
+            ngon.stroke,
+            Some(ngon.fill),
+            ngon.width,
+            true,
+        ),
+        Element::Ellipse(ellipse) => {
+            let points = ellipse.points(SEGMENTS);
+            (
+                points,
+                ellipse.stroke,
+                Some(ellipse.fill),
+                ellipse.width,
+                true,
+            )
+        }
+        Element::Arc(arc) => (
+            arc.points(SEGMENTS),
+            arc.stroke,
+            Some(arc.fill),
+            arc.width,
+            arc.pie,
+        ),
+        _ => return None,
+    };
+    let properties = item.element.properties();
+    let (stroke, fill) = (properties.stroke(stroke), fill.map(|f| properties.fill(f)));
+    let mut options = vec![
+        format!("draw={}", xcolor(stroke)),
+        format!("line width={}mm", mm(width * item.transform.scale_factor())),
+        "line cap=round".to_owned(),
+        "line join=round".to_owned(),
+    ];
+    let opacity = stroke.faded(item.opacity).opacity();
+    if opacity < 1. {
+        options.push(format!("draw opacity={:.2}", opacity));
+    }
+    let fill = fill
+        .map(|fill| fill.faded(item.opacity))
+        .filter(|f| f.a > 0);
+    if let Some(fill) = fill {
+        options.push(format!("fill={}", xcolor(fill)));
+        if fill.opacity() < 1. {
+            options.push(format!("fill opacity={:.2}", fill.opacity()));
+        }
+    }
+    let mut path = format!("\\path[{}] ", options.join(", "));
+    for (i, &point) in points.iter().enumerate() {
+        let (x, y) = item.transform.apply(point);
+        if i > 0 {
+            path.push_str(" -- ");
+        }
+        write!(path, "({},{})", mm(x), mm(y)).expect("writing to a String does not fail");
+    }
+    if closed {
+        path.push_str(" -- cycle");
+    }
+    path.push(';');
+    Some(path)
+}
+
+impl Document {
+    /// The visible elements as a TikZ picture in `mm`, `None` if any of
+    /// them is not a pen stroke, polyline, polygon, circle or arc, or is
+    /// clipped.
+    pub fn to_tikz(&self) -> Option<String> {
+        let paths = self
+            .render_items()
+            .iter()
+            .map(tikz_path)
+            .collect::<Option<Vec<_>>>()?;
+        // Documents have y pointing down
+        Some(format!(
+            "\\begin{{tikzpicture}}[x=1mm, y=-1mm]\n{}\n\\end{{tikzpicture}}",
+            paths.join("\n")
+        ))
+    }
+
+    /// The document as a figure for `format`, cropped to its content, see
+    /// [`Document::export_cropped`]. `name` is the name of the file without
+    /// the extension.
+    pub fn export_figure(&self, name: &str, format: FigureFormat) -> Figure {
+        if let (FigureFormat::Latex, Some(tikz)) = (format, self.to_tikz()) {
+            return Figure {
+                file: None,
+                snippet: tikz,
+            };
+        }
+        // The whole page of 100mm if nothing is visible
+        let width = self
+            .bounds()
+            .map_or_else(|| "100".to_owned(), |bounds| mm(bounds.width()));
+        let file = format!("{}.svg", name);
+        let snippet = match format {
+            FigureFormat::Typst => format!("#image(\"{}\", width: {}mm)", file, width),
+            FigureFormat::Latex => format!("\\includesvg[width={}mm]{{{}}}", width, name),
+        };
+        Figure {
+            file: Some((file, self.export_cropped(0.))),
+            snippet,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, FileRef, Line, LinePoint, Ngon, Properties};
+    use crate::figure::FigureFormat;
+    use crate::Document;
+
+    #[test]
+    fn figure() {
+        let mut doc = Document {
+            elements: vec![
+                Element::Line(Line {
+                    color: Color::rgb(0xFF, 0, 0),
+                    width: 10.,
+                    points: vec![LinePoint(0., 0., 1.), LinePoint(200., 100., 1.)],
+                    timestamps: vec![],
+                    properties: Properties::default(),
+                }),
+                Element::Ngon(Ngon {
+                    position: (100., 100.),
+                    stroke: Color::rgb(0, 0, 0),
+                    fill: Color::rgba(0, 0, 0, 0),
+                    width: 2.,
+                    angle: 0.,
+                    n: 4,
+                    radius: 50.,
+                    properties: Properties::default(),
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let figure = doc.export_figure("sketch", FigureFormat::Latex);
+        assert_eq!(figure.file, None);
+        assert!(figure.snippet.contains(
+            r"\path[draw={rgb,255:red,255;green,0;blue,0}, line width=0.5mm, line cap=round, line join=round] (0,0) -- (10,5);"
+        ));
+        assert_eq!(figure.snippet.matches("-- cycle;").count(), 1);
+
+        doc.elements.push(Element::FileRef(FileRef {
+            path: "photo.png".to_owned(),
+            position: (-100., 0.),
+            size: (400., 100.),
+            properties: Properties::default(),
+        }));
+        assert_eq!(doc.to_tikz(), None);
+        let figure = doc.export_figure("sketch", FigureFormat::Latex);
+        assert_eq!(figure.snippet, r"\includesvg[width=20mm]{sketch}");
+        let typst = doc.export_figure("sketch", FigureFormat::Typst);
+        assert_eq!(typst.snippet, "#image(\"sketch.svg\", width: 20mm)");
+        assert_eq!(
+            typst.file.map(|(name, _)| name),
+            Some("sketch.svg".to_owned())
+        );
+    }
+}
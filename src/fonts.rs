@@ -0,0 +1,112 @@
+//! Embedding the fonts of [`Text`]s on export, see
+//! [`SerializeOptions::fonts`], or converting them to paths, see
+//! [`SerializeOptions::outline_text`].
+//!
+//! The library cannot read fonts, so the host provides the files. They can
+//! be subset to the characters in [`Document::fonts_used`] before.
+//!
+//! [`Document::fonts_used`]: crate::Document::fonts_used
+//! [`SerializeOptions::fonts`]: crate::options::SerializeOptions::fonts
+//! [`SerializeOptions::outline_text`]: crate::options::SerializeOptions::outline_text
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use svg::node::element;
+
+use crate::base64;
+use crate::elements::{Element, Font, Text};
+use crate::scalar::Scalar;
+
+/// A font file to embed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FontFile {
+    pub data: Vec<u8>,
+    pub format: FontFormat,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum FontFormat {
+    TrueType,
+    OpenType,
+    Woff,
+    Woff2,
+}
+
+impl FontFormat {
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            FontFormat::TrueType => "font/ttf",
+            FontFormat::OpenType => "font/otf",
+            FontFormat::Woff => "font/woff",
+            FontFormat::Woff2 => "font/woff2",
+        }
+    }
+
+    /// The name used in the `format()` of CSS `@font-face` rules.
+    pub fn css_name(&self) -> &'static str {
+        match self {
+            FontFormat::TrueType => "truetype",
+            FontFormat::OpenType => "opentype",
+            FontFormat::Woff => "woff",
+            FontFormat::Woff2 => "woff2",
+        }
+    }
+}
+
+/// Provides glyph outlines, implemented by the host with whatever font stack
+/// it uses.
+pub trait FontLoader {
+    /// The outlines of `text` set in `font` on a single line, as path data
+    /// with the start of the baseline at `position`.
+    ///
+    /// `None` if the font is not available, the text is kept as text then.
+    fn outline(&self, text: &str, font: &Font, position: (Scalar, Scalar)) -> Option<String>;
+}
+
+/// The font families of all texts in `elements`, including hidden ones, with
+/// the characters set in them.
+pub fn fonts_used(elements: &[Element]) -> BTreeMap<String, BTreeSet<char>> {
+    let mut fonts = BTreeMap::new();
+    collect(elements, &mut fonts);
+    fonts
+}
+
+fn collect(elements: &[Element], fonts: &mut BTreeMap<String, BTreeSet<char>>) {
+    for element in elements {
+        match element {
+            Element::Text(Text { content, font, .. }) => fonts
+                .entry(font.family.clone())
+                .or_insert_with(BTreeSet::new)
+                .extend(content.chars().filter(|c| *c != '\n')),
+            Element::Layer(layer) => collect(&layer.elements, fonts),
+            _ => {}
+        }
+    }
+}
+
+/// A `<style>` with a `@font-face` rule for each of the `fonts` used in
+/// `elements`, `None` if there are none.
+pub(crate) fn style(
+    elements: &[Element],
+    fonts: &BTreeMap<String, FontFile>,
+) -> Option<element::Style> {
+    let rules: Vec<String> = fonts_used(elements)
+        .keys()
+        .filter_map(|family| fonts.get(family).map(|file| (family, file)))
+        .map(|(family, file)| {
+            format!(
+                "@font-face {{ font-family: '{}'; src: url(data:{};base64,{}) format('{}'); }}",
+                family,
+                file.format.mime_type(),
+                base64::encode(&file.data),
+                file.format.css_name()
+            )
+        })
+        .collect();
+    if rules.is_empty() {
+        None
+    } else {
+        Some(element::Style::new(rules.join("\n")))
+    }
+}
+
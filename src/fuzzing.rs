@@ -0,0 +1,495 @@
+//! [`Arbitrary`] documents for fuzzing, and [`assert_round_trip`] to check
+//! they survive serializing and parsing.
+//!
+//! The values are limited to what the format stores exactly, e.g. scalars
+//! are multiples of `1/16`, text has no surrounding whitespace and ids are
+//! alphanumeric, so any difference after a round trip is a bug.
+//! [`Ephemeral`](crate::elements::Ephemeral) strokes are left out, as they
+//! are never stored.
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::colors::Color;
+use crate::elements::{
+    Arc, Attachment, BlendMode, Clip, Custom, Dimension, Element, Ellipse, FileRef, Font, Frame,
+    Instance, Layer, LengthUnit, Line, LinePoint, Ngon, Parameters, Polyline, PolylinePoint,
+    Properties, Shape, Stored, Symbols, Text,
+};
+use crate::ids::ElementId;
+use crate::metadata::Target;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::Document;
+
+/// How deep layers are nested at most.
+const MAX_DEPTH: usize = 3;
+
+/// Panics if `document` is not the same after serializing and parsing it.
+pub fn assert_round_trip(document: &Document) {
+    let string = document.to_string();
+    match Document::from_str(&string) {
+        Ok(parsed) => assert_eq!(&parsed, document, "changed by a round trip:\n{}", string),
+        Err(error) => panic!("{} in\n{}", error, string),
+    }
+}
+
+/// [`assert_round_trip`] for the document generated from `data`, e.g. in a
+/// `cargo fuzz` target.
+pub fn round_trip(data: &[u8]) {
+    if let Ok(document) = Document::arbitrary(&mut Unstructured::new(data)) {
+        assert_round_trip(&document);
+    }
+}
+
+fn scalar(u: &mut Unstructured) -> Result<Scalar> {
+    Ok(i16::arbitrary(u)? as Scalar / 16.)
+}
+
+fn positive(u: &mut Unstructured) -> Result<Scalar> {
+    Ok(u16::arbitrary(u)? as Scalar / 16.)
+}
+
+fn point(u: &mut Unstructured) -> Result<(Scalar, Scalar)> {
+    Ok((scalar(u)?, scalar(u)?))
+}
+
+/// Above `0`, e.g. for the sizes of frames.
+fn size(u: &mut Unstructured) -> Result<Scalar> {
+    Ok((u16::arbitrary(u)? as Scalar + 1.) / 16.)
+}
+
+fn opacity(u: &mut Unstructured) -> Result<f32> {
+    Ok(u8::arbitrary(u)? as f32 / 255.)
+}
+
+fn word(u: &mut Unstructured) -> Result<String> {
+    const CHARS: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    let len = u.int_in_range(1..=12)?;
+    (0..len).map(|_| Ok(*u.choose(CHARS)? as char)).collect()
+}
+
+/// Up to four words, with characters that are escaped in XML and ones
+/// beyond ASCII, e.g. for names and titles.
+fn text(u: &mut Unstructured) -> Result<String> {
+    const SPECIAL: &[char] = &['&', '<', '>', '"', '\'', '#', '?', '%', ';', 'é', '€', '中'];
+    let mut words = vec![];
+    for _ in 0..u.int_in_range(1..=4)? {
+        let mut chars: Vec<char> = word(u)?.chars().collect();
+        for _ in 0..u.int_in_range(0..=2)? {
+            let at = u.int_in_range(0..=chars.len())?;
+            chars.insert(at, *u.choose(SPECIAL)?);
+        }
+        words.push(chars.into_iter().collect::<String>());
+    }
+    Ok(words.join(" "))
+}
+
+fn option<T>(
+    u: &mut Unstructured,
+    value: impl FnOnce(&mut Unstructured) -> Result<T>,
+) -> Result<Option<T>> {
+    Ok(if bool::arbitrary(u)? {
+        Some(value(u)?)
+    } else {
+        None
+    })
+}
+
+impl<'a> Arbitrary<'a> for Color {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Color::rgba(
+            u8::arbitrary(u)?,
+            u8::arbitrary(u)?,
+            u8::arbitrary(u)?,
+            u8::arbitrary(u)?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Clip {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if bool::arbitrary(u)? {
+            Clip::Rectangle {
+                position: point(u)?,
+                size: (size(u)?, size(u)?),
+            }
+        } else {
+            Clip::Ellipse {
+                position: point(u)?,
+                radius: (size(u)?, size(u)?),
+            }
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Transform {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Transform::new(
+            scalar(u)?,
+            scalar(u)?,
+            scalar(u)?,
+            scalar(u)?,
+            scalar(u)?,
+            scalar(u)?,
+        ))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Target {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Target {
+            document: option(u, |u| Ok(format!("{}.svg", text(u)?)))?,
+            page: option(u, |u| Ok(u.int_in_range(0..=99)?))?,
+            bookmark: option(u, text)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Properties {
+    /// Without opacities, which only some elements have, see
+    /// [`Properties::stroke_opacity`].
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Properties {
+            id: option(u, |u| Ok(ElementId::new(word(u)?)))?,
+            hidden: bool::arbitrary(u)?,
+            clip: option(u, Clip::arbitrary)?,
+            transform: if bool::arbitrary(u)? {
+                Transform::arbitrary(u)?
+            } else {
+                Transform::IDENTITY
+            },
+            non_scaling_stroke: bool::arbitrary(u)?,
+            title: option(u, text)?,
+            description: option(u, text)?,
+            author: option(u, text)?,
+            link: option(u, Target::arbitrary)?,
+            ..Default::default()
+        })
+    }
+}
+
+/// [`Properties::arbitrary`] with the opacity of the stroke, the fill or
+/// both.
+fn properties(u: &mut Unstructured, stroke: bool, fill: bool) -> Result<Properties> {
+    let mut properties = Properties::arbitrary(u)?;
+    if stroke {
+        properties.stroke_opacity = opacity(u)?;
+    }
+    if fill {
+        properties.fill_opacity = opacity(u)?;
+    }
+    Ok(properties)
+}
+
+impl<'a> Arbitrary<'a> for Line {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(1..=32)?;
+        let mut points = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (x, y) = point(u)?;
+            points.push(LinePoint(x, y, u8::arbitrary(u)? as Scalar / 255.));
+        }
+        let timestamps = if bool::arbitrary(u)? {
+            let mut time = u64::from(u32::arbitrary(u)?);
+            let mut timestamps = Vec::with_capacity(len);
+            for _ in 0..len {
+                time += u64::from(u8::arbitrary(u)?);
+                timestamps.push(time);
+            }
+            timestamps
+        } else {
+            vec![]
+        };
+        Ok(Line {
+            color: Color::arbitrary(u)?,
+            width: positive(u)?,
+            points,
+            timestamps,
+            properties: properties(u, true, false)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Ngon {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Ngon {
+            position: point(u)?,
+            stroke: Color::arbitrary(u)?,
+            fill: Color::arbitrary(u)?,
+            width: positive(u)?,
+            angle: scalar(u)?,
+            n: u.int_in_range(3..=12)?,
+            radius: positive(u)?,
+            properties: properties(u, true, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Ellipse {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Ellipse {
+            position: point(u)?,
+            stroke: Color::arbitrary(u)?,
+            fill: Color::arbitrary(u)?,
+            width: positive(u)?,
+            radius: positive(u)?,
+            properties: properties(u, true, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Polyline {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let len = u.int_in_range(2..=32)?;
+        let mut points = Vec::with_capacity(len);
+        for _ in 0..len {
+            let (x, y) = point(u)?;
+            points.push(PolylinePoint(x, y));
+        }
+        Ok(Polyline {
+            stroke: Color::arbitrary(u)?,
+            fill: Color::arbitrary(u)?,
+            width: positive(u)?,
+            points,
+            widths: vec![],
+            closed: bool::arbitrary(u)?,
+            properties: properties(u, true, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Text {
+    /// Up to three lines of [`text`].
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut lines = vec![];
+        for _ in 0..u.int_in_range(1..=3)? {
+            lines.push(text(u)?);
+        }
+        Ok(Text {
+            position: point(u)?,
+            content: lines.join("\n"),
+            color: Color::arbitrary(u)?,
+            font: Font {
+                family: u.choose(&["sans-serif", "serif", "monospace"])?.to_string(),
+                size: u.int_in_range(1..=64)? as Scalar,
+            },
+            properties: properties(u, false, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for FileRef {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(FileRef {
+            path: format!("{}/{}.png", word(u)?, word(u)?),
+            position: point(u)?,
+            size: (positive(u)?, positive(u)?),
+            properties: Properties::arbitrary(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Instance {
+    /// The symbol may not exist.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Instance {
+            symbol: word(u)?,
+            position: point(u)?,
+            properties: Properties::arbitrary(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Shape {
+    /// The points are not generated from the parameters, which documents do
+    /// not check.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut parameters = Parameters::new();
+        for _ in 0..u.int_in_range(0..=3)? {
+            parameters.insert(word(u)?, scalar(u)?);
+        }
+        let mut points = vec![];
+        for _ in 0..u.int_in_range(3..=12)? {
+            points.push(point(u)?);
+        }
+        Ok(Shape {
+            tool: u.choose(&["cross", "star"])?.to_string(),
+            position: point(u)?,
+            radius: positive(u)?,
+            angle: scalar(u)?,
+            parameters,
+            points,
+            stroke: Color::arbitrary(u)?,
+            fill: Color::arbitrary(u)?,
+            width: positive(u)?,
+            properties: properties(u, true, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Arc {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Arc {
+            position: point(u)?,
+            radius: positive(u)?,
+            start: scalar(u)?,
+            end: scalar(u)?,
+            pie: bool::arbitrary(u)?,
+            stroke: Color::arbitrary(u)?,
+            fill: Color::arbitrary(u)?,
+            width: positive(u)?,
+            properties: properties(u, true, true)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Dimension {
+    /// The attached elements may not exist.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let attachment = |u: &mut Unstructured| {
+            option(u, |u| {
+                Ok(Attachment {
+                    id: ElementId::new(word(u)?),
+                    anchor: u.int_in_range(0..=32)?,
+                })
+            })
+        };
+        Ok(Dimension {
+            start: point(u)?,
+            end: point(u)?,
+            attachments: [attachment(u)?, attachment(u)?],
+            offset: scalar(u)?,
+            unit: *u.choose(&[
+                LengthUnit::Millimeters,
+                LengthUnit::Centimeters,
+                LengthUnit::Inches,
+            ])?,
+            color: Color::arbitrary(u)?,
+            width: positive(u)?,
+            properties: Properties::arbitrary(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Frame {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Frame {
+            name: text(u)?,
+            index: u.int_in_range(0..=99)?,
+            position: point(u)?,
+            size: (size(u)?, size(u)?),
+            properties: Properties::arbitrary(u)?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Custom {
+    /// Read back as [`Stored`], as documents are parsed without a registry.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut attributes = BTreeMap::new();
+        for _ in 0..u.int_in_range(0..=3)? {
+            attributes.insert(format!("data-{}", word(u)?.to_lowercase()), word(u)?);
+        }
+        Ok(Custom {
+            properties: Properties::arbitrary(u)?,
+            ..Custom::new(Stored {
+                tool: format!("app-{}", word(u)?),
+                tag: u.choose(&["path", "rect", "circle"])?.to_string(),
+                attributes,
+            })
+        })
+    }
+}
+
+fn element(u: &mut Unstructured, depth: usize) -> Result<Element> {
+    let kinds = if depth < MAX_DEPTH { 13 } else { 12 };
+    Ok(match u.int_in_range(1..=kinds)? {
+        1 => Element::Line(Line::arbitrary(u)?),
+        2 => Element::Ngon(Ngon::arbitrary(u)?),
+        3 => Element::Ellipse(Ellipse::arbitrary(u)?),
+        4 => Element::Polyline(Polyline::arbitrary(u)?),
+        5 => Element::Text(Text::arbitrary(u)?),
+        6 => Element::FileRef(FileRef::arbitrary(u)?),
+        7 => Element::Shape(Shape::arbitrary(u)?),
+        8 => Element::Arc(Arc::arbitrary(u)?),
+        9 => Element::Instance(Instance::arbitrary(u)?),
+        10 => Element::Dimension(Dimension::arbitrary(u)?),
+        11 => Element::Frame(Frame::arbitrary(u)?),
+        12 => Element::Custom(Custom::arbitrary(u)?),
+        _ => Element::Layer(layer(u, depth + 1)?),
+    })
+}
+
+fn elements(u: &mut Unstructured, depth: usize) -> Result<Vec<Element>> {
+    let mut elements = vec![];
+    for _ in 0..u.int_in_range(0..=8)? {
+        elements.push(element(u, depth)?);
+    }
+    Ok(elements)
+}
+
+fn layer(u: &mut Unstructured, depth: usize) -> Result<Layer> {
+    Ok(Layer {
+        name: text(u)?,
+        elements: elements(u, depth)?,
+        opacity: u8::arbitrary(u)? as f32 / 255.,
+        blend: *u.choose(&[
+            BlendMode::Normal,
+            BlendMode::Multiply,
+            BlendMode::Screen,
+            BlendMode::Overlay,
+        ])?,
+        properties: Properties::arbitrary(u)?,
+    })
+}
+
+impl<'a> Arbitrary<'a> for Element {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        element(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Layer {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        layer(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Document {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut symbols = Symbols::new();
+        for _ in 0..u.int_in_range(0..=2)? {
+            symbols.insert(word(u)?, elements(u, 0)?);
+        }
+        Ok(Document {
+            elements: elements(u, 0)?,
+            symbols,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fuzzing::round_trip;
+
+    #[test]
+    fn round_trips() {
+        let mut state: u32 = 1;
+        for _ in 0..64 {
+            let data: Vec<u8> = (0..2048)
+                .map(|_| {
+                    // xorshift
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+            round_trip(&data);
+        }
+    }
+}
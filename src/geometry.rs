@@ -0,0 +1,409 @@
+//! Computational geometry on plain `(x, y)` points.
+
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+
+pub fn distance(a: (Scalar, Scalar), b: (Scalar, Scalar)) -> Scalar {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// The distance of `point` to the segment from `a` to `b`.
+pub fn distance_to_segment(
+    point: (Scalar, Scalar),
+    a: (Scalar, Scalar),
+    b: (Scalar, Scalar),
+) -> Scalar {
+    let t = project_to_segment(point, a, b);
+    distance(point, (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)))
+}
+
+/// Where the point on the segment from `a` to `b` closest to `point` is, `0`
+/// at `a` and `1` at `b`.
+pub fn project_to_segment(
+    point: (Scalar, Scalar),
+    a: (Scalar, Scalar),
+    b: (Scalar, Scalar),
+) -> Scalar {
+    let length = (b.0 - a.0).powi(2) + (b.1 - a.1).powi(2);
+    if length == 0.0 {
+        return 0.0;
+    }
+    (((point.0 - a.0) * (b.0 - a.0) + (point.1 - a.1) * (b.1 - a.1)) / length)
+        .max(0.0)
+        .min(1.0)
+}
+
+/// Whether `point` is inside the closed `polygon` (even-odd rule).
+pub fn contains(polygon: &[(Scalar, Scalar)], point: (Scalar, Scalar)) -> bool {
+    let mut inside = false;
+    for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+        if (a.1 > point.1) != (b.1 > point.1)
+            && point.0 < a.0 + (point.1 - a.1) / (b.1 - a.1) * (b.0 - a.0)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// An axis aligned rectangle.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: (Scalar, Scalar),
+    pub max: (Scalar, Scalar),
+}
+
+impl BoundingBox {
+    /// The smallest box containing all `points`, `None` if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = (Scalar, Scalar)>) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, p| {
+            Some(match bounds {
+                None => BoundingBox { min: p, max: p },
+                Some(b) => b.union(BoundingBox { min: p, max: p }),
+            })
+        })
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        BoundingBox {
+            min: (self.min.0.min(other.min.0), self.min.1.min(other.min.1)),
+            max: (self.max.0.max(other.max.0), self.max.1.max(other.max.1)),
+        }
+    }
+
+    /// Grows the box by `distance` in every direction.
+    pub fn expand(self, distance: Scalar) -> Self {
+        BoundingBox {
+            min: (self.min.0 - distance, self.min.1 - distance),
+            max: (self.max.0 + distance, self.max.1 + distance),
+        }
+    }
+
+    /// Whether the boxes overlap, touching counts.
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+
+    /// Whether `other` is completely inside this box.
+    pub fn encloses(&self, other: &Self) -> bool {
+        self.min.0 <= other.min.0
+            && self.min.1 <= other.min.1
+            && other.max.0 <= self.max.0
+            && other.max.1 <= self.max.1
+    }
+
+    pub fn width(&self) -> Scalar {
+        self.max.0 - self.min.0
+    }
+
+    pub fn height(&self) -> Scalar {
+        self.max.1 - self.min.1
+    }
+
+    pub fn center(&self) -> (Scalar, Scalar) {
+        (
+            (self.min.0 + self.max.0) / 2.0,
+            (self.min.1 + self.max.1) / 2.0,
+        )
+    }
+}
+
+/// Removes points from a polyline, so that it deviates at most `tolerance`
+/// from the original (Ramer-Douglas-Peucker).
+///
+/// The first and last point are always kept.
+pub fn simplify<T: Copy>(
+    points: &[T],
+    tolerance: Scalar,
+    position: impl Fn(&T) -> (Scalar, Scalar),
+) -> Vec<T> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let (a, b) = (position(&points[start]), position(&points[end]));
+        let farthest = (start + 1..end)
+            .map(|i| (i, distance_to_segment(position(&points[i]), a, b)))
+            .fold(None, |max: Option<(usize, Scalar)>, (i, d)| match max {
+                Some((_, max_d)) if max_d >= d => max,
+                _ => Some((i, d)),
+            });
+        if let Some((i, d)) = farthest {
+            if d > tolerance {
+                keep[i] = true;
+                ranges.push((start, i));
+                ranges.push((i, end));
+            }
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// Joins sharper than this ratio of miter length to offset are beveled.
+const MITER_LIMIT: Scalar = 4.0;
+
+fn normalize(v: (Scalar, Scalar)) -> (Scalar, Scalar) {
+    let length = (v.0 * v.0 + v.1 * v.1).sqrt();
+    (v.0 / length, v.1 / length)
+}
+
+/// The unit normal of the segment from `a` to `b`, pointing to its left
+/// (in SVG coordinates with y down, i.e. counterclockwise).
+fn normal(a: (Scalar, Scalar), b: (Scalar, Scalar)) -> (Scalar, Scalar) {
+    let (x, y) = normalize((b.0 - a.0, b.1 - a.1));
+    (y, -x)
+}
+
+fn dedup(points: &[(Scalar, Scalar)]) -> Vec<(Scalar, Scalar)> {
+    let mut points = points.to_vec();
+    points.dedup();
+    points
+}
+
+/// The signed area of a polygon, positive if the points are in clockwise
+/// order (in SVG coordinates with y down).
+pub fn signed_area(points: &[(Scalar, Scalar)]) -> Scalar {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum::<Scalar>()
+        / 2.0
+}
+
+/// The offset points of a join at `point` between the segments with normals
+/// `n1` and `n2`, offset by `distance` along the normals.
+fn join(
+    point: (Scalar, Scalar),
+    n1: (Scalar, Scalar),
+    n2: (Scalar, Scalar),
+    distance: Scalar,
+) -> Vec<(Scalar, Scalar)> {
+    let sum = (n1.0 + n2.0, n1.1 + n2.1);
+    let cos = n1.0 * n2.0 + n1.1 * n2.1;
+    // Half the angle between the normals gives the miter length
+    let half_cos = ((1.0 + cos) / 2.0).sqrt();
+    if sum.0.abs() < Scalar::EPSILON && sum.1.abs() < Scalar::EPSILON
+        || 1.0 / half_cos > MITER_LIMIT
+    {
+        vec![
+            (point.0 + n1.0 * distance, point.1 + n1.1 * distance),
+            (point.0 + n2.0 * distance, point.1 + n2.1 * distance),
+        ]
+    } else {
+        let miter = normalize(sum);
+        let length = distance / half_cos;
+        vec![(point.0 + miter.0 * length, point.1 + miter.1 * length)]
+    }
+}
+
+/// Grows (or for negative `distance` shrinks) a closed polygon by `distance`.
+///
+/// Sharp corners are beveled. Only convex polygons are supported: the loops
+/// that concave corners can produce are not removed, so the result may
+/// intersect itself. Shrinking a polygon until it turns inside out returns
+/// no points, shrinking it until only some edges collapse is not detected.
+pub fn offset_polygon(points: &[(Scalar, Scalar)], distance: Scalar) -> Vec<(Scalar, Scalar)> {
+    let mut points = dedup(points);
+    while points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 3 {
+        return points;
+    }
+    // Offset to the outside, independent of the orientation
+    let distance = if signed_area(&points) < 0.0 {
+        -distance
+    } else {
+        distance
+    };
+    let n = points.len();
+    let offset: Vec<_> = (0..n)
+        .flat_map(|i| {
+            let (prev, point, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            join(point, normal(prev, point), normal(point, next), distance)
+        })
+        .collect();
+    if signed_area(&offset) * signed_area(&points) <= 0.0 {
+        return vec![];
+    }
+    offset
+}
+
+/// Approximates a half circle around `center`, starting at `center + start`
+/// in clockwise direction (in SVG coordinates with y down), excluding the
+/// start and end point.
+fn half_circle(
+    center: (Scalar, Scalar),
+    start: (Scalar, Scalar),
+    segments: usize,
+) -> Vec<(Scalar, Scalar)> {
+    (1..segments)
+        .map(|i| {
+            let (sin, cos) = (PI * i as Scalar / segments as Scalar).sin_cos();
+            (
+                center.0 + start.0 * cos - start.1 * sin,
+                center.1 + start.0 * sin + start.1 * cos,
+            )
+        })
+        .collect()
+}
+
+/// The outline of a stroke along `points` with round caps, `widths` being the
+/// stroke width at each point.
+///
+/// The result is a closed polygon, it can intersect itself for strokes
+/// crossing themselves.
+pub fn stroke_outline(points: &[(Scalar, Scalar)], widths: &[Scalar]) -> Vec<(Scalar, Scalar)> {
+    const CAP_SEGMENTS: usize = 8;
+    let (points, widths): (Vec<_>, Vec<_>) = points
+        .iter()
+        .zip(widths)
+        .enumerate()
+        .filter(|&(i, (p, _))| i == 0 || *p != points[i - 1])
+        .map(|(_, (&p, &w))| (p, w / 2.0))
+        .unzip();
+    match points.len() {
+        0 => return vec![],
+        1 => {
+            let (p, r) = (points[0], widths[0]);
+            let mut circle = vec![(p.0 + r, p.1)];
+            circle.extend(half_circle(p, (r, 0.0), CAP_SEGMENTS));
+            circle.push((p.0 - r, p.1));
+            circle.extend(half_circle(p, (-r, 0.0), CAP_SEGMENTS));
+            return circle;
+        }
+        _ => {}
+    }
+    let n = points.len();
+    let normals: Vec<_> = points.windows(2).map(|w| normal(w[0], w[1])).collect();
+    let side = |sign: Scalar| -> Vec<(Scalar, Scalar)> {
+        (0..n)
+            .flat_map(|i| {
+                let n1 = normals[i.saturating_sub(1)];
+                let n2 = normals[i.min(n - 2)];
+                let (n1, n2) = ((n1.0 * sign, n1.1 * sign), (n2.0 * sign, n2.1 * sign));
+                join(points[i], n1, n2, widths[i])
+            })
+            .collect()
+    };
+    let mut outline = side(1.0);
+    let end = normals[n - 2];
+    outline.extend(half_circle(
+        points[n - 1],
+        (end.0 * widths[n - 1], end.1 * widths[n - 1]),
+        CAP_SEGMENTS,
+    ));
+    let mut right = side(-1.0);
+    right.reverse();
+    outline.extend(right);
+    let start = normals[0];
+    outline.extend(half_circle(
+        points[0],
+        (-start.0 * widths[0], -start.1 * widths[0]),
+        CAP_SEGMENTS,
+    ));
+    outline
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::geometry::{offset_polygon, signed_area, simplify, stroke_outline};
+    use crate::scalar::consts::PI;
+    use crate::scalar::Scalar;
+
+    #[test]
+    fn simplify_polyline() {
+        let points = [
+            (0., 0.),
+            (1., 0.1),
+            (2., -0.1),
+            (3., 5.),
+            (4., 6.),
+            (5., 7.),
+        ];
+        assert_eq!(
+            simplify(&points, 0.5, |&p| p),
+            vec![(0., 0.), (2., -0.1), (3., 5.), (5., 7.)]
+        );
+        assert_eq!(simplify(&points, 10., |&p| p), vec![(0., 0.), (5., 7.)]);
+        // Only removes collinear points
+        assert_eq!(
+            simplify(&points, 0., |&p| p),
+            vec![(0., 0.), (1., 0.1), (2., -0.1), (3., 5.), (5., 7.)]
+        );
+    }
+
+    fn assert_close(a: &[(Scalar, Scalar)], b: &[(Scalar, Scalar)]) {
+        assert_eq!(a.len(), b.len(), "{:?} != {:?}", a, b);
+        for (a, b) in a.iter().zip(b) {
+            assert!(
+                (a.0 - b.0).abs() < 1e-5 && (a.1 - b.1).abs() < 1e-5,
+                "{:?} != {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn offset() {
+        let square = [(0., 0.), (0., 2.), (2., 2.), (2., 0.)];
+        let mut grown = offset_polygon(&square, 1.);
+        assert_close(&grown, &[(-1., -1.), (-1., 3.), (3., 3.), (3., -1.)]);
+        grown.reverse();
+        assert_close(
+            &offset_polygon(&grown, -1.),
+            &[(2., 0.), (2., 2.), (0., 2.), (0., 0.)],
+        );
+
+        // A spike is beveled instead of reaching far out
+        let spike = offset_polygon(&[(0., 0.), (10., 1.), (0., 2.)], 1.);
+        assert_eq!(spike.len(), 4);
+        assert!(spike.iter().all(|p| p.0 < 12.));
+
+        // Convex polygons stay convex
+        let hexagon: Vec<_> = (0..6)
+            .map(|i| (PI / 3. * i as Scalar).sin_cos())
+            .map(|(sin, cos)| (5. * cos, 5. * sin))
+            .collect();
+        let grown = offset_polygon(&hexagon, 2.);
+        assert_eq!(grown.len(), 6);
+        let n = grown.len();
+        assert!((0..n).all(|i| {
+            let (a, b, c) = (grown[i], grown[(i + 1) % n], grown[(i + 2) % n]);
+            (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0) > 0.
+        }));
+        assert!(signed_area(&grown) > signed_area(&hexagon));
+
+        // Shrinking by more than half the width turns it inside out
+        let bar = [(0., 0.), (10., 0.), (10., 2.), (0., 2.)];
+        assert_eq!(offset_polygon(&bar, -0.5).len(), 4);
+        assert_eq!(offset_polygon(&bar, -1.5), vec![]);
+    }
+
+    #[test]
+    fn outline() {
+        let outline = stroke_outline(&[(0., 0.), (0., 0.), (10., 0.)], &[2., 2., 4.]);
+        // Both sides, with two caps of 7 points each
+        assert_eq!(outline.len(), 2 + 7 + 2 + 7);
+        assert_eq!(outline[0], (0., -1.));
+        assert_eq!(outline[1], (10., -2.));
+        assert_eq!(outline[9], (10., 2.));
+        assert_eq!(outline[10], (0., 1.));
+        assert!((signed_area(&outline).abs() - (30. + PI / 2. * 5.)).abs() < 1.);
+        assert_eq!(stroke_outline(&[(1., 1.)], &[2.]).len(), 16);
+    }
+}
@@ -0,0 +1,187 @@
+//! Giving elements ids, see [`IdGenerator`].
+
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashSet;
+use std::fmt::{self, Display};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::elements::Element;
+use crate::Document;
+
+/// The id of an element, see [`Properties::id`](crate::elements::Properties::id).
+///
+/// Ids are opaque, only compared and hashed, e.g. as keys of maps, which
+/// can be looked up by `&str`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct ElementId(String);
+
+impl ElementId {
+    pub fn new(id: impl Into<String>) -> Self {
+        ElementId(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for ElementId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<String> for ElementId {
+    fn from(id: String) -> Self {
+        ElementId(id)
+    }
+}
+
+impl From<&str> for ElementId {
+    fn from(id: &str) -> Self {
+        ElementId(id.to_owned())
+    }
+}
+
+impl From<ElementId> for String {
+    fn from(id: ElementId) -> Self {
+        id.0
+    }
+}
+
+impl Borrow<str> for ElementId {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Generates the [`id`](crate::elements::Properties::id)s of elements
+/// without one, when parsing with
+/// [`ParseOptions::ids`](crate::options::ParseOptions::ids), in the
+/// [`Editor`](crate::editor::Editor) or with [`Document::assign_ids`].
+///
+/// Ids already taken in the document get `-2`, `-3` and so on appended, so
+/// generators need not check.
+pub trait IdGenerator {
+    fn generate(&self, element: &Element) -> String;
+}
+
+/// `prefix` followed by `1`, `2` and so on, counting on across documents.
+#[derive(Debug)]
+pub struct Sequential {
+    prefix: String,
+    next: AtomicU64,
+}
+
+impl Sequential {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Sequential {
+            prefix: prefix.into(),
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdGenerator for Sequential {
+    fn generate(&self, _: &Element) -> String {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        format!("{}{}", self.prefix, n)
+    }
+}
+
+/// Random version 4 UUIDs, e.g. for elements created on several devices.
+///
+/// The randomness is the one of the standard library's hash maps, so they
+/// are unique but not unpredictable.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomUuid;
+
+impl IdGenerator for RandomUuid {
+    fn generate(&self, _: &Element) -> String {
+        let random = || RandomState::new().build_hasher().finish();
+        let (a, b) = (random(), random());
+        format!(
+            "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            a >> 32,
+            (a >> 16) & 0xFFFF,
+            a & 0xFFF,
+            (b >> 48) & 0x3FFF | 0x8000,
+            b & 0xFFFF_FFFF_FFFF
+        )
+    }
+}
+
+/// The [`fingerprint`](Element::fingerprint) of the element in hex, so
+/// the same element gets the same id everywhere, e.g. when merging copies
+/// of a document.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContentHash;
+
+impl IdGenerator for ContentHash {
+    fn generate(&self, element: &Element) -> String {
+        format!("{:016x}", element.fingerprint())
+    }
+}
+
+/// Adds the ids of `elements`, also in layers, to `taken`.
+pub(crate) fn collect(elements: &[Element], taken: &mut HashSet<ElementId>) {
+    for element in elements {
+        taken.extend(element.properties().id.clone());
+        if let Element::Layer(layer) = element {
+            collect(&layer.elements, taken);
+        }
+    }
+}
+
+/// The ids of the elements and symbols of `document`.
+pub(crate) fn taken(document: &Document) -> HashSet<ElementId> {
+    let mut taken: HashSet<ElementId> = document.symbols.keys().map(ElementId::new).collect();
+    collect(&document.elements, &mut taken);
+    for elements in document.symbols.values() {
+        collect(elements, &mut taken);
+    }
+    taken
+}
+
+/// Gives `element`, and the elements in it if it is a layer, an id if it
+/// has none, returning the number of ids given.
+pub(crate) fn assign(
+    element: &mut Element,
+    generator: &dyn IdGenerator,
+    taken: &mut HashSet<ElementId>,
+) -> usize {
+    let mut assigned = 0;
+    if element.properties().id.is_none() {
+        let id = generator.generate(element);
+        let id = Some(id.clone())
+            .into_iter()
+            .chain((2..).map(|n| format!("{}-{}", id, n)))
+            .map(ElementId::from)
+            .find(|id| !taken.contains(id))
+            .expect("the suffixes are endless");
+        taken.insert(id.clone());
+        element.properties_mut().id = Some(id);
+        assigned += 1;
+    }
+    if let Element::Layer(layer) = element {
+        for element in &mut layer.elements {
+            assigned += assign(element, generator, taken);
+        }
+    }
+    assigned
+}
+
+impl Document {
+    /// Gives the elements without an id one from `generator`, including the
+    /// ones of symbols, returning the number of ids given.
+    pub fn assign_ids(&mut self, generator: &dyn IdGenerator) -> usize {
+        let mut taken = taken(self);
+        self.elements
+            .iter_mut()
+            .chain(self.symbols.values_mut().flatten())
+            .map(|element| assign(element, generator, &mut taken))
+            .sum()
+    }
+}
@@ -0,0 +1,86 @@
+//! Placing drawings of other programs in a document as they are, see
+//! [`Document::import_svg`].
+
+use std::str::FromStr;
+
+use svg::node::element::tag;
+
+use crate::assets::{self, Asset};
+use crate::elements::{Element, FileRef, Properties};
+use crate::scalar::Scalar;
+use crate::xml::{self, Event};
+use crate::Document;
+
+/// The size of drawings without one, as in browsers.
+const DEFAULT_SIZE: (Scalar, Scalar) = (300., 150.);
+
+/// The size of the drawing `svg` in its user units, from the `viewBox`, or
+/// the `width` and `height` in pixels.
+fn size(svg: &str) -> Option<(Scalar, Scalar)> {
+    let attributes = xml::read(svg).ok()?.find_map(|event| match event {
+        Event::Tag(tag::SVG, _, attributes) => Some(attributes),
+        _ => None,
+    })?;
+    let view_box = attributes.get("viewBox").and_then(|view_box| {
+        view_box
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|n| !n.is_empty())
+            .map(Scalar::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .ok()
+    });
+    if let Some([_, _, width, height]) = view_box.as_deref() {
+        return Some((*width, *height));
+    }
+    let length = |name: &str| Scalar::from_str(attributes.get(name)?.trim_end_matches("px")).ok();
+    Some((length("width")?, length("height")?))
+}
+
+impl Document {
+    /// Places the drawing `svg` of another program with its top left corner
+    /// at `position`, scaled by `scale` from its user units.
+    ///
+    /// It is stored unchanged as an [asset](crate::assets) shown by a
+    /// [`FileRef`], so nothing viewers can show is lost, but it cannot be
+    /// edited. Drawings without a valid size are 300 by 150 units.
+    pub fn import_svg(&mut self, svg: &str, position: (Scalar, Scalar), scale: Scalar) {
+        let (width, height) = size(svg)
+            .filter(|&(width, height)| width > 0. && height > 0.)
+            .unwrap_or(DEFAULT_SIZE);
+        let asset = Asset::new(svg.as_bytes().to_vec(), "image/svg+xml");
+        self.elements.push(Element::FileRef(FileRef {
+            path: assets::reference(&self.assets.add(asset)),
+            position,
+            size: (width * scale, height * scale),
+            properties: Properties::default(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::Element;
+    use crate::Document;
+
+    #[test]
+    fn import_svg() {
+        let mut doc = Document::default();
+        let drawing = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 20"><filter id="blur"/><rect width="10" height="20" filter="url(#blur)"/></svg>"#;
+        doc.import_svg(drawing, (100., 50.), 2.);
+        doc.import_svg(r#"<svg width="40px" height="30">"#, (0., 0.), 1.);
+        doc.import_svg("not a drawing", (0., 0.), 0.5);
+        let sizes: Vec<_> = doc
+            .elements
+            .iter()
+            .map(|element| match element {
+                Element::FileRef(file) => file.size,
+                element => panic!("{:?}", element),
+            })
+            .collect();
+        assert_eq!(sizes, vec![(20., 40.), (40., 30.), (150., 75.)]);
+        assert_eq!(doc.assets.len(), 3);
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+    }
+}
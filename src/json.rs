@@ -0,0 +1,1054 @@
+//! The canonical JSON mapping of documents, for clients that do not want to
+//! parse SVG, described by the JSON Schema in [`SCHEMA`].
+//!
+//! Elements are objects with a `type` and their fields, e.g.
+//! `{"type": "ellipse", "position": [0, 0], "radius": 5, ...}`, colors are
+//! `#RRGGBBAA` strings and points are arrays. Properties are stored in the
+//! element, and omitted when they have the default value.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync;
+
+use serde_json::{json, Map, Value};
+use thiserror::Error;
+
+use crate::assets::{Asset, Assets};
+use crate::calibration::InputCalibration;
+use crate::colors::Color;
+use crate::elements::{
+    Arc, Attachment, BlendMode, Clip, Custom, Dimension, Element, Ellipse, Ephemeral, FileRef,
+    Font, Frame, Instance, Layer, LengthUnit, Line, LinePoint, Ngon, Parameters, Polyline,
+    PolylinePoint, Properties, Shape, Stored, Symbols, Text,
+};
+use crate::geometry::BoundingBox;
+use crate::ids::ElementId;
+use crate::metadata::{Bookmark, Heading, Metadata, Placeholder, Target, ToolState};
+use crate::ruling::{Ruling, RulingKind};
+use crate::scalar::Scalar;
+use crate::snap::{Grid, Guide};
+use crate::transform::Transform;
+use crate::Document;
+
+/// The JSON Schema of the mapping.
+pub const SCHEMA: &str = include_str!("../schema/svgnote.schema.json");
+
+/// The value of the `svgnote` field of documents.
+pub const VERSION: u64 = 1;
+
+#[derive(Error, Debug)]
+pub enum JsonError {
+    #[error("invalid JSON: {0}")]
+    Syntax(#[from] serde_json::Error),
+    #[error("missing field `{0}`")]
+    Missing(&'static str),
+    #[error("invalid `{0}`: {1}")]
+    Invalid(&'static str, Value),
+}
+
+type Object = Map<String, Value>;
+
+/// `value` as the shortest JSON number, e.g. `0.1` instead of
+/// `0.10000000149011612` for an `f32`.
+fn number(value: Scalar) -> Value {
+    f64::from_str(&value.to_string())
+        .map(Value::from)
+        .unwrap_or(Value::Null)
+}
+
+fn numbers(values: &[Scalar]) -> Value {
+    Value::Array(values.iter().map(|&v| number(v)).collect())
+}
+
+fn point(point: (Scalar, Scalar)) -> Value {
+    numbers(&[point.0, point.1])
+}
+
+fn get<'a>(object: &'a Object, key: &'static str) -> Result<&'a Value, JsonError> {
+    object.get(key).ok_or(JsonError::Missing(key))
+}
+
+fn object<'a>(value: &'a Value, key: &'static str) -> Result<&'a Object, JsonError> {
+    value
+        .as_object()
+        .ok_or_else(|| JsonError::Invalid(key, value.clone()))
+}
+
+fn array<'a>(object: &'a Object, key: &'static str) -> Result<&'a [Value], JsonError> {
+    match object.get(key) {
+        Some(Value::Array(values)) => Ok(values),
+        Some(value) => Err(JsonError::Invalid(key, value.clone())),
+        None => Ok(&[]),
+    }
+}
+
+fn to_scalar(value: &Value, key: &'static str) -> Result<Scalar, JsonError> {
+    value
+        .as_f64()
+        .map(|v| v as Scalar)
+        .ok_or_else(|| JsonError::Invalid(key, value.clone()))
+}
+
+fn scalar(object: &Object, key: &'static str) -> Result<Scalar, JsonError> {
+    to_scalar(get(object, key)?, key)
+}
+
+/// Exactly `n` numbers.
+fn to_scalars(value: &Value, key: &'static str, n: usize) -> Result<Vec<Scalar>, JsonError> {
+    match value.as_array() {
+        Some(values) if values.len() == n => values.iter().map(|v| to_scalar(v, key)).collect(),
+        _ => Err(JsonError::Invalid(key, value.clone())),
+    }
+}
+
+fn to_point(value: &Value, key: &'static str) -> Result<(Scalar, Scalar), JsonError> {
+    let v = to_scalars(value, key, 2)?;
+    Ok((v[0], v[1]))
+}
+
+fn point_at(object: &Object, key: &'static str) -> Result<(Scalar, Scalar), JsonError> {
+    to_point(get(object, key)?, key)
+}
+
+fn string(object: &Object, key: &'static str) -> Result<String, JsonError> {
+    let value = get(object, key)?;
+    value
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| JsonError::Invalid(key, value.clone()))
+}
+
+fn optional_string(object: &Object, key: &'static str) -> Result<Option<String>, JsonError> {
+    object.get(key).map(|_| string(object, key)).transpose()
+}
+
+fn color(object: &Object, key: &'static str) -> Result<Color, JsonError> {
+    let value = get(object, key)?;
+    value
+        .as_str()
+        .and_then(|s| Color::from_str(s).ok())
+        .ok_or_else(|| JsonError::Invalid(key, value.clone()))
+}
+
+fn to_target(value: &Value, key: &'static str) -> Result<Target, JsonError> {
+    value
+        .as_str()
+        .and_then(|s| Target::from_str(s).ok())
+        .ok_or_else(|| JsonError::Invalid(key, value.clone()))
+}
+
+/// The elements that are stored, i.e. without
+/// [`Ephemeral`] ones.
+fn elements_to_json(elements: &[Element]) -> Vec<Value> {
+    elements
+        .iter()
+        .filter(|e| !e.is_ephemeral())
+        .map(Element::to_json_value)
+        .collect()
+}
+
+fn clip_to_json(clip: Clip) -> Value {
+    match clip {
+        Clip::Rectangle { position, size } => json!({
+            "type": "rectangle",
+            "position": point(position),
+            "size": point(size),
+        }),
+        Clip::Ellipse { position, radius } => json!({
+            "type": "ellipse",
+            "position": point(position),
+            "radius": point(radius),
+        }),
+    }
+}
+
+fn clip_from_json(value: &Value) -> Result<Clip, JsonError> {
+    let clip = object(value, "clip")?;
+    match get(clip, "type")?.as_str() {
+        Some("rectangle") => Ok(Clip::rectangle(
+            point_at(clip, "position")?,
+            point_at(clip, "size")?,
+        )),
+        Some("ellipse") => Ok(Clip::ellipse(
+            point_at(clip, "position")?,
+            point_at(clip, "radius")?,
+        )),
+        _ => Err(JsonError::Invalid("clip", value.clone())),
+    }
+}
+
+impl Properties {
+    /// Inserts the properties that are not the default into `object`.
+    fn to_json(&self, object: &mut Object) {
+        if self.hidden {
+            object.insert("hidden".to_owned(), Value::Bool(true));
+        }
+        if let Some(clip) = self.clip {
+            object.insert("clip".to_owned(), clip_to_json(clip));
+        }
+        if !self.transform.is_identity() {
+            let t = self.transform;
+            object.insert(
+                "transform".to_owned(),
+                numbers(&[t.a, t.b, t.c, t.d, t.e, t.f]),
+            );
+        }
+        if self.non_scaling_stroke {
+            object.insert("nonScalingStroke".to_owned(), Value::Bool(true));
+        }
+        if let Some(id) = &self.id {
+            object.insert("id".to_owned(), Value::String(id.to_string()));
+        }
+        let strings = [
+            ("title", &self.title),
+            ("description", &self.description),
+            ("author", &self.author),
+        ];
+        for (key, value) in strings.iter() {
+            if let Some(value) = value {
+                object.insert((*key).to_owned(), Value::String(value.clone()));
+            }
+        }
+        if let Some(link) = &self.link {
+            object.insert("link".to_owned(), Value::String(link.to_string()));
+        }
+        let opacities = [
+            ("strokeOpacity", self.stroke_opacity),
+            ("fillOpacity", self.fill_opacity),
+        ];
+        for &(key, opacity) in opacities.iter() {
+            if opacity != 1. {
+                object.insert(key.to_owned(), number(opacity as Scalar));
+            }
+        }
+    }
+
+    fn from_json(object: &Object) -> Result<Self, JsonError> {
+        Ok(Properties {
+            hidden: match object.get("hidden") {
+                Some(Value::Bool(hidden)) => *hidden,
+                Some(value) => return Err(JsonError::Invalid("hidden", value.clone())),
+                None => false,
+            },
+            clip: object.get("clip").map(clip_from_json).transpose()?,
+            transform: match object.get("transform") {
+                Some(value) => {
+                    let t = to_scalars(value, "transform", 6)?;
+                    Transform::new(t[0], t[1], t[2], t[3], t[4], t[5])
+                }
+                None => Transform::IDENTITY,
+            },
+            non_scaling_stroke: match object.get("nonScalingStroke") {
+                Some(Value::Bool(non_scaling)) => *non_scaling,
+                Some(value) => return Err(JsonError::Invalid("nonScalingStroke", value.clone())),
+                None => false,
+            },
+            id: optional_string(object, "id")?.map(ElementId::from),
+            title: optional_string(object, "title")?,
+            description: optional_string(object, "description")?,
+            author: optional_string(object, "author")?,
+            link: object
+                .get("link")
+                .map(|v| to_target(v, "link"))
+                .transpose()?,
+            stroke_opacity: match object.get("strokeOpacity") {
+                Some(opacity) => to_scalar(opacity, "strokeOpacity")? as f32,
+                None => 1.,
+            },
+            fill_opacity: match object.get("fillOpacity") {
+                Some(opacity) => to_scalar(opacity, "fillOpacity")? as f32,
+                None => 1.,
+            },
+            ..Default::default()
+        })
+    }
+}
+
+impl Element {
+    /// [`Ephemeral`] elements are written as their line, documents leave
+    /// them out.
+    pub fn to_json_value(&self) -> Value {
+        let (properties, mut value) = match self {
+            Element::Line(e) | Element::Ephemeral(Ephemeral { line: e, .. }) => (
+                &e.properties,
+                json!({
+                    "type": "line",
+                    "color": e.color.to_string(),
+                    "width": number(e.width),
+                    "points": e.points
+                        .iter()
+                        .map(|p| numbers(&[p.0, p.1, p.2]))
+                        .collect::<Vec<_>>(),
+                }),
+            ),
+            Element::Ngon(e) => (
+                &e.properties,
+                json!({
+                    "type": "ngon",
+                    "position": point(e.position),
+                    "stroke": e.stroke.to_string(),
+                    "fill": e.fill.to_string(),
+                    "width": number(e.width),
+                    "angle": number(e.angle),
+                    "n": e.n,
+                    "radius": number(e.radius),
+                }),
+            ),
+            Element::Ellipse(e) => (
+                &e.properties,
+                json!({
+                    "type": "ellipse",
+                    "position": point(e.position),
+                    "stroke": e.stroke.to_string(),
+                    "fill": e.fill.to_string(),
+                    "width": number(e.width),
+                    "radius": number(e.radius),
+                }),
+            ),
+            Element::Polyline(e) => (
+                &e.properties,
+                json!({
+                    "type": "polyline",
+                    "stroke": e.stroke.to_string(),
+                    "fill": e.fill.to_string(),
+                    "width": number(e.width),
+                    "points": e.points.iter().map(|p| point((p.0, p.1))).collect::<Vec<_>>(),
+                }),
+            ),
+            Element::Text(e) => (
+                &e.properties,
+                json!({
+                    "type": "text",
+                    "position": point(e.position),
+                    "content": e.content,
+                    "color": e.color.to_string(),
+                    "font": {
+                        "family": e.font.family,
+                        "size": number(e.font.size),
+                    },
+                }),
+            ),
+            Element::FileRef(e) => (
+                &e.properties,
+                json!({
+                    "type": "file",
+                    "path": e.path,
+                    "position": point(e.position),
+                    "size": point(e.size),
+                }),
+            ),
+            Element::Frame(e) => (
+                &e.properties,
+                json!({
+                    "type": "frame",
+                    "name": e.name,
+                    "index": e.index,
+                    "position": point(e.position),
+                    "size": point(e.size),
+                }),
+            ),
+            Element::Layer(e) => (
+                &e.properties,
+                json!({
+                    "type": "layer",
+                    "name": e.name,
+                    "opacity": number(e.opacity as Scalar),
+                    "elements": elements_to_json(&e.elements),
+                }),
+            ),
+            Element::Shape(e) => (
+                &e.properties,
+                json!({
+                    "type": "shape",
+                    "tool": e.tool,
+                    "position": point(e.position),
+                    "radius": number(e.radius),
+                    "angle": number(e.angle),
+                    "parameters": e.parameters
+                        .iter()
+                        .map(|(name, &value)| (name.clone(), number(value)))
+                        .collect::<Object>(),
+                    "stroke": e.stroke.to_string(),
+                    "fill": e.fill.to_string(),
+                    "width": number(e.width),
+                    "points": e.points.iter().map(|&p| point(p)).collect::<Vec<_>>(),
+                }),
+            ),
+            Element::Arc(e) => (
+                &e.properties,
+                json!({
+                    "type": "arc",
+                    "position": point(e.position),
+                    "radius": number(e.radius),
+                    "start": number(e.start),
+                    "end": number(e.end),
+                    "pie": e.pie,
+                    "stroke": e.stroke.to_string(),
+                    "fill": e.fill.to_string(),
+                    "width": number(e.width),
+                }),
+            ),
+            Element::Instance(e) => (
+                &e.properties,
+                json!({
+                    "type": "instance",
+                    "symbol": e.symbol,
+                    "position": point(e.position),
+                }),
+            ),
+            Element::Custom(e) => (
+                &e.properties,
+                json!({
+                    "type": "custom",
+                    "tool": e.element.tool(),
+                    "tag": e.element.tag(),
+                    "attributes": e.element.attributes(),
+                }),
+            ),
+            Element::Dimension(e) => (
+                &e.properties,
+                json!({
+                    "type": "dimension",
+                    "start": point(e.start),
+                    "end": point(e.end),
+                    "offset": number(e.offset),
+                    "unit": e.unit.to_string(),
+                    "color": e.color.to_string(),
+                    "width": number(e.width),
+                }),
+            ),
+        };
+        let object = value.as_object_mut().expect("elements are objects");
+        match self {
+            Element::Line(line) if !line.timestamps.is_empty() => {
+                object.insert("timestamps".to_owned(), json!(line.timestamps));
+            }
+            Element::Layer(layer) if layer.blend != BlendMode::Normal => {
+                object.insert("blend".to_owned(), Value::String(layer.blend.to_string()));
+            }
+            Element::Dimension(dimension) if dimension.attachments.iter().any(Option::is_some) => {
+                let attachments = dimension
+                    .attachments
+                    .iter()
+                    .map(|attachment| match attachment {
+                        Some(a) => json!({ "id": a.id.as_str(), "anchor": a.anchor }),
+                        None => Value::Null,
+                    });
+                object.insert("attachments".to_owned(), attachments.collect());
+            }
+            Element::Polyline(polyline) => {
+                if !polyline.widths.is_empty() {
+                    object.insert("widths".to_owned(), numbers(&polyline.widths));
+                }
+                if polyline.closed {
+                    object.insert("closed".to_owned(), Value::Bool(true));
+                }
+            }
+            _ => {}
+        }
+        properties.to_json(object);
+        value
+    }
+
+    pub fn from_json_value(value: &Value) -> Result<Self, JsonError> {
+        let e = object(value, "element")?;
+        let properties = Properties::from_json(e)?;
+        Ok(match get(e, "type")?.as_str() {
+            Some("line") => Element::Line(Line {
+                color: color(e, "color")?,
+                width: scalar(e, "width")?,
+                points: array(e, "points")?
+                    .iter()
+                    .map(|p| {
+                        let p = to_scalars(p, "points", 3)?;
+                        Ok(LinePoint(p[0], p[1], p[2]))
+                    })
+                    .collect::<Result<_, JsonError>>()?,
+                timestamps: array(e, "timestamps")?
+                    .iter()
+                    .map(|t| {
+                        t.as_u64()
+                            .ok_or_else(|| JsonError::Invalid("timestamps", t.clone()))
+                    })
+                    .collect::<Result<_, _>>()?,
+                properties,
+            }),
+            Some("ngon") => Element::Ngon(Ngon {
+                position: point_at(e, "position")?,
+                stroke: color(e, "stroke")?,
+                fill: color(e, "fill")?,
+                width: scalar(e, "width")?,
+                angle: scalar(e, "angle")?,
+                n: {
+                    let n = get(e, "n")?;
+                    n.as_u64()
+                        .and_then(|n| u16::try_from(n).ok())
+                        .filter(|&n| n >= 3)
+                        .ok_or_else(|| JsonError::Invalid("n", n.clone()))?
+                },
+                radius: scalar(e, "radius")?,
+                properties,
+            }),
+            Some("ellipse") => Element::Ellipse(Ellipse {
+                position: point_at(e, "position")?,
+                stroke: color(e, "stroke")?,
+                fill: color(e, "fill")?,
+                width: scalar(e, "width")?,
+                radius: scalar(e, "radius")?,
+                properties,
+            }),
+            Some("polyline") => Element::Polyline(Polyline {
+                stroke: color(e, "stroke")?,
+                fill: color(e, "fill")?,
+                width: scalar(e, "width")?,
+                points: array(e, "points")?
+                    .iter()
+                    .map(|p| to_point(p, "points").map(|(x, y)| PolylinePoint(x, y)))
+                    .collect::<Result<_, _>>()?,
+                widths: array(e, "widths")?
+                    .iter()
+                    .map(|w| to_scalar(w, "widths"))
+                    .collect::<Result<_, _>>()?,
+                closed: match e.get("closed") {
+                    Some(Value::Bool(closed)) => *closed,
+                    Some(value) => return Err(JsonError::Invalid("closed", value.clone())),
+                    None => false,
+                },
+                properties,
+            }),
+            Some("text") => Element::Text(Text {
+                position: point_at(e, "position")?,
+                content: string(e, "content")?,
+                color: color(e, "color")?,
+                font: match e.get("font") {
+                    Some(font) => {
+                        let font = object(font, "font")?;
+                        Font {
+                            family: string(font, "family")?,
+                            size: scalar(font, "size")?,
+                        }
+                    }
+                    None => Font::default(),
+                },
+                properties,
+            }),
+            Some("file") => Element::FileRef(FileRef {
+                path: string(e, "path")?,
+                position: point_at(e, "position")?,
+                size: point_at(e, "size")?,
+                properties,
+            }),
+            Some("layer") => Element::Layer(Layer {
+                name: string(e, "name")?,
+                elements: array(e, "elements")?
+                    .iter()
+                    .map(Element::from_json_value)
+                    .collect::<Result<_, _>>()?,
+                opacity: match e.get("opacity") {
+                    Some(opacity) => to_scalar(opacity, "opacity")? as f32,
+                    None => 1.,
+                },
+                blend: match e.get("blend") {
+                    Some(blend) => blend
+                        .as_str()
+                        .and_then(|blend| BlendMode::from_str(blend).ok())
+                        .ok_or_else(|| JsonError::Invalid("blend", blend.clone()))?,
+                    None => BlendMode::Normal,
+                },
+                properties,
+            }),
+            Some("shape") => Element::Shape(Shape {
+                tool: string(e, "tool")?,
+                position: point_at(e, "position")?,
+                radius: scalar(e, "radius")?,
+                angle: scalar(e, "angle")?,
+                parameters: match e.get("parameters") {
+                    Some(parameters) => object(parameters, "parameters")?
+                        .iter()
+                        .map(|(name, value)| {
+                            to_scalar(value, "parameters").map(|value| (name.clone(), value))
+                        })
+                        .collect::<Result<_, _>>()?,
+                    None => Parameters::new(),
+                },
+                points: array(e, "points")?
+                    .iter()
+                    .map(|p| to_point(p, "points"))
+                    .collect::<Result<_, _>>()?,
+                stroke: color(e, "stroke")?,
+                fill: color(e, "fill")?,
+                width: scalar(e, "width")?,
+                properties,
+            }),
+            Some("arc") => Element::Arc(Arc {
+                position: point_at(e, "position")?,
+                radius: scalar(e, "radius")?,
+                start: scalar(e, "start")?,
+                end: scalar(e, "end")?,
+                pie: match e.get("pie") {
+                    Some(Value::Bool(pie)) => *pie,
+                    Some(value) => return Err(JsonError::Invalid("pie", value.clone())),
+                    None => false,
+                },
+                stroke: color(e, "stroke")?,
+                fill: color(e, "fill")?,
+                width: scalar(e, "width")?,
+                properties,
+            }),
+            Some("instance") => Element::Instance(Instance {
+                symbol: string(e, "symbol")?,
+                position: point_at(e, "position")?,
+                properties,
+            }),
+            Some("custom") => Element::Custom(Custom {
+                element: sync::Arc::new(Stored {
+                    tool: string(e, "tool")?,
+                    tag: string(e, "tag")?,
+                    attributes: match e.get("attributes") {
+                        Some(value) => object(value, "attributes")?
+                            .iter()
+                            .map(|(name, v)| match v {
+                                Value::String(v) => Ok((name.clone(), v.clone())),
+                                _ => Err(JsonError::Invalid("attributes", value.clone())),
+                            })
+                            .collect::<Result<_, _>>()?,
+                        None => BTreeMap::new(),
+                    },
+                }),
+                properties,
+            }),
+            Some("frame") => Element::Frame(Frame {
+                name: string(e, "name")?,
+                index: {
+                    let index = get(e, "index")?;
+                    index
+                        .as_u64()
+                        .and_then(|index| u32::try_from(index).ok())
+                        .ok_or_else(|| JsonError::Invalid("index", index.clone()))?
+                },
+                position: point_at(e, "position")?,
+                size: point_at(e, "size")?,
+                properties,
+            }),
+            Some("dimension") => Element::Dimension(Dimension {
+                start: point_at(e, "start")?,
+                end: point_at(e, "end")?,
+                attachments: {
+                    let attachment = |a: &Value| -> Result<Option<Attachment>, JsonError> {
+                        if a.is_null() {
+                            return Ok(None);
+                        }
+                        let a = object(a, "attachments")?;
+                        let anchor = get(a, "anchor")?;
+                        Ok(Some(Attachment {
+                            id: ElementId::new(string(a, "id")?),
+                            anchor: anchor
+                                .as_u64()
+                                .and_then(|anchor| usize::try_from(anchor).ok())
+                                .ok_or_else(|| JsonError::Invalid("anchor", anchor.clone()))?,
+                        }))
+                    };
+                    match array(e, "attachments")? {
+                        [] => [None, None],
+                        [start, end] => [attachment(start)?, attachment(end)?],
+                        _ => {
+                            return Err(JsonError::Invalid("attachments", e["attachments"].clone()))
+                        }
+                    }
+                },
+                offset: scalar(e, "offset")?,
+                unit: {
+                    let unit = get(e, "unit")?;
+                    unit.as_str()
+                        .and_then(|unit| LengthUnit::from_str(unit).ok())
+                        .ok_or_else(|| JsonError::Invalid("unit", unit.clone()))?
+                },
+                color: color(e, "color")?,
+                width: scalar(e, "width")?,
+                properties,
+            }),
+            _ => return Err(JsonError::Invalid("type", e["type"].clone())),
+        })
+    }
+}
+
+fn heading_to_json(heading: &Heading) -> Value {
+    json!({
+        "title": heading.title,
+        "target": heading.target.to_string(),
+        "children": heading.children.iter().map(heading_to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn heading_from_json(value: &Value) -> Result<Heading, JsonError> {
+    let heading = object(value, "outline")?;
+    Ok(Heading {
+        title: string(heading, "title")?,
+        target: to_target(get(heading, "target")?, "target")?,
+        children: array(heading, "children")?
+            .iter()
+            .map(heading_from_json)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+impl Metadata {
+    pub fn to_json_value(&self) -> Value {
+        let mut value = json!({
+            "bookmarks": self.bookmarks.iter().map(|b| json!({
+                "name": b.name,
+                "position": point(b.position),
+            })).collect::<Vec<_>>(),
+            "outline": self.outline.iter().map(heading_to_json).collect::<Vec<_>>(),
+            "placeholders": self.placeholders.iter().map(|p| {
+                let mut placeholder = json!({
+                    "name": p.name,
+                    "min": point(p.bounds.min),
+                    "max": point(p.bounds.max),
+                });
+                if let Some(id) = &p.element {
+                    placeholder["element"] = Value::String(id.to_string());
+                }
+                placeholder
+            }).collect::<Vec<_>>(),
+        });
+        if let Some(grid) = self.grid {
+            value["grid"] = json!({ "spacing": number(grid.spacing) });
+        }
+        if !self.guides.is_empty() {
+            value["guides"] = self
+                .guides
+                .iter()
+                .map(|guide| match *guide {
+                    Guide::Horizontal(y) => json!({ "orientation": "horizontal", "y": number(y) }),
+                    Guide::Vertical(x) => json!({ "orientation": "vertical", "x": number(x) }),
+                    Guide::Diagonal { position, angle } => json!({
+                        "orientation": "diagonal",
+                        "position": point(position),
+                        "angle": number(angle),
+                    }),
+                })
+                .collect();
+        }
+        if let Some(calibration) = self.calibration {
+            let t = calibration.transform;
+            value["calibration"] = json!({
+                "transform": numbers(&[t.a, t.b, t.c, t.d, t.e, t.f]),
+                "pressure": numbers(&[calibration.pressure.0, calibration.pressure.1]),
+                "deadband": number(calibration.deadband),
+            });
+        }
+        if let Some(unit) = self.unit {
+            value["unit"] = Value::String(unit.to_string());
+        }
+        if !self.tools.is_empty() {
+            value["tools"] = self
+                .tools
+                .iter()
+                .map(|t| {
+                    json!({
+                        "layer": t.layer,
+                        "tool": t.tool,
+                        "color": t.color.to_string(),
+                        "width": number(t.width),
+                    })
+                })
+                .collect();
+        }
+        if let Some(background) = self.background {
+            value["background"] = Value::String(background.to_string());
+        }
+        if let Some(ruling) = self.ruling {
+            value["ruling"] = json!({
+                "kind": ruling.kind.to_string(),
+                "spacing": number(ruling.spacing),
+                "color": ruling.color.to_string(),
+                "width": number(ruling.width),
+            });
+        }
+        value
+    }
+
+    pub fn from_json_value(value: &Value) -> Result<Self, JsonError> {
+        let metadata = object(value, "metadata")?;
+        Ok(Metadata {
+            bookmarks: array(metadata, "bookmarks")?
+                .iter()
+                .map(|b| {
+                    let b = object(b, "bookmarks")?;
+                    Ok(Bookmark::new(string(b, "name")?, point_at(b, "position")?))
+                })
+                .collect::<Result<_, JsonError>>()?,
+            outline: array(metadata, "outline")?
+                .iter()
+                .map(heading_from_json)
+                .collect::<Result<_, _>>()?,
+            placeholders: array(metadata, "placeholders")?
+                .iter()
+                .map(|p| {
+                    let p = object(p, "placeholders")?;
+                    let bounds = BoundingBox {
+                        min: point_at(p, "min")?,
+                        max: point_at(p, "max")?,
+                    };
+                    Ok(Placeholder {
+                        element: optional_string(p, "element")?.map(ElementId::from),
+                        ..Placeholder::new(string(p, "name")?, bounds)
+                    })
+                })
+                .collect::<Result<_, JsonError>>()?,
+            grid: match metadata.get("grid") {
+                Some(grid) => Some(Grid::new(scalar(object(grid, "grid")?, "spacing")?)),
+                None => None,
+            },
+            guides: array(metadata, "guides")?
+                .iter()
+                .map(|g| {
+                    let g = object(g, "guides")?;
+                    let orientation = get(g, "orientation")?;
+                    Ok(match orientation.as_str() {
+                        Some("horizontal") => Guide::Horizontal(scalar(g, "y")?),
+                        Some("vertical") => Guide::Vertical(scalar(g, "x")?),
+                        Some("diagonal") => Guide::Diagonal {
+                            position: point_at(g, "position")?,
+                            angle: scalar(g, "angle")?,
+                        },
+                        _ => return Err(JsonError::Invalid("orientation", orientation.clone())),
+                    })
+                })
+                .collect::<Result<_, JsonError>>()?,
+            calibration: match metadata.get("calibration") {
+                Some(calibration) => {
+                    let calibration = object(calibration, "calibration")?;
+                    let t = to_scalars(get(calibration, "transform")?, "transform", 6)?;
+                    let pressure = to_scalars(get(calibration, "pressure")?, "pressure", 2)?;
+                    Some(InputCalibration {
+                        transform: Transform::new(t[0], t[1], t[2], t[3], t[4], t[5]),
+                        pressure: (pressure[0], pressure[1]),
+                        deadband: scalar(calibration, "deadband")?,
+                    })
+                }
+                None => None,
+            },
+            unit: match metadata.get("unit") {
+                Some(unit) => Some(
+                    unit.as_str()
+                        .and_then(|unit| LengthUnit::from_str(unit).ok())
+                        .ok_or_else(|| JsonError::Invalid("unit", unit.clone()))?,
+                ),
+                None => None,
+            },
+            tools: match metadata.get("tools") {
+                Some(_) => array(metadata, "tools")?
+                    .iter()
+                    .map(|t| {
+                        let t = object(t, "tools")?;
+                        Ok(ToolState::new(
+                            string(t, "layer")?,
+                            string(t, "tool")?,
+                            color(t, "color")?,
+                            scalar(t, "width")?,
+                        ))
+                    })
+                    .collect::<Result<_, JsonError>>()?,
+                None => vec![],
+            },
+            background: match metadata.get("background") {
+                Some(_) => Some(color(metadata, "background")?),
+                None => None,
+            },
+            ruling: match metadata.get("ruling") {
+                Some(ruling) => {
+                    let ruling = object(ruling, "ruling")?;
+                    let kind = get(ruling, "kind")?;
+                    let spacing = scalar(ruling, "spacing")?;
+                    if spacing <= 0. || spacing.is_nan() {
+                        return Err(JsonError::Invalid(
+                            "spacing",
+                            get(ruling, "spacing")?.clone(),
+                        ));
+                    }
+                    Some(Ruling {
+                        kind: kind
+                            .as_str()
+                            .and_then(|kind| RulingKind::from_str(kind).ok())
+                            .ok_or_else(|| JsonError::Invalid("kind", kind.clone()))?,
+                        spacing,
+                        color: color(ruling, "color")?,
+                        width: scalar(ruling, "width")?,
+                    })
+                }
+                None => None,
+            },
+        })
+    }
+}
+
+impl Document {
+    /// The symbols and assets are only included if there are any, assets as
+    /// data URIs.
+    pub fn to_json_value(&self) -> Value {
+        let mut value = json!({
+            "svgnote": VERSION,
+            "elements": elements_to_json(&self.elements),
+            "metadata": self.metadata.to_json_value(),
+        });
+        if !self.symbols.is_empty() {
+            value["symbols"] = self
+                .symbols
+                .iter()
+                .map(|(name, symbol)| (name.clone(), Value::from(elements_to_json(symbol))))
+                .collect::<Object>()
+                .into();
+        }
+        if !self.assets.is_empty() {
+            value["assets"] = self
+                .assets
+                .iter()
+                .map(|(id, asset)| (id.clone(), Value::from(asset.to_data_uri())))
+                .collect::<Object>()
+                .into();
+        }
+        value
+    }
+
+    /// The document in the canonical JSON mapping, see [`json`](crate::json).
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    pub fn from_json_value(value: &Value) -> Result<Self, JsonError> {
+        let document = object(value, "document")?;
+        let version = get(document, "svgnote")?;
+        if version.as_u64() != Some(VERSION) {
+            return Err(JsonError::Invalid("svgnote", version.clone()));
+        }
+        Ok(Document {
+            elements: array(document, "elements")?
+                .iter()
+                .map(Element::from_json_value)
+                .collect::<Result<_, _>>()?,
+            metadata: match document.get("metadata") {
+                Some(metadata) => Metadata::from_json_value(metadata)?,
+                None => Metadata::default(),
+            },
+            symbols: match document.get("symbols") {
+                Some(symbols) => object(symbols, "symbols")?
+                    .iter()
+                    .map(|(name, symbol)| -> Result<_, JsonError> {
+                        let elements = symbol
+                            .as_array()
+                            .ok_or_else(|| JsonError::Invalid("symbols", symbol.clone()))?
+                            .iter()
+                            .map(Element::from_json_value)
+                            .collect::<Result<_, _>>()?;
+                        Ok((name.clone(), elements))
+                    })
+                    .collect::<Result<_, _>>()?,
+                None => Symbols::new(),
+            },
+            assets: match document.get("assets") {
+                Some(assets) => {
+                    let mut read = Assets::new();
+                    for (id, uri) in object(assets, "assets")? {
+                        let asset = uri
+                            .as_str()
+                            .and_then(Asset::from_data_uri)
+                            .ok_or_else(|| JsonError::Invalid("assets", uri.clone()))?;
+                        read.insert(id.clone(), asset);
+                    }
+                    read
+                }
+                None => Assets::new(),
+            },
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, JsonError> {
+        Document::from_json_value(&serde_json::from_str(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::colors::Color;
+    use crate::elements::{Clip, Element, Layer, Line, LinePoint, Properties};
+    use crate::json::{JsonError, SCHEMA};
+    use crate::metadata::{Bookmark, Target};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn round_trip() {
+        let line = Element::Line(Line {
+            color: Color::rgba(0, 0, 0xFF, 0x80),
+            width: 0.1,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(1.5, 2., 0.5)],
+            timestamps: vec![100, 116],
+            properties: Properties {
+                transform: Transform::translate(1., 0.),
+                clip: Some(Clip::ellipse((0., 0.), (5., 2.))),
+                link: Some(Target::page(2)),
+                title: Some("signature".to_owned()),
+                ..Default::default()
+            },
+        });
+        let mut layer = Layer::new("ink");
+        layer.elements.push(line.clone());
+        layer.opacity = 0.5;
+        let mut document = Document {
+            elements: vec![line, Element::Layer(layer)],
+            ..Default::default()
+        };
+        document
+            .metadata
+            .bookmarks
+            .push(Bookmark::new("start", (0., 10.)));
+
+        let json = document.to_json_value();
+        assert_eq!(
+            json["elements"][0],
+            json!({
+                "type": "line",
+                "color": "#0000FF80",
+                "width": 0.1,
+                "points": [[0.0, 0.0, 1.0], [1.5, 2.0, 0.5]],
+                "timestamps": [100, 116],
+                "clip": {"type": "ellipse", "position": [0.0, 0.0], "radius": [5.0, 2.0]},
+                "transform": [1.0, 0.0, 0.0, 1.0, 1.0, 0.0],
+                "title": "signature",
+                "link": "?page=2",
+            })
+        );
+        assert_eq!(Document::from_json(&document.to_json()).unwrap(), document);
+    }
+
+    #[test]
+    fn errors() {
+        assert_matches!(Document::from_json("{"), Err(JsonError::Syntax(_)));
+        assert_matches!(
+            Document::from_json(r#"{"elements": []}"#),
+            Err(JsonError::Missing("svgnote"))
+        );
+        assert_matches!(
+            Document::from_json(r#"{"svgnote": 1, "elements": [{"type": "circle"}]}"#),
+            Err(JsonError::Invalid("type", _))
+        );
+        assert_matches!(
+            Document::from_json(
+                r#"{"svgnote": 1, "elements": [{"type": "polyline", "stroke": "#000",
+                    "fill": "#0000", "width": 1, "points": [[0, 0, 1]]}]}"#
+            ),
+            Err(JsonError::Invalid("points", _))
+        );
+    }
+
+    #[test]
+    fn schema() {
+        let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+        assert_eq!(schema["properties"]["svgnote"]["const"], json!(1));
+    }
+}
@@ -0,0 +1,218 @@
+//! Exporting the layers of documents separately, e.g. to continue a sketch
+//! in a painting program with its layers intact, see
+//! [`Document::export_layers`].
+
+use crate::elements::{Element, Layer};
+use crate::Document;
+
+/// The name of the layers holding the elements outside of layers.
+pub const UNLAYERED: &str = "Elements";
+
+/// Renders documents to PNG, implemented by the host with whatever renderer
+/// it uses, as the library cannot rasterize.
+pub trait Rasterizer {
+    /// The page of the document `svg` as PNG of `width` by `height` pixels.
+    fn rasterize(&self, svg: &str, width: u32, height: u32) -> Vec<u8>;
+}
+
+impl Document {
+    /// The top level layers, bottom first. Elements outside of layers are
+    /// put into a layer called [`UNLAYERED`] per run between layers, so the
+    /// order of the elements is kept.
+    pub fn top_level_layers(&self) -> Vec<Layer> {
+        let mut layers: Vec<Layer> = vec![];
+        let mut unlayered = false;
+        for element in &self.elements {
+            match element {
+                Element::Layer(layer) => {
+                    layers.push(layer.clone());
+                    unlayered = false;
+                }
+                element => {
+                    if !unlayered {
+                        layers.push(Layer::new(UNLAYERED));
+                        unlayered = true;
+                    }
+                    if let Some(layer) = layers.last_mut() {
+                        layer.elements.push(element.clone());
+                    }
+                }
+            }
+        }
+        layers
+    }
+
+    /// A document per [top level layer](Document::top_level_layers) with
+    /// its name, bottom first. Each has the whole page, so they line up
+    /// when opened as layers.
+    pub fn export_layers(&self) -> Vec<(String, String)> {
+        self.top_level_layers()
+            .into_iter()
+            .map(|layer| (layer.name.clone(), self.with_layer(layer).to_string()))
+            .collect()
+    }
+
+    /// The document with only `layer`, keeping the symbols and assets it
+    /// may use.
+    fn with_layer(&self, layer: Layer) -> Document {
+        Document {
+            elements: vec![Element::Layer(layer)],
+            symbols: self.symbols.clone(),
+            assets: self.assets.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// The document as OpenRaster image, which painting programs like GIMP
+    /// and Krita open with the layers intact, with the page `pixels` wide
+    /// and high.
+    ///
+    /// Each [top level layer](Document::top_level_layers) is rasterized on
+    /// its own, with its opacity and visibility as attributes of the
+    /// OpenRaster layer.
+    #[cfg(feature = "openraster")]
+    pub fn to_openraster(
+        &self,
+        rasterizer: &dyn Rasterizer,
+        pixels: u32,
+    ) -> std::io::Result<Vec<u8>> {
+        openraster::write(self, rasterizer, pixels)
+    }
+}
+
+#[cfg(feature = "openraster")]
+mod openraster {
+    use std::io::{self, Cursor, Write};
+
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    use super::Rasterizer;
+    use crate::escape::escape;
+    use crate::Document;
+
+    /// The size of `Thumbnail/thumbnail.png` at most.
+    const THUMBNAIL: u32 = 256;
+
+    pub(super) fn write(
+        document: &Document,
+        rasterizer: &dyn Rasterizer,
+        pixels: u32,
+    ) -> io::Result<Vec<u8>> {
+        let to_io = |error| io::Error::new(io::ErrorKind::Other, error);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        // Has to be the first entry, uncompressed
+        zip.start_file("mimetype", stored).map_err(to_io)?;
+        zip.write_all(b"image/openraster")?;
+
+        let mut stack = vec![];
+        for (i, mut layer) in document.top_level_layers().into_iter().enumerate() {
+            let path = format!("data/layer{}.png", i);
+            stack.push(format!(
+                r#"<layer name="{}" src="{}" x="0" y="0" opacity="{}" visibility="{}"/>"#,
+                escape(&layer.name),
+                path,
+                layer.opacity,
+                if layer.properties.hidden {
+                    "hidden"
+                } else {
+                    "visible"
+                }
+            ));
+            layer.opacity = 1.;
+            layer.properties.hidden = false;
+            let svg = document.with_layer(layer).to_string();
+            zip.start_file(path, stored).map_err(to_io)?;
+            zip.write_all(&rasterizer.rasterize(&svg, pixels, pixels))?;
+        }
+        // The topmost layer comes first
+        stack.reverse();
+        zip.start_file("stack.xml", FileOptions::default())
+            .map_err(to_io)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8"?><image version="0.0.5" w="{0}" h="{0}"><stack>{1}</stack></image>"#,
+            pixels,
+            stack.concat()
+        )?;
+
+        let svg = document.to_string();
+        zip.start_file("mergedimage.png", stored).map_err(to_io)?;
+        zip.write_all(&rasterizer.rasterize(&svg, pixels, pixels))?;
+        let thumbnail = pixels.min(THUMBNAIL);
+        zip.start_file("Thumbnail/thumbnail.png", stored)
+            .map_err(to_io)?;
+        zip.write_all(&rasterizer.rasterize(&svg, thumbnail, thumbnail))?;
+        Ok(zip.finish().map_err(to_io)?.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Properties};
+    use crate::layered::UNLAYERED;
+    use crate::Document;
+
+    #[test]
+    fn layers() {
+        let dot = |x| {
+            Element::Ellipse(Ellipse {
+                position: (x, 0.),
+                stroke: Color::rgb(0, 0, 0),
+                fill: Color::rgb(0, 0, 0),
+                width: 1.,
+                radius: 5.,
+                properties: Properties::default(),
+            })
+        };
+        let mut sketch = Layer::new("sketch & notes");
+        sketch.elements = vec![dot(10.)];
+        sketch.opacity = 0.5;
+        let doc = Document {
+            elements: vec![dot(0.), Element::Layer(sketch), dot(20.), dot(30.)],
+            ..Default::default()
+        };
+
+        let layers = doc.export_layers();
+        let names: Vec<_> = layers.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec![UNLAYERED, "sketch & notes", UNLAYERED]);
+        let last = Document::from_str(&layers[2].1).unwrap();
+        assert_eq!(last.render_items().len(), 2);
+
+        #[cfg(feature = "openraster")]
+        {
+            use std::io::{Cursor, Read};
+
+            use crate::layered::Rasterizer;
+
+            struct Size;
+            impl Rasterizer for Size {
+                fn rasterize(&self, _: &str, width: u32, height: u32) -> Vec<u8> {
+                    format!("{}x{}", width, height).into_bytes()
+                }
+            }
+            let ora = doc.to_openraster(&Size, 1000).unwrap();
+            let mut archive = zip::ZipArchive::new(Cursor::new(ora)).unwrap();
+            assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+            let mut stack = String::new();
+            archive
+                .by_name("stack.xml")
+                .unwrap()
+                .read_to_string(&mut stack)
+                .unwrap();
+            assert!(stack.contains(r#"name="sketch &amp; notes" src="data/layer1.png""#));
+            assert!(stack.find("layer2.png") < stack.find("layer0.png"));
+            let mut thumbnail = String::new();
+            archive
+                .by_name("Thumbnail/thumbnail.png")
+                .unwrap()
+                .read_to_string(&mut thumbnail)
+                .unwrap();
+            assert_eq!(thumbnail, "256x256");
+        }
+    }
+}
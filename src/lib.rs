@@ -1,27 +1,552 @@
 #![feature(assert_matches, const_fn_floating_point_arithmetic)]
+#![cfg_attr(test, feature(test))]
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
 use std::fmt::Display;
+use std::hash::Hasher;
+use std::io;
 use std::str::FromStr;
 
 use indoc::writedoc;
+use svg::node::element::tag;
+use svg::node::Node;
 use thiserror::Error;
 
+#[cfg(feature = "recognition")]
+use crate::analysis::Paragraph;
+use crate::assets::Assets;
+use crate::colors::{ColorMap, ColorParseError};
+use crate::elements::clip;
+use crate::elements::hash::Fnv1a;
+use crate::elements::style::{self, StyleSheet};
+use crate::elements::Dimension;
 use crate::elements::Element;
+use crate::elements::Ellipse;
+use crate::elements::EllipseBuilder;
+use crate::elements::FromAttributes;
+use crate::elements::Layer;
+use crate::elements::Line;
+use crate::elements::LineBuilder;
+use crate::elements::Ngon;
+use crate::elements::NgonBuilder;
+use crate::elements::Output;
+use crate::elements::Properties;
+use crate::elements::StoredBounds;
+use crate::elements::Symbols;
+use crate::geometry::BoundingBox;
+use crate::ids::ElementId;
+use crate::metadata::{Metadata, Target};
+use crate::options::ParseOptions;
+use crate::options::SerializeOptions;
+use crate::playback::{Granularity, Step};
+use crate::render::RenderItem;
+#[cfg(feature = "tessellation")]
+use crate::render::VertexBuffers;
+use crate::repair::Repair;
+use crate::ruling::Ruling;
+use crate::scalar::Scalar;
+use crate::snap::{PointIndex, Snap};
+use crate::transform::Transform;
+use crate::xml::Event;
 
+#[cfg(test)]
+extern crate test;
+
+#[macro_use]
+mod macros;
+
+pub mod align;
+#[cfg(feature = "recognition")]
+pub mod analysis;
+pub mod archive;
+pub mod assets;
+#[cfg(feature = "async")]
+mod async_io;
+mod base64;
+pub mod batch;
+pub mod calibration;
+pub mod checksum;
 pub mod colors;
+pub mod compat;
+pub mod csv;
+pub mod editor;
 pub mod elements;
+mod escape;
+pub mod figure;
+pub mod fonts;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod geometry;
+pub mod ids;
+pub mod import;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod layered;
+pub mod metadata;
+#[cfg(feature = "notebook")]
+pub mod notebook;
+pub mod ops;
+pub mod optimize;
+pub mod options;
+pub mod playback;
+pub mod presentation;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod region;
+pub mod render;
+pub mod repair;
+pub mod ruling;
+pub mod save;
+pub mod scalar;
+pub mod segment;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod snap;
+pub mod stats;
+pub mod template;
+pub mod transform;
+#[cfg(feature = "will")]
+pub mod will;
+mod xml;
+
+/// The user units per millimeter, the page is `2000` units or `100mm` wide.
+const UNITS_PER_MM: Scalar = 20.;
 
 pub fn elems_eq<T: PartialEq>(a: &[T], b: &[T]) -> bool {
     a.len() == b.len() && a.iter().zip(b).filter(|&(a, b)| a == b).count() == a.len()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone)]
 pub struct Document {
     pub elements: Vec<Element>,
+    pub metadata: Metadata,
+    /// Elements placed by [`Instance`](elements::Instance)s, stored once in
+    /// `<defs>`.
+    pub symbols: Symbols,
+    /// Binary data shown by [`FileRef`](elements::FileRef)s or used by the
+    /// host, see [`assets`].
+    pub assets: Assets,
+}
+
+impl Document {
+    /// All elements that would be rendered, with layers flattened.
+    pub fn visible_elements(&self) -> Vec<&Element> {
+        self.render_items()
+            .into_iter()
+            .map(|item| item.element)
+            .collect()
+    }
+
+    /// All elements that would be rendered, with the opacity, transforms and
+    /// clips of the layers they are in resolved.
+    ///
+    /// Instances are replaced by the elements of their symbol.
+    pub fn render_items(&self) -> Vec<RenderItem> {
+        render::render_items_with(&self.elements, &self.symbols, None)
+    }
+
+    /// Like [`Document::render_items`], but only those that may be inside
+    /// `viewport`, going by the stored bounds of elements and layers where
+    /// they have them, see [`SerializeOptions::bounding_boxes`].
+    pub fn render_items_in(&self, viewport: BoundingBox) -> Vec<RenderItem> {
+        render::render_items_with(&self.elements, &self.symbols, Some(viewport))
+    }
+
+    /// The visible geometry as flat buffers, e.g. for uploading to a GPU.
+    ///
+    /// The [`DrawRange`](render::DrawRange)s reference the elements by their
+    /// index in [`Document::render_items`].
+    #[cfg(feature = "tessellation")]
+    pub fn to_vertex_buffers(&self) -> VertexBuffers {
+        render::vertex_buffers(&self.render_items())
+    }
+
+    /// The points of all visible pen strokes as CSV, in document coordinates,
+    /// see [`csv::HEADER`].
+    ///
+    /// The strokes are identified by their index in
+    /// [`Document::render_items`].
+    pub fn to_csv(&self) -> String {
+        csv::document(&self.render_items())
+    }
+
+    /// The visible elements in the order they were drawn, with the delays
+    /// between them, e.g. for replaying the notes.
+    pub fn playback(&self, granularity: Granularity) -> impl Iterator<Item = Step> + '_ {
+        playback::steps(&self.render_items(), granularity)
+    }
+
+    /// The visible elements with a point drawn in `start..end`, in
+    /// milliseconds since the Unix epoch.
+    ///
+    /// Only pen strokes have timestamps, other elements are never included.
+    pub fn elements_in_time_range(&self, start: u64, end: u64) -> Vec<&Element> {
+        self.render_items()
+            .into_iter()
+            .map(|item| item.element)
+            .filter(|element| match element {
+                Element::Line(line) => line.drawn_between(start, end),
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// Cuts the pen strokes to the points drawn in `start..end`, removing
+    /// the ones drawn entirely outside of it.
+    ///
+    /// Elements without timestamps are kept.
+    pub fn retain_time_range(&mut self, start: u64, end: u64) {
+        playback::retain_time_range(&mut self.elements, start, end);
+    }
+
+    /// The visible element closest to `point` (in document coordinates), and
+    /// its distance, with layers flattened.
+    pub fn nearest_element(&self, point: (Scalar, Scalar)) -> Option<(&Element, Scalar)> {
+        self.render_items()
+            .into_iter()
+            .map(|item| {
+                (
+                    item.element,
+                    item.element.distance_with(item.transform, point),
+                )
+            })
+            .fold(None, |nearest, (element, distance)| match nearest {
+                Some((_, d)) if d <= distance => nearest,
+                _ => Some((element, distance)),
+            })
+    }
+
+    /// An index over the anchor points of all visible elements, for snapping.
+    ///
+    /// The [`Anchor`](snap::Anchor)s reference the elements by their index in
+    /// [`Document::render_items`].
+    pub fn snap_index(&self) -> PointIndex {
+        PointIndex::from_items(&self.render_items())
+    }
+
+    /// Snaps the element at the index in [`Document::render_items`], moved
+    /// by `transform` in document coordinates, onto the
+    /// [guides](Metadata::guides) and the edges and centers of the other
+    /// elements at most `radius` away, see [`snap::snap_bounds`]. Ephemeral
+    /// elements are not snapped onto.
+    ///
+    /// Returns `transform` followed by the snapping translation, and the
+    /// snaps made.
+    pub fn snap_transform(
+        &self,
+        item: usize,
+        transform: Transform,
+        radius: Scalar,
+    ) -> (Transform, Vec<Snap>) {
+        let items = self.render_items();
+        let bounds = items
+            .get(item)
+            .and_then(RenderItem::bounds)
+            .and_then(|bounds| render::transform_bounds(bounds, transform));
+        let bounds = match bounds {
+            Some(bounds) => bounds,
+            None => return (transform, vec![]),
+        };
+        let others: Vec<_> = items
+            .iter()
+            .enumerate()
+            .filter(|&(i, other)| i != item && !other.element.is_ephemeral())
+            .filter_map(|(i, other)| Some((i, other.bounds()?)))
+            .collect();
+        let ((x, y), snaps) = snap::snap_bounds(bounds, &self.metadata.guides, &others, radius);
+        (Transform::translate(x, y) * transform, snaps)
+    }
+
+    /// Moves the ends of the [`Dimension`]s attached to other elements to
+    /// the anchors they are attached to, returning the number of dimensions
+    /// changed, e.g. after editing.
+    ///
+    /// Ends attached to missing or hidden elements are left where they are.
+    pub fn update_dimensions(&mut self) -> usize {
+        let anchors: HashMap<ElementId, Vec<(Scalar, Scalar)>> = self
+            .render_items()
+            .iter()
+            .filter_map(|item| {
+                let id = item.element.properties().id.clone()?;
+                let anchors = snap::anchors(item.element)
+                    .into_iter()
+                    .map(|point| item.transform.apply(point))
+                    .collect();
+                Some((id, anchors))
+            })
+            .collect();
+        let mut changed = 0;
+        for (element, transform) in render::render_items_mut(&mut self.elements) {
+            let dimension = match element {
+                Element::Dimension(dimension) => dimension,
+                _ => continue,
+            };
+            let to_local = match (transform * dimension.properties.transform).invert() {
+                Some(t) => t,
+                None => continue,
+            };
+            let Dimension {
+                start,
+                end,
+                attachments,
+                ..
+            } = dimension;
+            let mut moved = false;
+            for (point, attachment) in vec![start, end].into_iter().zip(attachments.iter()) {
+                let anchor = attachment
+                    .as_ref()
+                    .and_then(|a| anchors.get(&a.id)?.get(a.anchor));
+                if let Some(&anchor) = anchor {
+                    let anchor = to_local.apply(anchor);
+                    moved |= *point != anchor;
+                    *point = anchor;
+                }
+            }
+            changed += moved as usize;
+        }
+        changed
+    }
+
+    /// A [`Dimension`] from `start` to `end` in the [`Metadata::unit`],
+    /// millimeters if there is none.
+    pub fn dimension(&self, start: (Scalar, Scalar), end: (Scalar, Scalar)) -> Dimension {
+        Dimension::new(start, end, self.metadata.unit.unwrap_or_default())
+    }
+
+    /// [`Line::builder`] with the [`Metadata::calibration`], if there is
+    /// one.
+    pub fn line_builder(&self) -> LineBuilder {
+        match self.metadata.calibration {
+            Some(calibration) => Line::builder().calibration(calibration),
+            None => Line::builder(),
+        }
+    }
+
+    /// [`Ngon::builder`] on the [`Metadata::grid`], if there is one.
+    pub fn ngon_builder(&self) -> NgonBuilder {
+        match self.metadata.grid {
+            Some(grid) => Ngon::builder().grid(grid),
+            None => Ngon::builder(),
+        }
+    }
+
+    /// [`Ellipse::builder`] on the [`Metadata::grid`], if there is one.
+    pub fn ellipse_builder(&self) -> EllipseBuilder {
+        match self.metadata.grid {
+            Some(grid) => Ellipse::builder().grid(grid),
+            None => Ellipse::builder(),
+        }
+    }
+
+    /// The handwriting clustered into paragraphs, lines and words.
+    ///
+    /// The [`Word`](analysis::Word)s reference the strokes by their index in
+    /// [`Document::render_items`].
+    #[cfg(feature = "recognition")]
+    pub fn cluster_ink(&self) -> Vec<Paragraph> {
+        analysis::cluster(&self.render_items())
+    }
+
+    /// Rewraps a paragraph from [`Document::cluster_ink`] to `width` by moving
+    /// its words, see [`analysis::reflow`].
+    #[cfg(feature = "recognition")]
+    pub fn reflow_paragraph(&mut self, paragraph: &Paragraph, width: Scalar) {
+        let offsets = analysis::reflow(paragraph, width);
+        let mut items = render::render_items_mut(&mut self.elements);
+        for (item, (x, y)) in offsets {
+            if let Some((element, parent)) = items.get_mut(item) {
+                // The offset is in document coordinates, so it has to be
+                // mapped into the coordinates of the layers
+                if let Some(inverse) = parent.invert() {
+                    (inverse * Transform::translate(x, y) * *parent).apply_to(element);
+                }
+            }
+        }
+    }
+
+    /// Replaces the colors of all elements, including those of symbols,
+    /// according to `map`.
+    pub fn remap_colors(&mut self, map: &ColorMap) {
+        for element in self.all_elements_mut() {
+            element.remap_colors(map);
+        }
+    }
+
+    /// Snaps the coordinates and sizes of all elements, including those of
+    /// symbols, to fixed-point, see [`Element::snap_to_fixed_point`].
+    pub fn snap_to_fixed_point(&mut self) {
+        for element in self.all_elements_mut() {
+            element.snap_to_fixed_point();
+        }
+    }
+
+    /// Forgets the [`StoredBounds`](elements::StoredBounds) of all elements,
+    /// e.g. after changing their geometry, so they are computed anew.
+    pub fn clear_stored_bounds(&mut self) {
+        for element in self.all_elements_mut() {
+            element.clear_stored_bounds();
+        }
+    }
+
+    /// The top level elements and those of the symbols.
+    fn all_elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.elements
+            .iter_mut()
+            .chain(self.symbols.values_mut().flatten())
+    }
+
+    /// A copy of this document with the colors replaced according to `map`,
+    /// e.g. for rendering or exporting with
+    /// [`ColorMap::dark_background`] without changing the notes.
+    pub fn with_colors(&self, map: &ColorMap) -> Document {
+        let mut document = self.clone();
+        document.remap_colors(map);
+        document
+    }
+
+    /// The font families of all texts with the characters set in them, e.g.
+    /// for subsetting the fonts passed to [`SerializeOptions::fonts`].
+    pub fn fonts_used(&self) -> BTreeMap<String, BTreeSet<char>> {
+        fonts::fonts_used(&self.elements)
+    }
+
+    /// The links of all elements, including hidden ones.
+    pub fn links(&self) -> Vec<&Target> {
+        self.elements.iter().flat_map(Element::links).collect()
+    }
+
+    /// How many [`FileRef`](elements::FileRef)s, including those in layers
+    /// and symbols, show each asset, by its id.
+    ///
+    /// Assets that are only used by the host, e.g. recordings, are not
+    /// counted.
+    pub fn asset_references(&self) -> BTreeMap<String, usize> {
+        let mut references = BTreeMap::new();
+        assets::count_references(&self.elements, &mut references);
+        for elements in self.symbols.values() {
+            assets::count_references(elements, &mut references);
+        }
+        references
+    }
+
+    /// Removes the assets no [`FileRef`](elements::FileRef) shows, see
+    /// [`Document::asset_references`], returning their ids.
+    pub fn remove_unused_assets(&mut self) -> Vec<String> {
+        let references = self.asset_references();
+        self.assets.retain_referenced(&references)
+    }
+
+    /// Prefixes the relative paths of all [`FileRef`](elements::FileRef)s
+    /// with `base_dir`, see [`FileRef::relink`](elements::FileRef::relink).
+    pub fn relink(&mut self, base_dir: &str) {
+        fn relink(elements: &mut [Element], base_dir: &str) {
+            for element in elements {
+                match element {
+                    Element::FileRef(file) => file.relink(base_dir),
+                    Element::Layer(layer) => relink(&mut layer.elements, base_dir),
+                    _ => {}
+                }
+            }
+        }
+        relink(&mut self.elements, base_dir);
+    }
+
+    /// The area covered by all visible elements in document coordinates,
+    /// including their stroke widths, but not by ephemeral ones. `None` if
+    /// nothing is visible.
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.render_items()
+            .iter()
+            .filter(|item| !item.element.is_ephemeral())
+            .filter_map(RenderItem::bounds)
+            .reduce(BoundingBox::union)
+    }
+
+    /// The document sized to its content with `padding` around it, e.g. for
+    /// sharing a small sketch from a big page. The elements are written
+    /// unchanged, only the `viewBox` and size differ. The whole page if
+    /// nothing is visible.
+    pub fn export_cropped(&self, padding: Scalar) -> String {
+        self.to_string_with(&SerializeOptions {
+            view_box: self.bounds().map(|bounds| bounds.expand(padding)),
+            ..Default::default()
+        })
+    }
+
+    /// A copy of this document with all hidden elements and layers removed,
+    /// e.g. for exporting without a "solutions" layer.
+    pub fn without_hidden(&self) -> Document {
+        let mut document = self.clone();
+        elements::layer::retain_visible(&mut document.elements);
+        document
+    }
 }
 
 impl PartialEq for Document {
     fn eq(&self, other: &Self) -> bool {
         elems_eq(&self.elements, &other.elements)
+            && self.metadata == other.metadata
+            && self.symbols == other.symbols
+            && self.assets == other.assets
+    }
+}
+
+impl Document {
+    /// A hash over the elements that is the same on all platforms, see
+    /// [`Element::fingerprint`].
+    pub fn fingerprint(&self) -> u64 {
+        elements::hash::fingerprint(&self.elements)
+    }
+
+    /// A hash over everything stored of the document, the same however it
+    /// was formatted when read and on all platforms, e.g. for a sync client
+    /// to skip uploading unchanged pages.
+    ///
+    /// Unlike the [fingerprint](Document::fingerprint) it includes the
+    /// metadata, symbols, assets, timestamps and links, and changes with any
+    /// change of the geometry.
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = Fnv1a::default();
+        elements::hash::hash_stored(&self.elements, &mut hasher);
+        // Small, so it is hashed as written
+        let metadata = svg::node::element::Element::from(&self.metadata).to_string();
+        hasher.write(metadata.as_bytes());
+        hasher.write_usize(self.symbols.len());
+        for (name, elements) in &self.symbols {
+            hasher.write_usize(name.len());
+            hasher.write(name.as_bytes());
+            elements::hash::hash_stored(elements, &mut hasher);
+        }
+        for (id, asset) in self.assets.iter() {
+            for bytes in [id.as_bytes(), asset.mime.as_bytes(), &asset.data].iter() {
+                hasher.write_usize(bytes.len());
+                hasher.write(bytes);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Whether the documents are equal, with the geometry of the elements
+    /// differing by at most `epsilon`, see [`Element::approx_eq`].
+    pub fn approx_eq(&self, other: &Document, epsilon: Scalar) -> bool {
+        self.elements.len() == other.elements.len()
+            && self
+                .elements
+                .iter()
+                .zip(&other.elements)
+                .all(|(a, b)| a.approx_eq(b, epsilon))
+            && self.metadata == other.metadata
+            && self.assets == other.assets
+            && self.symbols.len() == other.symbols.len()
+            && self
+                .symbols
+                .iter()
+                .zip(&other.symbols)
+                .all(|((name, a), (other_name, b))| {
+                    name == other_name
+                        && a.len() == b.len()
+                        && a.iter().zip(b).all(|(a, b)| a.approx_eq(b, epsilon))
+                })
     }
 }
 
@@ -29,76 +554,784 @@ impl PartialEq for Document {
 pub enum DocumentError {
     #[error("Invalid {0}: `{1}`")]
     InvalidAttribute(String, String),
+    #[error("Invalid {0}: {1}")]
+    InvalidColor(String, #[source] ColorParseError),
     #[error("Missing {0}")]
     MissingAttribute(String),
     #[error("Invalid Point: `{0}`")]
     InvalidPoint(String),
     #[error("Unknown Event")]
     UnknownEvent,
+    #[error("Checksum does not match, the file is corrupted")]
+    Corrupted,
+    /// The file is not well-formed XML, or not text in a supported encoding.
+    #[error("Malformed document: {0}")]
+    Parse(#[from] io::Error),
+    /// An error in the tag at `location`.
+    #[error("{error} at {location}")]
+    At {
+        location: Location,
+        error: Box<DocumentError>,
+    },
+}
+
+impl DocumentError {
+    /// Where in the document the error is, if known.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            DocumentError::At { location, .. } => Some(location),
+            _ => None,
+        }
+    }
+
+    /// The error without its [`location`](Self::location).
+    pub fn without_location(self) -> DocumentError {
+        match self {
+            DocumentError::At { error, .. } => *error,
+            error => error,
+        }
+    }
+
+    fn at(self, location: Location) -> DocumentError {
+        match self {
+            DocumentError::At { .. } => self,
+            error => DocumentError::At {
+                location,
+                error: Box::new(error),
+            },
+        }
+    }
+}
+
+/// The position of a tag in a document, see [`DocumentError::location`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Location {
+    /// The byte offset of the `<`.
+    pub offset: usize,
+    /// Starting at `1`.
+    pub line: usize,
+    /// In characters, starting at `1`.
+    pub column: usize,
+    /// The index of the tag among the start and empty tags, `0` being the
+    /// `<svg>`.
+    pub element: usize,
+    pub id: Option<String>,
+}
+
+impl Location {
+    fn new(s: &str, offset: usize, element: usize, id: Option<String>) -> Self {
+        let before = s.get(..offset).unwrap_or(s);
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+            element,
+            id,
+        }
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (element {}",
+            self.line, self.column, self.element
+        )?;
+        if let Some(id) = &self.id {
+            write!(f, ", id `{}`", id)?;
+        }
+        f.write_str(")")
+    }
 }
 
 impl FromStr for Document {
     type Err = DocumentError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        return Ok(Self {
-            elements: svg::read(s)
-                .unwrap()
-                .map(|e| Element::from_event(e))
-                .filter(|e| match e {
-                    Err(DocumentError::UnknownEvent) => false,
-                    _ => true,
-                })
-                .collect::<Result<_, _>>()?,
+        Document::parse(s, &ParseOptions::default()).map(|(document, _)| document)
+    }
+}
+
+/// Decodes UTF-8, with or without BOM, and UTF-16, detected by its BOM or
+/// the leading `<`.
+impl TryFrom<&[u8]> for Document {
+    type Error = DocumentError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        Document::from_str(&decode(bytes)?)
+    }
+}
+
+fn decode(bytes: &[u8]) -> Result<String, DocumentError> {
+    let utf8 = |bytes: &[u8]| String::from_utf8(bytes.to_vec()).map_err(invalid_data);
+    let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        if bytes.len() % 2 != 0 {
+            return Err(invalid_data("UTF-16 with an odd number of bytes"));
+        }
+        let units: Vec<u16> = bytes.chunks(2).map(|c| from_bytes([c[0], c[1]])).collect();
+        String::from_utf16(&units).map_err(invalid_data)
+    };
+    match bytes {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => utf8(rest),
+        [0xFF, 0xFE, rest @ ..] => utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => utf16(rest, u16::from_be_bytes),
+        [b'<', 0, ..] => utf16(bytes, u16::from_le_bytes),
+        [0, b'<', ..] => utf16(bytes, u16::from_be_bytes),
+        _ => utf8(bytes),
+    }
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> DocumentError {
+    DocumentError::Parse(io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Fails if the stored bounds of `element` do not contain its geometry, or
+/// replaces them with the actual ones in `repair` mode.
+fn verify_bounds(
+    element: &mut Element,
+    repair: bool,
+    repairs: &mut Vec<Repair>,
+) -> Result<(), DocumentError> {
+    // Geometry written with a `precision` moves by up to half a unit
+    const TOLERANCE: Scalar = 0.5;
+    let stored = match element.properties().bounds.0 {
+        Some(stored) => stored,
+        None => return Ok(()),
+    };
+    let actual = render::element_bounds(element, Transform::IDENTITY);
+    if actual.map_or(true, |actual| stored.expand(TOLERANCE).encloses(&actual)) {
+        return Ok(());
+    }
+    if !repair {
+        return Err(DocumentError::InvalidAttribute(
+            "svgnote:bbox".to_owned(),
+            StoredBounds::to_attribute(stored),
+        ));
+    }
+    element.properties_mut().bounds = StoredBounds(actual);
+    repairs.push(Repair::StaleBoundingBox);
+    Ok(())
+}
+
+impl Document {
+    /// Parses a document as configured in `options`, returning the repairs
+    /// and defaults that were applied.
+    pub fn from_str_with(
+        s: &str,
+        options: &ParseOptions,
+    ) -> Result<(Self, Vec<Repair>), DocumentError> {
+        Document::parse(s, options)
+    }
+
+    /// Parses a damaged document, fixing what can be fixed and leaving out
+    /// elements that cannot, returning what was changed.
+    ///
+    /// Only errors in elements are repaired, e.g. a layer with an invalid
+    /// opacity still fails. A checksum that does not match is reported.
+    pub fn repair(s: &str) -> Result<(Self, Vec<Repair>), DocumentError> {
+        let options = ParseOptions {
+            repair: true,
+            verify_checksum: true,
+            ..Default::default()
+        };
+        Document::parse(s, &options)
+    }
+
+    fn parse(s: &str, options: &ParseOptions) -> Result<(Self, Vec<Repair>), DocumentError> {
+        let repair = options.repair;
+        let mut repairs = vec![];
+        // Clip paths and classes may be used before they are defined
+        let mut classes = style::ClassReader::default();
+        let clips = clip::clip_paths(xml::read(s)?.inspect(|event| classes.read(event)))?;
+        let classes = classes.finish();
+        let mut symbols = Symbols::new();
+        // The symbol whose elements are currently parsed
+        let mut symbol: Option<elements::symbol::Pending> = None;
+        let mut assets = Assets::new();
+        // The number of open tags
+        let mut depth: usize = 0;
+        // The depth of the children of the outermost `<defs>`
+        let mut defs = None;
+        // Open `<g>` tags, with the transform of groups that are not layers
+        let mut groups: Vec<Result<Layer, Transform>> = vec![];
+        // Depth inside `<defs>`, `<clipPath>` and `<symbol>`, which are not
+        // rendered
+        let mut definitions = 0;
+        // An element with children, e.g. a `<title>`, that is not closed yet
+        let mut open: Option<Element> = None;
+        // Whether `open` is a dimension, whose generated children are skipped
+        let mut in_dimension = false;
+        // The `<title>` or `<desc>` currently parsed
+        let mut text: Option<&str> = None;
+        let mut checksum = None;
+        let mut elements = vec![];
+        // The prefix of our namespace, if it is not `svgnote:`
+        let mut prefix = None;
+        // The namespaces declared on the `<svg>` tag, by prefix
+        let mut namespaces = HashMap::new();
+        let mut metadata = metadata::Reader::default();
+        let mut background = None;
+        let mut ruling = None;
+        // Whether inside `<metadata>`
+        let mut in_metadata = false;
+        // The number of start and empty tags so far
+        let mut tags = 0;
+        for event in xml::read(s)? {
+            if let Some(pending) = symbol.take() {
+                if pending.depth == depth {
+                    pending.finish(&mut symbols);
+                } else {
+                    symbol = Some(pending);
+                }
+            }
+            // The offset of the `<` of the tag, its index and id, for errors
+            let location = match &event {
+                Event::Tag(name, kind, attributes) if *kind != tag::Type::End => {
+                    tags += 1;
+                    let offset = (name.as_ptr() as usize).saturating_sub(s.as_ptr() as usize + 1);
+                    Some((
+                        offset,
+                        tags - 1,
+                        attributes.get("id").map(|id| id.to_string()),
+                    ))
+                }
+                _ => None,
+            };
+            let at = |error: DocumentError| match &location {
+                Some((offset, element, id)) => {
+                    error.at(Location::new(s, *offset, *element, id.clone()))
+                }
+                None => error,
+            };
+            let event = options.normalize_namespace(event, &mut prefix);
+            if let Event::Tag(_, tag::Type::End, _) = &event {
+                depth = depth.saturating_sub(1);
+            }
+            // The depth of the tag
+            let level = depth;
+            if let Event::Tag(name, kind, attributes) = &event {
+                // `<symbol>`s, and elements with an id directly in `<defs>`
+                let definition = defs == Some(level)
+                    && !matches!(
+                        *name,
+                        tag::Definitions | tag::ClipPath | tag::Style | tag::Symbol
+                    );
+                let asset = attributes.contains_key("svgnote:asset");
+                match (attributes.get("id"), kind) {
+                    (Some(id), tag::Type::Start) | (Some(id), tag::Type::Empty)
+                        if symbol.is_none() && ((*name == tag::Symbol && !asset) || definition) =>
+                    {
+                        symbol = Some(elements::symbol::Pending {
+                            id: id.to_string(),
+                            elements: vec![],
+                            definition,
+                            depth: level,
+                            definitions: definitions + usize::from(*name == tag::Symbol),
+                            groups: groups.len(),
+                        });
+                    }
+                    _ => {}
+                }
+                match (*name, kind) {
+                    (tag::Definitions, tag::Type::Start) if defs.is_none() => {
+                        defs = Some(level + 1)
+                    }
+                    (tag::Definitions, tag::Type::End) if defs == Some(level + 1) => defs = None,
+                    _ => {}
+                }
+                if *kind == tag::Type::Start {
+                    depth += 1;
+                }
+            }
+            // The groups of the symbol currently parsed, or of the document
+            let outer = symbol.as_ref().map_or(0, |symbol| symbol.groups);
+            // Whether inside `<defs>`, `<clipPath>` or `<symbol>` not parsed
+            let hidden = definitions > symbol.as_ref().map_or(0, |symbol| symbol.definitions);
+            // Transforms of plain groups are applied to the contained elements
+            let transform = groups
+                .iter()
+                .skip(outer)
+                .rev()
+                .take_while(|g| g.is_err())
+                .filter_map(|g| g.as_ref().err())
+                .fold(Transform::IDENTITY, |outer, &t| t * outer);
+            let clip = match &event {
+                Event::Tag(_, _, attributes) => attributes
+                    .get("clip-path")
+                    .and_then(|value| clip::url_id(value))
+                    .and_then(|id| clips.get(id))
+                    .copied(),
+                _ => None,
+            };
+            let element = match event {
+                Event::Error(error) => return Err(invalid_data(error)),
+                Event::Tag(tag::Definitions, tag::Type::Start, _)
+                | Event::Tag(tag::ClipPath, tag::Type::Start, _)
+                | Event::Tag(tag::Symbol, tag::Type::Start, _) => {
+                    definitions += 1;
+                    continue;
+                }
+                Event::Tag(tag::Definitions, tag::Type::End, _)
+                | Event::Tag(tag::ClipPath, tag::Type::End, _)
+                | Event::Tag(tag::Symbol, tag::Type::End, _) => {
+                    definitions -= 1;
+                    continue;
+                }
+                // The images of assets, see `assets::symbols`
+                Event::Tag(tag::Image, _, attributes)
+                    if hidden && attributes.contains_key("svgnote:asset") =>
+                {
+                    assets::read(&mut assets, &attributes, "svgnote:asset").map_err(at)?;
+                    continue;
+                }
+                _ if hidden => continue,
+                Event::Tag("metadata", tag::Type::Start, _) => {
+                    in_metadata = true;
+                    continue;
+                }
+                Event::Tag("metadata", tag::Type::End, _) => {
+                    in_metadata = false;
+                    continue;
+                }
+                Event::Tag("svgnote:asset", _, attributes) if in_metadata => {
+                    assets::read(&mut assets, &attributes, "id").map_err(at)?;
+                    continue;
+                }
+                Event::Tag(name, kind, attributes) if in_metadata => {
+                    metadata.read(name, kind, &attributes).map_err(at)?;
+                    continue;
+                }
+                _ if in_metadata => continue,
+                Event::Tag(tag::SVG, tag::Type::Start, attributes) => {
+                    namespaces = attributes
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            let prefix = name.strip_prefix("xmlns:")?;
+                            Some((prefix.to_owned(), value.to_string()))
+                        })
+                        .collect();
+                    checksum = attributes
+                        .get("svgnote:checksum")
+                        .map(|value| {
+                            u32::from_str_radix(value, 16).map_err(|_| {
+                                DocumentError::InvalidAttribute(
+                                    "svgnote:checksum".to_owned(),
+                                    value.to_string(),
+                                )
+                            })
+                        })
+                        .transpose()
+                        .map_err(at)?;
+                    continue;
+                }
+                Event::Tag(tag::Rectangle, _, attributes)
+                    if attributes.get("svgnote:tool").map(|tool| &**tool) == Some("background") =>
+                {
+                    background = Some(metadata::background(&attributes).map_err(at)?);
+                    continue;
+                }
+                Event::Tag(tag::Rectangle, _, attributes)
+                    if attributes.get("svgnote:tool").map(|tool| &**tool) == Some("ruling") =>
+                {
+                    ruling = Some(Ruling::from_attributes(&attributes).map_err(at)?);
+                    continue;
+                }
+                Event::Tag(name @ tag::Title, tag::Type::Start, _)
+                | Event::Tag(name @ tag::Description, tag::Type::Start, _) => {
+                    text = Some(name);
+                    continue;
+                }
+                Event::Tag(tag::Title, tag::Type::End, _)
+                | Event::Tag(tag::Description, tag::Type::End, _) => {
+                    text = None;
+                    continue;
+                }
+                // The lines of a `<text>`, see `Event::Text`
+                Event::Tag("tspan", _, _) => continue,
+                Event::Text(content) => {
+                    if let (None, Some(Element::Text(element))) = (text, &mut open) {
+                        element.push_line(escape::unescape(content.trim()));
+                        continue;
+                    }
+                    let properties = match (&mut open, groups.last_mut()) {
+                        (Some(element), _) => element.properties_mut(),
+                        (None, Some(Ok(layer))) => &mut layer.properties,
+                        _ => continue,
+                    };
+                    let content = Some(escape::unescape(content.trim()));
+                    match text {
+                        Some(tag::Title) => properties.title = content,
+                        Some(tag::Description) => properties.description = content,
+                        _ => {}
+                    }
+                    continue;
+                }
+                Event::Tag(tag::Group, tag::Type::End, _) if in_dimension => {
+                    in_dimension = false;
+                    match open.take() {
+                        Some(element) => element,
+                        None => continue,
+                    }
+                }
+                _ if in_dimension => continue,
+                Event::Tag(tag::Group, tag::Type::Start, attributes)
+                    if attributes.get("svgnote:tool").map(|tool| &**tool) != Some("dimension") =>
+                {
+                    groups.push(match attributes.get("svgnote:tool") {
+                        Some(tool) if &**tool == "layer" => {
+                            let mut layer = Layer::from_attributes(attributes).map_err(at)?;
+                            layer.properties.clip = clip;
+                            layer.properties.transform = transform * layer.properties.transform;
+                            Ok(layer)
+                        }
+                        _ => Err(Properties::from_attributes(&attributes)
+                            .map_err(at)?
+                            .transform),
+                    });
+                    continue;
+                }
+                Event::Tag(tag::Group, tag::Type::End, _) => match groups.pop() {
+                    Some(Ok(layer)) => Element::Layer(layer),
+                    _ => continue,
+                },
+                Event::Tag(tag::Group, tag::Type::Empty, attributes) => {
+                    match attributes.get("svgnote:tool") {
+                        Some(tool) if &**tool == "layer" => {
+                            let mut layer = Layer::from_attributes(attributes).map_err(at)?;
+                            layer.properties.clip = clip;
+                            layer.properties.transform = transform * layer.properties.transform;
+                            Element::Layer(layer)
+                        }
+                        _ => continue,
+                    }
+                }
+                Event::Tag(_, tag::Type::End, _) => match open.take() {
+                    Some(element) => element,
+                    None => continue,
+                },
+                e => {
+                    let start = matches!(e, Event::Tag(_, tag::Type::Start, _));
+                    let e = match e {
+                        Event::Tag(name, kind, mut attributes) => {
+                            style::apply(&classes, &mut attributes);
+                            options.apply_defaults(name, &mut attributes, &mut repairs);
+                            // Embedded images are shown from the assets
+                            if name == tag::Use {
+                                if let Some(id) = attributes.get("svgnote:asset") {
+                                    let reference = assets::reference(id);
+                                    attributes.insert("href", reference);
+                                }
+                            }
+                            if name == tag::Image {
+                                let asset = assets::read(&mut assets, &attributes, "svgnote:asset")
+                                    .map_err(at)?;
+                                if let Some(id) = asset {
+                                    attributes.insert("href", assets::reference(&id));
+                                    attributes.remove("xlink:href");
+                                }
+                            }
+                            Event::Tag(name, kind, attributes)
+                        }
+                        e => e,
+                    };
+                    let custom = match (&options.elements, &e) {
+                        (Some(registry), Event::Tag(_, _, attributes)) => registry
+                            .parse(attributes)
+                            .map(|custom| custom.map(Element::Custom)),
+                        _ => None,
+                    };
+                    let element = match custom {
+                        Some(element) => element,
+                        None if repair => repair::element(e, &mut repairs),
+                        None => match e {
+                            Event::Tag(name, _, attributes) => Element::from_tag(name, attributes),
+                            _ => Err(DocumentError::UnknownEvent),
+                        },
+                    };
+                    let mut element = match element {
+                        Err(DocumentError::UnknownEvent) => continue,
+                        e => e.map_err(at)?,
+                    };
+                    element.set_clip(clip);
+                    let properties = element.properties_mut();
+                    properties.transform = transform * properties.transform;
+                    properties.foreign.declare(&namespaces);
+                    if start {
+                        in_dimension = matches!(element, Element::Dimension(_));
+                        open = Some(element);
+                        continue;
+                    }
+                    element
+                }
+            };
+            let mut element = element;
+            if options.verify_bounding_boxes {
+                verify_bounds(&mut element, repair, &mut repairs).map_err(at)?;
+            }
+            match groups
+                .iter_mut()
+                .skip(outer)
+                .rev()
+                .find_map(|g| g.as_mut().ok())
+            {
+                Some(layer) => layer.elements.push(element),
+                None => match &mut symbol {
+                    Some(symbol) => symbol.elements.push(element),
+                    None => elements.push(element),
+                },
+            }
+        }
+        if let Some(pending) = symbol {
+            pending.finish(&mut symbols);
+        }
+        let mut document = Self {
+            elements,
+            metadata: Metadata {
+                background,
+                ruling,
+                ..metadata.finish()
+            },
+            symbols,
+            assets,
+        };
+        match checksum {
+            Some(checksum) if options.verify_checksum && checksum != document.checksum() => {
+                if !repair {
+                    return Err(DocumentError::Corrupted);
+                }
+                repairs.push(Repair::ChecksumMismatch);
+            }
+            _ => {}
+        }
+        if options.fixed_point {
+            document.snap_to_fixed_point();
+        }
+        if let Some(ids) = &options.ids {
+            document.assign_ids(&**ids);
+        }
+        Ok((document, repairs))
+    }
+}
+
+impl Document {
+    /// Only the fonts, outlines, style classes, derived attributes and view
+    /// box are taken from `options`.
+    fn to_svg(&self, options: &SerializeOptions) -> svg::Document {
+        self.to_svg_with(options, |doc, output| {
+            self.elements
+                .iter()
+                .fold(doc, |doc, element| element.add_to_with(doc, output))
+        })
+    }
+
+    /// Like [`Document::to_svg`], but with the elements added by `add`, e.g.
+    /// in groups.
+    pub(crate) fn to_svg_with(
+        &self,
+        options: &SerializeOptions,
+        add: impl FnOnce(svg::Document, &Output) -> svg::Document,
+    ) -> svg::Document {
+        let doc = match options.view_box {
+            Some(view_box) => svg::Document::new()
+                .set(
+                    "viewBox",
+                    format!(
+                        "{} {} {} {}",
+                        view_box.min.0,
+                        view_box.min.1,
+                        view_box.width(),
+                        view_box.height()
+                    ),
+                )
+                .set("width", format!("{}mm", view_box.width() / UNITS_PER_MM))
+                .set("height", format!("{}mm", view_box.height() / UNITS_PER_MM)),
+            None => svg::Document::new()
+                .set("viewBox", (0, 0, 2000, 2000))
+                .set("width", "100mm")
+                .set("height", "100mm"),
+        };
+        let mut doc = doc
+            .set("xmlns:svgnote", options::NAMESPACE)
+            .set("svgnote:version", "0.1");
+        if let Some(style) = fonts::style(&self.elements, &options.fonts) {
+            doc = doc.add(style);
+        }
+        let output = Output {
+            loader: options.outline_text.as_deref(),
+            styles: if options.style_classes {
+                Some(StyleSheet::new(&self.elements))
+            } else {
+                None
+            },
+            omit_points: options.omit_derived,
+            bounding_boxes: options.bounding_boxes,
+            assets: Some(&self.assets),
+        };
+        if let Some(style) = output.styles.as_ref().and_then(StyleSheet::to_style) {
+            doc = doc.add(style);
+        }
+        // Assets shown by images are written with them
+        let references = self.asset_references();
+        let unreferenced = assets::unreferenced(&self.assets, &references);
+        if !self.metadata.is_empty() || !unreferenced.is_empty() {
+            let mut metadata = svg::node::element::Element::from(&self.metadata);
+            for asset in unreferenced {
+                metadata.append(asset);
+            }
+            doc = doc.add(metadata);
+        }
+        let mut clips = clip::clips(&self.elements);
+        for clip in self
+            .symbols
+            .values()
+            .flat_map(|elements| clip::clips(elements))
+        {
+            if !clips.contains(&clip) {
+                clips.push(clip);
+            }
+        }
+        let view_box = options.view_box.unwrap_or(BoundingBox {
+            min: (0., 0.),
+            max: (2000., 2000.),
         });
+        if let Some(background) = self.metadata.background_rect(view_box) {
+            doc = doc.add(background);
+        }
+        if let Some(ruling) = self.metadata.ruling {
+            let mut rect = ruling.rect(view_box);
+            ruling.assign_to(&mut rect);
+            doc = doc
+                .add(svg::node::element::Definitions::new().add(ruling.pattern()))
+                .add(rect);
+        }
+        let asset_symbols = assets::symbols(&self.assets, &references);
+        if !clips.is_empty() || !self.symbols.is_empty() || !asset_symbols.is_empty() {
+            let defs = clips
+                .iter()
+                .fold(svg::node::element::Definitions::new(), |defs, clip| {
+                    defs.add(svg::node::element::ClipPath::from(clip))
+                });
+            doc = doc.add(
+                asset_symbols
+                    .into_iter()
+                    .chain(elements::symbol::to_symbols(&self.symbols, &output))
+                    .fold(defs, |defs, symbol| defs.add(symbol)),
+            );
+        }
+        add(doc, &output)
+    }
+
+    /// Serializes the document as configured in `options`.
+    pub fn to_string_with(&self, options: &SerializeOptions) -> String {
+        let rounded;
+        let modified =
+            options.precision.is_some() || options.fixed_point || options.non_scaling_strokes;
+        let document = if modified {
+            rounded = {
+                let mut document = self.clone();
+                for element in document.all_elements_mut() {
+                    if let Some(decimals) = options.precision {
+                        element.round(decimals);
+                    }
+                    if options.fixed_point {
+                        element.snap_to_fixed_point();
+                    }
+                    if options.non_scaling_strokes {
+                        element.properties_mut().non_scaling_stroke = true;
+                    }
+                }
+                document
+            };
+            &rounded
+        } else {
+            self
+        };
+        let doc = document.to_svg(options);
+        document.finish_svg(doc, options)
+    }
+
+    /// Adds the checksum to `doc` if enabled in `options` and writes it.
+    pub(crate) fn finish_svg(&self, mut doc: svg::Document, options: &SerializeOptions) -> String {
+        if options.checksum {
+            doc = doc.set("svgnote:checksum", format!("{:08x}", self.checksum()));
+        }
+        if options.pretty {
+            let mut string = String::new();
+            write_svg(&mut string, &doc).expect("writing to a String does not fail");
+            string
+        } else {
+            // Text is escaped, so this only matches between tags
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?>{}"#,
+                doc.to_string().replace(">\n<", "><")
+            )
+        }
+    }
+
+    /// The checksum of the serialization without the checksum.
+    fn checksum(&self) -> u32 {
+        let mut string = String::new();
+        write_svg(&mut string, &self.to_svg(&SerializeOptions::default()))
+            .expect("writing to a String does not fail");
+        checksum::crc32(string.as_bytes())
     }
 }
 
+fn write_svg(f: &mut impl std::fmt::Write, doc: &svg::Document) -> std::fmt::Result {
+    writedoc!(
+        f,
+        r##"
+        <?xml version="1.0" encoding="UTF-8" standalone="no"?>
+        <!-- Created with SVGNotes (https://github.com/ModProg/SVGNotesLib) -->
+
+        {}"##,
+        doc
+    )
+}
+
 impl Display for Document {
+    /// Writes with the default [`SerializeOptions`].
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut doc = svg::Document::new()
-            .set("viewBox", (0, 0, 2000, 2000))
-            .set("width", "100mm")
-            .set("height", "100mm")
-            .set("xmlns:svgnote", "https://github.com/ModProg/SVGNotesLib")
-            .set("svgnote:version", "0.1");
-        doc = self
-            .elements
-            .iter()
-            .fold(doc, |doc, element| match element {
-                Element::Line(e) => doc.add::<svg::node::element::Path>(e.into()),
-                Element::Ngon(e) => doc.add::<svg::node::element::Polygon>(e.into()),
-                Element::Ellipse(e) => doc.add::<svg::node::element::Ellipse>(e.into()),
-                Element::Polyline(e) => doc.add::<svg::node::element::Polyline>(e.into()),
-            });
-        writedoc!(
-            f,
-            r##"
-            <?xml version="1.0" encoding="UTF-8" standalone="no"?>
-            <!-- Created with SVGNotes (https://github.com/ModProg/SVGNotesLib) -->
-
-            {}"##,
-            doc
-        )
+        f.write_str(&self.to_string_with(&SerializeOptions::default()))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::f32::consts::PI;
+    use std::convert::TryFrom;
     use std::fs::File;
     use std::io::Write;
     use std::path::Path;
+    use std::rc::Rc;
     use std::str::FromStr;
 
+    use indoc::indoc;
+
     use crate::colors::Color;
+    use crate::elements::BlendMode;
+    use crate::elements::Clip;
+    use crate::elements::Layer;
     use crate::elements::Line;
     use crate::elements::LinePoint;
     use crate::elements::Ngon;
     use crate::elements::Polyline;
     use crate::elements::PolylinePoint;
+    use crate::elements::Properties;
     use crate::elements::{Element, Ellipse};
-    use crate::Document;
+    use crate::elements::{Font, Text};
+    use crate::fonts::{FontFile, FontFormat, FontLoader};
+    use crate::geometry::BoundingBox;
+    use crate::metadata::{Bookmark, Heading, Placeholder, Target};
+    use crate::ops::{Op, Style};
+    use crate::options::ParseOptions;
+    use crate::options::SerializeOptions;
+    use crate::repair::Repair;
+    use crate::scalar::consts::PI;
+    use crate::scalar::Scalar;
+    use crate::transform::Transform;
+    use crate::{Document, DocumentError, Location};
 
     #[test]
     fn parse() {
@@ -177,7 +1410,8 @@ mod tests {
                     g: 0,
                     b: 0,
                     a: 0xFF
-                }
+                },
+                ..
             })
         if *width == 4.0  &&  elems_eq(&points, &[(10.0,10.0,1.0),(60.0,30.0,4.0),(50.0, 60.0,3.0),(90.0, 10.0,2.0)].iter().map(|&(x,y,w)| LinePoint( x,y,w)).collect::<Vec<_>>()));
         assert_matches!(
@@ -192,16 +1426,19 @@ mod tests {
                     r: 0,
                     g: 0,
                     b: 0,
-                    a: 0
+                    a: 0xFF
                 },
                 stroke: Color {
                     r: 0xFF,
                     g: 0,
                     b: 0,
                     a: 0xFF
-                }
+                },
+                properties,
+                ..
             })
             if
+                properties.fill_opacity == 0. &&
                 *position == (65.0,65.0) &&
                 *width == 3.0 &&
                 *angle == 0.0 &&
@@ -218,16 +1455,19 @@ mod tests {
                     r: 0xFF,
                     g: 0xFF,
                     b: 0,
-                    a: 0x55
+                    a: 0xFF
                 },
                 stroke: Color {
                     r: 0xFF,
                     g: 0xFF,
                     b: 0,
                     a: 0xFF
-                }
+                },
+                properties,
+                ..
             })
             if
+                (properties.fill_opacity - 1. / 3.).abs() < 1e-6 &&
                 *position == (65.0,65.0) &&
                 *width == 2.0 &&
                 *radius == 10.0
@@ -239,6 +1479,256 @@ mod tests {
         }
     }
 
+    #[test]
+    fn line_from_path() {
+        let doc = Document::from_str(
+            r##"<svg><path d="M0,0 l1,2 C 0,0 0,0 3,3" stroke="#000000" svgnote:width="1" svgnote:tool="pen"/></svg>"##,
+        )
+        .unwrap();
+        assert_matches!(
+            &doc.elements[..],
+            [Element::Line(Line { points, .. })]
+                if *points == vec![LinePoint(0., 0., 1.), LinePoint(1., 2., 1.), LinePoint(3., 3., 1.)]
+        );
+        assert_matches!(
+            Document::from_str(r##"<svg><path d="M0,0 X" stroke="#000000" svgnote:width="1" svgnote:tool="pen"/></svg>"##)
+                .map_err(DocumentError::without_location),
+            Err(DocumentError::InvalidAttribute(attribute, _)) if attribute == "d"
+        );
+    }
+
+    #[test]
+    fn error_location() {
+        let svg = indoc! {r##"
+            <svg>
+            <path d="M0,0 L1,1" stroke="#000000" svgnote:width="1" svgnote:tool="pen"/>
+              <g svgnote:tool="layer" svgnote:name="ink">
+                <path id="broken" d="M0,0 X" stroke="#000000" svgnote:width="1" svgnote:tool="pen"/>
+              </g>
+            </svg>
+        "##};
+        let error = Document::from_str(svg).unwrap_err();
+        assert_eq!(
+            error.location(),
+            Some(&Location {
+                offset: svg.find("<path id").unwrap(),
+                line: 4,
+                column: 5,
+                element: 3,
+                id: Some("broken".to_owned()),
+            })
+        );
+        assert!(error
+            .to_string()
+            .ends_with("at line 4, column 5 (element 3, id `broken`)"));
+        assert_matches!(
+            error.without_location(),
+            DocumentError::InvalidAttribute(attribute, _) if attribute == "d"
+        );
+    }
+
+    #[test]
+    fn foreign_polygon() {
+        let ngon = Ngon {
+            position: (10., 20.),
+            stroke: Color::rgb(0xFF, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 2.,
+            angle: 0.3,
+            n: 5,
+            radius: 7.,
+            properties: Properties::default(),
+        };
+        let points: Vec<String> = ngon
+            .points()
+            .iter()
+            .rev()
+            .map(|(x, y)| format!("{},{}", x, y))
+            .collect();
+        let doc = Document::from_str(&format!(
+            r##"<svg>
+            <polygon points="{}" stroke="#FF0000" fill="#000000" fill-opacity="0" stroke-width="2"/>
+            <polygon points="0,0 1,0 1,5" stroke="#FF0000" fill="#000000" stroke-width="2"/>
+            </svg>"##,
+            points.join(" ")
+        ))
+        .unwrap();
+        match &doc.elements[..] {
+            [Element::Ngon(parsed), Element::Polyline(polyline)] => {
+                assert_eq!(parsed.n, 5);
+                assert!((parsed.angle - 0.3).abs() < 1e-4);
+                assert!((parsed.radius - 7.).abs() < 1e-4);
+                assert_eq!(
+                    polyline.points,
+                    vec![
+                        PolylinePoint(0., 0.),
+                        PolylinePoint(1., 0.),
+                        PolylinePoint(1., 5.)
+                    ]
+                );
+                assert!(polyline.closed);
+            }
+            elements => panic!("{:?}", elements),
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let svg = r##"<svg>
+            <ellipse cx="1" cy="2" rx="3" ry="3"/>
+            <path d="M0,0 L1,1" svgnote:tool="pen" stroke="#FF0000"/>
+            </svg>"##;
+        assert_matches!(
+            Document::from_str(svg).map_err(DocumentError::without_location),
+            Err(DocumentError::MissingAttribute(_))
+        );
+        let (doc, defaults) = Document::from_str_with(svg, &ParseOptions::svg_defaults()).unwrap();
+        assert_matches!(
+            &doc.elements[..],
+            [Element::Ellipse(Ellipse { stroke, fill, .. }), Element::Line(Line { color, .. })]
+                if *stroke == Color::rgba(0, 0, 0, 0)
+                    && *fill == Color::rgb(0, 0, 0)
+                    && *color == Color::rgb(0xFF, 0, 0)
+        );
+        let defaults: Vec<_> = defaults
+            .iter()
+            .map(|d| match d {
+                Repair::Default(attribute) => attribute.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(
+            defaults,
+            vec!["stroke", "stroke-width", "fill", "svgnote:width"]
+        );
+    }
+
+    #[test]
+    fn namespace() {
+        let doc = Document::from_str(
+            r##"<svg xmlns:notes="https://github.com/ModProg/SVGNotesLib">
+            <path d="M0,0 L1,1" stroke="#FF0000" notes:width="1" notes:tool="pen" notes:hidden="true"/>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_matches!(&doc.elements[..], [Element::Line(line)] if line.properties.hidden);
+    }
+
+    #[test]
+    fn fixed_point() {
+        let line = |x| {
+            Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(x, 1. / 3., 0.3)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })
+        };
+        let mut a = Document {
+            elements: vec![line(0.1 + 0.2)],
+            ..Default::default()
+        };
+        let mut b = Document {
+            elements: vec![line(0.3)],
+            ..Default::default()
+        };
+        a.snap_to_fixed_point();
+        b.snap_to_fixed_point();
+        assert_eq!(a.elements, b.elements);
+        assert_matches!(
+            &a.elements[..],
+            [Element::Line(Line { points, .. })] if *points == vec![LinePoint(77. / 256., 85. / 256., 0.3)]
+        );
+
+        let options = SerializeOptions {
+            fixed_point: true,
+            ..Default::default()
+        };
+        let parsed = Document::from_str(&b.to_string_with(&options)).unwrap();
+        assert_eq!(parsed.elements, b.elements);
+        let options = ParseOptions {
+            fixed_point: true,
+            ..Default::default()
+        };
+        let unsnapped = Document {
+            elements: vec![line(0.3)],
+            ..Default::default()
+        };
+        let (parsed, _) = Document::from_str_with(&unsnapped.to_string(), &options).unwrap();
+        assert_eq!(parsed.elements, b.elements);
+    }
+
+    #[cfg(feature = "f64")]
+    #[test]
+    fn f64_precision() {
+        let doc = Document {
+            elements: vec![Element::Polyline(Polyline {
+                stroke: Color::rgb(0, 0, 0),
+                fill: Color::rgba(0, 0, 0, 0),
+                width: 1.,
+                points: vec![PolylinePoint(1e9 + 0.25, -1e9 - 0.5)],
+                widths: vec![],
+                closed: false,
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        let parsed = Document::from_str(&doc.to_string()).unwrap();
+        assert_eq!(parsed.elements, doc.elements);
+    }
+
+    #[test]
+    fn serialize_options() {
+        let doc = Document {
+            elements: vec![Element::Polyline(Polyline {
+                stroke: Color::rgb(0, 0, 0),
+                fill: Color::rgba(0, 0, 0, 0),
+                width: 1.23456,
+                points: vec![PolylinePoint(0.001, 1.23456), PolylinePoint(2., 3.)],
+                widths: vec![],
+                closed: false,
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        let options = SerializeOptions {
+            precision: Some(2),
+            pretty: false,
+            ..Default::default()
+        };
+        let compact = doc.to_string_with(&options);
+        assert!(!compact.contains(">\n<"));
+        let parsed = Document::from_str(&compact).unwrap();
+        assert_matches!(
+            &parsed.elements[..],
+            [Element::Polyline(Polyline { points, width, .. })]
+                if (width - 1.23).abs() < 1e-6
+                    && format!("{:?}", points) == "[(0,1.23), (2,3)]"
+        );
+
+        let unchecked = doc.to_string_with(&SerializeOptions {
+            checksum: false,
+            ..Default::default()
+        });
+        assert!(!unchecked.contains("svgnote:checksum"));
+
+        let checked = doc.to_string_with(&SerializeOptions {
+            checksum: true,
+            ..Default::default()
+        });
+        let tampered = checked.replace("1.23456", "1.5");
+        let verify = ParseOptions {
+            verify_checksum: true,
+            ..Default::default()
+        };
+        assert_matches!(
+            Document::from_str_with(&tampered, &verify),
+            Err(DocumentError::Corrupted)
+        );
+        assert!(Document::from_str(&tampered).is_ok());
+    }
+
     #[test]
     fn encoding() {
         let doc = Document {
@@ -251,6 +1741,8 @@ mod tests {
                         LinePoint(2., 10., 1.),
                         LinePoint(1.2313, 10.213, 1.123),
                     ],
+                    timestamps: vec![],
+                    properties: Properties::default(),
                 }),
                 Element::Ngon(Ngon {
                     position: (3.0, 12.0),
@@ -260,6 +1752,7 @@ mod tests {
                     angle: PI / 4.0,
                     n: 9,
                     radius: 5.,
+                    properties: Properties::default(),
                 }),
                 Element::Ellipse(Ellipse {
                     position: (10., 2.),
@@ -267,6 +1760,7 @@ mod tests {
                     fill: Color::rgba(0xFF, 0, 0, 0xFE),
                     width: 13.2,
                     radius: 12.2,
+                    properties: Properties::default(),
                 }),
                 Element::Polyline(Polyline {
                     stroke: Color::rgb(0xFF, 0xFF, 12),
@@ -277,8 +1771,12 @@ mod tests {
                         PolylinePoint(1.1, 3.2),
                         PolylinePoint(51.2, 13.9),
                     ],
+                    widths: vec![],
+                    closed: false,
+                    properties: Properties::default(),
                 }),
             ],
+            ..Default::default()
         };
 
         let string = doc.to_string();
@@ -290,6 +1788,615 @@ mod tests {
         assert_eq!(doc, parsed);
     }
 
+    #[test]
+    fn timestamps() {
+        let doc = Document {
+            elements: vec![Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+                timestamps: vec![1_634_371_200_000, 1_634_371_200_016],
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+
+        let overflow = r##"<svg><path d="M0,0 L1,1" stroke="#000000" svgnote:width="1" svgnote:tool="pen" svgnote:points="0,0,1 1,1,1" svgnote:start="18446744073709551615" svgnote:times="0 1"/></svg>"##;
+        assert_matches!(
+            Document::from_str(overflow).map_err(DocumentError::without_location),
+            Err(DocumentError::InvalidAttribute(name, _)) if name == "svgnote:times"
+        );
+    }
+
+    #[test]
+    fn text() {
+        let text = Element::Text(Text {
+            position: (10., 20.),
+            content: "a < b\nsecond line".to_owned(),
+            color: Color::rgb(0, 0, 0xFF),
+            font: Font {
+                family: "Comic Neue".to_owned(),
+                size: 12.,
+            },
+            properties: Properties::default(),
+        });
+        let doc = Document {
+            elements: vec![text],
+            ..Default::default()
+        };
+        let string = doc.to_string();
+        assert!(string.contains("font-family=\"'Comic Neue', sans-serif\""));
+        assert_eq!(Document::from_str(&string).unwrap().elements, doc.elements);
+
+        let foreign = Document::from_str(
+            r##"<svg><text x="1" y="2" fill="#000000" font-family="Arial, sans-serif" font-size="8px">Hello</text></svg>"##,
+        )
+        .unwrap();
+        assert_matches!(
+            &foreign.elements[..],
+            [Element::Text(Text { content, font, .. })]
+                if content == "Hello" && font.family == "Arial" && (font.size - 8.).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn embedded_fonts() {
+        let doc = Document {
+            elements: vec![Element::Text(Text {
+                position: (0., 10.),
+                content: "aba\nc".to_owned(),
+                color: Color::rgb(0, 0, 0),
+                font: Font {
+                    family: "Hand".to_owned(),
+                    size: 10.,
+                },
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        let used = doc.fonts_used();
+        assert_eq!(used.len(), 1);
+        assert_eq!(used["Hand"].iter().collect::<String>(), "abc");
+
+        let mut options = SerializeOptions::default();
+        options.fonts.insert(
+            "Hand".to_owned(),
+            FontFile {
+                data: b"font".to_vec(),
+                format: FontFormat::Woff2,
+            },
+        );
+        options.fonts.insert(
+            "Unused".to_owned(),
+            FontFile {
+                data: vec![],
+                format: FontFormat::TrueType,
+            },
+        );
+        let string = doc.to_string_with(&options);
+        assert_eq!(string.matches("@font-face").count(), 1);
+        assert!(string.contains("url(data:font/woff2;base64,Zm9udA==)"));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+
+    #[test]
+    fn outlined_text() {
+        /// A box per character, only for "Hand".
+        struct Boxes;
+
+        impl FontLoader for Boxes {
+            fn outline(&self, text: &str, font: &Font, (x, y): (Scalar, Scalar)) -> Option<String> {
+                if font.family != "Hand" {
+                    return None;
+                }
+                let boxes: Vec<_> = (0..text.chars().count())
+                    .map(|i| format!("M{},{} h5 v-8 h-5 z", x + i as Scalar * 6., y))
+                    .collect();
+                Some(boxes.join(" "))
+            }
+        }
+
+        let text = |family: &str| {
+            Element::Text(Text {
+                position: (0., 10.),
+                content: "\"a\" & 'b'\n<c>".to_owned(),
+                color: Color::rgb(0, 0, 0),
+                font: Font {
+                    family: family.to_owned(),
+                    size: 10.,
+                },
+                properties: Properties::default(),
+            })
+        };
+        let mut layer = Layer::new("notes");
+        layer.elements.push(text("Hand"));
+        let doc = Document {
+            elements: vec![Element::Layer(layer), text("Missing")],
+            ..Default::default()
+        };
+        let options = SerializeOptions {
+            outline_text: Some(Rc::new(Boxes)),
+            ..Default::default()
+        };
+        let string = doc.to_string_with(&options);
+        assert!(string.contains("M0,10 h5 v-8 h-5 z"));
+        assert_eq!(string.matches("<text").count(), 1);
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+
+    #[test]
+    fn encodings() {
+        let doc = Document {
+            elements: vec![Element::Text(Text {
+                position: (0., 20.),
+                content: "Grüße".to_owned(),
+                color: Color::rgb(0, 0, 0),
+                font: Font::default(),
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        let string = doc.to_string();
+        let utf8_bom = [&[0xEF, 0xBB, 0xBF][..], string.as_bytes()].concat();
+        let utf16_le: Vec<u8> = string
+            .encode_utf16()
+            .flat_map(|u| u.to_le_bytes().to_vec())
+            .collect();
+        let utf16_be: Vec<u8> = string
+            .encode_utf16()
+            .flat_map(|u| u.to_be_bytes().to_vec())
+            .collect();
+        let utf16_be_bom = [&[0xFE, 0xFF][..], &utf16_be].concat();
+        let encodings: [&[u8]; 4] = [string.as_bytes(), &utf8_bom, &utf16_le, &utf16_be_bom];
+        for &bytes in &encodings {
+            assert_eq!(Document::try_from(bytes).unwrap(), doc);
+        }
+
+        assert_matches!(
+            Document::try_from(&[0xEF, 0xBB, 0xBF, 0xFF][..]),
+            Err(DocumentError::Parse(_))
+        );
+        assert_matches!(
+            Document::from_str(r#"<svg><polyline points="0,0"#),
+            Err(DocumentError::Parse(_))
+        );
+    }
+
+    #[test]
+    fn export_cropped() {
+        let mut doc = Document::default();
+        assert_eq!(doc.bounds(), None);
+        assert!(doc
+            .export_cropped(10.)
+            .contains(r#"viewBox="0 0 2000 2000""#));
+
+        let mut ellipse = Ellipse::builder()
+            .position(500., 300.)
+            .radius(40.)
+            .width(20.)
+            .build()
+            .unwrap();
+        ellipse.properties.transform = Transform::translate(100., 0.);
+        doc.elements.push(Element::Ellipse(ellipse));
+        let string = doc.export_cropped(10.);
+        assert!(string.contains(r#"viewBox="540 240 120 120""#));
+        assert!(string.contains(r#"width="6mm""#));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+
+    #[test]
+    fn bounding_boxes() {
+        let ellipse = |x, y| {
+            Element::Ellipse(
+                Ellipse::builder()
+                    .position(x, y)
+                    .radius(40.)
+                    .width(20.)
+                    .build()
+                    .unwrap(),
+            )
+        };
+        let mut layer = Layer::new("far");
+        layer.elements.push(ellipse(1500., 1500.));
+        let doc = Document {
+            elements: vec![ellipse(100., 100.), Element::Layer(layer)],
+            ..Default::default()
+        };
+        let string = doc.to_string_with(&SerializeOptions {
+            bounding_boxes: true,
+            ..Default::default()
+        });
+        assert!(string.contains(r#"svgnote:bbox="50 50 150 150""#));
+        assert!(!doc.to_string().contains("svgnote:bbox"));
+
+        let options = ParseOptions {
+            verify_bounding_boxes: true,
+            ..Default::default()
+        };
+        let (parsed, _) = Document::from_str_with(&string, &options).unwrap();
+        assert_ne!(parsed, doc);
+        assert!(parsed.elements[1].properties().bounds.0.is_some());
+        let mut cleared = parsed.clone();
+        cleared.clear_stored_bounds();
+        assert_eq!(cleared, doc);
+        // Stale once the geometry changes
+        let mut edited = parsed.clone();
+        let style = Style {
+            width: Some(1.),
+            ..Default::default()
+        };
+        edited
+            .apply(&Op::SetStyle {
+                path: vec![1, 0],
+                style,
+            })
+            .unwrap();
+        assert!(edited.elements[0].properties().bounds.0.is_some());
+        assert!(edited.elements[1].properties().bounds.0.is_none());
+        let viewport = BoundingBox {
+            min: (0., 0.),
+            max: (200., 200.),
+        };
+        assert_eq!(parsed.render_items_in(viewport).len(), 1);
+        assert_eq!(doc.render_items_in(viewport).len(), 1);
+
+        let stale = string.replace("50 50 150 150", "1000 1000 1100 1100");
+        assert_matches!(
+            Document::from_str_with(&stale, &options).map_err(DocumentError::without_location),
+            Err(DocumentError::InvalidAttribute(attribute, _)) if attribute == "svgnote:bbox"
+        );
+        // Trusted without verification
+        assert_eq!(
+            Document::from_str(&stale)
+                .unwrap()
+                .render_items_in(viewport)
+                .len(),
+            0
+        );
+        let (repaired, repairs) = Document::from_str_with(
+            &stale,
+            &ParseOptions {
+                repair: true,
+                ..options
+            },
+        )
+        .unwrap();
+        assert_matches!(&repairs[..], [Repair::StaleBoundingBox]);
+        assert_eq!(repaired.render_items_in(viewport).len(), 1);
+    }
+
+    #[test]
+    fn bookmarks() {
+        let mut doc = Document::default();
+        doc.metadata.bookmarks = vec![
+            Bookmark::new("Intro & \"Goals\"", (10., 20.)),
+            Bookmark::new("Summary", (0., 1500.5)),
+        ];
+        doc.metadata.outline.push(Heading::new(
+            "<Intro>",
+            Target::bookmark("Intro & \"Goals\""),
+        ));
+        let bounds = BoundingBox {
+            min: (0., 0.),
+            max: (10., 10.),
+        };
+        doc.metadata
+            .placeholders
+            .push(Placeholder::new("Q&A\nnotes", bounds));
+        let string = doc.to_string();
+        assert!(string.contains("<metadata>"));
+        let parsed = Document::from_str(&string).unwrap();
+        assert_eq!(parsed, doc);
+        assert_eq!(
+            parsed.metadata.bookmark("Summary").map(|b| b.position),
+            Some((0., 1500.5))
+        );
+    }
+
+    #[test]
+    fn title() {
+        let line = Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+            timestamps: vec![],
+            properties: Properties {
+                title: Some("Arrow <to> \"x\" & y".to_owned()),
+                author: Some("Ada".to_owned()),
+                ..Default::default()
+            },
+        });
+        let doc = Document {
+            elements: vec![
+                Element::Layer(Layer {
+                    name: "diagram".to_owned(),
+                    elements: vec![line.clone()],
+                    opacity: 1.0,
+                    blend: BlendMode::Normal,
+                    properties: Properties {
+                        title: Some("Diagram".to_owned()),
+                        description: Some("A line".to_owned()),
+                        ..Default::default()
+                    },
+                }),
+                line,
+            ],
+        };
+
+        let string = doc.to_string();
+        assert!(string.contains("<title>"));
+        assert!(string.contains("<desc>"));
+        assert!(string.contains(r#"svgnote:author="Ada""#));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+
+    #[test]
+    fn hidden_layer() {
+        let solution = Element::Ellipse(Ellipse {
+            position: (10., 2.),
+            stroke: Color::rgb(0, 0xFF, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 2.,
+            radius: 5.,
+            properties: Properties::default(),
+        });
+        let doc = Document {
+            elements: vec![
+                Element::Polyline(Polyline {
+                    stroke: Color::rgb(0, 0, 0),
+                    fill: Color::rgba(0, 0, 0, 0),
+                    width: 1.,
+                    points: vec![PolylinePoint(0.0, 0.0), PolylinePoint(1.0, 1.0)],
+                    widths: vec![],
+                    closed: false,
+                    properties: Properties {
+                        hidden: true,
+                        ..Default::default()
+                    },
+                }),
+                Element::Layer(Layer {
+                    name: "solutions".to_owned(),
+                    elements: vec![solution.clone()],
+                    opacity: 1.0,
+                    blend: BlendMode::Normal,
+                    properties: Properties {
+                        hidden: true,
+                        ..Default::default()
+                    },
+                }),
+            ],
+        };
+
+        let string = doc.to_string();
+        assert!(string.contains(r#"display="none""#));
+
+        let mut parsed = Document::from_str(&string).unwrap();
+        assert_eq!(doc, parsed);
+        assert!(parsed.visible_elements().is_empty());
+        assert!(parsed.without_hidden().elements.is_empty());
+
+        parsed.elements[1].set_hidden(false);
+        assert_eq!(parsed.visible_elements(), vec![&solution]);
+    }
+
+    #[test]
+    fn layer_opacity() {
+        let line = Element::Line(Line {
+            color: Color::rgb(0, 0, 0xFF),
+            width: 2.,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(4., 4., 1.)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        });
+        let mut inner = Layer::new("sketch");
+        inner.opacity = 0.5;
+        inner.elements.push(line.clone());
+        let mut outer = Layer::new("background");
+        outer.opacity = 0.5;
+        outer.elements.push(Element::Layer(inner));
+        let doc = Document {
+            elements: vec![Element::Layer(outer)],
+            ..Default::default()
+        };
+
+        let parsed = Document::from_str(&doc.to_string()).unwrap();
+        assert_eq!(doc, parsed);
+        let items = parsed.render_items();
+        assert_eq!(items.len(), 1);
+        assert_eq!((items[0].element, items[0].opacity), (&line, 0.25));
+        assert_eq!(
+            Color::rgb(0, 0, 0xFF).faded(0.25),
+            Color::rgba(0, 0, 0xFF, 63)
+        );
+    }
+
+    #[test]
+    fn clip() {
+        let mut ellipse = Element::Ellipse(Ellipse {
+            position: (10., 10.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0xFF, 0, 0),
+            width: 1.,
+            radius: 10.,
+            properties: Properties::default(),
+        });
+        ellipse.set_clip(Clip::rectangle((0., 0.), (10., 20.)));
+        let mut layer = Layer::new("cropped");
+        layer.elements.push(ellipse.clone());
+        layer.elements.push(ellipse);
+        let mut layer = Element::Layer(layer);
+        layer.set_clip(Clip::ellipse((5., 5.), (2., 3.5)));
+        let doc = Document {
+            elements: vec![layer],
+            ..Default::default()
+        };
+
+        let string = doc.to_string();
+        assert_eq!(string.matches("<clipPath").count(), 2);
+
+        assert_eq!(doc, Document::from_str(&string).unwrap());
+    }
+
+    #[test]
+    fn foreign_clip() {
+        let s = r##"
+<svg xmlns="http://www.w3.org/2000/svg">
+  <defs>
+    <clipPath id="round">
+      <circle cx="5" cy="6" r="4"/>
+    </clipPath>
+    <clipPath id="complex">
+      <path d="M 0,0 10,10 0,10 z"/>
+    </clipPath>
+    <clipPath id="corner">
+      <rect width="4" height="3"/>
+    </clipPath>
+  </defs>
+  <ellipse clip-path="url(#round)" fill="#000" stroke="#000" stroke-width="1" cx="5" cy="5" rx="5" ry="5"/>
+  <ellipse clip-path="url(#complex)" fill="#000" stroke="#000" stroke-width="1" cx="5" cy="5" rx="5" ry="5"/>
+  <ellipse clip-path="url(#corner)" fill="#000" stroke="#000" stroke-width="1" cx="5" cy="5" rx="5" ry="5"/>
+</svg>
+        "##;
+        let d = Document::from_str(s).unwrap();
+        assert_eq!(d.elements.len(), 3);
+        assert_eq!(
+            d.elements[0].clip(),
+            Some(Clip::ellipse((5., 6.), (4., 4.)))
+        );
+        assert_eq!(d.elements[1].clip(), None);
+        assert_eq!(
+            d.elements[2].clip(),
+            Some(Clip::rectangle((0., 0.), (4., 3.)))
+        );
+
+        let invalid = s.replace(r#"<rect width"#, r#"<rect x="left" width"#);
+        assert_matches!(
+            Document::from_str(&invalid).map_err(DocumentError::without_location),
+            Err(DocumentError::InvalidAttribute(name, _)) if name == "x"
+        );
+    }
+
+    #[test]
+    fn transform() {
+        let s = r##"
+<svg xmlns="http://www.w3.org/2000/svg">
+  <g transform="translate(10, 0)">
+    <g transform="scale(2)">
+      <ellipse transform="translate(1, 1)" fill="#000" stroke="#000" stroke-width="1" cx="5" cy="5" rx="5" ry="5"/>
+    </g>
+    <g svgnote:tool="layer" svgnote:name="layer" transform="translate(0, 10)">
+      <ellipse fill="#000" stroke="#000" stroke-width="1" cx="5" cy="5" rx="5" ry="5"/>
+    </g>
+  </g>
+</svg>
+        "##;
+        let d = Document::from_str(s).unwrap();
+        assert_eq!(d.elements.len(), 2);
+        assert_eq!(
+            d.elements[0].properties().transform,
+            Transform::new(2., 0., 0., 2., 12., 2.)
+        );
+        assert_eq!(
+            d.elements[1].properties().transform,
+            Transform::new(1., 0., 0., 1., 10., 10.)
+        );
+        match &d.elements[1] {
+            Element::Layer(layer) => {
+                assert!(layer.elements[0].properties().transform.is_identity())
+            }
+            e => panic!("expected a layer, got {:?}", e),
+        }
+
+        assert_eq!(d, Document::from_str(&d.to_string()).unwrap());
+    }
+
+    #[test]
+    fn content_hash() {
+        let line = Line::builder()
+            .color(Color::rgb(0, 0, 0))
+            .width(2.)
+            .timed_point(0., 0., 1., 1000)
+            .timed_point(10., 10., 1., 1100)
+            .build()
+            .unwrap();
+        let mut doc = Document {
+            elements: vec![Element::Line(line)],
+            ..Default::default()
+        };
+        let hash = doc.content_hash();
+        let compact = doc.to_string_with(&SerializeOptions {
+            pretty: false,
+            checksum: false,
+            ..Default::default()
+        });
+        assert_ne!(compact, doc.to_string());
+        let parsed = Document::from_str(&compact).unwrap();
+        assert_eq!(parsed.content_hash(), hash);
+
+        // The same however the opacity was written
+        let fill = Color::rgba(0xFF, 0, 0, 0x80);
+        let translucent = Document {
+            elements: vec![Element::Ellipse(Ellipse {
+                position: (0., 0.),
+                stroke: Color::rgb(0, 0, 0),
+                fill,
+                width: 1.,
+                radius: 1.,
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        }
+        .to_string();
+        let written = format!(r#"fill-opacity="{}""#, fill.opacity());
+        assert!(translucent.contains(&written));
+        let rewritten = translucent.replace(&written, r#"fill-opacity="0.502""#);
+        assert_eq!(
+            Document::from_str(&rewritten).unwrap().content_hash(),
+            Document::from_str(&translucent).unwrap().content_hash()
+        );
+
+        // Not part of the fingerprint
+        if let Element::Line(line) = &mut doc.elements[0] {
+            line.timestamps[1] = 1200;
+        }
+        assert_ne!(doc.content_hash(), hash);
+        doc.metadata
+            .bookmarks
+            .push(Bookmark::new("start", (0., 0.)));
+        assert_ne!(doc.content_hash(), hash);
+    }
+
+    #[test]
+    fn render_items() {
+        let clip = Clip::rectangle((0., 0.), (5., 5.));
+        let mut ellipse = Element::Ellipse(Ellipse {
+            position: (10., 10.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0xFF, 0, 0),
+            width: 1.,
+            radius: 10.,
+            properties: Properties::default(),
+        });
+        Transform::scale(2., 2.).apply_to(&mut ellipse);
+        let mut layer = Element::Layer(Layer::new("moved"));
+        if let Element::Layer(layer) = &mut layer {
+            layer.elements.push(ellipse.clone());
+        }
+        layer.set_clip(clip);
+        Transform::translate(10., 0.).apply_to(&mut layer);
+        let doc = Document {
+            elements: vec![layer],
+            ..Default::default()
+        };
+
+        let items = doc.render_items();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].element, &ellipse);
+        assert_eq!(items[0].transform.apply((1., 1.)), (12., 2.));
+        assert_eq!(items[0].clips, vec![(clip, Transform::translate(10., 0.))]);
+    }
+
     fn elems_eq<T: PartialEq>(a: &[T], b: &[T]) -> bool {
         a.len() == b.len() && a.iter().zip(b).filter(|&(a, b)| a == b).count() == a.len()
     }
@@ -0,0 +1,139 @@
+//! The [`document!`] macro, for fixtures and generated documents.
+
+/// Builds a [`Document`](crate::Document) from the calls to the element
+/// builders, e.g. [`Line::builder`](crate::elements::Line::builder).
+///
+/// The kinds are `line`, `ngon`, `ellipse`, `polyline`, `text` and `file`,
+/// and `layer(name)` containing other elements.
+///
+/// Panics if an element is invalid, see
+/// [`BuildError`](crate::elements::BuildError).
+///
+/// ```
+/// use svg_notes::colors::Color;
+/// use svg_notes::document;
+///
+/// let red = Color::rgb(0xFF, 0, 0);
+/// let doc = document! {
+///     line { color(red), point(0., 0., 1.), point(10., 5., 0.5) }
+///     layer("shapes") {
+///         ngon { position(20., 20.), radius(5.), n(6) }
+///         text { position(0., 40.), content("Hello") }
+///     }
+/// };
+/// assert_eq!(doc.elements.len(), 2);
+/// ```
+#[macro_export]
+macro_rules! document {
+    ($($elements:tt)*) => {
+        $crate::Document {
+            elements: $crate::__elements!([] $($elements)*),
+            ..::std::default::Default::default()
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __elements {
+    ([$($done:expr),*]) => {
+        vec![$($done),*]
+    };
+    ([$($done:expr),*] layer($name:expr) { $($elements:tt)* } $($rest:tt)*) => {
+        $crate::__elements!([$($done,)* $crate::elements::Element::Layer({
+            let mut layer = $crate::elements::Layer::new($name);
+            layer.elements = $crate::__elements!([] $($elements)*);
+            layer
+        })] $($rest)*)
+    };
+    (
+        [$($done:expr),*]
+        $kind:ident { $($method:ident($($arg:expr),*)),* $(,)? }
+        $($rest:tt)*
+    ) => {
+        $crate::__elements!(
+            [$($done,)* $crate::__element!($kind $(.$method($($arg),*))*)]
+            $($rest)*
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __element {
+    (line $($calls:tt)*) => {
+        $crate::elements::Element::Line(
+            $crate::elements::Line::builder() $($calls)* .build().expect("invalid line"),
+        )
+    };
+    (ngon $($calls:tt)*) => {
+        $crate::elements::Element::Ngon(
+            $crate::elements::Ngon::builder() $($calls)* .build().expect("invalid ngon"),
+        )
+    };
+    (ellipse $($calls:tt)*) => {
+        $crate::elements::Element::Ellipse(
+            $crate::elements::Ellipse::builder() $($calls)* .build().expect("invalid ellipse"),
+        )
+    };
+    (polyline $($calls:tt)*) => {
+        $crate::elements::Element::Polyline(
+            $crate::elements::Polyline::builder() $($calls)* .build().expect("invalid polyline"),
+        )
+    };
+    (text $($calls:tt)*) => {
+        $crate::elements::Element::Text(
+            $crate::elements::Text::builder() $($calls)* .build().expect("invalid text"),
+        )
+    };
+    (file $($calls:tt)*) => {
+        $crate::elements::Element::FileRef(
+            $crate::elements::FileRef::builder() $($calls)* .build().expect("invalid file"),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, LinePoint};
+    use crate::Document;
+
+    #[test]
+    fn document() {
+        let points = [(0., 0.), (5., 5.), (10., 0.)];
+        let doc = document! {
+            line {
+                color(Color::rgb(0, 0, 0xFF)),
+                width(2.),
+                point(points[0].0, points[0].1, 1.),
+                point(points[1].0, points[1].1, 0.5),
+            }
+            layer("background") {
+                ellipse { position(0., 0.), radius(10.), fill(Color::rgb(0xFF, 0xFF, 0)) }
+                polyline { point(0., 0.), point(10., 10.) }
+            }
+            file { path("scan.png"), size(100., 50.) }
+        };
+        match &doc.elements[..] {
+            [Element::Line(line), Element::Layer(layer), Element::FileRef(_)] => {
+                assert_eq!(line.points[1], LinePoint(5., 5., 0.5));
+                assert_eq!(layer.name, "background");
+                assert_eq!(layer.elements.len(), 2);
+            }
+            elements => panic!("{:?}", elements),
+        }
+        assert_eq!(Document::from_str(&doc.to_string()).unwrap(), doc);
+        assert!(document! {}.elements.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid ngon")]
+    fn invalid() {
+        document! {
+            ngon { position(0., 0.), n(5) }
+        };
+    }
+}
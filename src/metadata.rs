@@ -0,0 +1,655 @@
+//! Information about a document as a whole, stored in `<metadata>`.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use svg::node::element::{self, tag};
+use svg::node::Node;
+
+use crate::calibration::InputCalibration;
+use crate::colors::Color;
+use crate::elements::{Attributes, Layer, LengthUnit};
+use crate::escape::{escape, unescape};
+use crate::geometry::BoundingBox;
+use crate::ids::ElementId;
+use crate::ruling::Ruling;
+use crate::scalar::Scalar;
+use crate::snap::{Grid, Guide};
+use crate::transform::Transform;
+use crate::DocumentError;
+use DocumentError::InvalidAttribute;
+use DocumentError::MissingAttribute;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Metadata {
+    pub bookmarks: Vec<Bookmark>,
+    /// The headings of a table of contents, stored as nested
+    /// `<svgnote:heading>`s.
+    pub outline: Vec<Heading>,
+    /// The slots of a template, see
+    /// [`Document::instantiate`](crate::Document::instantiate).
+    pub placeholders: Vec<Placeholder>,
+    /// The grid new shapes are placed on, see
+    /// [`Document::ngon_builder`](crate::Document::ngon_builder).
+    pub grid: Option<Grid>,
+    /// The lines to align to, not rendered.
+    pub guides: Vec<Guide>,
+    /// The correction of pen input, see
+    /// [`Document::line_builder`](crate::Document::line_builder).
+    pub calibration: Option<InputCalibration>,
+    /// The unit lengths are shown in, see
+    /// [`Document::dimension`](crate::Document::dimension).
+    pub unit: Option<LengthUnit>,
+    /// The pen last used on each layer, see [`Metadata::tool_state`].
+    pub tools: Vec<ToolState>,
+    /// The color of the paper, stored as `<rect svgnote:tool="background">`
+    /// covering the view box below the elements, not in `<metadata>`.
+    ///
+    /// `None` leaves it to the app, usually white. A transparent color keeps
+    /// the paper transparent, e.g. for exports.
+    pub background: Option<Color>,
+    /// The lines or dots printed on the paper, stored as a `<rect>` above
+    /// the background, see [`Ruling`].
+    pub ruling: Option<Ruling>,
+}
+
+/// The pen last used on a layer, stored as `<svgnote:tool-state>`, so
+/// switching to the layer restores it in any app.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ToolState {
+    /// The layer, see [`ToolState::key`].
+    pub layer: String,
+    /// The name of the tool, e.g. `pen` or `highlighter`, as used by the app.
+    pub tool: String,
+    pub color: Color,
+    pub width: Scalar,
+}
+
+impl ToolState {
+    pub fn new(
+        layer: impl Into<String>,
+        tool: impl Into<String>,
+        color: Color,
+        width: Scalar,
+    ) -> Self {
+        ToolState {
+            layer: layer.into(),
+            tool: tool.into(),
+            color,
+            width,
+        }
+    }
+
+    /// The [`ToolState::layer`] of `layer`, its id if it has one, so the
+    /// state is kept when the layer is renamed, or else its name. Empty
+    /// for elements outside of layers.
+    pub fn key(layer: Option<&Layer>) -> String {
+        match layer {
+            Some(layer) => match &layer.properties.id {
+                Some(id) => id.as_str().to_owned(),
+                None => layer.name.clone(),
+            },
+            None => String::new(),
+        }
+    }
+}
+
+/// A named position to jump to, stored as `<svgnote:bookmark>`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bookmark {
+    pub name: String,
+    /// In document coordinates.
+    pub position: (Scalar, Scalar),
+}
+
+impl Bookmark {
+    pub fn new(name: impl Into<String>, position: (Scalar, Scalar)) -> Self {
+        Bookmark {
+            name: name.into(),
+            position,
+        }
+    }
+}
+
+/// A region or element of a template filled when it is instantiated,
+/// stored as `<svgnote:placeholder>`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Placeholder {
+    pub name: String,
+    /// In document coordinates.
+    pub bounds: BoundingBox,
+    /// The id of the element standing in for the content, e.g. a sample
+    /// text, which is replaced when the placeholder is filled.
+    pub element: Option<ElementId>,
+}
+
+impl Placeholder {
+    pub fn new(name: impl Into<String>, bounds: BoundingBox) -> Self {
+        Placeholder {
+            name: name.into(),
+            bounds,
+            element: None,
+        }
+    }
+
+    /// A placeholder replacing the element with the id `element`, filled
+    /// within `bounds`.
+    pub fn for_element(
+        name: impl Into<String>,
+        element: impl Into<ElementId>,
+        bounds: BoundingBox,
+    ) -> Self {
+        Placeholder {
+            element: Some(element.into()),
+            ..Placeholder::new(name, bounds)
+        }
+    }
+}
+
+/// An entry in the [`Metadata::outline`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Heading {
+    pub title: String,
+    pub target: Target,
+    pub children: Vec<Heading>,
+}
+
+impl Heading {
+    pub fn new(title: impl Into<String>, target: Target) -> Self {
+        Heading {
+            title: title.into(),
+            target,
+            children: vec![],
+        }
+    }
+}
+
+/// Where a [`Heading`] or a link (see
+/// [`Properties::link`](crate::elements::Properties::link)) points to.
+///
+/// Written like a relative URL, i.e. `other.svg?page=1#bookmark`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default)]
+pub struct Target {
+    /// The path of another document, relative to this one, `None` for this
+    /// document or notebook.
+    pub document: Option<String>,
+    /// The index of the page in the notebook, `None` for the same page.
+    pub page: Option<usize>,
+    /// The name of a [`Bookmark`] on the page, `None` for its start.
+    pub bookmark: Option<String>,
+}
+
+impl Target {
+    pub fn page(page: usize) -> Self {
+        Target {
+            page: Some(page),
+            ..Default::default()
+        }
+    }
+
+    pub fn bookmark(name: impl Into<String>) -> Self {
+        Target {
+            bookmark: Some(name.into()),
+            ..Default::default()
+        }
+    }
+
+    fn from_attributes(attributes: &Attributes<'_>) -> Result<Self, DocumentError> {
+        Ok(Target {
+            document: attributes.get("document").map(|d| unescape(d)),
+            page: attributes
+                .get("page")
+                .map(|page| {
+                    usize::from_str(page)
+                        .map_err(|_| InvalidAttribute("page".to_owned(), page.to_string()))
+                })
+                .transpose()?,
+            bookmark: attributes.get("bookmark").map(|b| unescape(b)),
+        })
+    }
+
+    fn assign_to<T: Node>(&self, node: &mut T) {
+        if let Some(document) = &self.document {
+            node.assign("document", escape(document));
+        }
+        if let Some(page) = self.page {
+            node.assign("page", page.to_string());
+        }
+        if let Some(bookmark) = &self.bookmark {
+            node.assign("bookmark", escape(bookmark));
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(document) = &self.document {
+            // `#` and `?` would start the bookmark or the page
+            for c in document.chars() {
+                match c {
+                    '#' | '?' | '%' => write!(f, "%{:02X}", c as u8)?,
+                    c => write!(f, "{}", c)?,
+                }
+            }
+        }
+        if let Some(page) = self.page {
+            write!(f, "?page={}", page)?;
+        }
+        if let Some(bookmark) = &self.bookmark {
+            write!(f, "#{}", bookmark)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Target {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, bookmark) = match s.split_once('#') {
+            Some((s, bookmark)) => (s, Some(bookmark.to_owned())),
+            None => (s, None),
+        };
+        let (document, page) = match s.split_once('?') {
+            Some((document, query)) => {
+                let page = query.strip_prefix("page=").ok_or(())?;
+                (document, Some(usize::from_str(page).map_err(|_| ())?))
+            }
+            None => (s, None),
+        };
+        Ok(Target {
+            document: Some(decode(document)?).filter(|d| !d.is_empty()),
+            page,
+            bookmark,
+        })
+    }
+}
+
+/// Reverses the percent-encoding of the document of a [`Target`].
+fn decode(document: &str) -> Result<String, ()> {
+    let mut bytes = Vec::with_capacity(document.len());
+    let mut rest = document.as_bytes();
+    while let Some((&byte, after)) = rest.split_first() {
+        rest = after;
+        if byte == b'%' {
+            let hex = rest.get(..2).ok_or(())?;
+            let hex = std::str::from_utf8(hex).map_err(|_| ())?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| ())?);
+            rest = &rest[2..];
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| ())
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.bookmarks.is_empty()
+            && self.outline.is_empty()
+            && self.placeholders.is_empty()
+            && self.grid.is_none()
+            && self.guides.is_empty()
+            && self.calibration.is_none()
+            && self.unit.is_none()
+            && self.tools.is_empty()
+    }
+
+    /// The `<rect>` of the [background](Metadata::background) covering
+    /// `view_box`, if there is one.
+    pub(crate) fn background_rect(&self, view_box: BoundingBox) -> Option<element::Rectangle> {
+        let color = self.background?;
+        let mut rect = element::Rectangle::new()
+            .set("x", view_box.min.0)
+            .set("y", view_box.min.1)
+            .set("width", view_box.width())
+            .set("height", view_box.height())
+            .set("fill", color.to_string_na())
+            .set("svgnote:tool", "background");
+        if color.a < 0xFF {
+            rect.assign("fill-opacity", color.opacity());
+        }
+        Some(rect)
+    }
+
+    /// The first bookmark called `name`.
+    pub fn bookmark(&self, name: &str) -> Option<&Bookmark> {
+        self.bookmarks.iter().find(|b| b.name == name)
+    }
+
+    /// The first placeholder called `name`.
+    pub fn placeholder(&self, name: &str) -> Option<&Placeholder> {
+        self.placeholders.iter().find(|p| p.name == name)
+    }
+
+    /// The pen last used on the layer with the [key](ToolState::key)
+    /// `layer`.
+    pub fn tool_state(&self, layer: &str) -> Option<&ToolState> {
+        self.tools.iter().find(|t| t.layer == layer)
+    }
+
+    /// Remembers `state` for its layer, replacing the one before.
+    pub fn set_tool_state(&mut self, state: ToolState) {
+        match self.tools.iter_mut().find(|t| t.layer == state.layer) {
+            Some(old) => *old = state,
+            None => self.tools.push(state),
+        }
+    }
+}
+
+/// Reads the tags inside `<metadata>`, others than ours are ignored.
+#[derive(Default)]
+pub(crate) struct Reader {
+    metadata: Metadata,
+    /// The `<svgnote:heading>`s that are not closed yet
+    headings: Vec<Heading>,
+}
+
+impl Reader {
+    pub(crate) fn read(
+        &mut self,
+        name: &str,
+        kind: tag::Type,
+        attributes: &Attributes<'_>,
+    ) -> Result<(), DocumentError> {
+        match (name, kind) {
+            ("svgnote:bookmark", tag::Type::Start) | ("svgnote:bookmark", tag::Type::Empty) => {
+                self.metadata.bookmarks.push(Bookmark {
+                    name: attribute(attributes, "name")?,
+                    position: (scalar(attributes, "x")?, scalar(attributes, "y")?),
+                })
+            }
+            ("svgnote:placeholder", tag::Type::Start)
+            | ("svgnote:placeholder", tag::Type::Empty) => {
+                let (x, y) = (scalar(attributes, "x")?, scalar(attributes, "y")?);
+                let (width, height) = (scalar(attributes, "width")?, scalar(attributes, "height")?);
+                self.metadata.placeholders.push(Placeholder {
+                    element: attributes
+                        .get("element")
+                        .map(|id| ElementId::new(unescape(id))),
+                    ..Placeholder::new(
+                        attribute(attributes, "name")?,
+                        BoundingBox {
+                            min: (x, y),
+                            max: (x + width, y + height),
+                        },
+                    )
+                })
+            }
+            ("svgnote:grid", tag::Type::Start) | ("svgnote:grid", tag::Type::Empty) => {
+                self.metadata.grid = Some(Grid::new(scalar(attributes, "spacing")?))
+            }
+            ("svgnote:guide", tag::Type::Start) | ("svgnote:guide", tag::Type::Empty) => {
+                let orientation = attribute(attributes, "orientation")?;
+                self.metadata.guides.push(match orientation.as_str() {
+                    "horizontal" => Guide::Horizontal(scalar(attributes, "y")?),
+                    "vertical" => Guide::Vertical(scalar(attributes, "x")?),
+                    "diagonal" => Guide::Diagonal {
+                        position: (scalar(attributes, "x")?, scalar(attributes, "y")?),
+                        angle: scalar(attributes, "angle")?,
+                    },
+                    _ => return Err(InvalidAttribute("orientation".to_owned(), orientation)),
+                })
+            }
+            ("svgnote:unit", tag::Type::Start) | ("svgnote:unit", tag::Type::Empty) => {
+                let length = attribute(attributes, "length")?;
+                self.metadata.unit = Some(
+                    LengthUnit::from_str(&length)
+                        .map_err(|_| InvalidAttribute("length".to_owned(), length))?,
+                )
+            }
+            ("svgnote:calibration", tag::Type::Start)
+            | ("svgnote:calibration", tag::Type::Empty) => {
+                let transform = attribute(attributes, "transform")?;
+                self.metadata.calibration = Some(InputCalibration {
+                    transform: Transform::from_str(&transform)
+                        .map_err(|_| InvalidAttribute("transform".to_owned(), transform))?,
+                    pressure: (
+                        scalar(attributes, "pressure-min")?,
+                        scalar(attributes, "pressure-max")?,
+                    ),
+                    deadband: scalar(attributes, "deadband")?,
+                })
+            }
+            ("svgnote:tool-state", tag::Type::Start) | ("svgnote:tool-state", tag::Type::Empty) => {
+                let color = attribute(attributes, "color")?;
+                self.metadata.tools.push(ToolState::new(
+                    attribute(attributes, "layer")?,
+                    attribute(attributes, "tool")?,
+                    Color::from_str(&color)
+                        .map_err(|_| InvalidAttribute("color".to_owned(), color))?,
+                    scalar(attributes, "width")?,
+                ))
+            }
+            ("svgnote:heading", tag::Type::End) => {
+                if let Some(heading) = self.headings.pop() {
+                    self.push_heading(heading);
+                }
+            }
+            ("svgnote:heading", kind) => {
+                let heading = Heading::new(
+                    attribute(attributes, "title")?,
+                    Target::from_attributes(attributes)?,
+                );
+                if kind == tag::Type::Start {
+                    self.headings.push(heading);
+                } else {
+                    self.push_heading(heading);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn push_heading(&mut self, heading: Heading) {
+        match self.headings.last_mut() {
+            Some(parent) => parent.children.push(heading),
+            None => self.metadata.outline.push(heading),
+        }
+    }
+
+    /// The metadata read, with unclosed headings closed.
+    pub(crate) fn finish(mut self) -> Metadata {
+        while let Some(heading) = self.headings.pop() {
+            self.push_heading(heading);
+        }
+        self.metadata
+    }
+}
+
+/// The color of a `<rect svgnote:tool="background">`, see
+/// [`Metadata::background`].
+pub(crate) fn background(attributes: &Attributes<'_>) -> Result<Color, DocumentError> {
+    let fill = attribute(attributes, "fill")?;
+    let color = Color::from_str(&fill).map_err(|_| InvalidAttribute("fill".to_owned(), fill))?;
+    Ok(match attributes.get("fill-opacity") {
+        Some(_) => color.faded(scalar(attributes, "fill-opacity")? as f32),
+        None => color,
+    })
+}
+
+/// The value of the attribute `name`, unescaped as written by
+/// [`escape`].
+fn attribute(attributes: &Attributes<'_>, name: &str) -> Result<String, DocumentError> {
+    attributes
+        .get(name)
+        .map(|value| unescape(value))
+        .ok_or_else(|| MissingAttribute(name.to_owned()))
+}
+
+fn scalar(attributes: &Attributes<'_>, name: &str) -> Result<Scalar, DocumentError> {
+    let value = attribute(attributes, name)?;
+    Scalar::from_str(&value).map_err(|_| InvalidAttribute(name.to_owned(), value))
+}
+
+impl From<&Metadata> for element::Element {
+    fn from(metadata: &Metadata) -> Self {
+        let mut element = element::Element::new("metadata");
+        for bookmark in &metadata.bookmarks {
+            let (x, y) = bookmark.position;
+            let mut node = element::Element::new("svgnote:bookmark");
+            node.assign("name", escape(&bookmark.name));
+            node.assign("x", x);
+            node.assign("y", y);
+            element.append(node);
+        }
+        for placeholder in &metadata.placeholders {
+            let bounds = placeholder.bounds;
+            let mut node = element::Element::new("svgnote:placeholder");
+            node.assign("name", escape(&placeholder.name));
+            node.assign("x", bounds.min.0);
+            node.assign("y", bounds.min.1);
+            node.assign("width", bounds.width());
+            node.assign("height", bounds.height());
+            if let Some(id) = &placeholder.element {
+                node.assign("element", escape(id.as_str()));
+            }
+            element.append(node);
+        }
+        for heading in &metadata.outline {
+            element.append(element::Element::from(heading));
+        }
+        if let Some(grid) = metadata.grid {
+            let mut node = element::Element::new("svgnote:grid");
+            node.assign("spacing", grid.spacing);
+            element.append(node);
+        }
+        for guide in &metadata.guides {
+            let mut node = element::Element::new("svgnote:guide");
+            match *guide {
+                Guide::Horizontal(y) => {
+                    node.assign("orientation", "horizontal");
+                    node.assign("y", y);
+                }
+                Guide::Vertical(x) => {
+                    node.assign("orientation", "vertical");
+                    node.assign("x", x);
+                }
+                Guide::Diagonal { position, angle } => {
+                    node.assign("orientation", "diagonal");
+                    node.assign("x", position.0);
+                    node.assign("y", position.1);
+                    node.assign("angle", angle);
+                }
+            }
+            element.append(node);
+        }
+        if let Some(calibration) = metadata.calibration {
+            let mut node = element::Element::new("svgnote:calibration");
+            node.assign("transform", calibration.transform.to_string());
+            node.assign("pressure-min", calibration.pressure.0);
+            node.assign("pressure-max", calibration.pressure.1);
+            node.assign("deadband", calibration.deadband);
+            element.append(node);
+        }
+        if let Some(unit) = metadata.unit {
+            let mut node = element::Element::new("svgnote:unit");
+            node.assign("length", unit.to_string());
+            element.append(node);
+        }
+        for state in &metadata.tools {
+            let mut node = element::Element::new("svgnote:tool-state");
+            node.assign("layer", escape(&state.layer));
+            node.assign("tool", escape(&state.tool));
+            node.assign("color", state.color.to_string());
+            node.assign("width", state.width);
+            element.append(node);
+        }
+        element
+    }
+}
+
+impl From<&Heading> for element::Element {
+    fn from(heading: &Heading) -> Self {
+        let mut element = element::Element::new("svgnote:heading");
+        element.assign("title", escape(&heading.title));
+        heading.target.assign_to(&mut element);
+        for child in &heading.children {
+            element.append(element::Element::from(child));
+        }
+        element
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::Layer;
+    use crate::ids::ElementId;
+    use crate::metadata::{Target, ToolState};
+    use crate::Document;
+
+    #[test]
+    fn tool_states() {
+        let mut doc = Document::default();
+        let red = Color::rgba(0xFF, 0, 0, 0x80);
+        doc.metadata
+            .set_tool_state(ToolState::new("sketch", "pen", red, 2.));
+        doc.metadata
+            .set_tool_state(ToolState::new("", "pen", Color::rgb(0, 0, 0), 1.));
+        doc.metadata
+            .set_tool_state(ToolState::new("sketch", "highlighter", red, 8.));
+        assert_eq!(doc.metadata.tools.len(), 2);
+        let doc = Document::from_str(&doc.to_string()).unwrap();
+        assert_eq!(
+            doc.metadata.tool_state("sketch"),
+            Some(&ToolState::new("sketch", "highlighter", red, 8.))
+        );
+
+        let mut layer = Layer::new("Q&A \"draft\"");
+        assert_eq!(ToolState::key(Some(&layer)), layer.name);
+        layer.properties.id = Some(ElementId::new("layer-1"));
+        assert_eq!(ToolState::key(Some(&layer)), "layer-1");
+        assert_eq!(ToolState::key(None), "");
+
+        let mut doc = Document::default();
+        let state = ToolState::new(layer.name, "<pen>", red, 2.);
+        doc.metadata.set_tool_state(state.clone());
+        let doc = Document::from_str(&doc.to_string()).unwrap();
+        assert_eq!(doc.metadata.tools, vec![state]);
+    }
+
+    #[test]
+    fn background() {
+        let mut doc = Document::default();
+        doc.metadata.background = Some(Color::rgb(0xF4, 0xEC, 0xD8));
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"svgnote:tool="background""#));
+        assert!(svg.contains(r#"width="2000""#));
+        assert!(!svg.contains("<metadata"));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+
+        doc.metadata.background = Some(Color::rgba(0, 0, 0, 0));
+        let svg = doc.to_string();
+        assert!(svg.contains(r#"fill-opacity="0""#));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+    }
+
+    #[test]
+    fn target_documents() {
+        for name in ["Q&A #2.svg", "what?.svg", "100%.svg", "notes.svg"] {
+            let target = Target {
+                document: Some(name.to_owned()),
+                page: Some(1),
+                bookmark: Some("a#b".to_owned()),
+            };
+            let s = target.to_string();
+            assert_eq!(Target::from_str(&s), Ok(target));
+        }
+        assert_eq!(
+            Target::from_str("Q&A %232.svg?page=1#start")
+                .unwrap()
+                .document
+                .unwrap(),
+            "Q&A #2.svg"
+        );
+        assert_eq!(
+            Target::from_str("what%3F.svg").unwrap().to_string(),
+            "what%3F.svg"
+        );
+        assert_eq!(Target::from_str("100%.svg"), Err(()));
+    }
+}
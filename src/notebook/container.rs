@@ -0,0 +1,175 @@
+//! The `.svgnotes` container, a zip archive holding the pages as SVG, the
+//! assets in `assets/` and a `manifest.json` with the order of the pages and
+//! the type of each asset:
+//!
+//! ```json
+//! {"svgnotes": 1, "pages": ["pages/0.svg"], "assets": {"scan.png": "image/png"}}
+//! ```
+//!
+//! Pages are compressed, assets are stored as they are, as most formats are
+//! compressed already.
+
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::assets::{Asset, Assets};
+use crate::save;
+
+use super::{read_page, Notebook, NotebookError};
+
+/// The version of the format in `manifest.json`.
+const VERSION: u64 = 1;
+const MANIFEST: &str = "manifest.json";
+/// The size of the pages and assets read at most, so a small archive
+/// cannot unpack to more than fits into memory.
+const MAX_ENTRY: u64 = 1 << 30;
+
+fn invalid(error: impl Display) -> NotebookError {
+    NotebookError::InvalidContainer(error.to_string())
+}
+
+impl Notebook {
+    /// Opens a `.svgnotes` container written by [`Notebook::save`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, NotebookError> {
+        Notebook::read_container(BufReader::new(File::open(path)?))
+    }
+
+    /// Saves the notebook as a `.svgnotes` container, a zip archive of the
+    /// pages and assets, replacing the file only once it is written, like
+    /// [`Document::save_atomic`](crate::Document::save_atomic).
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), NotebookError> {
+        let mut data = Cursor::new(vec![]);
+        self.write_container(&mut data)?;
+        save::write_atomic(path.as_ref(), data.get_ref(), 0)?;
+        Ok(())
+    }
+
+    /// Reads a container from memory or any other seekable source, see
+    /// [`Notebook::open`].
+    pub fn read_container(reader: impl Read + Seek) -> Result<Self, NotebookError> {
+        let mut archive = ZipArchive::new(reader).map_err(invalid)?;
+        let mut entry = |path: &str| -> Result<Vec<u8>, NotebookError> {
+            let file = archive
+                .by_name(path)
+                .map_err(|_| invalid(format!("missing {}", path)))?;
+            let too_big = || invalid(format!("{} is too big", path));
+            if file.size() > MAX_ENTRY {
+                return Err(too_big());
+            }
+            // The size in the archive may be wrong
+            let mut data = vec![];
+            file.take(MAX_ENTRY + 1).read_to_end(&mut data)?;
+            if data.len() as u64 > MAX_ENTRY {
+                return Err(too_big());
+            }
+            Ok(data)
+        };
+        let manifest: Value = serde_json::from_slice(&entry(MANIFEST)?).map_err(invalid)?;
+        if manifest["svgnotes"].as_u64() != Some(VERSION) {
+            return Err(invalid(format!(
+                "unsupported version {}",
+                manifest["svgnotes"]
+            )));
+        }
+        let invalid_manifest = || invalid("invalid manifest");
+        let mut pages = vec![];
+        for path in manifest["pages"].as_array().ok_or_else(invalid_manifest)? {
+            let path = path.as_str().ok_or_else(invalid_manifest)?;
+            pages.push(read_page(path, entry(path)?)?);
+        }
+        let mut assets = Assets::new();
+        if let Some(types) = manifest.get("assets") {
+            for (name, mime) in types.as_object().ok_or_else(invalid_manifest)? {
+                let mime = mime.as_str().ok_or_else(invalid_manifest)?;
+                let data = entry(&format!("assets/{}", name))?;
+                assets.insert(name.clone(), Asset::new(data, mime));
+            }
+        }
+        Ok(Notebook { pages, assets })
+    }
+
+    /// Writes the notebook as a container, see [`Notebook::save`].
+    pub fn write_container(&self, writer: impl Write + Seek) -> Result<(), NotebookError> {
+        let mut zip = ZipWriter::new(writer);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let mut pages = vec![];
+        for (i, page) in self.pages.iter().enumerate() {
+            let path = format!("pages/{}.svg", i);
+            zip.start_file(path.as_str(), deflated).map_err(invalid)?;
+            zip.write_all(page.to_string().as_bytes())?;
+            pages.push(path);
+        }
+        let mut types = Map::new();
+        for (name, asset) in &self.assets {
+            zip.start_file(format!("assets/{}", name), stored)
+                .map_err(invalid)?;
+            zip.write_all(&asset.data)?;
+            types.insert(name.clone(), asset.mime.clone().into());
+        }
+        let manifest = json!({
+            "svgnotes": VERSION,
+            "pages": pages,
+            "assets": types,
+        });
+        zip.start_file(MANIFEST, deflated).map_err(invalid)?;
+        zip.write_all(manifest.to_string().as_bytes())?;
+        zip.finish().map_err(invalid)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Cursor;
+
+    use crate::assets::{self, Asset};
+    use crate::elements::{Element, FileRef, Properties};
+    use crate::notebook::{Notebook, NotebookError};
+    use crate::Document;
+
+    #[test]
+    fn container() {
+        let scan = Element::FileRef(FileRef {
+            path: assets::reference("scan.png"),
+            position: (0., 0.),
+            size: (210., 297.),
+            properties: Properties::default(),
+        });
+        let mut notebook = Notebook {
+            pages: vec![
+                Document {
+                    elements: vec![scan],
+                    ..Default::default()
+                },
+                Document::default(),
+            ],
+            ..Default::default()
+        };
+        notebook.assets.insert(
+            "scan.png",
+            Asset::new(vec![0x89, b'P', b'N', b'G'], "image/png"),
+        );
+
+        let dir = std::env::temp_dir().join(format!("svg_notes_container_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.svgnotes");
+        notebook.save(&path).unwrap();
+        assert_eq!(Notebook::open(&path).unwrap(), notebook);
+        assert_eq!(&fs::read(&path).unwrap()[..2], b"PK");
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_matches!(
+            Notebook::read_container(Cursor::new(b"plain".to_vec())),
+            Err(NotebookError::InvalidContainer(_))
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! An encrypted container holding all entries of a notebook.
+//!
+//! The layout is the magic bytes, a salt for deriving the key from the
+//! passphrase (PBKDF2-HMAC-SHA256), a nonce, and the entries encrypted with
+//! AES-256-GCM. Each entry is stored as the length of its path, the path,
+//! the length of its data and the data, lengths as little endian `u32`.
+
+use std::convert::TryInto;
+
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use hmac::Hmac;
+use sha2::Sha256;
+
+use super::{Notebook, NotebookError};
+
+const MAGIC: &[u8] = b"svgnotes-encrypted-1\n";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const ROUNDS: u32 = 100_000;
+
+fn key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0; 32];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(passphrase.as_bytes(), salt, ROUNDS, &mut key);
+    key
+}
+
+impl Notebook {
+    /// Opens a notebook written by [`Notebook::to_encrypted`].
+    pub fn open_encrypted(data: &[u8], passphrase: &str) -> Result<Self, NotebookError> {
+        let invalid = || NotebookError::InvalidContainer("not an encrypted notebook".to_owned());
+        let data = data.strip_prefix(MAGIC).ok_or_else(invalid)?;
+        if data.len() < SALT_LENGTH + NONCE_LENGTH {
+            return Err(invalid());
+        }
+        let (salt, data) = data.split_at(SALT_LENGTH);
+        let (nonce, ciphertext) = data.split_at(NONCE_LENGTH);
+        let plaintext = Aes256Gcm::new(Key::from_slice(&key(passphrase, salt)))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| NotebookError::Decryption)?;
+        Notebook::from_entries(unpack(&plaintext).ok_or_else(invalid)?)
+    }
+
+    /// Encrypts all pages and assets with a key derived from `passphrase`.
+    pub fn to_encrypted(&self, passphrase: &str) -> Result<Vec<u8>, NotebookError> {
+        let mut salt = [0; SALT_LENGTH];
+        let mut nonce = [0; NONCE_LENGTH];
+        getrandom::getrandom(&mut salt).map_err(|_| NotebookError::Random)?;
+        getrandom::getrandom(&mut nonce).map_err(|_| NotebookError::Random)?;
+        let ciphertext = Aes256Gcm::new(Key::from_slice(&key(passphrase, &salt)))
+            .encrypt(Nonce::from_slice(&nonce), &pack(&self.to_entries())[..])
+            .map_err(|_| NotebookError::Encryption)?;
+        Ok([MAGIC, &salt, &nonce, &ciphertext].concat())
+    }
+}
+
+fn pack(entries: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![];
+    for (path, content) in entries {
+        for part in &[path.as_bytes(), content] {
+            data.extend_from_slice(&(part.len() as u32).to_le_bytes());
+            data.extend_from_slice(part);
+        }
+    }
+    data
+}
+
+fn unpack(mut data: &[u8]) -> Option<Vec<(String, Vec<u8>)>> {
+    let mut next = || -> Option<Vec<u8>> {
+        let length = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        let part = data.get(4..4 + length)?.to_vec();
+        data = &data[4 + length..];
+        Some(part)
+    };
+    let mut entries = vec![];
+    while let Some(path) = next() {
+        entries.push((String::from_utf8(path).ok()?, next()?));
+    }
+    Some(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assets::Asset;
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Properties};
+    use crate::notebook::{Notebook, NotebookError};
+    use crate::Document;
+
+    #[test]
+    fn encryption() {
+        let mut notebook = Notebook {
+            pages: vec![
+                Document::default(),
+                Document {
+                    elements: vec![Element::Ellipse(Ellipse {
+                        position: (1., 2.),
+                        stroke: Color::rgb(0, 0, 0),
+                        fill: Color::rgba(0, 0, 0, 0),
+                        width: 1.,
+                        radius: 3.,
+                        properties: Properties::default(),
+                    })],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        notebook
+            .assets
+            .insert("image.png", Asset::new(vec![0, 1, 2, 3], "image/png"));
+
+        let encrypted = notebook.to_encrypted("secret").unwrap();
+        assert!(!encrypted
+            .windows(4)
+            .any(|w| w == b"<svg" || w == [0, 1, 2, 3]));
+        assert_eq!(
+            Notebook::open_encrypted(&encrypted, "secret").unwrap(),
+            notebook
+        );
+        assert_matches!(
+            Notebook::open_encrypted(&encrypted, "wrong"),
+            Err(NotebookError::Decryption)
+        );
+        assert_matches!(
+            Notebook::open_encrypted(b"plain", "secret"),
+            Err(NotebookError::InvalidContainer(_))
+        );
+    }
+}
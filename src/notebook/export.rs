@@ -0,0 +1,222 @@
+//! Writing the pages of a notebook into a directory, one file per page or
+//! frame, e.g. for a web viewer, see [`Notebook::export_pages`].
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::layered::Rasterizer;
+use crate::options::SerializeOptions;
+use crate::save;
+use crate::scalar::Scalar;
+
+use super::{Notebook, NotebookError};
+
+/// The files [`Notebook::export_pages`] writes.
+#[derive(Clone, Copy)]
+pub enum PageFormat<'a> {
+    Svg,
+    /// Rasterized by the host, `width` pixels wide and as high as the page
+    /// or frame in proportion.
+    Png {
+        rasterizer: &'a dyn Rasterizer,
+        width: u32,
+    },
+}
+
+/// How [`Notebook::export_pages`] names the files, before the extension.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PageNaming {
+    /// The number of the page and of the frame on it, counting from 1 and
+    /// zero padded so they sort in order, e.g. `02` or `02-3`.
+    Number,
+    /// The number of the page and the name of the frame, e.g. `02-intro`,
+    /// with characters not safe in file names replaced by `_`.
+    Name,
+}
+
+/// Passed to the progress callback of [`Notebook::export_pages`] after each
+/// file.
+#[derive(Debug)]
+pub struct PageProgress<'a> {
+    /// The file that was just written.
+    pub path: &'a Path,
+    /// The index of its page.
+    pub page: usize,
+    /// The number of files written so far, including this one.
+    pub done: usize,
+    pub total: usize,
+}
+
+/// `i` counting from 1, zero padded to the width of `count`.
+fn number(i: usize, count: usize) -> String {
+    format!("{:01$}", i + 1, count.to_string().len())
+}
+
+/// `name` with the characters that are not allowed in file names on some
+/// systems replaced by `_`.
+fn file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || " -_.".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl Notebook {
+    /// Writes a file per page into `dir`, creating it if needed, or one per
+    /// [frame](crate::Document::frames) for pages with frames, returning the
+    /// paths in page order. Pages show the shared assets they use. Frames
+    /// without area are skipped.
+    ///
+    /// `progress` is called after each file. Stops at the first file that
+    /// cannot be written.
+    pub fn export_pages(
+        &self,
+        dir: impl AsRef<Path>,
+        format: PageFormat,
+        naming: PageNaming,
+        mut progress: impl FnMut(PageProgress),
+    ) -> Result<Vec<PathBuf>, NotebookError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        let pages: Vec<_> = self
+            .pages
+            .iter()
+            .map(|page| self.with_shared_assets(page))
+            .collect();
+        // The page, name and view box of each file
+        let mut files = vec![];
+        for (i, page) in pages.iter().enumerate() {
+            let page_number = number(i, pages.len());
+            let frames: Vec<_> = page
+                .frames()
+                .into_iter()
+                .filter(|(_, bounds)| bounds.width() > 0. && bounds.height() > 0.)
+                .collect();
+            if frames.is_empty() {
+                files.push((i, page_number.clone(), None));
+            }
+            for (j, &(frame, bounds)) in frames.iter().enumerate() {
+                let frame_number = number(j, frames.len());
+                let mut name = match naming {
+                    PageNaming::Number => format!("{}-{}", page_number, frame_number),
+                    PageNaming::Name => format!("{}-{}", page_number, file_name(&frame.name)),
+                };
+                // Frames of the same name would overwrite each other
+                if files.iter().any(|(_, n, _)| *n == name) {
+                    name = format!("{}-{}", name, frame_number);
+                }
+                files.push((i, name, Some(bounds)));
+            }
+        }
+
+        let total = files.len();
+        let mut paths = Vec::with_capacity(total);
+        for (page, name, view_box) in files {
+            let options = SerializeOptions {
+                view_box,
+                ..Default::default()
+            };
+            let svg = pages[page].to_string_with(&options);
+            let (extension, data) = match format {
+                PageFormat::Svg => ("svg", svg.into_bytes()),
+                PageFormat::Png { rasterizer, width } => {
+                    // The whole page is square
+                    let height = view_box.map_or(width, |b| {
+                        (width as Scalar * b.height() / b.width()).round() as u32
+                    });
+                    ("png", rasterizer.rasterize(&svg, width, height))
+                }
+            };
+            let path = dir.join(format!("{}.{}", name, extension));
+            save::write_atomic(&path, &data, 0)?;
+            progress(PageProgress {
+                path: &path,
+                page,
+                done: paths.len() + 1,
+                total,
+            });
+            paths.push(path);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::elements::{Element, Frame};
+    use crate::geometry::BoundingBox;
+    use crate::layered::Rasterizer;
+    use crate::notebook::{Notebook, PageFormat, PageNaming};
+    use crate::Document;
+
+    #[test]
+    fn export_pages() {
+        struct Size;
+        impl Rasterizer for Size {
+            fn rasterize(&self, _: &str, width: u32, height: u32) -> Vec<u8> {
+                format!("{}x{}", width, height).into_bytes()
+            }
+        }
+        let frame = |name: &str, index| {
+            Element::Frame(Frame::new(
+                name,
+                index,
+                BoundingBox {
+                    min: (0., 0.),
+                    max: (1600., 900.),
+                },
+            ))
+        };
+        let empty = BoundingBox {
+            min: (10., 10.),
+            max: (10., 10.),
+        };
+        let slides = Document {
+            elements: vec![
+                frame("end", 1),
+                frame("intro / overview", 0),
+                Element::Frame(Frame::new("empty", 2, empty)),
+            ],
+            ..Default::default()
+        };
+        let notebook = Notebook {
+            pages: vec![Document::default(), slides],
+            ..Default::default()
+        };
+        let dir =
+            std::env::temp_dir().join(format!("svg_notes_export_pages_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut progress = vec![];
+        let paths = notebook
+            .export_pages(&dir, PageFormat::Svg, PageNaming::Number, |p| {
+                progress.push((p.page, p.done, p.total))
+            })
+            .unwrap();
+        let names: Vec<_> = paths.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["1.svg", "2-1.svg", "2-2.svg"]);
+        assert_eq!(progress, [(0, 1, 3), (1, 2, 3), (1, 3, 3)]);
+        let intro = fs::read_to_string(&paths[1]).unwrap();
+        assert!(intro.contains(r#"viewBox="0 0 1600 900""#));
+
+        let format = PageFormat::Png {
+            rasterizer: &Size,
+            width: 800,
+        };
+        let paths = notebook
+            .export_pages(&dir, format, PageNaming::Name, |_| {})
+            .unwrap();
+        let names: Vec<_> = paths.iter().map(|p| p.file_name().unwrap()).collect();
+        assert_eq!(names, ["1.png", "2-intro _ overview.png", "2-end.png"]);
+        assert_eq!(fs::read(&paths[0]).unwrap(), b"800x800");
+        assert_eq!(fs::read(&paths[1]).unwrap(), b"800x450");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
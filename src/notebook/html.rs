@@ -0,0 +1,81 @@
+//! A standalone HTML file for viewing a notebook without the app, see
+//! [`Notebook::to_html`].
+
+use crate::escape::escape;
+use crate::options::SerializeOptions;
+
+use super::Notebook;
+
+/// The page with the viewer, `{{title}}` and `{{pages}}` are replaced.
+const VIEWER: &str = include_str!("viewer.html");
+
+impl Notebook {
+    /// A single HTML file showing the pages one at a time, with buttons and
+    /// the arrow keys to change the page, dragging to pan and the mouse
+    /// wheel to zoom. Titled by the first heading of the outline.
+    ///
+    /// The pages are inline SVG, with the shared assets they show embedded.
+    /// Files referenced by a relative path are only shown next to them.
+    pub fn to_html(&self) -> String {
+        let pages: String = self
+            .pages
+            .iter()
+            .map(|page| {
+                format!(
+                    "<template class=\"page\">{}</template>\n",
+                    self.with_shared_assets(page)
+                        .to_svg(&SerializeOptions::default())
+                )
+            })
+            .collect();
+        let title = self
+            .outline()
+            .first()
+            .map_or_else(|| "Notes".to_owned(), |heading| escape(&heading.title));
+        let (head, tail) = VIEWER.split_at(VIEWER.find("{{pages}}").unwrap());
+        format!(
+            "{}{}{}",
+            head.replace("{{title}}", &title),
+            pages,
+            &tail["{{pages}}".len()..]
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assets::{self, Asset};
+    use crate::elements::{Element, FileRef, Properties};
+    use crate::metadata::{Heading, Target};
+    use crate::notebook::Notebook;
+    use crate::Document;
+
+    #[test]
+    fn html() {
+        let scan = Asset::new(vec![0x89, b'P', b'N', b'G'], "image/png");
+        let mut first = Document {
+            elements: vec![Element::FileRef(FileRef {
+                path: assets::reference("scan.png"),
+                position: (0., 0.),
+                size: (210., 297.),
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        first
+            .metadata
+            .outline
+            .push(Heading::new("Physics & Maths", Target::default()));
+        let mut notebook = Notebook {
+            pages: vec![first, Document::default()],
+            ..Default::default()
+        };
+        notebook.assets.insert("scan.png", scan.clone());
+
+        let html = notebook.to_html();
+        assert!(html.contains("<title>Physics &amp; Maths</title>"));
+        assert_eq!(html.matches("<template class=\"page\"><svg").count(), 2);
+        assert_eq!(html.matches(&scan.to_data_uri()).count(), 1);
+        assert!(!html.contains("{{"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Notebooks, i.e. multiple documents with the files they use.
+
+#[cfg(feature = "container")]
+mod container;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod export;
+mod html;
+
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use crate::assets::{self, Asset, Assets};
+use crate::metadata::{Bookmark, Heading, Target};
+use crate::{Document, DocumentError};
+
+pub use self::export::{PageFormat, PageNaming, PageProgress};
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Notebook {
+    pub pages: Vec<Document>,
+    /// Binary files shared by the pages, e.g. images, by their name. Pages
+    /// show them like their own [`Document::assets`].
+    pub assets: Assets,
+}
+
+/// Where a link points to in a notebook, see [`Notebook::resolve_link`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Destination<'a> {
+    Page {
+        page: usize,
+        /// `None` for the start of the page.
+        bookmark: Option<&'a Bookmark>,
+    },
+    /// Another document, which cannot be checked.
+    External(&'a Target),
+}
+
+#[derive(Error, Debug)]
+pub enum NotebookError {
+    #[error("Invalid page {0}: {1}")]
+    InvalidPage(String, DocumentError),
+    #[error("Invalid container: {0}")]
+    InvalidContainer(String),
+    #[error("Wrong passphrase or corrupted container")]
+    Decryption,
+    #[error("Encryption failed, the notebook is too large")]
+    Encryption,
+    #[error("Random number generation failed")]
+    Random,
+    #[error("Could not access the notebook: {0}")]
+    Io(#[from] io::Error),
+}
+
+impl Notebook {
+    /// The bookmarks of all pages, with the index of their page.
+    pub fn bookmarks(&self) -> Vec<(usize, &Bookmark)> {
+        self.pages
+            .iter()
+            .enumerate()
+            .flat_map(|(i, page)| page.metadata.bookmarks.iter().map(move |b| (i, b)))
+            .collect()
+    }
+
+    /// The page and position to jump to for the first bookmark called
+    /// `name`.
+    pub fn resolve_bookmark(&self, name: &str) -> Option<(usize, &Bookmark)> {
+        self.bookmarks().into_iter().find(|(_, b)| b.name == name)
+    }
+
+    /// Where `target` points to from the page `from`, `None` if its page or
+    /// bookmark does not exist.
+    pub fn resolve_link<'a>(&'a self, from: usize, target: &'a Target) -> Option<Destination<'a>> {
+        if target.document.is_some() {
+            return Some(Destination::External(target));
+        }
+        let page = target.page.unwrap_or(from);
+        let bookmark = match &target.bookmark {
+            Some(name) => Some(self.pages.get(page)?.metadata.bookmark(name)?),
+            None => {
+                self.pages.get(page)?;
+                None
+            }
+        };
+        Some(Destination::Page { page, bookmark })
+    }
+
+    /// The links and headings on all pages that do not resolve, with the
+    /// index of their page.
+    pub fn broken_links(&self) -> Vec<(usize, &Target)> {
+        fn headings<'a>(outline: &'a [Heading], targets: &mut Vec<&'a Target>) {
+            for heading in outline {
+                targets.push(&heading.target);
+                headings(&heading.children, targets);
+            }
+        }
+        let mut broken = vec![];
+        for (i, page) in self.pages.iter().enumerate() {
+            let mut targets = page.links();
+            headings(&page.metadata.outline, &mut targets);
+            broken.extend(
+                targets
+                    .into_iter()
+                    .filter(|target| self.resolve_link(i, target).is_none())
+                    .map(|target| (i, target)),
+            );
+        }
+        broken
+    }
+
+    /// Prefixes the relative paths of the files referenced by all pages with
+    /// `base_dir`, e.g. `..` if the notebook was moved into a subdirectory
+    /// without the files.
+    pub fn relink(&mut self, base_dir: &str) {
+        for page in &mut self.pages {
+            page.relink(base_dir);
+        }
+    }
+
+    /// How many [`FileRef`](crate::elements::FileRef)s on all pages show
+    /// each asset, by its id.
+    pub fn asset_references(&self) -> BTreeMap<String, usize> {
+        let mut references = BTreeMap::new();
+        for page in &self.pages {
+            for (id, count) in page.asset_references() {
+                *references.entry(id).or_insert(0) += count;
+            }
+        }
+        references
+    }
+
+    /// Removes the shared assets no page shows, returning their ids. The
+    /// assets of the pages are left as they are, see
+    /// [`Document::remove_unused_assets`].
+    pub fn remove_unused_assets(&mut self) -> Vec<String> {
+        let references = self.asset_references();
+        self.assets.retain_referenced(&references)
+    }
+
+    /// The outlines of all pages, with the targets on the same page set to
+    /// the index of their page.
+    pub fn outline(&self) -> Vec<Heading> {
+        fn resolve(heading: &mut Heading, page: usize) {
+            heading.target.page.get_or_insert(page);
+            for child in &mut heading.children {
+                resolve(child, page);
+            }
+        }
+        let mut outline = vec![];
+        for (i, page) in self.pages.iter().enumerate() {
+            for heading in &page.metadata.outline {
+                let mut heading = heading.clone();
+                resolve(&mut heading, i);
+                outline.push(heading);
+            }
+        }
+        outline
+    }
+
+    /// `page` with the shared assets it shows added to its own, so it shows
+    /// them outside of the notebook.
+    fn with_shared_assets(&self, page: &Document) -> Document {
+        let mut page = page.clone();
+        for id in page.asset_references().keys() {
+            match self.assets.get(id) {
+                Some(asset) if !page.assets.contains(id) => {
+                    page.assets.insert(id.clone(), asset.clone());
+                }
+                _ => {}
+            }
+        }
+        page
+    }
+
+    /// The files the notebook consists of, by their path in a container:
+    /// `pages/<index>.svg`, `assets/<name>` and `assets.txt` with the type of
+    /// each asset on a line of `<name>\t<mime>`.
+    pub fn to_entries(&self) -> Vec<(String, Vec<u8>)> {
+        let manifest: String = self
+            .assets
+            .iter()
+            .map(|(name, asset)| format!("{}\t{}\n", name, asset.mime))
+            .collect();
+        self.pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| (format!("pages/{}.svg", i), page.to_string().into_bytes()))
+            .chain(
+                self.assets
+                    .iter()
+                    .map(|(name, asset)| (format!("assets/{}", name), asset.data.clone())),
+            )
+            .chain(Some(("assets.txt".to_owned(), manifest.into_bytes())))
+            .collect()
+    }
+
+    /// Reads a notebook from the files of [`Notebook::to_entries`], other
+    /// files are ignored. Assets missing from `assets.txt` are of an
+    /// [unknown type](assets::UNKNOWN_MIME).
+    pub fn from_entries(
+        entries: impl IntoIterator<Item = (String, Vec<u8>)>,
+    ) -> Result<Self, NotebookError> {
+        let mut pages = BTreeMap::new();
+        let mut files = BTreeMap::new();
+        let mut mimes = BTreeMap::new();
+        for (path, data) in entries {
+            if let Some(name) = path.strip_prefix("assets/") {
+                files.insert(name.to_owned(), data);
+            } else if path == "assets.txt" {
+                let manifest = String::from_utf8(data)
+                    .map_err(|_| NotebookError::InvalidContainer(path.clone()))?;
+                for line in manifest.lines() {
+                    let (name, mime) = line
+                        .split_once('\t')
+                        .ok_or_else(|| NotebookError::InvalidContainer(path.clone()))?;
+                    mimes.insert(name.to_owned(), mime.to_owned());
+                }
+            } else if let Some(index) = path
+                .strip_prefix("pages/")
+                .and_then(|p| p.strip_suffix(".svg"))
+            {
+                let index = usize::from_str(index)
+                    .map_err(|_| NotebookError::InvalidContainer(path.clone()))?;
+                pages.insert(index, read_page(&path, data)?);
+            }
+        }
+        let mut assets = Assets::new();
+        for (name, data) in files {
+            let mime = mimes
+                .remove(&name)
+                .unwrap_or_else(|| assets::UNKNOWN_MIME.to_owned());
+            assets.insert(name, Asset::new(data, mime));
+        }
+        Ok(Notebook {
+            pages: pages.into_iter().map(|(_, page)| page).collect(),
+            assets,
+        })
+    }
+}
+
+/// Parses the page stored at `path` in a container.
+fn read_page(path: &str, data: Vec<u8>) -> Result<Document, NotebookError> {
+    let page =
+        String::from_utf8(data).map_err(|_| NotebookError::InvalidContainer(path.to_owned()))?;
+    Document::from_str(&page).map_err(|e| NotebookError::InvalidPage(path.to_owned(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Properties};
+    use crate::metadata::{Bookmark, Heading, Target};
+    use crate::notebook::Notebook;
+    use crate::Document;
+
+    #[test]
+    fn outline() {
+        let mut first = Document::default();
+        let mut chapter = Heading::new("Chapter", Target::default());
+        chapter
+            .children
+            .push(Heading::new("Appendix", Target::default()));
+        let section = Target {
+            page: Some(1),
+            ..Target::bookmark("section")
+        };
+        chapter.children.push(Heading::new("Section", section));
+        first.metadata.outline.push(chapter);
+        let mut second = Document::default();
+        second
+            .metadata
+            .bookmarks
+            .push(Bookmark::new("section", (0., 100.)));
+        let mut notebook = Notebook {
+            pages: vec![first, second],
+            ..Default::default()
+        };
+
+        let outline = notebook.outline();
+        assert_eq!(outline[0].target, Target::page(0));
+        let targets: Vec<_> = outline[0].children.iter().map(|h| h.target.page).collect();
+        assert_eq!(targets, vec![Some(0), Some(1)]);
+        assert_eq!(
+            notebook
+                .resolve_bookmark("section")
+                .map(|(page, b)| (page, b.position)),
+            Some((1, (0., 100.)))
+        );
+
+        assert!(notebook.broken_links().is_empty());
+        notebook.pages[1].elements.push(Element::Ellipse(Ellipse {
+            position: (0., 0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgb(0, 0, 0),
+            width: 1.,
+            radius: 1.,
+            properties: Properties {
+                link: Some(Target::from_str("?page=0#missing").unwrap()),
+                ..Default::default()
+            },
+        }));
+        let link = Target {
+            page: Some(0),
+            ..Target::bookmark("missing")
+        };
+        assert_eq!(notebook.broken_links(), vec![(1, &link)]);
+        assert_eq!(
+            Target::from_str("other.svg#intro").unwrap().to_string(),
+            "other.svg#intro"
+        );
+
+        let page = &notebook.pages[1];
+        assert_eq!(&Document::from_str(&page.to_string()).unwrap(), page);
+    }
+}
@@ -0,0 +1,295 @@
+//! Documents as a log of operations, e.g. for syncing only the changes since
+//! a revision, or undoing by replaying up to an earlier one.
+
+use thiserror::Error;
+
+use crate::colors::Color;
+use crate::editor::{clear_bounds, parent_mut, ElementPath};
+use crate::elements::Element;
+use crate::elements::Ephemeral;
+use crate::scalar::Scalar;
+use crate::transform::Transform;
+use crate::Document;
+
+/// The number of operations applied to an [`OpLog`].
+pub type Revision = usize;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum OpError {
+    #[error("no element at {0:?}")]
+    InvalidPath(ElementPath),
+}
+
+/// Colors and width to set, `None` keeps the current one.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Style {
+    /// The stroke, or the color of lines and texts.
+    pub stroke: Option<Color>,
+    pub fill: Option<Color>,
+    pub width: Option<Scalar>,
+}
+
+impl Style {
+    /// Sets the style of `element`, or of all elements in a layer.
+    pub fn apply_to(&self, element: &mut Element) {
+        let set = |value: &mut Color, color: Option<Color>| {
+            if let Some(color) = color {
+                *value = color;
+            }
+        };
+        let set_width = |value: &mut Scalar| {
+            if let Some(width) = self.width {
+                *value = width;
+            }
+        };
+        match element {
+            Element::Line(e) | Element::Ephemeral(Ephemeral { line: e, .. }) => {
+                set(&mut e.color, self.stroke);
+                set_width(&mut e.width);
+            }
+            Element::Ngon(e) => {
+                set(&mut e.stroke, self.stroke);
+                set(&mut e.fill, self.fill);
+                set_width(&mut e.width);
+            }
+            Element::Shape(e) => {
+                set(&mut e.stroke, self.stroke);
+                set(&mut e.fill, self.fill);
+                set_width(&mut e.width);
+            }
+            Element::Arc(e) => {
+                set(&mut e.stroke, self.stroke);
+                set(&mut e.fill, self.fill);
+                set_width(&mut e.width);
+            }
+            Element::Ellipse(e) => {
+                set(&mut e.stroke, self.stroke);
+                set(&mut e.fill, self.fill);
+                set_width(&mut e.width);
+            }
+            Element::Polyline(e) => {
+                set(&mut e.stroke, self.stroke);
+                set(&mut e.fill, self.fill);
+                set_width(&mut e.width);
+            }
+            Element::Text(e) => set(&mut e.color, self.stroke),
+            Element::Dimension(e) => {
+                set(&mut e.color, self.stroke);
+                set_width(&mut e.width);
+            }
+            Element::FileRef(_) | Element::Instance(_) | Element::Custom(_) | Element::Frame(_) => {
+            }
+            Element::Layer(e) => e.elements.iter_mut().for_each(|e| self.apply_to(e)),
+        }
+    }
+}
+
+/// An operation on the element at a path, see [`ElementPath`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Op {
+    /// Inserts the element, shifting the following ones.
+    AddStroke {
+        path: ElementPath,
+        element: Element,
+    },
+    Erase {
+        path: ElementPath,
+    },
+    /// Adds the transform to the one the element already has, see
+    /// [`Transform::apply_to`].
+    Transform {
+        path: ElementPath,
+        transform: Transform,
+    },
+    SetStyle {
+        path: ElementPath,
+        style: Style,
+    },
+}
+
+impl Document {
+    /// Applies the operations to an empty document.
+    pub fn from_ops<'a>(ops: impl IntoIterator<Item = &'a Op>) -> Result<Document, OpError> {
+        let mut document = Document::default();
+        for op in ops {
+            document.apply(op)?;
+        }
+        Ok(document)
+    }
+
+    /// Applies `op`, leaving the document unchanged on an error.
+    pub fn apply(&mut self, op: &Op) -> Result<(), OpError> {
+        let (path, parent) = match op {
+            Op::AddStroke { path, .. }
+            | Op::Erase { path }
+            | Op::Transform { path, .. }
+            | Op::SetStyle { path, .. } => (path, parent_mut(&mut self.elements, path)),
+        };
+        let (elements, i) = match parent {
+            Some((elements, i)) if i < elements.len() => (elements, i),
+            Some((elements, i)) if i == elements.len() && matches!(op, Op::AddStroke { .. }) => {
+                (elements, i)
+            }
+            _ => return Err(OpError::InvalidPath(path.clone())),
+        };
+        match op {
+            Op::AddStroke { element, .. } => elements.insert(i, element.clone()),
+            Op::Erase { .. } => {
+                elements.remove(i);
+            }
+            Op::Transform { transform, .. } => transform.apply_to(&mut elements[i]),
+            Op::SetStyle { style, .. } => {
+                // The bounds include the width
+                style.apply_to(&mut elements[i]);
+                elements[i].clear_stored_bounds();
+            }
+        }
+        clear_bounds(&mut self.elements, &path[..path.len() - 1]);
+        Ok(())
+    }
+}
+
+/// A document with the operations that led to it.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct OpLog {
+    ops: Vec<Op>,
+    document: Document,
+}
+
+impl OpLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replays `ops`, see [`Document::from_ops`].
+    pub fn from_ops(ops: Vec<Op>) -> Result<Self, OpError> {
+        Ok(OpLog {
+            document: Document::from_ops(&ops)?,
+            ops,
+        })
+    }
+
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.ops.len()
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    /// The operations applied after `revision`, e.g. to send to a client at
+    /// that revision. Empty for future revisions.
+    pub fn ops_since(&self, revision: Revision) -> &[Op] {
+        self.ops.get(revision..).unwrap_or_default()
+    }
+
+    /// Applies and records `op`, returning the new revision.
+    pub fn apply(&mut self, op: Op) -> Result<Revision, OpError> {
+        self.document.apply(&op)?;
+        self.ops.push(op);
+        Ok(self.revision())
+    }
+
+    /// The document at an earlier `revision`, e.g. to undo.
+    pub fn document_at(&self, revision: Revision) -> Document {
+        Document::from_ops(&self.ops[..revision.min(self.ops.len())])
+            .expect("recorded operations are valid")
+    }
+
+    /// Drops the operations after `revision`, e.g. to undo them.
+    pub fn truncate(&mut self, revision: Revision) {
+        if revision < self.ops.len() {
+            self.ops.truncate(revision);
+            self.document = self.document_at(revision);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::ops::{Op, OpError, OpLog, Style};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    fn line() -> Element {
+        Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        })
+    }
+
+    #[test]
+    fn op_log() {
+        let mut log = OpLog::new();
+        let ops = vec![
+            Op::AddStroke {
+                path: vec![0],
+                element: Element::Layer(Layer::new("ink")),
+            },
+            Op::AddStroke {
+                path: vec![0, 0],
+                element: line(),
+            },
+            Op::AddStroke {
+                path: vec![1],
+                element: line(),
+            },
+            Op::Transform {
+                path: vec![0, 0],
+                transform: Transform::translate(1., 2.),
+            },
+            Op::SetStyle {
+                path: vec![0],
+                style: Style {
+                    stroke: Some(Color::rgb(0xFF, 0, 0)),
+                    ..Default::default()
+                },
+            },
+            Op::Erase { path: vec![1] },
+        ];
+        for op in &ops {
+            log.apply(op.clone()).unwrap();
+        }
+        assert_eq!(log.revision(), 6);
+        assert_eq!(log.ops_since(4), &ops[4..]);
+        assert!(log.ops_since(7).is_empty());
+
+        match &log.document().elements[..] {
+            [Element::Layer(layer)] => match &layer.elements[..] {
+                [Element::Line(line)] => {
+                    assert_eq!(line.color, Color::rgb(0xFF, 0, 0));
+                    assert_eq!(line.properties.transform, Transform::translate(1., 2.));
+                }
+                elements => panic!("{:?}", elements),
+            },
+            elements => panic!("{:?}", elements),
+        }
+        assert_eq!(&Document::from_ops(&ops).unwrap(), log.document());
+
+        assert_eq!(
+            log.apply(Op::Erase { path: vec![1] }),
+            Err(OpError::InvalidPath(vec![1]))
+        );
+        assert_eq!(
+            log.apply(Op::AddStroke {
+                path: vec![0, 0, 0],
+                element: line(),
+            }),
+            Err(OpError::InvalidPath(vec![0, 0, 0]))
+        );
+        assert_eq!(log.revision(), 6);
+
+        log.truncate(3);
+        assert_eq!(log.document().elements.len(), 2);
+        assert_eq!(log.document(), &log.document_at(3));
+    }
+}
@@ -0,0 +1,175 @@
+//! Shrinking serialized documents, see [`Document::optimize_for_size`].
+
+use crate::elements::Element;
+use crate::geometry;
+use crate::options::SerializeOptions;
+use crate::scalar::Scalar;
+use crate::Document;
+
+/// How much [`Document::optimize_for_size_with`] may change the geometry.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct OptimizeOptions {
+    /// How far simplified lines and polylines may deviate from the original.
+    pub tolerance: Scalar,
+    /// The decimal places the geometry is rounded to.
+    pub precision: u8,
+}
+
+impl Default for OptimizeOptions {
+    fn default() -> Self {
+        OptimizeOptions {
+            tolerance: 0.5,
+            precision: 2,
+        }
+    }
+}
+
+/// The size in bytes of the serialization after each step of
+/// [`Document::optimize_for_size`], each including the ones before.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SizeReport {
+    /// As written by [`Display`](std::fmt::Display).
+    pub original: usize,
+    /// With fewer points in lines and polylines.
+    pub simplified: usize,
+    /// With the geometry rounded.
+    pub rounded: usize,
+    /// With shared styles as classes.
+    pub style_classes: usize,
+    /// Without whitespace, the header comment and derived attributes.
+    pub compact: usize,
+}
+
+impl SizeReport {
+    /// The bytes saved by all steps.
+    pub fn saved(&self) -> usize {
+        self.original.saturating_sub(self.compact)
+    }
+
+    /// The optimized size relative to the original one.
+    pub fn ratio(&self) -> f64 {
+        if self.original == 0 {
+            1.0
+        } else {
+            self.compact as f64 / self.original as f64
+        }
+    }
+}
+
+impl Element {
+    /// Removes points from lines and polylines (also in layers) so that they
+    /// deviate at most `tolerance`, see [`geometry::simplify`]. Timestamps
+    /// and widths of the removed points are removed as well.
+    pub fn simplify(&mut self, tolerance: Scalar) {
+        match self {
+            Element::Line(line) => {
+                let indices: Vec<usize> = (0..line.points.len()).collect();
+                let kept = geometry::simplify(&indices, tolerance, |&i| line.points[i].into());
+                if !line.timestamps.is_empty() {
+                    line.timestamps = kept.iter().map(|&i| line.timestamps[i]).collect();
+                }
+                line.points = kept.iter().map(|&i| line.points[i]).collect();
+            }
+            Element::Polyline(polyline) => {
+                let indices: Vec<usize> = (0..polyline.points.len()).collect();
+                let kept = geometry::simplify(&indices, tolerance, |&i| polyline.points[i].into());
+                if !polyline.widths.is_empty() {
+                    polyline.widths = kept.iter().map(|&i| polyline.widths[i]).collect();
+                }
+                polyline.points = kept.iter().map(|&i| polyline.points[i]).collect();
+            }
+            Element::Layer(layer) => layer
+                .elements
+                .iter_mut()
+                .for_each(|e| e.simplify(tolerance)),
+            _ => {}
+        }
+    }
+}
+
+impl Document {
+    /// [`Document::optimize_for_size_with`] the default options.
+    pub fn optimize_for_size(&self) -> (String, SizeReport) {
+        self.optimize_for_size_with(&OptimizeOptions::default())
+    }
+
+    /// The smallest serialization this crate writes, with the geometry
+    /// simplified and rounded as allowed by `options`, and how much each step
+    /// saved.
+    ///
+    /// The result parses like any other document, but only to the
+    /// simplified one.
+    pub fn optimize_for_size_with(&self, options: &OptimizeOptions) -> (String, SizeReport) {
+        let original = self.to_string().len();
+        let mut document = self.clone();
+        for element in &mut document.elements {
+            element.simplify(options.tolerance);
+        }
+        let mut serialize = SerializeOptions::default();
+        let simplified = document.to_string_with(&serialize).len();
+        serialize.precision = Some(options.precision);
+        let rounded = document.to_string_with(&serialize).len();
+        serialize.style_classes = true;
+        let style_classes = document.to_string_with(&serialize).len();
+        serialize.pretty = false;
+        serialize.omit_derived = true;
+        let string = document.to_string_with(&serialize);
+        let report = SizeReport {
+            original,
+            simplified,
+            rounded,
+            style_classes,
+            compact: string.len(),
+        };
+        (string, report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Line, LinePoint, Properties};
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    #[test]
+    fn optimize_for_size() {
+        let line = |y: Scalar| {
+            Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 2.,
+                points: (0..50)
+                    .map(|x| LinePoint(x as Scalar / 3., y + (x % 2) as Scalar * 0.01, 1.))
+                    .collect(),
+                timestamps: (0..50).map(|t| 1000 + t * 10).collect(),
+                properties: Properties::default(),
+            })
+        };
+        let document = Document {
+            elements: (0..5).map(|y| line(y as Scalar * 10.)).collect(),
+            ..Default::default()
+        };
+        let (string, report) = document.optimize_for_size();
+        assert_eq!(report.original, document.to_string().len());
+        assert!(report.simplified < report.original);
+        assert!(report.rounded <= report.simplified);
+        assert!(report.style_classes < report.rounded);
+        assert!(report.compact < report.style_classes);
+        assert_eq!(report.compact, string.len());
+        assert!(report.ratio() < 0.5);
+        // The straight lines keep only their ends, and `d` has their points
+        assert!(!string.contains("svgnote:points"));
+        let parsed = Document::from_str(&string).unwrap();
+        for element in &parsed.elements {
+            match element {
+                Element::Line(line) => {
+                    assert_eq!(line.points.len(), 2);
+                    assert_eq!(line.timestamps, vec![1000, 1490]);
+                }
+                element => panic!("{:?}", element),
+            }
+        }
+    }
+}
@@ -0,0 +1,226 @@
+//! Configuration for parsing and serializing documents.
+
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use derivative::Derivative;
+
+use svg::node::element::tag;
+
+use crate::colors::Color;
+use crate::elements::{Attributes, ElementRegistry};
+use crate::fonts::{FontFile, FontLoader};
+use crate::geometry::BoundingBox;
+use crate::ids::IdGenerator;
+use crate::repair::Repair;
+use crate::scalar::Scalar;
+use crate::xml::Event;
+
+/// How to parse a document, see [`Document::from_str_with`].
+///
+/// The default is strict, i.e. missing style attributes and damaged elements
+/// are errors. Checksums are only verified with
+/// [`verify_checksum`](ParseOptions::verify_checksum).
+///
+/// [`Document::from_str_with`]: crate::Document::from_str_with
+#[derive(Derivative, Clone)]
+#[derivative(Debug, PartialEq)]
+pub struct ParseOptions {
+    /// Fix what can be fixed and leave out elements that cannot, see
+    /// [`Document::repair`](crate::Document::repair).
+    pub repair: bool,
+    /// Fail on a `svgnote:checksum` that does not match the document, which
+    /// serializes it once more. Documents without one are not verified.
+    pub verify_checksum: bool,
+    /// Snap the geometry to fixed-point, see
+    /// [`scalar::to_fixed_point`](crate::scalar::to_fixed_point).
+    pub fixed_point: bool,
+    /// The namespace URI of our attributes. They are read under whatever
+    /// prefix the document binds to it, not only `svgnote:`.
+    pub namespace: String,
+    /// Used for elements without a `stroke`.
+    pub stroke: Option<Color>,
+    /// Used for shapes without a `fill`.
+    pub fill: Option<Color>,
+    /// Used for elements without a `stroke-width` (or `svgnote:width` for
+    /// pen strokes).
+    pub width: Option<Scalar>,
+    /// Fail on a `svgnote:bbox` that does not contain the element, instead
+    /// of trusting it for culling.
+    pub verify_bounding_boxes: bool,
+    /// Gives the elements without an id one, see [`Document::assign_ids`].
+    ///
+    /// [`Document::assign_ids`]: crate::Document::assign_ids
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub ids: Option<Arc<dyn IdGenerator + Send + Sync>>,
+    /// Parses the elements of the tools registered by the app.
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub elements: Option<Arc<ElementRegistry>>,
+}
+
+impl ParseOptions {
+    /// The defaults SVG uses, i.e. no stroke, black fill and a width of `1`.
+    pub fn svg_defaults() -> Self {
+        ParseOptions {
+            stroke: Some(Color::rgba(0, 0, 0, 0)),
+            fill: Some(Color::rgb(0, 0, 0)),
+            width: Some(1.0),
+            ..Default::default()
+        }
+    }
+
+    /// Renames the attributes in the namespace to use the `svgnote:` prefix.
+    ///
+    /// `prefix` is the one the document binds to the namespace, it is set on
+    /// the `<svg>` tag.
+    pub(crate) fn normalize_namespace<'a>(
+        &self,
+        event: Event<'a>,
+        prefix: &mut Option<String>,
+    ) -> Event<'a> {
+        match event {
+            Event::Tag(name, kind, mut attributes) => {
+                if name == tag::SVG && kind != tag::Type::End {
+                    *prefix = attributes
+                        .iter()
+                        .filter(|(_, value)| **value == *self.namespace)
+                        .find_map(|(attribute, _)| attribute.strip_prefix("xmlns:"))
+                        .filter(|&prefix| prefix != "svgnote")
+                        .map(|prefix| format!("{}:", prefix));
+                }
+                if let Some(prefix) = prefix {
+                    let foreign: Vec<String> = attributes
+                        .iter()
+                        .map(|(attribute, _)| attribute)
+                        .filter(|attribute| attribute.starts_with(&**prefix))
+                        .map(str::to_owned)
+                        .collect();
+                    for attribute in foreign {
+                        let value = attributes.remove(&attribute).unwrap();
+                        let name = format!("svgnote:{}", &attribute[prefix.len()..]);
+                        attributes.insert(name, value);
+                    }
+                }
+                Event::Tag(name, kind, attributes)
+            }
+            event => event,
+        }
+    }
+
+    /// Adds the defaults for missing style attributes of the element `name`,
+    /// recording them as [`Repair::Default`].
+    pub(crate) fn apply_defaults(
+        &self,
+        name: &str,
+        attributes: &mut Attributes<'_>,
+        repairs: &mut Vec<Repair>,
+    ) {
+        let width = match name {
+            tag::Path => "svgnote:width",
+            tag::Polygon | tag::Polyline | tag::Ellipse => "stroke-width",
+            _ => return,
+        };
+        let mut defaults = vec![
+            ("stroke", self.stroke.map(|c| c.to_string())),
+            (width, self.width.map(|w| w.to_string())),
+        ];
+        // Pen strokes are never filled
+        if name != tag::Path {
+            defaults.push(("fill", self.fill.map(|c| c.to_string())));
+        }
+        for (attribute, default) in defaults {
+            if let (false, Some(default)) = (attributes.contains_key(attribute), default) {
+                attributes.insert(attribute, default);
+                repairs.push(Repair::Default(attribute.to_owned()));
+            }
+        }
+    }
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            repair: false,
+            verify_checksum: false,
+            fixed_point: false,
+            namespace: NAMESPACE.to_owned(),
+            stroke: None,
+            fill: None,
+            width: None,
+            verify_bounding_boxes: false,
+            ids: None,
+            elements: None,
+        }
+    }
+}
+
+/// The namespace URI of the `svgnote:` attributes.
+pub const NAMESPACE: &str = "https://github.com/ModProg/SVGNotesLib";
+
+/// How to serialize a document, see [`Document::to_string_with`].
+///
+/// The default is what [`Display`](std::fmt::Display) writes.
+///
+/// [`Document::to_string_with`]: crate::Document::to_string_with
+#[derive(Derivative, Clone)]
+#[derivative(Debug, PartialEq)]
+pub struct SerializeOptions {
+    /// Round coordinates, widths and transforms to this many decimal places.
+    pub precision: Option<u8>,
+    /// Snap the geometry to fixed-point, see
+    /// [`scalar::to_fixed_point`](crate::scalar::to_fixed_point).
+    pub fixed_point: bool,
+    /// Put every element on its own line and add the header comment.
+    pub pretty: bool,
+    /// Include a `svgnote:checksum`, e.g. to detect truncated files, see
+    /// [`ParseOptions::verify_checksum`].
+    pub checksum: bool,
+    /// Write styles used by more than one element once, as a class in a
+    /// `<style>`, instead of as attributes of every element.
+    pub style_classes: bool,
+    /// Leave out attributes that are read back the same from others, i.e.
+    /// `svgnote:points` of pen strokes without pressure, which are then read
+    /// from the path data.
+    pub omit_derived: bool,
+    /// Font files by family, embedded for the texts using them so they
+    /// render the same without the font installed. Not part of the checksum.
+    pub fonts: BTreeMap<String, FontFile>,
+    /// Convert texts to paths of their glyph outlines, for viewers that do
+    /// not render text well. The content is kept in `svgnote:content`, so
+    /// they are still texts when parsed.
+    #[derivative(Debug = "ignore", PartialEq = "ignore")]
+    pub outline_text: Option<Rc<dyn FontLoader>>,
+    /// The area shown, with the size in `mm` to match, instead of the whole
+    /// page. Not part of the checksum, see
+    /// [`Document::export_cropped`](crate::Document::export_cropped).
+    pub view_box: Option<BoundingBox>,
+    /// Write the bounds of every element as `svgnote:bbox`, so viewers can
+    /// skip what is off screen without looking at the geometry, see
+    /// [`Document::render_items_in`](crate::Document::render_items_in).
+    pub bounding_boxes: bool,
+    /// Write every element with a non-scaling stroke, see
+    /// [`Properties::non_scaling_stroke`], e.g. for diagrams scaled into web
+    /// pages.
+    ///
+    /// [`Properties::non_scaling_stroke`]: crate::elements::Properties::non_scaling_stroke
+    pub non_scaling_strokes: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        SerializeOptions {
+            precision: None,
+            fixed_point: false,
+            pretty: true,
+            checksum: false,
+            style_classes: false,
+            omit_derived: false,
+            fonts: BTreeMap::new(),
+            outline_text: None,
+            view_box: None,
+            bounding_boxes: false,
+            non_scaling_strokes: false,
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Replaying how a document was drawn, see
+//! [`Document::playback`](crate::Document::playback), and cutting it to a
+//! time range.
+
+use crate::elements::{Element, Line};
+use crate::render::RenderItem;
+
+/// How fine the steps of a playback are.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Granularity {
+    /// A step per element.
+    Elements,
+    /// A step per point of pen strokes with timestamps, and per element for
+    /// everything else.
+    Points,
+}
+
+/// Something drawn during a playback.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Step<'a> {
+    /// The index of the element in
+    /// [`Document::render_items`](crate::Document::render_items).
+    pub item: usize,
+    pub element: &'a Element,
+    /// The index of the point drawn in this step, `None` if the whole element
+    /// is drawn.
+    pub point: Option<usize>,
+    /// When the step was drawn, in milliseconds since the Unix epoch. `None`
+    /// for elements without timestamps.
+    pub timestamp: Option<u64>,
+    /// The time since the previous step, in milliseconds.
+    pub delay: u64,
+}
+
+/// The elements of `items` in the order they were drawn.
+///
+/// Elements without timestamps are drawn right after the last point of the
+/// element before them in `items`, and at the start if there is none.
+pub(crate) fn steps<'a>(
+    items: &[RenderItem<'a>],
+    granularity: Granularity,
+) -> impl Iterator<Item = Step<'a>> + 'a {
+    let mut steps = vec![];
+    // The time untimed elements are sorted at
+    let mut previous = None;
+    for (item, render_item) in items.iter().enumerate() {
+        let element = render_item.element;
+        let timestamps = match element {
+            Element::Line(line) => &line.timestamps[..],
+            _ => &[][..],
+        };
+        let step = |point, timestamp| Step {
+            item,
+            element,
+            point,
+            timestamp,
+            delay: 0,
+        };
+        match (granularity, timestamps.first()) {
+            (Granularity::Points, Some(_)) => {
+                for (point, &timestamp) in timestamps.iter().enumerate() {
+                    steps.push((timestamp, step(Some(point), Some(timestamp))));
+                }
+            }
+            (_, Some(&start)) => steps.push((start, step(None, Some(start)))),
+            (_, None) => steps.push((previous.unwrap_or(0), step(None, None))),
+        }
+        previous = timestamps.last().copied().or(previous);
+    }
+    // Stable, so steps drawn at the same time keep their order
+    steps.sort_by_key(|&(time, _)| time);
+    let mut last = None;
+    steps.into_iter().map(move |(time, mut step)| {
+        step.delay = last.map(|last| time - last).unwrap_or(0);
+        last = Some(time);
+        step
+    })
+}
+
+impl Line {
+    /// Whether any point was drawn in `start..end` (in milliseconds since the
+    /// Unix epoch), `false` without timestamps.
+    pub fn drawn_between(&self, start: u64, end: u64) -> bool {
+        self.timestamps.iter().any(|t| (start..end).contains(t))
+    }
+}
+
+/// Removes the points of pen strokes drawn outside of `start..end`, and the
+/// strokes without any points left, descending into layers.
+///
+/// Elements without timestamps are kept.
+pub(crate) fn retain_time_range(elements: &mut Vec<Element>, start: u64, end: u64) {
+    for element in elements.iter_mut() {
+        match element {
+            Element::Line(line) if !line.timestamps.is_empty() => {
+                let (points, timestamps) = line
+                    .points
+                    .iter()
+                    .zip(&line.timestamps)
+                    .filter(|(_, t)| (start..end).contains(*t))
+                    .map(|(&p, &t)| (p, t))
+                    .unzip();
+                line.points = points;
+                line.timestamps = timestamps;
+            }
+            Element::Layer(layer) => retain_time_range(&mut layer.elements, start, end),
+            _ => {}
+        }
+    }
+    elements.retain(|e| !matches!(e, Element::Line(line) if line.points.is_empty()));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Line, LinePoint, Properties};
+    use crate::playback::Granularity;
+    use crate::Document;
+
+    fn line(timestamps: Vec<u64>) -> Element {
+        Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: timestamps.iter().map(|_| LinePoint(0., 0., 1.)).collect(),
+            timestamps,
+            properties: Properties::default(),
+        })
+    }
+
+    #[test]
+    fn playback() {
+        let ellipse = Element::Ellipse(Ellipse {
+            position: (0., 0.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 1.,
+            radius: 1.,
+            properties: Properties::default(),
+        });
+        let doc = Document {
+            elements: vec![line(vec![2000, 2100]), ellipse, line(vec![1000, 1050])],
+            ..Default::default()
+        };
+
+        let steps = doc.playback(Granularity::Elements);
+        let order: Vec<_> = steps.map(|s| (s.item, s.delay)).collect();
+        assert_eq!(order, vec![(2, 0), (0, 1000), (1, 100)]);
+
+        let steps = doc.playback(Granularity::Points);
+        let order: Vec<_> = steps.map(|s| (s.item, s.point, s.delay)).collect();
+        assert_eq!(
+            order,
+            vec![
+                (2, Some(0), 0),
+                (2, Some(1), 50),
+                (0, Some(0), 950),
+                (0, Some(1), 100),
+                (1, None, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn time_range() {
+        let mut layer = Layer::new("meeting");
+        layer.elements.push(line(vec![1000, 2000, 3000]));
+        layer.elements.push(line(vec![5000]));
+        let mut doc = Document {
+            elements: vec![Element::Layer(layer), line(vec![])],
+            ..Default::default()
+        };
+        assert_eq!(doc.elements_in_time_range(1500, 2500).len(), 1);
+        assert!(doc.elements_in_time_range(3500, 4000).is_empty());
+
+        doc.retain_time_range(1500, 4000);
+        match &doc.elements[..] {
+            [Element::Layer(layer), Element::Line(untimed)] => {
+                assert_eq!(layer.elements, vec![line(vec![2000, 3000])]);
+                assert!(untimed.timestamps.is_empty());
+            }
+            elements => panic!("{:?}", elements),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Exporting documents to be revealed step by step, e.g. to build up a
+//! diagram in front of a class the way it was planned.
+
+use svg::node::element::Group;
+
+use crate::elements::{Element, Layer};
+use crate::options::SerializeOptions;
+use crate::Document;
+
+/// How [`Document::reveal_steps`] splits a document into steps.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Reveal {
+    /// A step per top level layer. Elements outside of layers are shown
+    /// from the start.
+    Layers,
+    /// A step per top level element, with layers revealed as a whole.
+    Elements,
+    /// A step per this many milliseconds of drawing, by when pen strokes
+    /// were started. Elements without timestamps are revealed with the
+    /// element before them, like in [`Document::playback`].
+    Time(u64),
+}
+
+impl Document {
+    /// What is added in each step, in order, in the layers it is in.
+    /// Steps without elements are left out.
+    pub fn reveal_steps(&self, reveal: Reveal) -> Vec<Document> {
+        let keys = keys(&self.elements, reveal);
+        steps(&keys)
+            .into_iter()
+            .map(|step| self.retained(&keys, |key| key == step))
+            .collect()
+    }
+
+    /// A document per step of [`Document::reveal_steps`], each with
+    /// everything up to it.
+    pub fn export_steps(&self, reveal: Reveal) -> Vec<String> {
+        let keys = keys(&self.elements, reveal);
+        steps(&keys)
+            .into_iter()
+            .map(|step| self.retained(&keys, |key| key <= step).to_string())
+            .collect()
+    }
+
+    /// The document with the elements of every step of
+    /// [`Document::reveal_steps`] in a `<g class="step step-{index}">`, so a
+    /// viewer can reveal them with CSS or a script.
+    ///
+    /// Later steps are drawn on top of earlier ones. Without a checksum, as
+    /// the elements are reordered.
+    pub fn export_presentation(&self, reveal: Reveal) -> String {
+        let options = SerializeOptions {
+            checksum: false,
+            ..Default::default()
+        };
+        let steps = self.reveal_steps(reveal);
+        let doc = self.to_svg_with(&options, |doc, output| {
+            steps.iter().enumerate().fold(doc, |doc, (i, step)| {
+                doc.add(step.elements.iter().fold(
+                    Group::new().set("class", format!("step step-{}", i)),
+                    |group, element| element.add_to_with(group, output),
+                ))
+            })
+        });
+        self.finish_svg(doc, &options)
+    }
+
+    /// The document with only the elements whose key passes `keep`.
+    fn retained(&self, keys: &[u64], keep: impl Fn(u64) -> bool) -> Document {
+        Document {
+            elements: retain(&self.elements, &mut keys.iter().copied(), &keep),
+            metadata: self.metadata.clone(),
+            symbols: self.symbols.clone(),
+            assets: self.assets.clone(),
+        }
+    }
+}
+
+/// The step of every element that is not a layer, in document order. Only
+/// their order matters.
+fn keys(elements: &[Element], reveal: Reveal) -> Vec<u64> {
+    fn leaves<'a>(element: &'a Element, f: &mut impl FnMut(&'a Element)) {
+        match element {
+            Element::Layer(layer) => layer.elements.iter().for_each(|e| leaves(e, f)),
+            element => f(element),
+        }
+    }
+    let mut keys = vec![];
+    // The bucket elements without timestamps are put in
+    let mut previous = 0;
+    let mut layers = 0;
+    for (i, element) in elements.iter().enumerate() {
+        let top = match (reveal, element) {
+            (Reveal::Layers, Element::Layer(_)) => {
+                layers += 1;
+                layers
+            }
+            (Reveal::Elements, _) => i as u64,
+            _ => 0,
+        };
+        leaves(element, &mut |leaf| {
+            keys.push(match (reveal, leaf) {
+                (Reveal::Time(bucket), Element::Line(line)) => {
+                    if let Some(&start) = line.timestamps.first() {
+                        previous = start / bucket.max(1);
+                    }
+                    previous
+                }
+                (Reveal::Time(_), _) => previous,
+                _ => top,
+            })
+        });
+    }
+    keys
+}
+
+/// The distinct `keys` in order.
+fn steps(keys: &[u64]) -> Vec<u64> {
+    let mut steps = keys.to_vec();
+    steps.sort_unstable();
+    steps.dedup();
+    steps
+}
+
+/// The elements whose key passes `keep`, in the layers they are in. Layers
+/// left without elements are left out.
+fn retain(
+    elements: &[Element],
+    keys: &mut impl Iterator<Item = u64>,
+    keep: &impl Fn(u64) -> bool,
+) -> Vec<Element> {
+    let mut retained = vec![];
+    for element in elements {
+        match element {
+            Element::Layer(layer) => {
+                let elements = retain(&layer.elements, keys, keep);
+                if !elements.is_empty() {
+                    retained.push(Element::Layer(Layer {
+                        name: layer.name.clone(),
+                        elements,
+                        opacity: layer.opacity,
+                        blend: layer.blend,
+                        properties: layer.properties.clone(),
+                    }));
+                }
+            }
+            element => {
+                if keys.next().map_or(false, keep) {
+                    retained.push(element.clone());
+                }
+            }
+        }
+    }
+    retained
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Line, LinePoint, Properties};
+    use crate::presentation::Reveal;
+    use crate::Document;
+
+    fn line(timestamps: Vec<u64>) -> Element {
+        Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: timestamps.iter().map(|_| LinePoint(0., 0., 1.)).collect(),
+            timestamps,
+            properties: Properties::default(),
+        })
+    }
+
+    #[test]
+    fn reveal() {
+        let mut axes = Layer::new("axes");
+        axes.elements = vec![line(vec![1000]), line(vec![1500])];
+        let mut plot = Layer::new("plot");
+        plot.elements = vec![line(vec![5000]), line(vec![])];
+        let doc = Document {
+            elements: vec![line(vec![6000]), Element::Layer(axes), Element::Layer(plot)],
+            ..Default::default()
+        };
+
+        let steps = doc.reveal_steps(Reveal::Layers);
+        let counts: Vec<_> = steps.iter().map(|s| s.elements.len()).collect();
+        assert_eq!(counts, vec![1, 1, 1]);
+        assert_eq!(steps[2].elements, doc.elements[2..]);
+
+        // Time buckets descend into layers, the untimed stroke goes with the
+        // one before it
+        let steps = doc.reveal_steps(Reveal::Time(2000));
+        let lines: Vec<_> = steps.iter().map(|s| s.render_items().len()).collect();
+        assert_eq!(lines, vec![2, 2, 1]);
+
+        let exports = doc.export_steps(Reveal::Elements);
+        assert_eq!(exports.len(), 3);
+        assert_eq!(Document::from_str(&exports[2]).unwrap(), doc);
+        assert_eq!(
+            Document::from_str(&exports[0]).unwrap().elements,
+            doc.elements[..1]
+        );
+
+        let presentation = doc.export_presentation(Reveal::Layers);
+        assert!(presentation.contains(r#"class="step step-2""#));
+        assert_eq!(Document::from_str(&presentation).unwrap(), doc);
+    }
+}
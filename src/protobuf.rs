@@ -0,0 +1,1352 @@
+//! The protobuf encoding of documents and of [`Op`]s, for syncing with
+//! clients over slow connections, described by `schema/svgnote.proto`.
+//!
+//! Coordinates are stored as doubles, so they survive the round trip also
+//! with the `f64` feature, points as packed doubles and timestamps as
+//! differences.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+use std::sync;
+
+use prost::Message;
+use thiserror::Error;
+
+use crate::assets::{Asset, Assets};
+use crate::calibration::InputCalibration;
+use crate::colors::Color;
+use crate::elements::{
+    Arc, Attachment, BlendMode, Clip, Custom, Dimension, Element, Ellipse, Ephemeral, FileRef,
+    Font, Frame, Instance, Layer, LengthUnit, Line, LinePoint, Ngon, Polyline, PolylinePoint,
+    Properties, Shape, Stored, Text,
+};
+use crate::geometry::BoundingBox;
+use crate::ids::ElementId;
+use crate::metadata::{Bookmark, Heading, Metadata, Placeholder, Target, ToolState};
+use crate::ops::{Op, Revision, Style};
+use crate::ruling::{Ruling, RulingKind};
+use crate::scalar::Scalar;
+use crate::snap::{Grid, Guide};
+use crate::transform::Transform;
+use crate::Document;
+
+/// The messages of `schema/svgnote.proto`.
+mod wire {
+    use std::collections::BTreeMap;
+
+    use prost::Message;
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Document {
+        #[prost(message, repeated, tag = "1")]
+        pub elements: Vec<Element>,
+        #[prost(message, optional, tag = "2")]
+        pub metadata: Option<Metadata>,
+        #[prost(message, repeated, tag = "3")]
+        pub symbols: Vec<Symbol>,
+        #[prost(message, repeated, tag = "4")]
+        pub assets: Vec<Asset>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Asset {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(string, tag = "2")]
+        pub mime: String,
+        #[prost(bytes, tag = "3")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Symbol {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(message, repeated, tag = "2")]
+        pub elements: Vec<Element>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Properties {
+        #[prost(bool, tag = "1")]
+        pub hidden: bool,
+        #[prost(message, optional, tag = "2")]
+        pub clip: Option<Clip>,
+        #[prost(double, repeated, tag = "3")]
+        pub transform: Vec<f64>,
+        #[prost(string, optional, tag = "4")]
+        pub title: Option<String>,
+        #[prost(string, optional, tag = "5")]
+        pub description: Option<String>,
+        #[prost(string, optional, tag = "6")]
+        pub author: Option<String>,
+        #[prost(string, optional, tag = "7")]
+        pub link: Option<String>,
+        #[prost(bool, tag = "8")]
+        pub non_scaling_stroke: bool,
+        #[prost(string, optional, tag = "9")]
+        pub id: Option<String>,
+        #[prost(float, optional, tag = "10")]
+        pub stroke_opacity: Option<f32>,
+        #[prost(float, optional, tag = "11")]
+        pub fill_opacity: Option<f32>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Clip {
+        #[prost(bool, tag = "1")]
+        pub ellipse: bool,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+        #[prost(double, tag = "4")]
+        pub width: f64,
+        #[prost(double, tag = "5")]
+        pub height: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Element {
+        #[prost(message, optional, tag = "1")]
+        pub properties: Option<Properties>,
+        #[prost(
+            oneof = "element::Kind",
+            tags = "2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14"
+        )]
+        pub kind: Option<element::Kind>,
+    }
+
+    pub mod element {
+        use prost::Oneof;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum Kind {
+            #[prost(message, tag = "2")]
+            Line(super::Line),
+            #[prost(message, tag = "3")]
+            Ngon(super::Ngon),
+            #[prost(message, tag = "4")]
+            Ellipse(super::Ellipse),
+            #[prost(message, tag = "5")]
+            Polyline(super::Polyline),
+            #[prost(message, tag = "6")]
+            Text(super::Text),
+            #[prost(message, tag = "7")]
+            File(super::FileRef),
+            #[prost(message, tag = "8")]
+            Layer(super::Layer),
+            #[prost(message, tag = "9")]
+            Shape(super::Shape),
+            #[prost(message, tag = "10")]
+            Arc(super::Arc),
+            #[prost(message, tag = "11")]
+            Instance(super::Instance),
+            #[prost(message, tag = "12")]
+            Custom(super::Custom),
+            #[prost(message, tag = "13")]
+            Dimension(super::Dimension),
+            #[prost(message, tag = "14")]
+            Frame(super::Frame),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Line {
+        #[prost(fixed32, tag = "1")]
+        pub color: u32,
+        #[prost(double, tag = "2")]
+        pub width: f64,
+        #[prost(double, repeated, tag = "3")]
+        pub points: Vec<f64>,
+        #[prost(uint64, repeated, tag = "4")]
+        pub timestamps: Vec<u64>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Ngon {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(fixed32, tag = "3")]
+        pub stroke: u32,
+        #[prost(fixed32, tag = "4")]
+        pub fill: u32,
+        #[prost(double, tag = "5")]
+        pub width: f64,
+        #[prost(double, tag = "6")]
+        pub angle: f64,
+        #[prost(uint32, tag = "7")]
+        pub n: u32,
+        #[prost(double, tag = "8")]
+        pub radius: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Ellipse {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(fixed32, tag = "3")]
+        pub stroke: u32,
+        #[prost(fixed32, tag = "4")]
+        pub fill: u32,
+        #[prost(double, tag = "5")]
+        pub width: f64,
+        #[prost(double, tag = "6")]
+        pub radius: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Polyline {
+        #[prost(fixed32, tag = "1")]
+        pub stroke: u32,
+        #[prost(fixed32, tag = "2")]
+        pub fill: u32,
+        #[prost(double, tag = "3")]
+        pub width: f64,
+        #[prost(double, repeated, tag = "4")]
+        pub points: Vec<f64>,
+        #[prost(double, repeated, tag = "5")]
+        pub widths: Vec<f64>,
+        #[prost(bool, tag = "6")]
+        pub closed: bool,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Text {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(string, tag = "3")]
+        pub content: String,
+        #[prost(fixed32, tag = "4")]
+        pub color: u32,
+        #[prost(string, tag = "5")]
+        pub font_family: String,
+        #[prost(double, tag = "6")]
+        pub font_size: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct FileRef {
+        #[prost(string, tag = "1")]
+        pub path: String,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+        #[prost(double, tag = "4")]
+        pub width: f64,
+        #[prost(double, tag = "5")]
+        pub height: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Shape {
+        #[prost(string, tag = "1")]
+        pub tool: String,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+        #[prost(double, tag = "4")]
+        pub radius: f64,
+        #[prost(double, tag = "5")]
+        pub angle: f64,
+        #[prost(btree_map = "string, double", tag = "6")]
+        pub parameters: BTreeMap<String, f64>,
+        #[prost(fixed32, tag = "7")]
+        pub stroke: u32,
+        #[prost(fixed32, tag = "8")]
+        pub fill: u32,
+        #[prost(double, tag = "9")]
+        pub width: f64,
+        #[prost(double, repeated, tag = "10")]
+        pub points: Vec<f64>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Arc {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(double, tag = "3")]
+        pub radius: f64,
+        #[prost(double, tag = "4")]
+        pub start: f64,
+        #[prost(double, tag = "5")]
+        pub end: f64,
+        #[prost(bool, tag = "6")]
+        pub pie: bool,
+        #[prost(fixed32, tag = "7")]
+        pub stroke: u32,
+        #[prost(fixed32, tag = "8")]
+        pub fill: u32,
+        #[prost(double, tag = "9")]
+        pub width: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Instance {
+        #[prost(string, tag = "1")]
+        pub symbol: String,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Custom {
+        #[prost(string, tag = "1")]
+        pub tool: String,
+        #[prost(string, tag = "2")]
+        pub tag: String,
+        #[prost(btree_map = "string, string", tag = "3")]
+        pub attributes: BTreeMap<String, String>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Dimension {
+        #[prost(double, tag = "1")]
+        pub start_x: f64,
+        #[prost(double, tag = "2")]
+        pub start_y: f64,
+        #[prost(double, tag = "3")]
+        pub end_x: f64,
+        #[prost(double, tag = "4")]
+        pub end_y: f64,
+        #[prost(message, optional, tag = "5")]
+        pub start_attachment: Option<Attachment>,
+        #[prost(message, optional, tag = "6")]
+        pub end_attachment: Option<Attachment>,
+        #[prost(double, tag = "7")]
+        pub offset: f64,
+        #[prost(string, tag = "8")]
+        pub unit: String,
+        #[prost(fixed32, tag = "9")]
+        pub color: u32,
+        #[prost(double, tag = "10")]
+        pub width: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Frame {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(uint32, tag = "2")]
+        pub index: u32,
+        #[prost(double, tag = "3")]
+        pub x: f64,
+        #[prost(double, tag = "4")]
+        pub y: f64,
+        #[prost(double, tag = "5")]
+        pub width: f64,
+        #[prost(double, tag = "6")]
+        pub height: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Attachment {
+        #[prost(string, tag = "1")]
+        pub id: String,
+        #[prost(uint64, tag = "2")]
+        pub anchor: u64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Layer {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(float, tag = "2")]
+        pub opacity: f32,
+        #[prost(message, repeated, tag = "3")]
+        pub elements: Vec<Element>,
+        #[prost(string, tag = "4")]
+        pub blend: String,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Metadata {
+        #[prost(message, repeated, tag = "1")]
+        pub bookmarks: Vec<Bookmark>,
+        #[prost(message, repeated, tag = "2")]
+        pub outline: Vec<Heading>,
+        #[prost(message, repeated, tag = "3")]
+        pub placeholders: Vec<Placeholder>,
+        /// The spacing of the grid.
+        #[prost(double, optional, tag = "4")]
+        pub grid: Option<f64>,
+        #[prost(message, optional, tag = "5")]
+        pub calibration: Option<Calibration>,
+        #[prost(message, repeated, tag = "6")]
+        pub tools: Vec<ToolState>,
+        #[prost(fixed32, optional, tag = "7")]
+        pub background: Option<u32>,
+        #[prost(message, optional, tag = "8")]
+        pub ruling: Option<Ruling>,
+        #[prost(message, repeated, tag = "9")]
+        pub guides: Vec<Guide>,
+        /// `mm`, `cm` or `in`.
+        #[prost(string, optional, tag = "10")]
+        pub unit: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Guide {
+        /// `horizontal` only uses `y`, `vertical` only `x`.
+        #[prost(string, tag = "1")]
+        pub orientation: String,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+        #[prost(double, tag = "4")]
+        pub angle: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Ruling {
+        #[prost(string, tag = "1")]
+        pub kind: String,
+        #[prost(double, tag = "2")]
+        pub spacing: f64,
+        #[prost(fixed32, tag = "3")]
+        pub color: u32,
+        #[prost(double, tag = "4")]
+        pub width: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct ToolState {
+        #[prost(string, tag = "1")]
+        pub layer: String,
+        #[prost(string, tag = "2")]
+        pub tool: String,
+        #[prost(fixed32, tag = "3")]
+        pub color: u32,
+        #[prost(double, tag = "4")]
+        pub width: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Calibration {
+        #[prost(double, repeated, tag = "1")]
+        pub transform: Vec<f64>,
+        #[prost(double, tag = "2")]
+        pub pressure_min: f64,
+        #[prost(double, tag = "3")]
+        pub pressure_max: f64,
+        #[prost(double, tag = "4")]
+        pub deadband: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Bookmark {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(double, tag = "2")]
+        pub x: f64,
+        #[prost(double, tag = "3")]
+        pub y: f64,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Heading {
+        #[prost(string, tag = "1")]
+        pub title: String,
+        #[prost(string, tag = "2")]
+        pub target: String,
+        #[prost(message, repeated, tag = "3")]
+        pub children: Vec<Heading>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Placeholder {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(double, tag = "2")]
+        pub min_x: f64,
+        #[prost(double, tag = "3")]
+        pub min_y: f64,
+        #[prost(double, tag = "4")]
+        pub max_x: f64,
+        #[prost(double, tag = "5")]
+        pub max_y: f64,
+        #[prost(string, optional, tag = "6")]
+        pub element: Option<String>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Ops {
+        #[prost(uint64, tag = "1")]
+        pub since: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub ops: Vec<Op>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Op {
+        #[prost(uint32, repeated, tag = "1")]
+        pub path: Vec<u32>,
+        #[prost(oneof = "op::Kind", tags = "2, 3, 4, 5")]
+        pub kind: Option<op::Kind>,
+    }
+
+    pub mod op {
+        use prost::Oneof;
+
+        #[derive(Clone, PartialEq, Oneof)]
+        pub enum Kind {
+            #[prost(message, tag = "2")]
+            AddStroke(super::Element),
+            #[prost(message, tag = "3")]
+            Erase(super::Erase),
+            #[prost(message, tag = "4")]
+            Transform(super::Transform),
+            #[prost(message, tag = "5")]
+            SetStyle(super::Style),
+        }
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Erase {}
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Transform {
+        #[prost(double, repeated, tag = "1")]
+        pub matrix: Vec<f64>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Style {
+        #[prost(fixed32, optional, tag = "1")]
+        pub stroke: Option<u32>,
+        #[prost(fixed32, optional, tag = "2")]
+        pub fill: Option<u32>,
+        #[prost(double, optional, tag = "3")]
+        pub width: Option<f64>,
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ProtobufError {
+    #[error(transparent)]
+    Decode(#[from] prost::DecodeError),
+    #[error("missing `{0}`")]
+    Missing(&'static str),
+    #[error("invalid `{0}`")]
+    Invalid(&'static str),
+}
+
+fn encode(message: &impl Message) -> Vec<u8> {
+    let mut buffer = Vec::with_capacity(message.encoded_len());
+    message
+        .encode(&mut buffer)
+        .expect("the buffer has enough capacity");
+    buffer
+}
+
+fn color(color: Color) -> u32 {
+    u32::from_be_bytes([color.r, color.g, color.b, color.a])
+}
+
+fn to_color(color: u32) -> Color {
+    let [r, g, b, a] = color.to_be_bytes();
+    Color::rgba(r, g, b, a)
+}
+
+fn matrix(t: Transform) -> Vec<f64> {
+    [t.a, t.b, t.c, t.d, t.e, t.f]
+        .iter()
+        .map(|&v| v as f64)
+        .collect()
+}
+
+fn to_matrix(matrix: &[f64]) -> Result<Transform, ProtobufError> {
+    match *matrix {
+        [a, b, c, d, e, f] => Ok(Transform::new(
+            a as Scalar,
+            b as Scalar,
+            c as Scalar,
+            d as Scalar,
+            e as Scalar,
+            f as Scalar,
+        )),
+        _ => Err(ProtobufError::Invalid("transform")),
+    }
+}
+
+fn to_target(target: &str) -> Result<Target, ProtobufError> {
+    Target::from_str(target).map_err(|_| ProtobufError::Invalid("target"))
+}
+
+fn properties(properties: &Properties) -> wire::Properties {
+    wire::Properties {
+        hidden: properties.hidden,
+        clip: properties.clip.map(|clip| match clip {
+            Clip::Rectangle { position, size } => wire::Clip {
+                ellipse: false,
+                x: position.0 as f64,
+                y: position.1 as f64,
+                width: size.0 as f64,
+                height: size.1 as f64,
+            },
+            Clip::Ellipse { position, radius } => wire::Clip {
+                ellipse: true,
+                x: position.0 as f64,
+                y: position.1 as f64,
+                width: radius.0 as f64,
+                height: radius.1 as f64,
+            },
+        }),
+        transform: if properties.transform.is_identity() {
+            vec![]
+        } else {
+            matrix(properties.transform)
+        },
+        title: properties.title.clone(),
+        description: properties.description.clone(),
+        author: properties.author.clone(),
+        link: properties.link.as_ref().map(Target::to_string),
+        non_scaling_stroke: properties.non_scaling_stroke,
+        id: properties.id.as_ref().map(ElementId::to_string),
+        stroke_opacity: Some(properties.stroke_opacity).filter(|&o| o != 1.),
+        fill_opacity: Some(properties.fill_opacity).filter(|&o| o != 1.),
+    }
+}
+
+fn to_properties(properties: wire::Properties) -> Result<Properties, ProtobufError> {
+    Ok(Properties {
+        hidden: properties.hidden,
+        clip: properties.clip.map(|clip| {
+            let position = (clip.x as Scalar, clip.y as Scalar);
+            let size = (clip.width as Scalar, clip.height as Scalar);
+            if clip.ellipse {
+                Clip::ellipse(position, size)
+            } else {
+                Clip::rectangle(position, size)
+            }
+        }),
+        transform: if properties.transform.is_empty() {
+            Transform::IDENTITY
+        } else {
+            to_matrix(&properties.transform)?
+        },
+        title: properties.title,
+        description: properties.description,
+        author: properties.author,
+        link: properties.link.as_deref().map(to_target).transpose()?,
+        non_scaling_stroke: properties.non_scaling_stroke,
+        id: properties.id.map(ElementId::from),
+        stroke_opacity: properties.stroke_opacity.unwrap_or(1.),
+        fill_opacity: properties.fill_opacity.unwrap_or(1.),
+        ..Default::default()
+    })
+}
+
+/// The elements that are stored, i.e. without
+/// [`Ephemeral`] ones.
+fn elements(elements: &[Element]) -> Vec<wire::Element> {
+    elements
+        .iter()
+        .filter(|e| !e.is_ephemeral())
+        .map(element)
+        .collect()
+}
+
+fn element(element: &Element) -> wire::Element {
+    use wire::element::Kind;
+    let kind = match element {
+        // Left out by `elements`, written as its line on its own
+        Element::Line(e) | Element::Ephemeral(Ephemeral { line: e, .. }) => {
+            Kind::Line(wire::Line {
+                color: color(e.color),
+                width: e.width as f64,
+                points: e
+                    .points
+                    .iter()
+                    .flat_map(|p| vec![p.0 as f64, p.1 as f64, p.2 as f64])
+                    .collect(),
+                timestamps: e
+                    .timestamps
+                    .iter()
+                    .scan(0, |previous, &t| {
+                        let difference = t.wrapping_sub(*previous);
+                        *previous = t;
+                        Some(difference)
+                    })
+                    .collect(),
+            })
+        }
+        Element::Ngon(e) => Kind::Ngon(wire::Ngon {
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            stroke: color(e.stroke),
+            fill: color(e.fill),
+            width: e.width as f64,
+            angle: e.angle as f64,
+            n: e.n.into(),
+            radius: e.radius as f64,
+        }),
+        Element::Ellipse(e) => Kind::Ellipse(wire::Ellipse {
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            stroke: color(e.stroke),
+            fill: color(e.fill),
+            width: e.width as f64,
+            radius: e.radius as f64,
+        }),
+        Element::Polyline(e) => Kind::Polyline(wire::Polyline {
+            stroke: color(e.stroke),
+            fill: color(e.fill),
+            width: e.width as f64,
+            points: e
+                .points
+                .iter()
+                .flat_map(|p| vec![p.0 as f64, p.1 as f64])
+                .collect(),
+            widths: e.widths.iter().map(|&w| w as f64).collect(),
+            closed: e.closed,
+        }),
+        Element::Text(e) => Kind::Text(wire::Text {
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            content: e.content.clone(),
+            color: color(e.color),
+            font_family: e.font.family.clone(),
+            font_size: e.font.size as f64,
+        }),
+        Element::FileRef(e) => Kind::File(wire::FileRef {
+            path: e.path.clone(),
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            width: e.size.0 as f64,
+            height: e.size.1 as f64,
+        }),
+        Element::Layer(e) => Kind::Layer(wire::Layer {
+            name: e.name.clone(),
+            opacity: e.opacity,
+            elements: elements(&e.elements),
+            blend: match e.blend {
+                BlendMode::Normal => String::new(),
+                blend => blend.to_string(),
+            },
+        }),
+        Element::Shape(e) => Kind::Shape(wire::Shape {
+            tool: e.tool.clone(),
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            radius: e.radius as f64,
+            angle: e.angle as f64,
+            parameters: e
+                .parameters
+                .iter()
+                .map(|(name, &value)| (name.clone(), value as f64))
+                .collect(),
+            stroke: color(e.stroke),
+            fill: color(e.fill),
+            width: e.width as f64,
+            points: e
+                .points
+                .iter()
+                .flat_map(|p| vec![p.0 as f64, p.1 as f64])
+                .collect(),
+        }),
+        Element::Arc(e) => Kind::Arc(wire::Arc {
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            radius: e.radius as f64,
+            start: e.start as f64,
+            end: e.end as f64,
+            pie: e.pie,
+            stroke: color(e.stroke),
+            fill: color(e.fill),
+            width: e.width as f64,
+        }),
+        Element::Instance(e) => Kind::Instance(wire::Instance {
+            symbol: e.symbol.clone(),
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+        }),
+        Element::Custom(e) => Kind::Custom(wire::Custom {
+            tool: e.element.tool().to_owned(),
+            tag: e.element.tag().to_owned(),
+            attributes: e.element.attributes(),
+        }),
+        Element::Frame(e) => Kind::Frame(wire::Frame {
+            name: e.name.clone(),
+            index: e.index,
+            x: e.position.0 as f64,
+            y: e.position.1 as f64,
+            width: e.size.0 as f64,
+            height: e.size.1 as f64,
+        }),
+        Element::Dimension(e) => {
+            let attachment = |a: &Option<Attachment>| {
+                a.as_ref().map(|a| wire::Attachment {
+                    id: a.id.to_string(),
+                    anchor: a.anchor as u64,
+                })
+            };
+            Kind::Dimension(wire::Dimension {
+                start_x: e.start.0 as f64,
+                start_y: e.start.1 as f64,
+                end_x: e.end.0 as f64,
+                end_y: e.end.1 as f64,
+                start_attachment: attachment(&e.attachments[0]),
+                end_attachment: attachment(&e.attachments[1]),
+                offset: e.offset as f64,
+                unit: e.unit.to_string(),
+                color: color(e.color),
+                width: e.width as f64,
+            })
+        }
+    };
+    wire::Element {
+        properties: Some(properties(element.properties())),
+        kind: Some(kind),
+    }
+}
+
+fn to_element(element: wire::Element) -> Result<Element, ProtobufError> {
+    use wire::element::Kind;
+    let properties = to_properties(element.properties.unwrap_or_default())?;
+    let s = |v: f64| v as Scalar;
+    Ok(match element.kind.ok_or(ProtobufError::Missing("kind"))? {
+        Kind::Line(e) => {
+            if e.points.len() % 3 != 0 {
+                return Err(ProtobufError::Invalid("points"));
+            }
+            Element::Line(Line {
+                color: to_color(e.color),
+                width: s(e.width),
+                points: e
+                    .points
+                    .chunks(3)
+                    .map(|p| LinePoint(s(p[0]), s(p[1]), s(p[2])))
+                    .collect(),
+                timestamps: e
+                    .timestamps
+                    .iter()
+                    .scan(0u64, |previous, &difference| {
+                        *previous = previous.wrapping_add(difference);
+                        Some(*previous)
+                    })
+                    .collect(),
+                properties,
+            })
+        }
+        Kind::Ngon(e) => Element::Ngon(Ngon {
+            position: (s(e.x), s(e.y)),
+            stroke: to_color(e.stroke),
+            fill: to_color(e.fill),
+            width: s(e.width),
+            angle: s(e.angle),
+            n: u16::try_from(e.n).map_err(|_| ProtobufError::Invalid("n"))?,
+            radius: s(e.radius),
+            properties,
+        }),
+        Kind::Ellipse(e) => Element::Ellipse(Ellipse {
+            position: (s(e.x), s(e.y)),
+            stroke: to_color(e.stroke),
+            fill: to_color(e.fill),
+            width: s(e.width),
+            radius: s(e.radius),
+            properties,
+        }),
+        Kind::Polyline(e) => {
+            if e.points.len() % 2 != 0 {
+                return Err(ProtobufError::Invalid("points"));
+            }
+            Element::Polyline(Polyline {
+                stroke: to_color(e.stroke),
+                fill: to_color(e.fill),
+                width: s(e.width),
+                points: e
+                    .points
+                    .chunks(2)
+                    .map(|p| PolylinePoint(s(p[0]), s(p[1])))
+                    .collect(),
+                widths: e.widths.into_iter().map(s).collect(),
+                closed: e.closed,
+                properties,
+            })
+        }
+        Kind::Text(e) => Element::Text(Text {
+            position: (s(e.x), s(e.y)),
+            content: e.content,
+            color: to_color(e.color),
+            font: Font {
+                family: e.font_family,
+                size: s(e.font_size),
+            },
+            properties,
+        }),
+        Kind::File(e) => Element::FileRef(FileRef {
+            path: e.path,
+            position: (s(e.x), s(e.y)),
+            size: (s(e.width), s(e.height)),
+            properties,
+        }),
+        Kind::Layer(e) => Element::Layer(Layer {
+            name: e.name,
+            opacity: e.opacity,
+            elements: e
+                .elements
+                .into_iter()
+                .map(to_element)
+                .collect::<Result<_, _>>()?,
+            blend: match &*e.blend {
+                "" => BlendMode::Normal,
+                blend => BlendMode::from_str(blend).map_err(|_| ProtobufError::Invalid("blend"))?,
+            },
+            properties,
+        }),
+        Kind::Shape(e) => {
+            if e.points.len() % 2 != 0 {
+                return Err(ProtobufError::Invalid("points"));
+            }
+            Element::Shape(Shape {
+                tool: e.tool,
+                position: (s(e.x), s(e.y)),
+                radius: s(e.radius),
+                angle: s(e.angle),
+                parameters: e
+                    .parameters
+                    .into_iter()
+                    .map(|(name, value)| (name, s(value)))
+                    .collect(),
+                points: e.points.chunks(2).map(|p| (s(p[0]), s(p[1]))).collect(),
+                stroke: to_color(e.stroke),
+                fill: to_color(e.fill),
+                width: s(e.width),
+                properties,
+            })
+        }
+        Kind::Arc(e) => Element::Arc(Arc {
+            position: (s(e.x), s(e.y)),
+            radius: s(e.radius),
+            start: s(e.start),
+            end: s(e.end),
+            pie: e.pie,
+            stroke: to_color(e.stroke),
+            fill: to_color(e.fill),
+            width: s(e.width),
+            properties,
+        }),
+        Kind::Instance(e) => Element::Instance(Instance {
+            symbol: e.symbol,
+            position: (s(e.x), s(e.y)),
+            properties,
+        }),
+        Kind::Custom(e) => Element::Custom(Custom {
+            element: sync::Arc::new(Stored {
+                tool: e.tool,
+                tag: e.tag,
+                attributes: e.attributes,
+            }),
+            properties,
+        }),
+        Kind::Frame(e) => Element::Frame(Frame {
+            name: e.name,
+            index: e.index,
+            position: (s(e.x), s(e.y)),
+            size: (s(e.width), s(e.height)),
+            properties,
+        }),
+        Kind::Dimension(e) => {
+            let attachment = |a: Option<wire::Attachment>| {
+                a.map(|a| {
+                    Ok(Attachment {
+                        id: ElementId::new(a.id),
+                        anchor: usize::try_from(a.anchor)
+                            .map_err(|_| ProtobufError::Invalid("anchor"))?,
+                    })
+                })
+                .transpose()
+            };
+            Element::Dimension(Dimension {
+                start: (s(e.start_x), s(e.start_y)),
+                end: (s(e.end_x), s(e.end_y)),
+                attachments: [
+                    attachment(e.start_attachment)?,
+                    attachment(e.end_attachment)?,
+                ],
+                offset: s(e.offset),
+                unit: LengthUnit::from_str(&e.unit).map_err(|_| ProtobufError::Invalid("unit"))?,
+                color: to_color(e.color),
+                width: s(e.width),
+                properties,
+            })
+        }
+    })
+}
+
+fn heading(heading: &Heading) -> wire::Heading {
+    wire::Heading {
+        title: heading.title.clone(),
+        target: heading.target.to_string(),
+        children: heading.children.iter().map(self::heading).collect(),
+    }
+}
+
+fn to_heading(heading: wire::Heading) -> Result<Heading, ProtobufError> {
+    Ok(Heading {
+        title: heading.title,
+        target: to_target(&heading.target)?,
+        children: heading
+            .children
+            .into_iter()
+            .map(to_heading)
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+fn metadata(metadata: &Metadata) -> wire::Metadata {
+    wire::Metadata {
+        bookmarks: metadata
+            .bookmarks
+            .iter()
+            .map(|b| wire::Bookmark {
+                name: b.name.clone(),
+                x: b.position.0 as f64,
+                y: b.position.1 as f64,
+            })
+            .collect(),
+        outline: metadata.outline.iter().map(heading).collect(),
+        placeholders: metadata
+            .placeholders
+            .iter()
+            .map(|p| wire::Placeholder {
+                name: p.name.clone(),
+                min_x: p.bounds.min.0 as f64,
+                min_y: p.bounds.min.1 as f64,
+                max_x: p.bounds.max.0 as f64,
+                max_y: p.bounds.max.1 as f64,
+                element: p.element.as_ref().map(ElementId::to_string),
+            })
+            .collect(),
+        grid: metadata.grid.map(|grid| grid.spacing as f64),
+        guides: metadata
+            .guides
+            .iter()
+            .map(|guide| {
+                let (orientation, (x, y), angle) = match *guide {
+                    Guide::Horizontal(y) => ("horizontal", (0., y), 0.),
+                    Guide::Vertical(x) => ("vertical", (x, 0.), 0.),
+                    Guide::Diagonal { position, angle } => ("diagonal", position, angle),
+                };
+                wire::Guide {
+                    orientation: orientation.to_owned(),
+                    x: x as f64,
+                    y: y as f64,
+                    angle: angle as f64,
+                }
+            })
+            .collect(),
+        calibration: metadata.calibration.map(|c| wire::Calibration {
+            transform: matrix(c.transform),
+            pressure_min: c.pressure.0 as f64,
+            pressure_max: c.pressure.1 as f64,
+            deadband: c.deadband as f64,
+        }),
+        unit: metadata.unit.map(|unit| unit.to_string()),
+        tools: metadata
+            .tools
+            .iter()
+            .map(|t| wire::ToolState {
+                layer: t.layer.clone(),
+                tool: t.tool.clone(),
+                color: color(t.color),
+                width: t.width as f64,
+            })
+            .collect(),
+        background: metadata.background.map(color),
+        ruling: metadata.ruling.map(|r| wire::Ruling {
+            kind: r.kind.to_string(),
+            spacing: r.spacing as f64,
+            color: color(r.color),
+            width: r.width as f64,
+        }),
+    }
+}
+
+fn to_metadata(metadata: wire::Metadata) -> Result<Metadata, ProtobufError> {
+    let s = |v: f64| v as Scalar;
+    Ok(Metadata {
+        bookmarks: metadata
+            .bookmarks
+            .into_iter()
+            .map(|b| Bookmark::new(b.name, (s(b.x), s(b.y))))
+            .collect(),
+        outline: metadata
+            .outline
+            .into_iter()
+            .map(to_heading)
+            .collect::<Result<_, _>>()?,
+        placeholders: metadata
+            .placeholders
+            .into_iter()
+            .map(|p| {
+                let bounds = BoundingBox {
+                    min: (s(p.min_x), s(p.min_y)),
+                    max: (s(p.max_x), s(p.max_y)),
+                };
+                Placeholder {
+                    element: p.element.map(ElementId::new),
+                    ..Placeholder::new(p.name, bounds)
+                }
+            })
+            .collect(),
+        grid: metadata.grid.map(|spacing| Grid::new(s(spacing))),
+        guides: metadata
+            .guides
+            .into_iter()
+            .map(|g| match &*g.orientation {
+                "horizontal" => Ok(Guide::Horizontal(s(g.y))),
+                "vertical" => Ok(Guide::Vertical(s(g.x))),
+                "diagonal" => Ok(Guide::Diagonal {
+                    position: (s(g.x), s(g.y)),
+                    angle: s(g.angle),
+                }),
+                _ => Err(ProtobufError::Invalid("orientation")),
+            })
+            .collect::<Result<_, _>>()?,
+        calibration: match metadata.calibration {
+            Some(c) => Some(InputCalibration {
+                transform: to_matrix(&c.transform)?,
+                pressure: (s(c.pressure_min), s(c.pressure_max)),
+                deadband: s(c.deadband),
+            }),
+            None => None,
+        },
+        unit: match metadata.unit {
+            Some(unit) => {
+                Some(LengthUnit::from_str(&unit).map_err(|_| ProtobufError::Invalid("unit"))?)
+            }
+            None => None,
+        },
+        tools: metadata
+            .tools
+            .into_iter()
+            .map(|t| ToolState::new(t.layer, t.tool, to_color(t.color), s(t.width)))
+            .collect(),
+        background: metadata.background.map(to_color),
+        ruling: match metadata.ruling {
+            Some(r) if r.spacing > 0. => Some(Ruling {
+                kind: RulingKind::from_str(&r.kind).map_err(|_| ProtobufError::Invalid("kind"))?,
+                spacing: s(r.spacing),
+                color: to_color(r.color),
+                width: s(r.width),
+            }),
+            Some(_) => return Err(ProtobufError::Invalid("spacing")),
+            None => None,
+        },
+    })
+}
+
+fn op(op: &Op) -> wire::Op {
+    use wire::op::Kind;
+    let (path, kind) = match op {
+        Op::AddStroke { path, element } => (path, Kind::AddStroke(self::element(element))),
+        Op::Erase { path } => (path, Kind::Erase(wire::Erase {})),
+        Op::Transform { path, transform } => (
+            path,
+            Kind::Transform(wire::Transform {
+                matrix: matrix(*transform),
+            }),
+        ),
+        Op::SetStyle { path, style } => (
+            path,
+            Kind::SetStyle(wire::Style {
+                stroke: style.stroke.map(color),
+                fill: style.fill.map(color),
+                width: style.width.map(|w| w as f64),
+            }),
+        ),
+    };
+    wire::Op {
+        path: path.iter().map(|&i| i as u32).collect(),
+        kind: Some(kind),
+    }
+}
+
+fn to_op(op: wire::Op) -> Result<Op, ProtobufError> {
+    use wire::op::Kind;
+    let path = op.path.into_iter().map(|i| i as usize).collect();
+    Ok(match op.kind.ok_or(ProtobufError::Missing("kind"))? {
+        Kind::AddStroke(element) => Op::AddStroke {
+            path,
+            element: to_element(element)?,
+        },
+        Kind::Erase(_) => Op::Erase { path },
+        Kind::Transform(transform) => Op::Transform {
+            path,
+            transform: to_matrix(&transform.matrix)?,
+        },
+        Kind::SetStyle(style) => Op::SetStyle {
+            path,
+            style: Style {
+                stroke: style.stroke.map(to_color),
+                fill: style.fill.map(to_color),
+                width: style.width.map(|w| w as Scalar),
+            },
+        },
+    })
+}
+
+impl Document {
+    pub fn to_protobuf(&self) -> Vec<u8> {
+        encode(&wire::Document {
+            elements: elements(&self.elements),
+            metadata: Some(metadata(&self.metadata)),
+            symbols: self
+                .symbols
+                .iter()
+                .map(|(name, elements)| wire::Symbol {
+                    name: name.clone(),
+                    elements: self::elements(elements),
+                })
+                .collect(),
+            assets: self
+                .assets
+                .iter()
+                .map(|(id, asset)| wire::Asset {
+                    id: id.clone(),
+                    mime: asset.mime.clone(),
+                    data: asset.data.clone(),
+                })
+                .collect(),
+        })
+    }
+
+    pub fn from_protobuf(bytes: &[u8]) -> Result<Self, ProtobufError> {
+        let document = wire::Document::decode(bytes)?;
+        Ok(Document {
+            elements: document
+                .elements
+                .into_iter()
+                .map(to_element)
+                .collect::<Result<_, _>>()?,
+            metadata: to_metadata(document.metadata.unwrap_or_default())?,
+            symbols: document
+                .symbols
+                .into_iter()
+                .map(|symbol| {
+                    let elements = symbol
+                        .elements
+                        .into_iter()
+                        .map(to_element)
+                        .collect::<Result<_, _>>()?;
+                    Ok((symbol.name, elements))
+                })
+                .collect::<Result<_, ProtobufError>>()?,
+            assets: document
+                .assets
+                .into_iter()
+                .fold(Assets::new(), |mut assets, asset| {
+                    assets.insert(asset.id, Asset::new(asset.data, asset.mime));
+                    assets
+                }),
+        })
+    }
+}
+
+/// Encodes the operations applied after the revision `since`, e.g.
+/// [`OpLog::ops_since`](crate::ops::OpLog::ops_since).
+pub fn ops_to_protobuf(since: Revision, ops: &[Op]) -> Vec<u8> {
+    encode(&wire::Ops {
+        since: since as u64,
+        ops: ops.iter().map(op).collect(),
+    })
+}
+
+/// The revision the operations apply to, and the operations.
+pub fn ops_from_protobuf(bytes: &[u8]) -> Result<(Revision, Vec<Op>), ProtobufError> {
+    let ops = wire::Ops::decode(bytes)?;
+    Ok((
+        ops.since as Revision,
+        ops.ops.into_iter().map(to_op).collect::<Result<_, _>>()?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Clip, Element, Layer, Line, LinePoint, Properties};
+    use crate::metadata::{Heading, Target};
+    use crate::ops::{Op, Style};
+    use crate::protobuf::{ops_from_protobuf, ops_to_protobuf, ProtobufError};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    fn line() -> Element {
+        Element::Line(Line {
+            color: Color::rgba(0xFF, 0, 0, 0x80),
+            width: 2.5,
+            // Not exact as a float with the `f64` feature
+            points: vec![LinePoint(0., 0., 1.), LinePoint(1.5, -2.1, 0.5)],
+            timestamps: vec![1_600_000_000_000, 1_600_000_000_016],
+            properties: Properties {
+                transform: Transform::translate(1., 0.),
+                clip: Some(Clip::rectangle((0., 0.), (10., 5.))),
+                title: Some("signature".to_owned()),
+                ..Default::default()
+            },
+        })
+    }
+
+    #[test]
+    fn document() {
+        let mut layer = Layer::new("ink");
+        layer.elements.push(line());
+        let mut document = Document {
+            elements: vec![line(), Element::Layer(layer)],
+            ..Default::default()
+        };
+        document
+            .metadata
+            .outline
+            .push(Heading::new("Intro", Target::page(1)));
+
+        let bytes = document.to_protobuf();
+        assert_eq!(Document::from_protobuf(&bytes).unwrap(), document);
+        assert!(bytes.len() < document.to_string().len() / 2);
+        assert!(matches!(
+            Document::from_protobuf(&[0xFF]),
+            Err(ProtobufError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn ops() {
+        let ops = vec![
+            Op::AddStroke {
+                path: vec![0],
+                element: line(),
+            },
+            Op::SetStyle {
+                path: vec![0],
+                style: Style {
+                    width: Some(3.),
+                    ..Default::default()
+                },
+            },
+            Op::Transform {
+                path: vec![0],
+                transform: Transform::scale(2., 2.),
+            },
+            Op::Erase { path: vec![0] },
+        ];
+        assert_eq!(
+            ops_from_protobuf(&ops_to_protobuf(3, &ops)).unwrap(),
+            (3, ops)
+        );
+    }
+}
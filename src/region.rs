@@ -0,0 +1,295 @@
+//! Loading only the part of a document that is looked at, by the bounds
+//! stored with
+//! [`SerializeOptions::bounding_boxes`](crate::options::SerializeOptions::bounding_boxes).
+
+use std::io::Read;
+use std::ops::Range;
+
+use svg::node::element::tag;
+
+use crate::elements::Properties;
+use crate::geometry::BoundingBox;
+use crate::options::ParseOptions;
+use crate::render;
+use crate::scalar::Scalar;
+use crate::xml::{self, Event};
+use crate::{Document, DocumentError};
+
+/// A top level element that was not parsed yet, see
+/// [`PartialDocument::load_region`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct Stub {
+    /// Its stored bounds in document coordinates.
+    pub bounds: BoundingBox,
+    /// Where it is in the source.
+    range: Range<usize>,
+}
+
+/// A top level element of the source, parsed or not.
+#[derive(Debug, Clone)]
+enum Slot {
+    /// The number of elements it was parsed to, e.g. none for unknown tags.
+    Loaded(usize),
+    Stub(Stub),
+}
+
+/// A document of which only some top level elements are parsed, see
+/// [`Document::load_region`].
+///
+/// The checksum is not verified, as it covers all elements.
+#[derive(Debug, Clone)]
+pub struct PartialDocument {
+    source: String,
+    /// The source without the top level elements, which are put back in one
+    /// at a time to parse them.
+    skeleton: String,
+    /// Where the top level elements were in the skeleton, by slot.
+    offsets: Vec<usize>,
+    slots: Vec<Slot>,
+    /// The elements parsed so far, in document order.
+    document: Document,
+}
+
+impl Document {
+    /// Reads a document, but only parses the top level elements (i.e.
+    /// elements and layers) whose `svgnote:bbox` intersects `region`, the
+    /// others are kept as [`Stub`]s. Elements without one are always parsed.
+    ///
+    /// Decoded like the `TryFrom<&[u8]>` implementation.
+    pub fn load_region(
+        mut reader: impl Read,
+        region: BoundingBox,
+    ) -> Result<PartialDocument, DocumentError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes)?;
+        let mut document = PartialDocument::new(crate::decode(&bytes)?)?;
+        document.load_region(region)?;
+        Ok(document)
+    }
+}
+
+impl PartialDocument {
+    /// Finds the top level elements of `source` without parsing any of them.
+    fn new(source: String) -> Result<Self, DocumentError> {
+        let options = ParseOptions::default();
+        let mut prefix = None;
+        let mut ranges: Vec<(Range<usize>, Option<BoundingBox>)> = vec![];
+        // The one whose end is not known yet
+        let mut current: Option<(usize, Option<BoundingBox>)> = None;
+        let mut depth = 0;
+        for event in xml::read(&source)? {
+            let offset = |name: &str, kind| {
+                let tag = name.as_ptr() as usize - source.as_ptr() as usize;
+                if kind == tag::Type::End {
+                    tag - 2
+                } else {
+                    tag - 1
+                }
+            };
+            let (name, kind, attributes) = match options.normalize_namespace(event, &mut prefix) {
+                Event::Error(error) => return Err(crate::invalid_data(error)),
+                Event::Tag(name, kind, attributes) => (name, kind, attributes),
+                _ => continue,
+            };
+            // Only the start of the next top level tag or the end of the
+            // root ends an element
+            if kind == tag::Type::End {
+                depth -= 1;
+                if depth > 0 {
+                    continue;
+                }
+            } else if depth > 1 {
+                if kind == tag::Type::Start {
+                    depth += 1;
+                }
+                continue;
+            }
+            if let Some((start, bounds)) = current.take() {
+                ranges.push((start..offset(name, kind), bounds));
+            }
+            let element = matches!(
+                name,
+                tag::Path
+                    | tag::Polygon
+                    | tag::Polyline
+                    | tag::Ellipse
+                    | tag::Text
+                    | tag::Image
+                    | tag::Use
+                    | tag::Group
+            );
+            if depth == 1 && element && kind != tag::Type::End {
+                // Only the transform is needed, anything invalid is
+                // reported when it is parsed
+                let bounds = Properties::from_attributes(&attributes)
+                    .ok()
+                    .and_then(|p| render::transform_bounds(p.bounds.0?, p.transform));
+                current = Some((offset(name, kind), bounds));
+            }
+            if kind == tag::Type::Start {
+                depth += 1;
+            }
+        }
+        let mut skeleton = String::new();
+        let mut offsets = vec![];
+        let mut slots = vec![];
+        let mut end = 0;
+        for (range, bounds) in ranges {
+            skeleton.push_str(&source[end..range.start]);
+            end = range.end;
+            offsets.push(skeleton.len());
+            slots.push(Slot::Stub(Stub {
+                // Without bounds, so they are in every region
+                bounds: bounds.unwrap_or(BoundingBox {
+                    min: (Scalar::NEG_INFINITY, Scalar::NEG_INFINITY),
+                    max: (Scalar::INFINITY, Scalar::INFINITY),
+                }),
+                range,
+            }));
+        }
+        skeleton.push_str(&source[end..]);
+        let (document, _) = Document::from_str_with(&skeleton, &Self::options())
+            .map_err(DocumentError::without_location)?;
+        Ok(PartialDocument {
+            source,
+            skeleton,
+            offsets,
+            slots,
+            document,
+        })
+    }
+
+    fn options() -> ParseOptions {
+        ParseOptions {
+            verify_checksum: false,
+            ..Default::default()
+        }
+    }
+
+    /// The elements parsed so far, with the metadata.
+    pub fn document(&self) -> &Document {
+        &self.document
+    }
+
+    /// The top level elements that are not parsed yet.
+    pub fn stubs(&self) -> impl Iterator<Item = &Stub> {
+        self.slots.iter().filter_map(|slot| match slot {
+            Slot::Stub(stub) => Some(stub),
+            Slot::Loaded(_) => None,
+        })
+    }
+
+    /// Parses the stubs intersecting `region`, returning how many there
+    /// were.
+    ///
+    /// Error locations are left out, as they would be in the part parsed
+    /// instead of the source.
+    pub fn load_region(&mut self, region: BoundingBox) -> Result<usize, DocumentError> {
+        self.load(|stub| stub.bounds.intersects(&region))
+    }
+
+    /// Parses all stubs, giving the complete document.
+    pub fn load_all(mut self) -> Result<Document, DocumentError> {
+        self.load(|_| true)?;
+        Ok(self.document)
+    }
+
+    fn load(&mut self, filter: impl Fn(&Stub) -> bool) -> Result<usize, DocumentError> {
+        let mut loaded = 0;
+        // Where the elements of the slot go in the document
+        let mut index = 0;
+        for (slot, &offset) in self.slots.iter_mut().zip(&self.offsets) {
+            let stub = match slot {
+                Slot::Loaded(n) => {
+                    index += *n;
+                    continue;
+                }
+                Slot::Stub(stub) if filter(stub) => stub,
+                Slot::Stub(_) => continue,
+            };
+            let part = format!(
+                "{}{}{}",
+                &self.skeleton[..offset],
+                &self.source[stub.range.clone()],
+                &self.skeleton[offset..]
+            );
+            let (part, _) = Document::from_str_with(&part, &Self::options())
+                .map_err(DocumentError::without_location)?;
+            let n = part.elements.len();
+            self.document.elements.splice(index..index, part.elements);
+            self.document.assets.merge(part.assets);
+            *slot = Slot::Loaded(n);
+            index += n;
+            loaded += 1;
+        }
+        Ok(loaded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::elements::{Element, Ellipse, Layer};
+    use crate::geometry::BoundingBox;
+    use crate::metadata::Bookmark;
+    use crate::options::SerializeOptions;
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn load_region() {
+        let ellipse = |x| {
+            Element::Ellipse(
+                Ellipse::builder()
+                    .position(x, 100.)
+                    .radius(40.)
+                    .build()
+                    .unwrap(),
+            )
+        };
+        let mut layer = Layer::new("moved");
+        layer.properties.transform = Transform::translate(1000., 0.);
+        layer.elements = vec![ellipse(0.), ellipse(500.)];
+        let mut doc = Document {
+            elements: vec![ellipse(100.), ellipse(600.), Element::Layer(layer)],
+            ..Default::default()
+        };
+        doc.metadata
+            .bookmarks
+            .push(Bookmark::new("start", (0., 0.)));
+        let string = doc.to_string_with(&SerializeOptions {
+            bounding_boxes: true,
+            ..Default::default()
+        });
+        let region = |min_x, max_x| BoundingBox {
+            min: (min_x, 0.),
+            max: (max_x, 200.),
+        };
+
+        // Without the bounds read
+        let loaded = |document: &Document| {
+            let mut document = document.clone();
+            document.clear_stored_bounds();
+            document
+        };
+
+        let mut partial = Document::load_region(string.as_bytes(), region(0., 200.)).unwrap();
+        assert_eq!(loaded(partial.document()).elements, vec![ellipse(100.)]);
+        assert_eq!(partial.document().metadata, doc.metadata);
+        assert_eq!(partial.stubs().count(), 2);
+        // The layer by the bounds of its elements, with its transform
+        assert_eq!(partial.load_region(region(300., 400.)).unwrap(), 0);
+        assert_eq!(partial.load_region(region(1000., 1100.)).unwrap(), 1);
+        assert_eq!(loaded(partial.document()).elements[1], doc.elements[2]);
+        assert_eq!(loaded(&partial.load_all().unwrap()), doc);
+
+        // Elements without bounds are always parsed
+        let partial = Document::load_region(doc.to_string().as_bytes(), region(0., 0.)).unwrap();
+        assert_eq!(partial.stubs().count(), 0);
+        assert_eq!(
+            partial.document(),
+            &Document::from_str(&doc.to_string()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use crate::elements::ApproximateShaper;
+use crate::elements::Clip;
+use crate::elements::Element;
+use crate::elements::Ephemeral;
+use crate::elements::Symbols;
+use crate::elements::TextShaper;
+use crate::geometry::BoundingBox;
+use crate::transform::Transform;
+
+#[cfg(feature = "tessellation")]
+mod tessellation;
+
+#[cfg(feature = "tessellation")]
+pub use self::tessellation::{vertex_buffers, DrawRange, Primitive, VertexBuffers};
+
+/// A visible element, with everything inherited from the layers it is in
+/// resolved.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RenderItem<'a> {
+    pub element: &'a Element,
+    /// The combined opacity of all layers the element is in.
+    ///
+    /// Use [`Color::faded`](crate::colors::Color::faded) to get the
+    /// effective colors of the element.
+    pub opacity: f32,
+    /// Maps the coordinates of the element to document coordinates,
+    /// including the element's own transform.
+    pub transform: Transform,
+    /// The clips of the element and of all layers it is in, each with the
+    /// transform mapping its coordinates to document coordinates.
+    pub clips: Vec<(Clip, Transform)>,
+}
+
+impl RenderItem<'_> {
+    /// The area covered by the element in document coordinates, including
+    /// its stroke width, ignoring clips.
+    ///
+    /// `None` for elements without any points. Text is measured with the
+    /// [`ApproximateShaper`], see [`RenderItem::bounds_with`].
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        self.bounds_with(&ApproximateShaper)
+    }
+
+    /// The bounds, with text measured by `shaper`.
+    pub fn bounds_with(&self, shaper: &dyn TextShaper) -> Option<BoundingBox> {
+        let (points, width) = match self.element {
+            Element::Line(line) | Element::Ephemeral(Ephemeral { line, .. }) => {
+                (line.points.iter().map(|&p| p.into()).collect(), line.width)
+            }
+            Element::Polyline(polyline) => (
+                polyline.points.iter().map(|&p| p.into()).collect(),
+                polyline.max_width(),
+            ),
+            Element::Ngon(ngon) => (ngon.points(), ngon.width),
+            Element::Shape(shape) => (shape.points.clone(), shape.width),
+            Element::Arc(arc) => (arc.points(64), arc.width),
+            Element::Ellipse(ellipse) => {
+                let (x, y, r) = (ellipse.position.0, ellipse.position.1, ellipse.radius);
+                (
+                    vec![
+                        (x - r, y - r),
+                        (x + r, y - r),
+                        (x + r, y + r),
+                        (x - r, y + r),
+                    ],
+                    ellipse.width,
+                )
+            }
+            Element::Text(text) => match text.bounds(shaper) {
+                Some(BoundingBox { min, max }) => {
+                    (vec![min, (max.0, min.1), max, (min.0, max.1)], 0.0)
+                }
+                None => (vec![], 0.0),
+            },
+            Element::FileRef(file) => {
+                let BoundingBox { min, max } = file.bounds();
+                (vec![min, (max.0, min.1), max, (min.0, max.1)], 0.0)
+            }
+            // Layers are flattened by `render_items`, instances replaced by
+            // their symbol
+            Element::Layer(_) | Element::Instance(_) => (vec![], 0.0),
+            Element::Custom(custom) => match custom.element.bounds() {
+                Some(BoundingBox { min, max }) => {
+                    (vec![min, (max.0, min.1), max, (min.0, max.1)], 0.0)
+                }
+                None => (vec![], 0.0),
+            },
+            Element::Frame(frame) => (frame.corners().to_vec(), 0.0),
+            // The label is only roughly included, by where it starts
+            Element::Dimension(dimension) => {
+                let mut points: Vec<_> = dimension.segments().iter().flatten().copied().collect();
+                points.push(dimension.label_position().0);
+                (points, dimension.width)
+            }
+        };
+        BoundingBox::from_points(points.into_iter().map(|p| self.transform.apply(p)))
+            .map(|bounds| bounds.expand(width * self.transform.scale_factor() / 2.0))
+    }
+
+    /// Like [`RenderItem::bounds`], but from the
+    /// [`StoredBounds`](crate::elements::StoredBounds) if the element has
+    /// them, without looking at the geometry.
+    pub fn stored_bounds(&self) -> Option<BoundingBox> {
+        match self.element.properties().bounds.0 {
+            Some(bounds) => transform_bounds(bounds, self.transform),
+            None => self.bounds(),
+        }
+    }
+}
+
+/// The box around `bounds` after `transform`.
+pub(crate) fn transform_bounds(bounds: BoundingBox, transform: Transform) -> Option<BoundingBox> {
+    let BoundingBox { min, max } = bounds;
+    BoundingBox::from_points(
+        vec![min, (max.0, min.1), max, (min.0, max.1)]
+            .into_iter()
+            .map(|p| transform.apply(p)),
+    )
+}
+
+/// The bounds of `element` after `transform`, instead of its own transform.
+/// Those of layers include all their visible elements.
+pub(crate) fn element_bounds(element: &Element, transform: Transform) -> Option<BoundingBox> {
+    match element {
+        Element::Layer(layer) => render_items(&layer.elements)
+            .into_iter()
+            .filter_map(|item| {
+                RenderItem {
+                    transform: transform * item.transform,
+                    ..item
+                }
+                .bounds()
+            })
+            .reduce(BoundingBox::union),
+        element => RenderItem {
+            element,
+            opacity: 1.,
+            transform,
+            clips: vec![],
+        }
+        .bounds(),
+    }
+}
+
+/// All visible elements in `elements`, in drawing order, with layers
+/// flattened.
+///
+/// Instances are skipped, as the symbols are part of the document, see
+/// [`Document::render_items`](crate::Document::render_items).
+pub fn render_items(elements: &[Element]) -> Vec<RenderItem> {
+    collect(elements, 1.0, Transform::IDENTITY, &[], None, None, &[])
+}
+
+/// The [`render_items`] that may be inside `viewport`, by their
+/// [`RenderItem::stored_bounds`]. Layers with stored bounds outside of it are
+/// skipped as a whole.
+pub fn render_items_in(elements: &[Element], viewport: BoundingBox) -> Vec<RenderItem> {
+    collect(
+        elements,
+        1.0,
+        Transform::IDENTITY,
+        &[],
+        Some(viewport),
+        None,
+        &[],
+    )
+}
+
+/// Like [`render_items`], with instances replaced by the elements of their
+/// symbol in `symbols`.
+pub(crate) fn render_items_with<'a>(
+    elements: &'a [Element],
+    symbols: &'a Symbols,
+    viewport: Option<BoundingBox>,
+) -> Vec<RenderItem<'a>> {
+    collect(
+        elements,
+        1.0,
+        Transform::IDENTITY,
+        &[],
+        viewport,
+        Some(symbols),
+        &[],
+    )
+}
+
+/// `active` are the symbols being expanded, instances of them are skipped so
+/// a symbol placed inside itself ends.
+fn collect<'a>(
+    elements: &'a [Element],
+    opacity: f32,
+    transform: Transform,
+    clips: &[(Clip, Transform)],
+    viewport: Option<BoundingBox>,
+    symbols: Option<&'a Symbols>,
+    active: &[&'a str],
+) -> Vec<RenderItem<'a>> {
+    let visible = |bounds: Option<BoundingBox>| match (viewport, bounds) {
+        (Some(viewport), Some(bounds)) => viewport.intersects(&bounds),
+        // Elements without any points are kept, like without a viewport
+        _ => true,
+    };
+    let mut items = vec![];
+    for element in elements.iter().filter(|e| !e.is_hidden()) {
+        let properties = element.properties();
+        let transform = transform * properties.transform;
+        let mut clips = clips.to_vec();
+        clips.extend(properties.clip.map(|clip| (clip, transform)));
+        // Layers and instances are skipped as a whole by their stored bounds
+        let culled = || {
+            let bounds = properties
+                .bounds
+                .0
+                .and_then(|bounds| transform_bounds(bounds, transform));
+            bounds.is_some() && !visible(bounds)
+        };
+        match element {
+            Element::Layer(layer) => {
+                if !culled() {
+                    items.extend(collect(
+                        &layer.elements,
+                        opacity * layer.opacity,
+                        transform,
+                        &clips,
+                        viewport,
+                        symbols,
+                        active,
+                    ))
+                }
+            }
+            Element::Instance(instance) => {
+                let symbol = &*instance.symbol;
+                let elements = match symbols.and_then(|symbols| symbols.get(symbol)) {
+                    Some(elements) if !active.contains(&symbol) => elements,
+                    _ => continue,
+                };
+                if !culled() {
+                    let mut active = active.to_vec();
+                    active.push(symbol);
+                    items.extend(collect(
+                        elements,
+                        opacity,
+                        transform * instance.placement(),
+                        &clips,
+                        viewport,
+                        symbols,
+                        &active,
+                    ))
+                }
+            }
+            element => {
+                let item = RenderItem {
+                    element,
+                    opacity,
+                    transform,
+                    clips,
+                };
+                if viewport.is_none() || visible(item.stored_bounds()) {
+                    items.push(item);
+                }
+            }
+        }
+    }
+    items
+}
+
+/// The elements of [`render_items`], mutable, each with the transform of the
+/// layers it is in.
+pub(crate) fn render_items_mut(elements: &mut [Element]) -> Vec<(&mut Element, Transform)> {
+    collect_mut(elements, Transform::IDENTITY)
+}
+
+fn collect_mut(elements: &mut [Element], transform: Transform) -> Vec<(&mut Element, Transform)> {
+    let mut items = vec![];
+    for element in elements.iter_mut().filter(|e| !e.is_hidden()) {
+        match element {
+            Element::Layer(layer) => {
+                let transform = transform * layer.properties.transform;
+                items.extend(collect_mut(&mut layer.elements, transform))
+            }
+            element => items.push((element, transform)),
+        }
+    }
+    items
+}
@@ -0,0 +1,304 @@
+//! Flattening the geometry into vertex buffers.
+
+use crate::colors::Color;
+use crate::elements::Element;
+use crate::elements::Ephemeral;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+
+use super::RenderItem;
+
+/// How the vertices of a [`DrawRange`] are connected.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Primitive {
+    /// A stroke along the vertices.
+    LineStrip,
+    /// A stroke along the vertices, connecting the last to the first.
+    LineLoop,
+    /// A filled polygon, the first vertex is shared by all triangles, so all
+    /// of it must be visible from there, e.g. if it is convex.
+    TriangleFan,
+}
+
+/// A range of vertices in [`VertexBuffers`] to be drawn in one call.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct DrawRange {
+    /// The index of the element in [`render_items`](super::render_items).
+    pub item: usize,
+    pub primitive: Primitive,
+    /// The index of the first vertex.
+    pub start: usize,
+    pub count: usize,
+}
+
+/// The geometry of a document as flat buffers, ready to upload to a GPU.
+///
+/// The buffers are always `f32`, also with the `f64` feature.
+///
+/// All positions are in document coordinates, i.e. with transforms and layer
+/// opacity applied. Fills are only generated for convex shapes ([`Ngon`]s and
+/// [`Ellipse`]s) and skipped if fully transparent, clips are not applied.
+///
+/// [`Ngon`]: crate::elements::Ngon
+/// [`Ellipse`]: crate::elements::Ellipse
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct VertexBuffers {
+    /// Two values (x, y) per vertex.
+    pub positions: Vec<f32>,
+    /// One value per vertex, the stroke width at that vertex (`0` for fills).
+    ///
+    /// For [`Line`](crate::elements::Line)s the width is scaled by the
+    /// pressure of the point.
+    pub widths: Vec<f32>,
+    /// Four values (r, g, b, a in `0.0..=1.0`) per vertex.
+    pub colors: Vec<f32>,
+    pub ranges: Vec<DrawRange>,
+}
+
+impl VertexBuffers {
+    pub fn vertex_count(&self) -> usize {
+        self.widths.len()
+    }
+
+    fn push(
+        &mut self,
+        item: usize,
+        primitive: Primitive,
+        points: impl IntoIterator<Item = ((Scalar, Scalar), Scalar)>,
+        color: Color,
+    ) {
+        if primitive == Primitive::TriangleFan && color.a == 0 {
+            return;
+        }
+        let start = self.vertex_count();
+        let (r, g, b, a) = color.floats();
+        for ((x, y), width) in points {
+            self.positions.extend_from_slice(&[x as f32, y as f32]);
+            self.widths.push(width as f32);
+            self.colors.extend_from_slice(&[r, g, b, a]);
+        }
+        let count = self.vertex_count() - start;
+        if count > 0 {
+            self.ranges.push(DrawRange {
+                item,
+                primitive,
+                start,
+                count,
+            });
+        }
+    }
+}
+
+/// The number of segments to approximate an ellipse with `radius` (in
+/// document coordinates), so that segments are about 4 units long.
+fn ellipse_segments(radius: Scalar) -> usize {
+    ((2. * PI * radius / 4.).ceil() as usize).clamp(8, 256)
+}
+
+pub fn vertex_buffers(items: &[RenderItem]) -> VertexBuffers {
+    let mut buffers = VertexBuffers::default();
+    for (i, item) in items.iter().enumerate() {
+        let t = item.transform;
+        let scale = t.scale_factor();
+        let fill = |points: &[(Scalar, Scalar)]| {
+            points
+                .iter()
+                .map(|&p| (t.apply(p), 0.0))
+                .collect::<Vec<_>>()
+        };
+        let stroke = |points: &[(Scalar, Scalar)], width: Scalar| {
+            points
+                .iter()
+                .map(|&p| (t.apply(p), width * scale))
+                .collect::<Vec<_>>()
+        };
+        match item.element {
+            Element::Line(line) | Element::Ephemeral(Ephemeral { line, .. }) => buffers.push(
+                i,
+                Primitive::LineStrip,
+                line.points
+                    .iter()
+                    .map(|p| (t.apply((p.0, p.1)), line.width * p.2 * scale)),
+                line.properties.stroke(line.color).faded(item.opacity),
+            ),
+            Element::Polyline(polyline) => buffers.push(
+                i,
+                if polyline.closed {
+                    Primitive::LineLoop
+                } else {
+                    Primitive::LineStrip
+                },
+                polyline
+                    .points
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &p)| (t.apply(p.into()), polyline.width_at(j) * scale)),
+                polyline
+                    .properties
+                    .stroke(polyline.stroke)
+                    .faded(item.opacity),
+            ),
+            Element::Ngon(ngon) => {
+                let points = ngon.points();
+                buffers.push(
+                    i,
+                    Primitive::TriangleFan,
+                    fill(&points),
+                    ngon.properties.fill(ngon.fill).faded(item.opacity),
+                );
+                buffers.push(
+                    i,
+                    Primitive::LineLoop,
+                    stroke(&points, ngon.width),
+                    ngon.properties.stroke(ngon.stroke).faded(item.opacity),
+                );
+            }
+            Element::Shape(shape) => {
+                // Fanned out from the center, as the outline need not be
+                // convex, only visible from it, see `ShapeKind::outline`
+                let mut fan = vec![shape.position];
+                fan.extend(&shape.points);
+                fan.extend(shape.points.first());
+                buffers.push(
+                    i,
+                    Primitive::TriangleFan,
+                    fill(&fan),
+                    shape.properties.fill(shape.fill).faded(item.opacity),
+                );
+                buffers.push(
+                    i,
+                    Primitive::LineLoop,
+                    stroke(&shape.points, shape.width),
+                    shape.properties.stroke(shape.stroke).faded(item.opacity),
+                );
+            }
+            Element::Arc(arc) => {
+                let turns = arc.sweep().abs() / (2. * PI);
+                let segments = (ellipse_segments(arc.radius * scale) as Scalar * turns).ceil();
+                let points = arc.points(segments as usize);
+                // From the center of pies, the fill of arcs is closed by the
+                // chord
+                let mut fan = points.clone();
+                if arc.pie {
+                    fan.rotate_right(1);
+                }
+                buffers.push(
+                    i,
+                    Primitive::TriangleFan,
+                    fill(&fan),
+                    arc.properties.fill(arc.fill).faded(item.opacity),
+                );
+                buffers.push(
+                    i,
+                    if arc.pie {
+                        Primitive::LineLoop
+                    } else {
+                        Primitive::LineStrip
+                    },
+                    stroke(&points, arc.width),
+                    arc.properties.stroke(arc.stroke).faded(item.opacity),
+                );
+            }
+            Element::Ellipse(ellipse) => {
+                let points = ellipse.points(ellipse_segments(ellipse.radius * scale));
+                buffers.push(
+                    i,
+                    Primitive::TriangleFan,
+                    fill(&points),
+                    ellipse.properties.fill(ellipse.fill).faded(item.opacity),
+                );
+                buffers.push(
+                    i,
+                    Primitive::LineLoop,
+                    stroke(&points, ellipse.width),
+                    ellipse
+                        .properties
+                        .stroke(ellipse.stroke)
+                        .faded(item.opacity),
+                );
+            }
+            // Glyphs are left to the host, see `TextShaper`
+            Element::Text(_) => {}
+            // The file is loaded by the host
+            Element::FileRef(_) => {}
+            // Layers are flattened by `render_items`, instances replaced by
+            // their symbol
+            Element::Layer(_) | Element::Instance(_) => {}
+            // Drawn by the app, see `NoteElement`
+            Element::Custom(_) => {}
+            // Not printed, its outline is left to the app
+            Element::Frame(_) => {}
+            // The label is left to the host like texts
+            Element::Dimension(dimension) => {
+                for segment in dimension.segments().iter() {
+                    buffers.push(
+                        i,
+                        Primitive::LineStrip,
+                        stroke(&segment[..], dimension.width),
+                        dimension.color.faded(item.opacity),
+                    );
+                }
+            }
+        }
+    }
+    buffers
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Line, LinePoint, Properties};
+    use crate::render::{DrawRange, Primitive};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn vertex_buffers() {
+        let mut line = Element::Line(Line {
+            color: Color::rgb(0xFF, 0, 0),
+            width: 2.,
+            points: vec![LinePoint(0., 0., 1.), LinePoint(1., 2., 0.5)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        });
+        Transform::scale(2., 2.).apply_to(&mut line);
+        let doc = Document {
+            elements: vec![
+                line,
+                Element::Ellipse(Ellipse {
+                    position: (0., 0.),
+                    stroke: Color::rgb(0, 0, 0),
+                    fill: Color::rgba(0, 0, 0, 0),
+                    width: 1.,
+                    radius: 1.,
+                    properties: Properties::default(),
+                }),
+            ],
+            ..Default::default()
+        };
+
+        let buffers = doc.to_vertex_buffers();
+        assert_eq!(
+            buffers.ranges,
+            vec![
+                DrawRange {
+                    item: 0,
+                    primitive: Primitive::LineStrip,
+                    start: 0,
+                    count: 2
+                },
+                DrawRange {
+                    item: 1,
+                    primitive: Primitive::LineLoop,
+                    start: 2,
+                    count: 8
+                }
+            ]
+        );
+        assert_eq!(buffers.positions[..4].to_vec(), vec![0., 0., 2., 4.]);
+        assert_eq!(buffers.widths[..3].to_vec(), vec![4., 2., 1.]);
+        assert_eq!(buffers.colors[..4].to_vec(), vec![1., 0., 0., 1.]);
+        assert_eq!(buffers.positions.len(), 2 * buffers.vertex_count());
+        assert_eq!(buffers.colors.len(), 4 * buffers.vertex_count());
+    }
+}
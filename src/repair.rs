@@ -0,0 +1,120 @@
+//! Best-effort recovery of damaged or hand-edited documents.
+
+use svg::node::element::tag;
+
+use crate::elements::{Attributes, Element};
+use crate::xml::Event;
+use crate::DocumentError;
+
+/// A change made to be able to load a document, see
+/// [`Document::repair`](crate::Document::repair) and
+/// [`Document::from_str_with`](crate::Document::from_str_with).
+#[derive(Debug)]
+pub enum Repair {
+    /// The attribute was missing, the default from the
+    /// [`ParseOptions`](crate::options::ParseOptions) was used.
+    Default(String),
+    /// A pen stroke had no `svgnote:points`, so they were taken from the `d`
+    /// attribute, with full pressure.
+    PointsFromPath,
+    /// A missing `svgnote:width` was taken from the `stroke-width`.
+    WidthFromStrokeWidth,
+    /// An element that could not be repaired was left out.
+    Dropped(DocumentError),
+    /// The checksum did not match, everything readable was kept.
+    ChecksumMismatch,
+    /// The `svgnote:bbox` did not contain the element and was replaced by
+    /// its actual bounds.
+    StaleBoundingBox,
+}
+
+/// Parses an element like [`Element::from_event`], fixing missing attributes
+/// where possible.
+///
+/// Elements that cannot be fixed are recorded as [`Repair::Dropped`] and
+/// reported as [`DocumentError::UnknownEvent`], so they are skipped.
+pub(crate) fn element(event: Event, repairs: &mut Vec<Repair>) -> Result<Element, DocumentError> {
+    let (name, mut attributes) = match event {
+        Event::Tag(name, _, attributes) => (name, attributes),
+        _ => return Err(DocumentError::UnknownEvent),
+    };
+    // Parsed by `Line`, but reported as it loses the pressure
+    let from_path = name == tag::Path
+        && attributes.get("svgnote:tool").map(|tool| &**tool) != Some("text")
+        && !attributes.contains_key("svgnote:points");
+    let mut fixes = vec![];
+    if from_path {
+        fixes.push(Repair::PointsFromPath);
+    }
+    let error = match Element::from_tag(name, attributes.clone()) {
+        Err(DocumentError::UnknownEvent) => return Err(DocumentError::UnknownEvent),
+        Err(error) => error,
+        // Of an app, not drawn from the path
+        Ok(Element::Custom(custom)) => return Ok(Element::Custom(custom)),
+        element => {
+            repairs.extend(fixes);
+            return element;
+        }
+    };
+    if name == tag::Path {
+        fixes.extend(fix_width(&mut attributes));
+    }
+    match Element::from_tag(name, attributes) {
+        Ok(element) if !fixes.is_empty() => {
+            repairs.extend(fixes);
+            Ok(element)
+        }
+        _ => {
+            repairs.push(Repair::Dropped(error));
+            Err(DocumentError::UnknownEvent)
+        }
+    }
+}
+
+fn fix_width(attributes: &mut Attributes<'_>) -> Option<Repair> {
+    if attributes.contains_key("svgnote:width") {
+        return None;
+    }
+    let width = attributes.get("stroke-width")?.clone();
+    attributes.insert("svgnote:width", width);
+    Some(Repair::WidthFromStrokeWidth)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::elements::{Element, LinePoint};
+    use crate::repair::Repair;
+    use crate::scalar::Scalar;
+    use crate::{Document, DocumentError};
+
+    #[test]
+    fn repair() {
+        let (doc, repairs) = Document::repair(
+            r##"
+            <svg xmlns:svgnote="https://github.com/ModProg/SVGNotesLib" svgnote:checksum="00000000">
+            <path d="M0,0 L1,2" stroke="#000000" stroke-width="3" svgnote:tool="pen"/>
+            <path d="M0,0 L1,2" stroke="#000000" svgnote:tool="pen"/>
+            <path stroke="#000000" stroke-width="1" svgnote:tool="pen"/>
+            <polygon svgnote:tool="ngon"/>
+            </svg>
+            "##,
+        )
+        .unwrap();
+        assert_matches!(
+            &doc.elements[..],
+            [Element::Line(line)] if (line.width - 3.).abs() < Scalar::EPSILON
+                && line.points == vec![LinePoint(0., 0., 1.), LinePoint(1., 2., 1.)]
+        );
+        assert_matches!(
+            &repairs[..],
+            [
+                Repair::PointsFromPath,
+                Repair::WidthFromStrokeWidth,
+                Repair::Dropped(DocumentError::MissingAttribute(_)),
+                Repair::Dropped(DocumentError::MissingAttribute(_)),
+                Repair::Dropped(DocumentError::MissingAttribute(_)),
+                Repair::ChecksumMismatch,
+            ]
+        );
+    }
+}
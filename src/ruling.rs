@@ -0,0 +1,195 @@
+//! Ruled, squared and dotted paper, see [`Ruling`].
+
+use std::fmt;
+use std::str::FromStr;
+
+use svg::node::element::Element as SvgElement;
+use svg::node::Node;
+
+use crate::colors::Color;
+use crate::elements::attributes::{AttributeReader, Attributes};
+use crate::geometry::BoundingBox;
+use crate::scalar::Scalar;
+use crate::DocumentError;
+
+/// The id of the `<pattern>` of the ruling.
+pub(crate) const PATTERN: &str = "svgnote-ruling";
+
+/// What the paper is printed with.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RulingKind {
+    /// Horizontal lines.
+    Lines,
+    /// Horizontal and vertical lines.
+    Grid,
+    /// Dots where grid lines would cross.
+    Dots,
+}
+
+impl fmt::Display for RulingKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RulingKind::Lines => "lines",
+            RulingKind::Grid => "grid",
+            RulingKind::Dots => "dots",
+        })
+    }
+}
+
+impl FromStr for RulingKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(RulingKind::Lines),
+            "grid" => Ok(RulingKind::Grid),
+            "dots" => Ok(RulingKind::Dots),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The ruling of the paper, stored in the
+/// [`Metadata`](crate::metadata::Metadata) of a document.
+///
+/// It is written as one `<rect svgnote:tool="ruling">` covering the view
+/// box, filled with a `<pattern>` of a single tile, so the size of the
+/// document does not depend on the size of the page.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Ruling {
+    pub kind: RulingKind,
+    /// The distance between lines or dots, positive. Lines pass through the
+    /// origin.
+    pub spacing: Scalar,
+    pub color: Color,
+    /// The width of lines and the diameter of dots.
+    pub width: Scalar,
+}
+
+impl Ruling {
+    /// Light blue lines of width `1`.
+    pub fn new(kind: RulingKind, spacing: Scalar) -> Self {
+        Ruling {
+            kind,
+            spacing,
+            color: Color::rgb(0xA0, 0xC0, 0xE0),
+            width: 1.,
+        }
+    }
+
+    /// The `<pattern>` of one tile, `spacing` wide and high, with its
+    /// corner at the origin.
+    pub(crate) fn pattern(&self) -> SvgElement {
+        let s = self.spacing;
+        let mut pattern = SvgElement::new("pattern");
+        pattern.assign("id", PATTERN);
+        pattern.assign("patternUnits", "userSpaceOnUse");
+        pattern.assign("width", s);
+        pattern.assign("height", s);
+        // Lines and dots on the edges are cut in half by the tile, the
+        // neighbouring tiles draw the other halves
+        match self.kind {
+            RulingKind::Lines | RulingKind::Grid => {
+                let mut d = format!("M0,0 H{0} M0,{0} H{0}", s);
+                if self.kind == RulingKind::Grid {
+                    d.push_str(&format!(" M0,0 V{0} M{0},0 V{0}", s));
+                }
+                let mut path = SvgElement::new("path");
+                path.assign("d", d);
+                path.assign("stroke", self.color.to_string_na());
+                if self.color.a < 0xFF {
+                    path.assign("stroke-opacity", self.color.opacity());
+                }
+                path.assign("stroke-width", self.width);
+                path.assign("fill", "none");
+                pattern.append(path);
+            }
+            RulingKind::Dots => {
+                for &(x, y) in &[(0., 0.), (s, 0.), (0., s), (s, s)] {
+                    let mut dot = SvgElement::new("circle");
+                    dot.assign("cx", x);
+                    dot.assign("cy", y);
+                    dot.assign("r", self.width / 2.);
+                    dot.assign("fill", self.color.to_string_na());
+                    if self.color.a < 0xFF {
+                        dot.assign("fill-opacity", self.color.opacity());
+                    }
+                    pattern.append(dot);
+                }
+            }
+        }
+        pattern
+    }
+
+    /// A `<rect>` covering `view_box`, filled with the [pattern](Ruling::pattern).
+    pub(crate) fn rect(&self, view_box: BoundingBox) -> SvgElement {
+        let mut rect = SvgElement::new("rect");
+        rect.assign("x", view_box.min.0);
+        rect.assign("y", view_box.min.1);
+        rect.assign("width", view_box.width());
+        rect.assign("height", view_box.height());
+        rect.assign("fill", format!("url(#{})", PATTERN));
+        rect
+    }
+
+    /// Stores the ruling as `svgnote:` attributes of its `<rect>`, so it is
+    /// read without looking into the pattern.
+    pub(crate) fn assign_to<T: Node>(&self, node: &mut T) {
+        node.assign("svgnote:tool", "ruling");
+        node.assign("svgnote:kind", self.kind.to_string());
+        node.assign("svgnote:spacing", self.spacing);
+        node.assign("svgnote:color", self.color.to_string());
+        node.assign("svgnote:width", self.width);
+    }
+
+    /// The ruling of a `<rect svgnote:tool="ruling">`.
+    pub(crate) fn from_attributes(attributes: &Attributes<'_>) -> Result<Self, DocumentError> {
+        let reader = AttributeReader::new(attributes);
+        Ok(Ruling {
+            kind: reader.parse("svgnote:kind")?,
+            spacing: reader.positive("svgnote:spacing")?,
+            color: reader.parse("svgnote:color")?,
+            width: reader.scalar("svgnote:width")?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::geometry::BoundingBox;
+    use crate::options::SerializeOptions;
+    use crate::ruling::{Ruling, RulingKind};
+    use crate::Document;
+
+    #[test]
+    fn ruling() {
+        let mut doc = Document::default();
+        doc.metadata.ruling = Some(Ruling {
+            color: Color::rgba(0x80, 0x80, 0x80, 0x80),
+            ..Ruling::new(RulingKind::Grid, 100.)
+        });
+        let svg = doc.to_string();
+        assert_eq!(svg.matches("<pattern").count(), 1);
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains(r#"fill="url(#svgnote-ruling)""#));
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+
+        // One tile, however large the page
+        let large = SerializeOptions {
+            view_box: Some(BoundingBox {
+                min: (0., 0.),
+                max: (100_000., 100_000.),
+            }),
+            ..Default::default()
+        };
+        assert_eq!(doc.to_string_with(&large).matches("<path").count(), 1);
+
+        doc.metadata.ruling = Some(Ruling::new(RulingKind::Dots, 50.));
+        let svg = doc.to_string();
+        assert_eq!(svg.matches("<circle").count(), 4);
+        assert_eq!(Document::from_str(&svg).unwrap(), doc);
+    }
+}
@@ -0,0 +1,130 @@
+//! Saving documents without losing the previous version if writing fails,
+//! see [`Document::save_atomic`].
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::Document;
+
+/// The path of the `n`th backup of `path` made by
+/// [`Document::save_atomic`], `notes.svg.1.bak` being the newest for
+/// `notes.svg`.
+pub fn backup_path(path: &Path, n: usize) -> PathBuf {
+    with_suffix(path, &format!(".{}.bak", n))
+}
+
+/// A temporary file next to `path`, unique to this call, so concurrent
+/// saves do not write to the same file.
+fn temp_path(path: &Path) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    with_suffix(path, &format!(".{}-{}.tmp", process::id(), n))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+impl Document {
+    /// Saves the document to `path`, so that it holds either the old or the
+    /// new version, even if the program or system crashes.
+    ///
+    /// The document is written to a temporary file next to `path`, synced
+    /// to disk and then renamed. The previous `keep_backups` versions are
+    /// kept, see [`backup_path`].
+    pub fn save_atomic(&self, path: impl AsRef<Path>, keep_backups: usize) -> io::Result<()> {
+        write_atomic(path.as_ref(), self.to_string().as_bytes(), keep_backups)
+    }
+}
+
+/// Writes `data` to `path` the way [`Document::save_atomic`] does, e.g. for
+/// notebooks and exports.
+pub(crate) fn write_atomic(path: &Path, data: &[u8], keep_backups: usize) -> io::Result<()> {
+    let temp = temp_path(path);
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()
+    })();
+    if let Err(error) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+
+    if keep_backups > 0 && path.exists() {
+        for n in (1..keep_backups).rev() {
+            let backup = backup_path(path, n);
+            if backup.exists() {
+                fs::rename(&backup, backup_path(path, n + 1))?;
+            }
+        }
+        // The file stays in place until it is replaced
+        let newest = backup_path(path, 1);
+        if fs::hard_link(path, &newest).is_err() {
+            fs::copy(path, &newest)?;
+        }
+    }
+    if let Err(error) = fs::rename(&temp, path) {
+        let _ = fs::remove_file(&temp);
+        return Err(error);
+    }
+
+    // The rename is only durable once the directory is synced
+    #[cfg(unix)]
+    {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Properties};
+    use crate::save::{backup_path, temp_path};
+    use crate::scalar::Scalar;
+    use crate::Document;
+
+    #[test]
+    fn save_atomic() {
+        let dir = std::env::temp_dir().join(format!("svg_notes_save_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.svg");
+        let version = |radius| Document {
+            elements: vec![Element::Ellipse(Ellipse {
+                position: (0., 0.),
+                stroke: Color::rgb(0, 0, 0),
+                fill: Color::rgba(0, 0, 0, 0),
+                width: 1.,
+                radius,
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+        for radius in 1..=4 {
+            version(radius as Scalar).save_atomic(&path, 2).unwrap();
+        }
+        let read = |path| Document::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(read(&path), version(4.));
+        assert_eq!(read(&backup_path(&path, 1)), version(3.));
+        assert_eq!(read(&backup_path(&path, 2)), version(2.));
+        assert!(!backup_path(&path, 3).exists());
+        assert_ne!(temp_path(&path), temp_path(&path));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
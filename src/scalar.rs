@@ -0,0 +1,40 @@
+//! The type of coordinates, `f32` unless the `f64` feature is enabled.
+//!
+//! Colors, opacities and [`VertexBuffers`](crate::render::VertexBuffers) are
+//! `f32` either way.
+
+#[cfg(not(feature = "f64"))]
+pub use std::f32::consts;
+#[cfg(feature = "f64")]
+pub use std::f64::consts;
+
+#[cfg(not(feature = "f64"))]
+pub type Scalar = f32;
+#[cfg(feature = "f64")]
+pub type Scalar = f64;
+
+/// Parses a coordinate, with `fast-float` if the `fast-parse` feature is
+/// enabled.
+#[cfg(feature = "fast-parse")]
+pub(crate) fn parse(s: &str) -> Option<Scalar> {
+    fast_float::parse(s).ok()
+}
+
+#[cfg(not(feature = "fast-parse"))]
+pub(crate) fn parse(s: &str) -> Option<Scalar> {
+    s.parse().ok()
+}
+
+/// The resolution of fixed-point coordinates, which are multiples of
+/// `1 / FIXED_POINT_SCALE`.
+pub const FIXED_POINT_SCALE: Scalar = 256.;
+
+/// Rounds `value` to fixed-point.
+///
+/// Fixed-point values are exact in binary floating point (for `f32` up to
+/// `±65536`), so they are written and parsed without rounding. Documents
+/// edited concurrently, e.g. with a CRDT, stay bit-identical on all
+/// platforms if every edit is snapped to fixed-point.
+pub fn to_fixed_point(value: Scalar) -> Scalar {
+    (value * FIXED_POINT_SCALE).round() / FIXED_POINT_SCALE
+}
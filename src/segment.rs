@@ -0,0 +1,111 @@
+//! Splitting flat streams of points into pen strokes, for importing formats
+//! that do not have strokes, e.g. InkML traces or CSV, see [`Gaps`].
+
+use std::ops::Range;
+
+use crate::elements::{Line, LinePoint};
+use crate::geometry::distance;
+use crate::scalar::Scalar;
+use crate::UNITS_PER_MM;
+
+/// The gaps between consecutive points that start a new stroke, shared by
+/// the importers so they split alike.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Gaps {
+    /// More milliseconds between two points, e.g. the pen was lifted.
+    pub time: u64,
+    /// Points farther apart, in user units, e.g. the pen moved while out of
+    /// range.
+    pub distance: Scalar,
+}
+
+impl Default for Gaps {
+    /// `100` milliseconds and `10mm`.
+    fn default() -> Self {
+        Gaps {
+            time: 100,
+            distance: 10. * UNITS_PER_MM,
+        }
+    }
+}
+
+impl Gaps {
+    /// The ranges of `points` that form strokes, split between points that
+    /// are further apart in time or space than the gaps.
+    ///
+    /// `timestamps` are only used if there is one per point. Timestamps
+    /// going back are gaps as well, e.g. a new trace counting from `0`.
+    pub fn split(&self, points: &[LinePoint], timestamps: &[u64]) -> Vec<Range<usize>> {
+        let timed = timestamps.len() == points.len();
+        let mut strokes = vec![];
+        let mut start = 0;
+        for i in 1..points.len() {
+            let (a, b) = (points[i - 1], points[i]);
+            let late = timed
+                && (timestamps[i] < timestamps[i - 1]
+                    || timestamps[i] - timestamps[i - 1] > self.time);
+            if late || distance((a.0, a.1), (b.0, b.1)) > self.distance {
+                strokes.push(start..i);
+                start = i;
+            }
+        }
+        if start < points.len() {
+            strokes.push(start..points.len());
+        }
+        strokes
+    }
+
+    /// A copy of `template`, i.e. with its color, width and properties, per
+    /// stroke [split](Gaps::split) from `points`, with their timestamps.
+    pub fn strokes(&self, points: &[LinePoint], timestamps: &[u64], template: &Line) -> Vec<Line> {
+        let timed = timestamps.len() == points.len();
+        self.split(points, timestamps)
+            .into_iter()
+            .map(|range| Line {
+                points: points[range.clone()].to_vec(),
+                timestamps: if timed {
+                    timestamps[range].to_vec()
+                } else {
+                    vec![]
+                },
+                ..template.clone()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Line, LinePoint, Properties};
+    use crate::segment::Gaps;
+
+    #[test]
+    fn split() {
+        let points = vec![
+            LinePoint(0., 0., 1.),
+            LinePoint(1., 0., 1.),
+            // Lifted
+            LinePoint(2., 0., 1.),
+            // Moved out of range
+            LinePoint(500., 0., 1.),
+            LinePoint(501., 0., 1.),
+        ];
+        let timestamps = vec![0, 10, 500, 510, 520];
+        let gaps = Gaps::default();
+        assert_eq!(gaps.split(&points, &timestamps), vec![0..2, 2..3, 3..5]);
+        assert_eq!(gaps.split(&points, &[]), vec![0..3, 3..5]);
+
+        let template = Line {
+            color: Color::rgb(0xFF, 0, 0),
+            width: 2.,
+            points: vec![],
+            timestamps: vec![],
+            properties: Properties::default(),
+        };
+        let strokes = gaps.strokes(&points, &timestamps, &template);
+        assert_eq!(strokes.len(), 3);
+        assert_eq!(strokes[2].timestamps, vec![510, 520]);
+        assert_eq!(strokes[2].color, Color::rgb(0xFF, 0, 0));
+    }
+}
@@ -0,0 +1,125 @@
+//! Detached signatures embedded in the document, e.g. for exam submissions.
+//!
+//! The signature covers the serialization of the document without the
+//! signature, so a document verifies as long as it parses to the same
+//! elements.
+
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+pub use ed25519_dalek::{Keypair, PublicKey, Signature, Signer};
+use svg::node::element;
+use svg::node::Node;
+use svg::parser::Event;
+use thiserror::Error;
+
+use crate::{Document, DocumentError};
+
+#[derive(Error, Debug)]
+pub enum SignatureError {
+    #[error("Document is not signed")]
+    Unsigned,
+    #[error("Invalid signature: `{0}`")]
+    Malformed(String),
+    #[error("Signature does not match the document")]
+    Mismatch,
+    #[error(transparent)]
+    Document(#[from] DocumentError),
+}
+
+/// Serializes `document` with a signature by `keypair` in its `<metadata>`.
+pub fn sign(document: &Document, keypair: &Keypair) -> String {
+    let signature: Signature = keypair.sign(document.to_string().as_bytes());
+    let mut signature_node = element::Element::new("svgnote:signature");
+    signature_node.assign("svgnote:key", hex(&keypair.public.to_bytes()));
+    signature_node.assign("svgnote:value", hex(&signature.to_bytes()));
+    let mut metadata = element::Element::new("metadata");
+    metadata.append(signature_node);
+    let mut string = String::new();
+    crate::write_svg(&mut string, &document.to_svg().add(metadata))
+        .expect("writing to a String does not fail");
+    string
+}
+
+/// Parses a document signed with [`sign`], returning it with the key it was
+/// signed with, if the signature matches.
+///
+/// Callers have to check the key belongs to whom they expect.
+pub fn verify(s: &str) -> Result<(Document, PublicKey), SignatureError> {
+    let (key, value) = svg::read(s)
+        .map_err(DocumentError::from)?
+        .find_map(|event| match event {
+            Event::Tag("svgnote:signature", _, attributes) => Some((
+                attributes.get("svgnote:key").map(|v| v.to_string()),
+                attributes.get("svgnote:value").map(|v| v.to_string()),
+            )),
+            _ => None,
+        })
+        .ok_or(SignatureError::Unsigned)?;
+    let key = key.ok_or(SignatureError::Unsigned)?;
+    let value = value.ok_or(SignatureError::Unsigned)?;
+    let public_key = unhex(&key)
+        .and_then(|bytes| PublicKey::from_bytes(&bytes).ok())
+        .ok_or_else(|| SignatureError::Malformed(key.clone()))?;
+    let signature = unhex(&value)
+        .and_then(|bytes| Signature::try_from(&bytes[..]).ok())
+        .ok_or_else(|| SignatureError::Malformed(value.clone()))?;
+
+    let document = Document::from_str(s)?;
+    public_key
+        .verify_strict(document.to_string().as_bytes(), &signature)
+        .map_err(|_| SignatureError::Mismatch)?;
+    Ok((document, public_key))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SecretKey;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Line, LinePoint, Properties};
+    use crate::signing::{sign, verify, Keypair, PublicKey, SignatureError};
+    use crate::Document;
+
+    #[test]
+    fn signing() {
+        let secret = SecretKey::from_bytes(&[7; 32]).unwrap();
+        let keypair = Keypair {
+            public: PublicKey::from(&secret),
+            secret,
+        };
+        let doc = Document {
+            elements: vec![Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 1.,
+                points: vec![LinePoint(0., 0., 1.), LinePoint(1., 1., 1.)],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })],
+            ..Default::default()
+        };
+
+        let signed = sign(&doc, &keypair);
+        let (verified, key) = verify(&signed).unwrap();
+        assert_eq!(verified, doc);
+        assert_eq!(key, keypair.public);
+
+        let tampered = signed.replace("1,1,1", "2,1,1");
+        assert_matches!(verify(&tampered), Err(SignatureError::Mismatch));
+        assert_matches!(verify(&doc.to_string()), Err(SignatureError::Unsigned));
+    }
+}
@@ -0,0 +1,536 @@
+//! Snapping new geometry precisely onto existing geometry.
+
+use crate::elements::Element;
+use crate::geometry::{distance, BoundingBox};
+use crate::render::RenderItem;
+use crate::scalar::Scalar;
+
+/// A square grid new shapes are placed on, stored in the
+/// [`Metadata`](crate::metadata::Metadata) of a document as
+/// `<svgnote:grid>`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Grid {
+    /// The distance between grid lines, positive. Lines pass through the
+    /// origin.
+    pub spacing: Scalar,
+}
+
+impl Grid {
+    pub fn new(spacing: Scalar) -> Self {
+        Grid { spacing }
+    }
+
+    /// The grid point closest to `point`.
+    pub fn snap(&self, (x, y): (Scalar, Scalar)) -> (Scalar, Scalar) {
+        (self.snap_length(x), self.snap_length(y))
+    }
+
+    /// The multiple of the spacing closest to `length`.
+    pub fn snap_length(&self, length: Scalar) -> Scalar {
+        (length / self.spacing).round() * self.spacing
+    }
+}
+
+/// A non-printing line to align to, stored in the
+/// [`Metadata`](crate::metadata::Metadata) of a document as
+/// `<svgnote:guide>`.
+///
+/// Guides are not rendered, e.g. in
+/// [`Document::export_archival`](crate::Document::export_archival), only
+/// apps show them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Guide {
+    /// The line at `y`.
+    Horizontal(Scalar),
+    /// The line at `x`.
+    Vertical(Scalar),
+    /// The line through `position` at `angle` radians, clockwise from the
+    /// x axis.
+    Diagonal {
+        position: (Scalar, Scalar),
+        angle: Scalar,
+    },
+}
+
+impl Guide {
+    /// The point on the guide closest to `point`.
+    pub fn nearest(&self, (x, y): (Scalar, Scalar)) -> (Scalar, Scalar) {
+        match *self {
+            Guide::Horizontal(at) => (x, at),
+            Guide::Vertical(at) => (at, y),
+            Guide::Diagonal { position, angle } => {
+                let (dx, dy) = (angle.cos(), angle.sin());
+                let t = (x - position.0) * dx + (y - position.1) * dy;
+                (position.0 + t * dx, position.1 + t * dy)
+            }
+        }
+    }
+
+    pub fn distance_to(&self, point: (Scalar, Scalar)) -> Scalar {
+        distance(point, self.nearest(point))
+    }
+}
+
+/// What moved bounds were snapped to by [`snap_bounds`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SnapTarget {
+    /// The guide at the index in
+    /// [`Metadata::guides`](crate::metadata::Metadata::guides).
+    Guide(usize),
+    /// An edge or the center of the bounds of the element at the index in
+    /// [`render_items`](crate::render::render_items).
+    Item(usize),
+}
+
+/// A snap made by [`snap_bounds`], e.g. for showing an indicator.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Snap {
+    /// The line snapped to, in document coordinates.
+    pub line: Guide,
+    /// A point of the snapped bounds on the line, i.e. on the snapped edge
+    /// level with the center, or the center.
+    pub point: (Scalar, Scalar),
+    pub target: SnapTarget,
+}
+
+/// The offset moving `bounds` onto the closest guide or edge or center of
+/// `items` at most `radius` away, with the snaps made, at most one per
+/// axis.
+///
+/// The edges and the center of `bounds` snap to vertical and horizontal
+/// lines. Its center only snaps to diagonal guides if nothing else is in
+/// reach. Guides win ties.
+pub fn snap_bounds(
+    bounds: BoundingBox,
+    guides: &[Guide],
+    items: &[(usize, BoundingBox)],
+    radius: Scalar,
+) -> ((Scalar, Scalar), Vec<Snap>) {
+    let center = bounds.center();
+    let edges = [
+        [bounds.min.0, center.0, bounds.max.0],
+        [bounds.min.1, center.1, bounds.max.1],
+    ];
+    // The closest line per axis, with the offset to it
+    let mut best: [Option<(Scalar, Scalar, SnapTarget)>; 2] = [None, None];
+    let mut consider = |axis: usize, line: Scalar, target: SnapTarget| {
+        for &edge in &edges[axis] {
+            let offset = line - edge;
+            if offset.abs() <= radius && best[axis].map_or(true, |(o, _, _)| offset.abs() < o.abs())
+            {
+                best[axis] = Some((offset, line, target));
+            }
+        }
+    };
+    for (i, guide) in guides.iter().enumerate() {
+        match *guide {
+            Guide::Vertical(x) => consider(0, x, SnapTarget::Guide(i)),
+            Guide::Horizontal(y) => consider(1, y, SnapTarget::Guide(i)),
+            Guide::Diagonal { .. } => {}
+        }
+    }
+    for &(item, other) in items {
+        let (x, y) = other.center();
+        for &line in &[other.min.0, x, other.max.0] {
+            consider(0, line, SnapTarget::Item(item));
+        }
+        for &line in &[other.min.1, y, other.max.1] {
+            consider(1, line, SnapTarget::Item(item));
+        }
+    }
+
+    if let [None, None] = best {
+        let diagonal = guides
+            .iter()
+            .enumerate()
+            .filter(|(_, guide)| matches!(guide, Guide::Diagonal { .. }))
+            .map(|(i, guide)| (i, *guide, guide.distance_to(center)))
+            .filter(|&(_, _, distance)| distance <= radius)
+            .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal));
+        return match diagonal {
+            Some((i, line, _)) => {
+                let point = line.nearest(center);
+                let snap = Snap {
+                    line,
+                    point,
+                    target: SnapTarget::Guide(i),
+                };
+                ((point.0 - center.0, point.1 - center.1), vec![snap])
+            }
+            None => ((0., 0.), vec![]),
+        };
+    }
+
+    let offset = (
+        best[0].map_or(0., |(offset, _, _)| offset),
+        best[1].map_or(0., |(offset, _, _)| offset),
+    );
+    let center = (center.0 + offset.0, center.1 + offset.1);
+    let mut snaps = vec![];
+    if let Some((_, x, target)) = best[0] {
+        snaps.push(Snap {
+            line: Guide::Vertical(x),
+            point: (x, center.1),
+            target,
+        });
+    }
+    if let Some((_, y, target)) = best[1] {
+        snaps.push(Snap {
+            line: Guide::Horizontal(y),
+            point: (center.0, y),
+            target,
+        });
+    }
+    (offset, snaps)
+}
+
+/// A point new geometry can snap to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Anchor {
+    /// In document coordinates.
+    pub position: (Scalar, Scalar),
+    /// The index of the element in [`render_items`](crate::render::render_items).
+    pub item: usize,
+}
+
+/// The anchor points of an element in its own coordinates: the endpoints of
+/// strokes, the corners and centers of shapes.
+pub fn anchors(element: &Element) -> Vec<(Scalar, Scalar)> {
+    match element {
+        Element::Line(line) => line
+            .points
+            .first()
+            .into_iter()
+            .chain(line.points.last().filter(|_| line.points.len() > 1))
+            .map(|&p| p.into())
+            .collect(),
+        Element::Polyline(polyline) => polyline
+            .points
+            .first()
+            .into_iter()
+            .chain(polyline.points.last().filter(|_| polyline.points.len() > 1))
+            .map(|&p| p.into())
+            .collect(),
+        Element::Ngon(ngon) => {
+            let mut points = ngon.points();
+            points.push(ngon.position);
+            points
+        }
+        Element::Shape(shape) => {
+            let mut points = shape.points.clone();
+            points.push(shape.position);
+            points
+        }
+        Element::Arc(arc) => vec![arc.point_at(arc.start), arc.point_at(arc.end), arc.position],
+        Element::Ellipse(ellipse) => vec![ellipse.position],
+        Element::Text(text) => vec![text.position],
+        Element::FileRef(file) => {
+            let BoundingBox { min, max } = file.bounds();
+            vec![min, (max.0, min.1), max, (min.0, max.1)]
+        }
+        // The symbol is not known here
+        Element::Instance(instance) => vec![instance.position],
+        Element::Custom(custom) => match custom.element.bounds() {
+            Some(BoundingBox { min, max }) => vec![min, (max.0, min.1), max, (min.0, max.1)],
+            None => vec![],
+        },
+        Element::Dimension(dimension) => vec![dimension.start, dimension.end],
+        Element::Frame(frame) => frame.corners().to_vec(),
+        // Gone soon, not worth aligning to
+        Element::Ephemeral(_) => vec![],
+        Element::Layer(layer) => layer
+            .elements
+            .iter()
+            .flat_map(|e| {
+                let transform = e.properties().transform;
+                anchors(e).into_iter().map(move |p| transform.apply(p))
+            })
+            .collect(),
+    }
+}
+
+/// A k-d tree over anchor points, for finding the closest one quickly.
+#[derive(Debug, Clone, Default)]
+pub struct PointIndex {
+    /// Each subslice is a subtree, with its root in the middle, split along x
+    /// on even depths and along y on odd depths.
+    anchors: Vec<Anchor>,
+}
+
+impl PointIndex {
+    pub fn new(mut anchors: Vec<Anchor>) -> Self {
+        build(&mut anchors, 0);
+        PointIndex { anchors }
+    }
+
+    /// Indexes the anchors of all `items`, except ephemeral ones.
+    pub fn from_items(items: &[RenderItem]) -> Self {
+        Self::new(
+            items
+                .iter()
+                .enumerate()
+                .filter(|(_, i)| !i.element.is_ephemeral())
+                .flat_map(|(item, i)| {
+                    anchors(i.element).into_iter().map(move |p| Anchor {
+                        position: i.transform.apply(p),
+                        item,
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    pub fn len(&self) -> usize {
+        self.anchors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+
+    /// The anchor closest to `point`, if it is at most `radius` away.
+    pub fn nearest_within(&self, point: (Scalar, Scalar), radius: Scalar) -> Option<Anchor> {
+        let mut best = None;
+        nearest(&self.anchors, 0, point, &mut (radius * radius), &mut best);
+        best
+    }
+}
+
+fn axis(point: (Scalar, Scalar), depth: usize) -> Scalar {
+    if depth % 2 == 0 {
+        point.0
+    } else {
+        point.1
+    }
+}
+
+fn build(anchors: &mut [Anchor], depth: usize) {
+    if anchors.len() < 2 {
+        return;
+    }
+    anchors.sort_by(|a, b| {
+        axis(a.position, depth)
+            .partial_cmp(&axis(b.position, depth))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let middle = anchors.len() / 2;
+    let (left, right) = anchors.split_at_mut(middle);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+fn nearest(
+    anchors: &[Anchor],
+    depth: usize,
+    point: (Scalar, Scalar),
+    best_distance: &mut Scalar,
+    best: &mut Option<Anchor>,
+) {
+    if anchors.is_empty() {
+        return;
+    }
+    let middle = anchors.len() / 2;
+    let anchor = anchors[middle];
+    let (dx, dy) = (anchor.position.0 - point.0, anchor.position.1 - point.1);
+    // Compared squared, to avoid the square root
+    let distance = dx * dx + dy * dy;
+    if distance <= *best_distance {
+        *best_distance = distance;
+        *best = Some(anchor);
+    }
+    let offset = axis(point, depth) - axis(anchor.position, depth);
+    let (near, far) = if offset < 0.0 {
+        (&anchors[..middle], &anchors[middle + 1..])
+    } else {
+        (&anchors[middle + 1..], &anchors[..middle])
+    };
+    nearest(near, depth + 1, point, best_distance, best);
+    if offset * offset <= *best_distance {
+        nearest(far, depth + 1, point, best_distance, best);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ephemeral, Line, LinePoint, Properties};
+    use crate::geometry::BoundingBox;
+    use crate::scalar::Scalar;
+    use crate::snap::{snap_bounds, Anchor, Grid, Guide, PointIndex, Snap, SnapTarget};
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn nearest_within() {
+        let anchors: Vec<_> = (0..100)
+            .map(|i| Anchor {
+                position: ((i % 10) as Scalar * 10., (i / 10) as Scalar * 10.),
+                item: i,
+            })
+            .collect();
+        let index = PointIndex::new(anchors);
+        assert_eq!(index.len(), 100);
+        assert_eq!(
+            index.nearest_within((32., 58.), 5.).map(|a| a.item),
+            Some(63)
+        );
+        assert_eq!(index.nearest_within((-3., 1.), 5.).map(|a| a.item), Some(0));
+        assert_eq!(index.nearest_within((35., 55.), 5.), None);
+        assert_eq!(PointIndex::default().nearest_within((0., 0.), 5.), None);
+
+        let mut line = Element::Line(Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![
+                LinePoint(0., 0., 1.),
+                LinePoint(5., 5., 1.),
+                LinePoint(10., 0., 1.),
+            ],
+            timestamps: vec![],
+            properties: Properties::default(),
+        });
+        Transform::translate(100., 0.).apply_to(&mut line);
+        let doc = Document {
+            elements: vec![line],
+            ..Default::default()
+        };
+        let index = doc.snap_index();
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index.nearest_within((109., 1.), 2.).map(|a| a.item),
+            Some(0)
+        );
+        assert_eq!(index.nearest_within((105., 5.), 2.), None);
+    }
+
+    #[test]
+    fn grid() {
+        let grid = Grid::new(5.);
+        assert_eq!(grid.snap((12., -8.)), (10., -10.));
+        assert_eq!(grid.snap_length(2.4), 0.);
+
+        let mut doc = Document::default();
+        doc.metadata.grid = Some(grid);
+        let ngon = doc
+            .ngon_builder()
+            .position(12., 18.)
+            .radius(9.)
+            .n(6)
+            .build()
+            .unwrap();
+        assert_eq!((ngon.position, ngon.radius), ((10., 20.), 10.));
+        // Never collapsed to a point
+        let ellipse = doc
+            .ellipse_builder()
+            .position(1., 1.)
+            .radius(1.)
+            .build()
+            .unwrap();
+        assert_eq!((ellipse.position, ellipse.radius), ((0., 0.), 5.));
+        assert!(doc
+            .ellipse_builder()
+            .position(0., 0.)
+            .radius(1.)
+            .grid(Grid::new(0.))
+            .build()
+            .is_err());
+
+        let string = doc.to_string();
+        assert!(string.contains(r#"<svgnote:grid spacing="5""#));
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+    }
+
+    #[test]
+    fn guides() {
+        let diagonal = Guide::Diagonal {
+            position: (10., 0.),
+            angle: (45. as Scalar).to_radians(),
+        };
+        let (x, y) = diagonal.nearest((20., 0.));
+        assert!((x - 15.).abs() < 1e-4 && (y - 5.).abs() < 1e-4);
+        assert_eq!(Guide::Vertical(3.).distance_to((7., 1.)), 4.);
+
+        let mut doc = Document::default();
+        doc.metadata.guides = vec![Guide::Horizontal(100.), Guide::Vertical(-5.), diagonal];
+        let string = doc.to_string();
+        assert_eq!(string.matches("<svgnote:guide").count(), 3);
+        assert_eq!(Document::from_str(&string).unwrap(), doc);
+        assert!(!doc.export_archival(None).contains("guide"));
+    }
+
+    #[test]
+    fn snap_transform() {
+        let square = |x: Scalar| {
+            Element::Line(Line {
+                color: Color::rgb(0, 0, 0),
+                width: 0.,
+                points: vec![
+                    LinePoint(x, 0., 1.),
+                    LinePoint(x + 10., 0., 1.),
+                    LinePoint(x + 10., 10., 1.),
+                ],
+                timestamps: vec![],
+                properties: Properties::default(),
+            })
+        };
+        let mut doc = Document {
+            elements: vec![square(0.), square(100.)],
+            ..Default::default()
+        };
+        doc.metadata.guides = vec![Guide::Horizontal(52.)];
+
+        // The left edge onto the right edge of the other, the top onto the
+        // guide
+        let (transform, snaps) = doc.snap_transform(1, Transform::translate(-88., 50.), 3.);
+        assert_eq!(transform, Transform::translate(-90., 52.));
+        assert_eq!(
+            snaps,
+            vec![
+                Snap {
+                    line: Guide::Vertical(10.),
+                    point: (10., 57.),
+                    target: SnapTarget::Item(0),
+                },
+                Snap {
+                    line: Guide::Horizontal(52.),
+                    point: (15., 52.),
+                    target: SnapTarget::Guide(0),
+                },
+            ]
+        );
+        let (transform, snaps) = doc.snap_transform(1, Transform::translate(0., 30.), 3.);
+        assert_eq!(transform, Transform::translate(0., 30.));
+        assert!(snaps.is_empty());
+
+        // Not onto ephemeral strokes, which are not part of the bounds either
+        let bounds = doc.bounds();
+        let trail = Line {
+            color: Color::rgb(0xFF, 0, 0),
+            width: 1.,
+            points: vec![LinePoint(11.5, 200., 1.), LinePoint(11.5, 300., 1.)],
+            timestamps: vec![],
+            properties: Properties::default(),
+        };
+        doc.elements
+            .push(Element::Ephemeral(Ephemeral::new(trail, 500, 0)));
+        assert_eq!(doc.bounds(), bounds);
+        let (transform, _) = doc.snap_transform(1, Transform::translate(-88., 50.), 3.);
+        assert_eq!(transform, Transform::translate(-90., 52.));
+        assert!(doc.snap_index().nearest_within((11.5, 200.), 1.).is_none());
+
+        // Diagonal guides snap the center
+        let bounds = BoundingBox {
+            min: (0., 0.),
+            max: (2., 2.),
+        };
+        let guide = Guide::Diagonal {
+            position: (0., 2.),
+            angle: 0.,
+        };
+        let (offset, snaps) = snap_bounds(bounds, &[guide], &[], 3.);
+        assert_eq!(offset, (0., 1.));
+        assert_eq!(snaps[0].point, (1., 2.));
+    }
+}
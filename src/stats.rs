@@ -0,0 +1,159 @@
+//! Kinematics of pen strokes, e.g. for research or for telling slow,
+//! deliberate shapes from quick handwriting.
+
+use crate::elements::{Line, LinePoint};
+use crate::scalar::Scalar;
+
+/// Measurements of a [`Line`], see [`Line::stats`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct StrokeStats {
+    /// The length of the path through the points.
+    pub length: Scalar,
+    /// `None` for a line without points.
+    pub pressure: Option<Distribution>,
+    /// `None` if the line has no timestamps.
+    pub kinematics: Option<Kinematics>,
+}
+
+/// A summary of some values.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Distribution {
+    pub min: Scalar,
+    pub max: Scalar,
+    pub mean: Scalar,
+    pub median: Scalar,
+    pub std_dev: Scalar,
+}
+
+impl Distribution {
+    /// `None` if there are no `values`.
+    pub fn of(values: impl IntoIterator<Item = Scalar>) -> Option<Self> {
+        let mut values: Vec<Scalar> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let n = values.len() as Scalar;
+        let mean = values.iter().sum::<Scalar>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<Scalar>() / n;
+        let middle = values.len() / 2;
+        Some(Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean,
+            median: if values.len() % 2 == 0 {
+                (values[middle - 1] + values[middle]) / 2.
+            } else {
+                values[middle]
+            },
+            std_dev: variance.sqrt(),
+        })
+    }
+}
+
+/// How a stroke was drawn over time.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Kinematics {
+    /// From the first to the last point, in milliseconds.
+    pub duration: u64,
+    /// The speed between consecutive points, in units per second.
+    ///
+    /// Points drawn at the same time are combined.
+    pub velocities: Vec<Scalar>,
+    /// The change between consecutive `velocities`, in units per second
+    /// squared.
+    pub accelerations: Vec<Scalar>,
+    /// `None` if all points were drawn at the same time.
+    pub velocity: Option<Distribution>,
+}
+
+impl Kinematics {
+    /// `None` if `timestamps` are not one per point.
+    fn of(points: &[LinePoint], timestamps: &[u64]) -> Option<Self> {
+        if timestamps.len() != points.len() || points.is_empty() {
+            return None;
+        }
+        // The segments with their speed and the time in their middle
+        let mut segments: Vec<(Scalar, Scalar)> = vec![];
+        let mut distance = 0.;
+        let mut start = 0;
+        for i in 1..points.len() {
+            distance += points[i - 1].distance_to(points[i]);
+            let seconds = timestamps[i].saturating_sub(timestamps[start]) as Scalar / 1000.;
+            if seconds > 0. {
+                // Relative to the first point, as epoch milliseconds lose
+                // precision as floats and their sum may overflow
+                let since = |i: usize| timestamps[i].saturating_sub(timestamps[0]);
+                let middle = (since(start) + since(i)) as Scalar / 2000.;
+                segments.push((distance / seconds, middle));
+                distance = 0.;
+                start = i;
+            }
+        }
+        let accelerations = segments
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0) / (w[1].1 - w[0].1))
+            .collect();
+        let velocities: Vec<Scalar> = segments.into_iter().map(|(v, _)| v).collect();
+        Some(Kinematics {
+            duration: timestamps[timestamps.len() - 1].saturating_sub(timestamps[0]),
+            velocity: Distribution::of(velocities.iter().copied()),
+            velocities,
+            accelerations,
+        })
+    }
+}
+
+impl Line {
+    /// The length, pressure and, with timestamps, speed of the stroke.
+    pub fn stats(&self) -> StrokeStats {
+        StrokeStats {
+            length: self.points.windows(2).map(|w| w[0].distance_to(w[1])).sum(),
+            pressure: Distribution::of(self.points.iter().map(|p| p.2)),
+            kinematics: Kinematics::of(&self.points, &self.timestamps),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::colors::Color;
+    use crate::elements::{Line, LinePoint, Properties};
+
+    #[test]
+    fn stats() {
+        let mut line = Line {
+            color: Color::rgb(0, 0, 0),
+            width: 1.,
+            points: vec![
+                LinePoint(0., 0., 0.2),
+                LinePoint(3., 4., 0.4),
+                LinePoint(3., 4., 0.6),
+                LinePoint(3., 14., 1.),
+            ],
+            timestamps: vec![],
+            properties: Properties::default(),
+        };
+        let stats = line.stats();
+        assert_eq!(stats.length, 15.);
+        let pressure = stats.pressure.unwrap();
+        assert_eq!((pressure.min, pressure.max), (0.2, 1.));
+        assert!((pressure.median - 0.5).abs() < 1e-6);
+        assert!(stats.kinematics.is_none());
+
+        // The third point is drawn at the same time as the second
+        line.timestamps = vec![1000, 1500, 1500, 2500];
+        let kinematics = line.stats().kinematics.unwrap();
+        assert_eq!(kinematics.duration, 1500);
+        assert_eq!(kinematics.velocities, vec![10., 10.]);
+        assert_eq!(kinematics.accelerations, vec![0.]);
+        assert_eq!(kinematics.velocity.unwrap().max, 10.);
+
+        // Accelerating, at times far from zero
+        let start = u64::MAX - 10_000;
+        line.timestamps = vec![start, start + 1000, start + 1000, start + 2000];
+        let kinematics = line.stats().kinematics.unwrap();
+        assert_eq!(kinematics.velocities, vec![5., 10.]);
+        assert_eq!(kinematics.accelerations, vec![5.]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Documents with [`Placeholder`]s, e.g. for meeting notes or planners, see
+//! [`Document::instantiate`].
+
+use std::collections::HashMap;
+
+use crate::colors::Color;
+use crate::elements::{Element, Font, Properties, Text};
+use crate::ids::ElementId;
+use crate::metadata::Placeholder;
+use crate::transform::Transform;
+use crate::Document;
+
+/// What a [`Placeholder`] is filled with.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Fill {
+    /// Black text in the default font, starting at the top left corner.
+    Text(String),
+    /// Elements relative to the top left corner of the placeholder.
+    Elements(Vec<Element>),
+}
+
+impl Fill {
+    fn into_elements(self, placeholder: &Placeholder) -> Vec<Element> {
+        let (x, y) = placeholder.bounds.min;
+        match self {
+            Fill::Text(content) => {
+                let font = Font::default();
+                vec![Element::Text(Text {
+                    // The baseline of the first line
+                    position: (x, y + font.size),
+                    content,
+                    color: Color::rgb(0, 0, 0),
+                    font,
+                    properties: Properties::default(),
+                })]
+            }
+            Fill::Elements(mut elements) => {
+                for element in &mut elements {
+                    Transform::translate(x, y).apply_to(element);
+                }
+                elements
+            }
+        }
+    }
+}
+
+/// Replaces the first element with the id `id` by `fill`, also in layers,
+/// returning `fill` if there is none.
+fn replace(
+    elements: &mut Vec<Element>,
+    id: &ElementId,
+    mut fill: Vec<Element>,
+) -> Option<Vec<Element>> {
+    for i in 0..elements.len() {
+        if elements[i].properties().id.as_ref() == Some(id) {
+            elements.remove(i);
+            let rest = elements.split_off(i);
+            elements.extend(fill);
+            elements.extend(rest);
+            return None;
+        }
+        if let Element::Layer(layer) = &mut elements[i] {
+            fill = replace(&mut layer.elements, id, fill)?;
+        }
+    }
+    Some(fill)
+}
+
+impl Document {
+    /// A copy of `template` with the placeholders named in `values` filled.
+    ///
+    /// The content of a placeholder for an element replaces the element, in
+    /// its layer, otherwise it is added on top.
+    ///
+    /// The filled placeholders are removed, the others are kept to be filled
+    /// later.
+    pub fn instantiate(template: &Document, mut values: HashMap<String, Fill>) -> Document {
+        let mut doc = template.clone();
+        let mut placeholders = vec![];
+        for placeholder in doc.metadata.placeholders.drain(..) {
+            let elements = match values.remove(&placeholder.name) {
+                Some(fill) => fill.into_elements(&placeholder),
+                None => {
+                    placeholders.push(placeholder);
+                    continue;
+                }
+            };
+            let rest = match &placeholder.element {
+                Some(id) => replace(&mut doc.elements, id, elements),
+                None => Some(elements),
+            };
+            doc.elements.extend(rest.into_iter().flatten());
+        }
+        doc.metadata.placeholders = placeholders;
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Ellipse, Layer, Properties};
+    use crate::geometry::BoundingBox;
+    use crate::ids::ElementId;
+    use crate::metadata::Placeholder;
+    use crate::template::Fill;
+    use crate::transform::Transform;
+    use crate::Document;
+
+    #[test]
+    fn instantiate() {
+        let ellipse = Element::Ellipse(Ellipse {
+            position: (5., 5.),
+            stroke: Color::rgb(0, 0, 0),
+            fill: Color::rgba(0, 0, 0, 0),
+            width: 1.,
+            radius: 5.,
+            properties: Properties::default(),
+        });
+        let mut logo = ellipse.clone();
+        logo.properties_mut().id = Some(ElementId::new("logo"));
+        let mut header = Layer::new("header");
+        header.elements = vec![logo, ellipse.clone()];
+        let mut template = Document {
+            elements: vec![Element::Layer(header)],
+            ..Default::default()
+        };
+        template.metadata.placeholders = vec![
+            Placeholder::new(
+                "title",
+                BoundingBox {
+                    min: (10., 10.),
+                    max: (200., 40.),
+                },
+            ),
+            Placeholder::new(
+                "attendees",
+                BoundingBox {
+                    min: (10., 50.),
+                    max: (200., 100.),
+                },
+            ),
+            Placeholder::for_element(
+                "logo",
+                "logo",
+                BoundingBox {
+                    min: (0., 0.),
+                    max: (10., 10.),
+                },
+            ),
+        ];
+        let template = Document::from_str(&template.to_string()).unwrap();
+        assert_eq!(template.metadata.placeholders.len(), 3);
+        assert_eq!(
+            template.metadata.placeholder("logo").unwrap().element,
+            Some(ElementId::new("logo"))
+        );
+
+        let mut values = HashMap::new();
+        values.insert("title".to_owned(), Fill::Text("Weekly".to_owned()));
+        values.insert("notes".to_owned(), Fill::Elements(vec![ellipse.clone()]));
+        values.insert("logo".to_owned(), Fill::Text("ACME".to_owned()));
+        let doc = Document::instantiate(&template, values);
+        match &doc.elements[..] {
+            [Element::Layer(header), Element::Text(title)] => {
+                assert_eq!(title.content, "Weekly");
+                assert_eq!(title.position, (10., 26.));
+                // The logo is replaced in its layer
+                assert_matches!(
+                    &header.elements[..],
+                    [Element::Text(logo), Element::Ellipse(_)] if logo.content == "ACME"
+                );
+            }
+            elements => panic!("{:?}", elements),
+        }
+        let names: Vec<_> = doc.metadata.placeholders.iter().map(|p| &p.name).collect();
+        assert_eq!(names, vec!["attendees"]);
+
+        let title = doc.elements[1].clone();
+        let mut values = HashMap::new();
+        values.insert("attendees".to_owned(), Fill::Elements(vec![title]));
+        let doc = Document::instantiate(&doc, values);
+        assert!(doc.metadata.placeholders.is_empty());
+        assert_eq!(
+            doc.elements[2].properties().transform,
+            Transform::translate(10., 50.)
+        );
+    }
+}
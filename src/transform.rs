@@ -0,0 +1,291 @@
+use std::fmt::Display;
+use std::ops::Mul;
+use std::str::FromStr;
+
+use crate::elements::Element;
+use crate::scalar::consts::PI;
+use crate::scalar::Scalar;
+
+/// An affine transformation, the matrix
+///
+/// ```text
+/// | a c e |
+/// | b d f |
+/// | 0 0 1 |
+/// ```
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Transform {
+    pub a: Scalar,
+    pub b: Scalar,
+    pub c: Scalar,
+    pub d: Scalar,
+    pub e: Scalar,
+    pub f: Scalar,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+
+    #[allow(clippy::many_single_char_names)]
+    pub const fn new(a: Scalar, b: Scalar, c: Scalar, d: Scalar, e: Scalar, f: Scalar) -> Self {
+        Transform { a, b, c, d, e, f }
+    }
+
+    pub const fn translate(x: Scalar, y: Scalar) -> Self {
+        Transform::new(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub const fn scale(x: Scalar, y: Scalar) -> Self {
+        Transform::new(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /// Rotates clockwise by `angle` (in radians) around the origin.
+    pub fn rotate(angle: Scalar) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform::new(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /// Rotates clockwise by `angle` (in radians) around `center`.
+    pub fn rotate_around(angle: Scalar, center: (Scalar, Scalar)) -> Self {
+        Transform::translate(center.0, center.1)
+            * Transform::rotate(angle)
+            * Transform::translate(-center.0, -center.1)
+    }
+
+    /// The transform `t` of the way from `self` to `other`.
+    ///
+    /// Both are split into a translation, a rotation, which takes the
+    /// shorter way round, and a scale with skew, which are interpolated on
+    /// their own, so e.g. a rotation keeps the size. Transforms collapsing
+    /// the plane onto a line are interpolated by coefficient.
+    pub fn lerp(self, other: Transform, t: Scalar) -> Self {
+        let lerp = |a: Scalar, b: Scalar| a + (b - a) * t;
+        match (self.decompose(), other.decompose()) {
+            (Some(a), Some(b)) => {
+                let mut turn = (b.rotation - a.rotation) % (2. * PI);
+                if turn > PI {
+                    turn -= 2. * PI;
+                } else if turn < -PI {
+                    turn += 2. * PI;
+                }
+                Transform::translate(lerp(self.e, other.e), lerp(self.f, other.f))
+                    * Transform::rotate(a.rotation + turn * t)
+                    * Transform::new(
+                        lerp(a.scale.0, b.scale.0),
+                        0.0,
+                        lerp(a.skew, b.skew),
+                        lerp(a.scale.1, b.scale.1),
+                        0.0,
+                        0.0,
+                    )
+            }
+            _ => Transform::new(
+                lerp(self.a, other.a),
+                lerp(self.b, other.b),
+                lerp(self.c, other.c),
+                lerp(self.d, other.d),
+                lerp(self.e, other.e),
+                lerp(self.f, other.f),
+            ),
+        }
+    }
+
+    /// The linear part as a rotation after a scale with skew, `None` if it
+    /// collapses the first axis.
+    fn decompose(&self) -> Option<Decomposed> {
+        let scale = self.a.hypot(self.b);
+        if scale == 0.0 || !scale.is_finite() {
+            return None;
+        }
+        let rotation = self.b.atan2(self.a);
+        let (sin, cos) = rotation.sin_cos();
+        Some(Decomposed {
+            rotation,
+            // The second axis rotated back
+            skew: self.c * cos + self.d * sin,
+            scale: (scale, self.d * cos - self.c * sin),
+        })
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    /// Composes the transforms, so that `self` is applied first, then `t`.
+    pub fn then(self, t: Transform) -> Self {
+        t * self
+    }
+
+    /// The transform undoing `self`, `None` if it collapses the plane, e.g.
+    /// a scale by `0`.
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+        let linear = Transform::new(
+            self.d / det,
+            -self.b / det,
+            -self.c / det,
+            self.a / det,
+            0.0,
+            0.0,
+        );
+        let translation = linear.linear(-self.e, -self.f);
+        Some(Transform {
+            e: translation.0,
+            f: translation.1,
+            ..linear
+        })
+    }
+
+    pub fn apply(&self, point: (Scalar, Scalar)) -> (Scalar, Scalar) {
+        let (x, y) = self.linear(point.0, point.1);
+        (x + self.e, y + self.f)
+    }
+
+    /// Transforms `element`, by adding `self` to the transform it already has.
+    ///
+    /// The geometry of the element is left unchanged.
+    pub fn apply_to(&self, element: &mut Element) {
+        let properties = element.properties_mut();
+        properties.transform = *self * properties.transform;
+    }
+
+    /// The factor lengths are scaled by, exact for transforms without skew
+    /// and non-uniform scaling.
+    pub fn scale_factor(&self) -> Scalar {
+        (self.a * self.d - self.b * self.c).abs().sqrt()
+    }
+
+    /// Applies only the linear part, i.e. without the translation.
+    fn linear(&self, x: Scalar, y: Scalar) -> (Scalar, Scalar) {
+        (self.a * x + self.c * y, self.b * x + self.d * y)
+    }
+}
+
+/// See [`Transform::decompose`], the linear part is
+/// `rotate(rotation) * new(scale.0, 0, skew, scale.1, 0, 0)`.
+struct Decomposed {
+    rotation: Scalar,
+    skew: Scalar,
+    scale: (Scalar, Scalar),
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// `a * b` applies `b` first, same as nesting `b` inside a group with `a`.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, t: Self) -> Self::Output {
+        let x = self.linear(t.a, t.b);
+        let y = self.linear(t.c, t.d);
+        let translation = self.linear(t.e, t.f);
+        Transform::new(
+            x.0,
+            x.1,
+            y.0,
+            y.1,
+            translation.0 + self.e,
+            translation.1 + self.f,
+        )
+    }
+}
+
+/// Parses an SVG transform list, e.g. `translate(10) rotate(45, 5, 5)`.
+impl FromStr for Transform {
+    type Err = svgtypes::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let t = svgtypes::Transform::from_str(s)?;
+        Ok(Transform::new(
+            t.a as Scalar,
+            t.b as Scalar,
+            t.c as Scalar,
+            t.d as Scalar,
+            t.e as Scalar,
+            t.f as Scalar,
+        ))
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "matrix({} {} {} {} {} {})",
+            self.a, self.b, self.c, self.d, self.e, self.f
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::scalar::consts::PI;
+    use crate::transform::Transform;
+
+    #[test]
+    fn parse_transform() {
+        assert_eq!(
+            Transform::from_str("translate(10, 20) scale(2)").unwrap(),
+            Transform::new(2., 0., 0., 2., 10., 20.)
+        );
+        assert_eq!(
+            Transform::from_str("scale(2) translate(10, 20)").unwrap(),
+            Transform::new(2., 0., 0., 2., 20., 40.)
+        );
+        let t = Transform::new(1., 2., 3., 4., 5., 6.);
+        assert_eq!(Transform::from_str(&t.to_string()).unwrap(), t);
+        assert!(Transform::from_str("skew(10)").is_err());
+    }
+
+    #[test]
+    fn compose_transform() {
+        let t = Transform::translate(10., 5.).then(Transform::scale(2., 3.));
+        assert_eq!(t.apply((1., 1.)), (22., 18.));
+        assert_eq!(t, Transform::scale(2., 3.) * Transform::translate(10., 5.));
+
+        let inverse = t.invert().unwrap();
+        assert_eq!(inverse.apply((22., 18.)), (1., 1.));
+        assert_eq!(t * inverse, Transform::IDENTITY);
+        assert_eq!(Transform::scale(0., 1.).invert(), None);
+
+        let (x, y) = Transform::rotate_around(PI / 2., (1., 1.)).apply((2., 1.));
+        assert!((x - 1.).abs() < 1e-6 && (y - 2.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn lerp_transform() {
+        let near = |a: Transform, b: Transform| {
+            let (a, b) = (
+                [a.a, a.b, a.c, a.d, a.e, a.f],
+                [b.a, b.b, b.c, b.d, b.e, b.f],
+            );
+            a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-5)
+        };
+        // Rotations keep the size, and take the shorter way
+        let half = Transform::IDENTITY.lerp(Transform::rotate(PI / 2.), 0.5);
+        assert!(near(half, Transform::rotate(PI / 4.)));
+        let half = Transform::rotate(0.9 * PI).lerp(Transform::rotate(-0.9 * PI), 0.5);
+        assert!(near(half, Transform::rotate(PI)));
+
+        let a = Transform::translate(10., 0.) * Transform::scale(2., 2.);
+        let b = Transform::translate(20., 10.) * Transform::rotate(PI / 2.);
+        assert!(near(
+            a.lerp(b, 0.5),
+            Transform::translate(15., 5.) * Transform::rotate(PI / 4.) * Transform::scale(1.5, 1.5)
+        ));
+        // Collapsed transforms by coefficient
+        assert_eq!(
+            Transform::scale(0., 0.).lerp(Transform::IDENTITY, 0.5),
+            Transform::scale(0.5, 0.5)
+        );
+    }
+}
@@ -0,0 +1,417 @@
+//! Importing the ink of Wacom pen displays, stored in the WILL format, see
+//! [`Document::from_will`].
+//!
+//! WILL 2.0 stores strokes as Catmull-Rom splines in protobuf, with the
+//! control points and widths as integers of a given decimal precision,
+//! each the difference to the one before on its axis. The control points
+//! are used as points of the strokes, without the first and last, which
+//! only shape the ends.
+//!
+//! The WILL file format packages the ink data as OPC, a zip archive, with
+//! one or more files of it in `sections/media`. The strokes of all of them
+//! are read, in the order of their names.
+//!
+//! WILL 3.1 (Universal Ink Model) files are RIFF files, with the ink data
+//! in protobuf in the `INKD` chunk. Its control points are floats, with the
+//! sizes and colors optionally stored per point. Compressed chunks and WILL
+//! 3.0.0, which stores everything in one chunk, are not read.
+
+use std::io::{Cursor, Read};
+
+use prost::Message;
+use thiserror::Error;
+use zip::ZipArchive;
+
+use crate::colors::Color;
+use crate::elements::{Element, Line, LinePoint, Properties};
+use crate::scalar::Scalar;
+use crate::Document;
+
+/// The messages of the WILL 2.0 ink data.
+mod wire {
+    use prost::Message;
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Paths {
+        #[prost(uint32, optional, tag = "1")]
+        pub decimal_precision: Option<u32>,
+        #[prost(message, repeated, tag = "2")]
+        pub paths: Vec<Path>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Path {
+        #[prost(float, optional, tag = "1")]
+        pub start_parameter: Option<f32>,
+        #[prost(float, optional, tag = "2")]
+        pub end_parameter: Option<f32>,
+        #[prost(uint32, optional, tag = "3")]
+        pub decimal_precision: Option<u32>,
+        /// `x` and `y` of each control point.
+        #[prost(sint32, repeated, tag = "4")]
+        pub data: Vec<i32>,
+        /// One for all control points, or one for each.
+        #[prost(sint32, repeated, tag = "5")]
+        pub stroke_widths: Vec<i32>,
+        /// Red, green, blue and alpha, from `0` to `255`.
+        #[prost(sint32, repeated, tag = "6")]
+        pub stroke_color: Vec<i32>,
+    }
+
+    /// The ink data of WILL 3.1, without the parts not read.
+    #[derive(Clone, PartialEq, Message)]
+    pub struct InkData {
+        #[prost(message, repeated, tag = "1")]
+        pub strokes: Vec<Stroke>,
+    }
+
+    #[derive(Clone, PartialEq, Message)]
+    pub struct Stroke {
+        #[prost(message, optional, tag = "4")]
+        pub spline_data: Option<SplineData>,
+    }
+
+    /// A value per control point, or none if it is not stored.
+    #[derive(Clone, PartialEq, Message)]
+    pub struct SplineData {
+        #[prost(float, repeated, tag = "1")]
+        pub spline_x: Vec<f32>,
+        #[prost(float, repeated, tag = "2")]
+        pub spline_y: Vec<f32>,
+        /// From `0` to `1`, like the other channels.
+        #[prost(float, repeated, tag = "4")]
+        pub red: Vec<f32>,
+        #[prost(float, repeated, tag = "5")]
+        pub green: Vec<f32>,
+        #[prost(float, repeated, tag = "6")]
+        pub blue: Vec<f32>,
+        #[prost(float, repeated, tag = "7")]
+        pub alpha: Vec<f32>,
+        #[prost(float, repeated, tag = "8")]
+        pub size: Vec<f32>,
+    }
+}
+
+/// The precision of files that do not specify it.
+const DEFAULT_PRECISION: u32 = 2;
+/// More decimal places than `Scalar` holds, it keeps the factor of
+/// [`undelta`] finite.
+const MAX_PRECISION: u32 = 9;
+/// The size of the ink data read at most from a package, so a small archive
+/// cannot unpack to more than fits into memory.
+const MAX_MEDIA: u64 = 1 << 30;
+
+#[derive(Error, Debug)]
+pub enum WillError {
+    #[error(transparent)]
+    Decode(#[from] prost::DecodeError),
+    #[error(transparent)]
+    Package(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{0} files are not supported")]
+    Unsupported(&'static str),
+    #[error("invalid `{0}`")]
+    Invalid(&'static str),
+}
+
+/// The values of `deltas`, each the difference to the one before, with
+/// `precision` decimal places.
+fn undelta(deltas: &[i32], precision: u32) -> Vec<Scalar> {
+    let factor = (10 as Scalar).powi(precision as i32);
+    deltas
+        .iter()
+        .scan(0i64, |value, &delta| {
+            *value += delta as i64;
+            Some(*value as Scalar / factor)
+        })
+        .collect()
+}
+
+/// A line through the control points `xs` and `ys`, with `widths` as
+/// pressure, one for all control points or one for each.
+fn stroke(
+    xs: &[Scalar],
+    ys: &[Scalar],
+    widths: &[Scalar],
+    color: Color,
+) -> Result<Line, WillError> {
+    let count = xs.len();
+    let widths = match widths.len() {
+        0 => vec![1.; count],
+        1 => vec![widths[0]; count],
+        n if n == count => widths.to_vec(),
+        _ => return Err(WillError::Invalid("strokeWidths")),
+    };
+    let width = widths.iter().copied().fold(0., Scalar::max);
+    // The first and last control points are not on the stroke
+    let range = if count > 2 { 1..count - 1 } else { 0..count };
+    let points = range
+        .map(|i| {
+            let pressure = if width > 0. { widths[i] / width } else { 1. };
+            LinePoint(xs[i], ys[i], pressure)
+        })
+        .collect();
+    Ok(Line {
+        color,
+        width,
+        points,
+        timestamps: vec![],
+        properties: Properties::default(),
+    })
+}
+
+fn line(path: wire::Path, precision: u32) -> Result<Line, WillError> {
+    let precision = path.decimal_precision.unwrap_or(precision);
+    if precision > MAX_PRECISION {
+        return Err(WillError::Invalid("decimalPrecision"));
+    }
+    if path.data.len() % 2 != 0 {
+        return Err(WillError::Invalid("data"));
+    }
+    let axis = |offset: usize| {
+        let deltas: Vec<i32> = path.data.iter().skip(offset).step_by(2).copied().collect();
+        undelta(&deltas, precision)
+    };
+    let channel = |i: usize| {
+        path.stroke_color
+            .get(i)
+            .map_or(255, |&c| c.max(0).min(255) as u8)
+    };
+    let color = match path.stroke_color.len() {
+        0 => Color::rgb(0, 0, 0),
+        4 => Color::rgba(channel(0), channel(1), channel(2), channel(3)),
+        _ => return Err(WillError::Invalid("strokeColor")),
+    };
+    let widths = undelta(&path.stroke_widths, precision);
+    stroke(&axis(0), &axis(1), &widths, color)
+}
+
+/// The lines of WILL 2.0 ink data.
+fn lines(bytes: &[u8]) -> Result<Vec<Line>, WillError> {
+    let paths = wire::Paths::decode(bytes)?;
+    let precision = paths.decimal_precision.unwrap_or(DEFAULT_PRECISION);
+    paths
+        .paths
+        .into_iter()
+        .map(|path| line(path, precision))
+        .collect()
+}
+
+/// The lines of the ink data in `sections/media` of a WILL package.
+fn package_lines(bytes: &[u8]) -> Result<Vec<Line>, WillError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut media = vec![];
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        let name = file.name().to_owned();
+        if !(name.starts_with("sections/media/") && name.ends_with(".protobuf")) {
+            continue;
+        }
+        // The size in the archive may be wrong
+        let mut data = vec![];
+        file.take(MAX_MEDIA + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > MAX_MEDIA {
+            return Err(WillError::Invalid("sections/media"));
+        }
+        media.push((name, data));
+    }
+    media.sort();
+    let mut all = vec![];
+    for (_, data) in media {
+        all.extend(lines(&data)?);
+    }
+    Ok(all)
+}
+
+/// The chunks of the RIFF file `bytes`, by their id.
+fn chunks(bytes: &[u8]) -> Result<Vec<(&[u8], &[u8])>, WillError> {
+    let mut rest = bytes.get(12..).ok_or(WillError::Invalid("RIFF"))?;
+    let mut chunks = vec![];
+    while rest.len() >= 8 {
+        let size = u32::from_le_bytes([rest[4], rest[5], rest[6], rest[7]]) as usize;
+        let data = rest[8..].get(..size).ok_or(WillError::Invalid("RIFF"))?;
+        chunks.push((&rest[..4], data));
+        // Chunks are padded to an even size
+        rest = rest[8..].get(size + size % 2..).unwrap_or(&[]);
+    }
+    Ok(chunks)
+}
+
+/// The lines of a WILL 3.1 file.
+fn riff_lines(bytes: &[u8]) -> Result<Vec<Line>, WillError> {
+    let chunks = chunks(bytes)?;
+    // The version, then for each other chunk its version, content type and
+    // compression, and a reserved byte
+    let head = match chunks.first() {
+        Some(&(id, head)) if id == b"HEAD" => head,
+        _ => return Err(WillError::Invalid("HEAD")),
+    };
+    if head.get(..2) != Some(&[3, 1][..]) {
+        return Err(WillError::Unsupported("WILL 3.0"));
+    }
+    let index = chunks[1..]
+        .iter()
+        .position(|&(id, _)| id == b"INKD")
+        .ok_or(WillError::Invalid("INKD"))?;
+    match head.get(4 + index * 4..8 + index * 4) {
+        Some(&[_, _, 0, _]) => {}
+        Some(_) => return Err(WillError::Unsupported("Compressed WILL 3.1")),
+        None => return Err(WillError::Invalid("HEAD")),
+    }
+    let ink = wire::InkData::decode(chunks[index + 1].1)?;
+    ink.strokes
+        .into_iter()
+        .filter_map(|stroke| stroke.spline_data)
+        .map(|spline| {
+            let count = spline.spline_x.len();
+            if spline.spline_y.len() != count {
+                return Err(WillError::Invalid("splineY"));
+            }
+            let scalars =
+                |values: &[f32]| -> Vec<Scalar> { values.iter().map(|&v| v as Scalar).collect() };
+            // Lines have one color, the one of the first control point
+            let channel = |values: &[f32], default| {
+                values
+                    .first()
+                    .map_or(default, |&c| (c.max(0.).min(1.) * 255.).round() as u8)
+            };
+            let color = Color::rgba(
+                channel(&spline.red, 0),
+                channel(&spline.green, 0),
+                channel(&spline.blue, 0),
+                channel(&spline.alpha, 255),
+            );
+            stroke(
+                &scalars(&spline.spline_x),
+                &scalars(&spline.spline_y),
+                &scalars(&spline.size),
+                color,
+            )
+        })
+        .collect()
+}
+
+impl Document {
+    /// Reads WILL 2.0 ink data, WILL packages or WILL 3.1 files as pen
+    /// strokes in their coordinates, see [`will`](crate::will).
+    pub fn from_will(bytes: &[u8]) -> Result<Document, WillError> {
+        let lines = if bytes.starts_with(b"RIFF") {
+            riff_lines(bytes)?
+        } else if bytes.starts_with(b"PK") {
+            package_lines(bytes)?
+        } else {
+            lines(bytes)?
+        };
+        Ok(Document {
+            elements: lines.into_iter().map(Element::Line).collect(),
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use prost::Message;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, LinePoint};
+    use crate::will::{wire, WillError};
+    use crate::Document;
+
+    #[test]
+    fn from_will() {
+        let paths = wire::Paths {
+            decimal_precision: Some(1),
+            paths: vec![wire::Path {
+                start_parameter: Some(0.),
+                end_parameter: Some(1.),
+                decimal_precision: None,
+                // (0, 0), (1, 2), (3, 2), (3, 2)
+                data: vec![0, 0, 10, 20, 20, 0, 0, 0],
+                stroke_widths: vec![20, 0, -10, 0],
+                stroke_color: vec![0, 0, 255, 128],
+            }],
+        };
+        let mut bytes = vec![];
+        paths.encode(&mut bytes).unwrap();
+        let doc = Document::from_will(&bytes).unwrap();
+        match &doc.elements[..] {
+            [Element::Line(line)] => {
+                assert_eq!(line.color, Color::rgba(0, 0, 255, 128));
+                assert_eq!(line.width, 2.);
+                assert_eq!(
+                    line.points,
+                    vec![LinePoint(1., 2., 1.), LinePoint(3., 2., 0.5)]
+                );
+            }
+            elements => panic!("{:?}", elements),
+        }
+
+        // Packaged
+        let mut package = ZipWriter::new(Cursor::new(vec![]));
+        package
+            .start_file("sections/media/ink1.protobuf", FileOptions::default())
+            .unwrap();
+        package.write_all(&bytes).unwrap();
+        let package = package.finish().unwrap().into_inner();
+        assert_eq!(Document::from_will(&package).unwrap(), doc);
+
+        // WILL 3.1, with an uncompressed `INKD` chunk
+        let ink = wire::InkData {
+            strokes: vec![wire::Stroke {
+                spline_data: Some(wire::SplineData {
+                    spline_x: vec![0., 1., 3., 3.],
+                    spline_y: vec![0., 2., 2., 2.],
+                    red: vec![0.; 4],
+                    green: vec![0.; 4],
+                    blue: vec![1.; 4],
+                    alpha: vec![],
+                    size: vec![2., 2., 1., 1.],
+                }),
+            }],
+        };
+        let mut data = vec![];
+        ink.encode(&mut data).unwrap();
+        let riff = |compression: u8| {
+            let mut riff = b"RIFF\0\0\0\0UINK".to_vec();
+            for (id, chunk) in [
+                (b"HEAD", vec![3, 1, 0, 0, 1, 0, compression, 0]),
+                (b"INKD", data.clone()),
+            ]
+            .iter()
+            {
+                riff.extend_from_slice(*id);
+                riff.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                riff.extend_from_slice(chunk);
+                if chunk.len() % 2 != 0 {
+                    riff.push(0);
+                }
+            }
+            riff
+        };
+        let mut blue = doc;
+        match &mut blue.elements[0] {
+            Element::Line(line) => line.color = Color::rgb(0, 0, 255),
+            _ => unreachable!(),
+        }
+        assert_eq!(Document::from_will(&riff(0)).unwrap(), blue);
+        assert_matches!(
+            Document::from_will(&riff(1)),
+            Err(WillError::Unsupported(_))
+        );
+
+        let mut invalid = paths;
+        invalid.decimal_precision = Some(40);
+        let mut bytes = vec![];
+        invalid.encode(&mut bytes).unwrap();
+        assert_matches!(
+            Document::from_will(&bytes),
+            Err(WillError::Invalid("decimalPrecision"))
+        );
+    }
+}
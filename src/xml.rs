@@ -0,0 +1,262 @@
+//! The XML events documents are parsed from, read by `svg` or, with the
+//! `fast-parse` feature, by `quick-xml`, which tokenizes big documents
+//! considerably faster.
+//!
+//! The tag names and texts point into the document, for the locations of
+//! errors. With `quick-xml` the [`Attributes`] do as well, so reading a tag
+//! does not allocate for them.
+
+use std::io;
+
+use svg::node::element::tag::Type;
+
+use crate::elements::Attributes;
+
+/// A tag or text, like the events of `svg` without the ones documents do
+/// not read.
+pub(crate) enum Event<'a> {
+    Tag(&'a str, Type, Attributes<'a>),
+    Text(&'a str),
+    Error(svg::parser::Error),
+}
+
+impl<'a> Event<'a> {
+    /// `None` for comments, declarations and instructions.
+    pub(crate) fn from_svg(event: svg::parser::Event<'a>) -> Option<Self> {
+        use svg::parser::Event as Svg;
+        match event {
+            Svg::Tag(name, kind, attributes) => Some(Event::Tag(name, kind, attributes.into())),
+            Svg::Text(text) => Some(Event::Text(text)),
+            Svg::Error(error) => Some(Event::Error(error)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(not(feature = "fast-parse"))]
+pub(crate) fn read(s: &str) -> io::Result<impl Iterator<Item = Event<'_>>> {
+    Ok(svg::read(s)?.filter_map(Event::from_svg))
+}
+
+#[cfg(feature = "fast-parse")]
+pub(crate) fn read(s: &str) -> io::Result<impl Iterator<Item = Event<'_>>> {
+    let mut reader = quick_xml::Reader::from_str(s);
+    // Mismatched end tags are no errors with `svg` either
+    reader.check_end_names(false);
+    Ok(Events {
+        s,
+        reader,
+        buf: vec![],
+        done: false,
+    })
+}
+
+#[cfg(feature = "fast-parse")]
+struct Events<'a> {
+    s: &'a str,
+    reader: quick_xml::Reader<&'a [u8]>,
+    /// Reused for every event.
+    buf: Vec<u8>,
+    /// After the end or an error.
+    done: bool,
+}
+
+#[cfg(feature = "fast-parse")]
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        use quick_xml::events::Event as Xml;
+
+        let s = self.s;
+        let error = |offset: usize, message: String| {
+            let location = crate::Location::new(s, offset, 0, None);
+            Event::Error(svg::parser::Error::new(
+                (location.line, location.column),
+                message,
+            ))
+        };
+        while !self.done {
+            // Where the event starts, at the latest
+            let position = self.reader.buffer_position();
+            self.buf.clear();
+            let event = match self.reader.read_event(&mut self.buf) {
+                Ok(event) => event,
+                Err(e) => {
+                    self.done = true;
+                    return Some(error(self.reader.buffer_position(), e.to_string()));
+                }
+            };
+            let event = match &event {
+                Xml::Start(start) => tag(s, position, start, Type::Start),
+                Xml::Empty(start) => tag(s, position, start, Type::Empty),
+                Xml::End(end) => find(s, position, end.name())
+                    .map(|name| Event::Tag(name, Type::End, Attributes::new())),
+                Xml::Text(text) => match find(s, position, text.escaped()) {
+                    // Trimmed and without blank ones, like with `svg`
+                    Ok(text) if text.trim().is_empty() => continue,
+                    text => text.map(|text| Event::Text(text.trim())),
+                },
+                Xml::Eof => {
+                    self.done = true;
+                    return None;
+                }
+                // Not read by documents, like with `svg`
+                _ => continue,
+            };
+            return Some(match event {
+                Ok(event) => event,
+                Err(e) => {
+                    self.done = true;
+                    error(position, e)
+                }
+            });
+        }
+        None
+    }
+}
+
+/// The `bytes` of an event as they are in `s`, at or after `position`.
+///
+/// Events are copied from `s` into a buffer, so their names and texts are
+/// looked up to borrow them from `s` instead.
+#[cfg(feature = "fast-parse")]
+fn find<'a>(s: &'a str, position: usize, bytes: &[u8]) -> Result<&'a str, String> {
+    let text = std::str::from_utf8(bytes).map_err(|e| e.to_string())?;
+    let start = s
+        .get(position..)
+        .and_then(|rest| rest.find(text))
+        .ok_or_else(|| format!("{:?} is not in the document", text))?;
+    Ok(&s[position + start..][..text.len()])
+}
+
+/// A start or empty tag borrowing from `s`. The tag is copied as a whole,
+/// so the attributes are found relative to its name.
+#[cfg(feature = "fast-parse")]
+fn tag<'a>(
+    s: &'a str,
+    position: usize,
+    start: &quick_xml::events::BytesStart<'_>,
+    kind: Type,
+) -> Result<Event<'a>, String> {
+    use std::borrow::Cow;
+
+    let name = find(s, position, start.name())?;
+    let offset = name.as_ptr() as usize - s.as_ptr() as usize;
+    let source = |bytes: &[u8]| {
+        let at = offset + (bytes.as_ptr() as usize - start.name().as_ptr() as usize);
+        s.get(at..at + bytes.len())
+            .ok_or_else(|| "Attribute is not in the document".to_owned())
+    };
+    // Duplicates are not checked, like with `svg`
+    let attributes = start
+        .attributes()
+        .with_checks(false)
+        .map(|attribute| {
+            let attribute = attribute.map_err(|e| e.to_string())?;
+            // Values are kept escaped, like with `svg`
+            Ok((
+                Cow::Borrowed(source(attribute.key)?),
+                Cow::Borrowed(source(&*attribute.value)?),
+            ))
+        })
+        .collect::<Result<Attributes<'a>, String>>()?;
+    Ok(Event::Tag(name, kind, attributes))
+}
+
+#[cfg(all(test, feature = "fast-parse"))]
+mod tests {
+    use std::str::FromStr;
+
+    use test::Bencher;
+
+    use crate::colors::Color;
+    use crate::elements::{Element, Layer, Properties, Text};
+    use crate::xml::Event;
+    use crate::Document;
+
+    /// The tag names, texts and attribute values by where they are in `s`.
+    fn event(event: Event<'_>) -> String {
+        match event {
+            Event::Tag(name, kind, attributes) => {
+                let mut attributes: Vec<_> = attributes
+                    .iter()
+                    .map(|(k, v)| format!("{}={:?}", k, v))
+                    .collect();
+                attributes.sort();
+                format!("{:p} {} {:?} {:?}", name, name, kind, attributes)
+            }
+            Event::Text(text) => format!("{:p} {}", text, text),
+            Event::Error(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn same_events_as_svg() {
+        let mut layer = Layer::new("a & b");
+        layer.properties.title = Some("<title>".to_owned());
+        layer.elements.push(Element::Text(Text {
+            position: (1., 2.),
+            content: "two\nlines".to_owned(),
+            color: Color::rgb(0, 0, 0),
+            font: Default::default(),
+            properties: Properties::default(),
+        }));
+        let document = Document {
+            elements: vec![Element::Layer(layer)],
+            ..Default::default()
+        };
+        let s = document.to_string();
+        let expected: Vec<_> = svg::read(&s)
+            .unwrap()
+            .filter_map(Event::from_svg)
+            .map(event)
+            .collect();
+        let events: Vec<_> = super::read(&s).unwrap().map(event).collect();
+        assert_eq!(events, expected);
+        assert_eq!(Document::from_str(&s).unwrap(), document);
+        // Texts followed by tags, and the attributes borrow from `s` as well
+        let s = "<svg>a<g id='b'>c</g>d</svg>";
+        let offset = |text: &str| text.as_ptr() as usize - s.as_ptr() as usize;
+        let offsets: Vec<_> = super::read(s)
+            .unwrap()
+            .map(|event| match event {
+                Event::Tag(name, _, attributes) => {
+                    if let Some(id) = attributes.get("id") {
+                        assert_eq!(offset(&**id), 13);
+                    }
+                    offset(name)
+                }
+                Event::Text(text) => offset(text),
+                Event::Error(error) => panic!("{}", error),
+            })
+            .collect();
+        assert_eq!(offsets, [1, 5, 7, 16, 19, 21, 24]);
+    }
+
+    /// A document of many strokes, with the attributes of other editors.
+    fn strokes() -> String {
+        let mut s =
+            String::from("<svg xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\">");
+        for i in 0..2000 {
+            s.push_str(&format!(
+                r##"<polyline id="path{0}" inkscape:label="Stroke {0}" points="{0},0 {0}.5,1 {0},2" stroke="#FF0000" fill="none" stroke-width="1.5" stroke-opacity="0.5"/>"##,
+                i
+            ));
+        }
+        s.push_str("</svg>");
+        s
+    }
+
+    #[bench]
+    fn read_with_svg(b: &mut Bencher) {
+        let s = strokes();
+        b.iter(|| svg::read(&s).unwrap().filter_map(Event::from_svg).count());
+    }
+
+    #[bench]
+    fn read_with_quick_xml(b: &mut Bencher) {
+        let s = strokes();
+        b.iter(|| super::read(&s).unwrap().count());
+    }
+}